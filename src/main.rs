@@ -1,9 +1,11 @@
 mod settings;
+mod stub;
 
 use crate::settings::RunCommand;
 use crate::settings::Settings;
 
 use std::error::Error;
+use std::process;
 use std::process::Command;
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -15,6 +17,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     // structure.  The last step would be to actually execute the command and finish up the final
     // work.
 
+    // Act as a stand-in for `retroarch` instead, if started by option `--retroarch-stub`.
+    if let Some(code) = stub::run_if_requested() {
+        process::exit(code);
+    }
+
     let argument_options = Settings::new_from_cmdline(None);
 
     // Exit program after printing fullpath or opening the user settings ini file.
@@ -22,58 +29,283 @@ fn main() -> Result<(), Box<dyn Error>> {
         return Ok(());
     }
 
+    // Exit program after creating the user settings ini file with the guided setup.
+    if argument_options.is_first_run() {
+        return argument_options.run_wizard();
+    }
+
+    // Exit program after upgrading the user settings ini file to the current schema version.
+    if argument_options.is_migrate_config() {
+        return argument_options.migrate_config();
+    }
+
+    // Exit program after restoring the user settings ini file from the last backup.
+    if argument_options.is_undo_config() {
+        return argument_options.undo_config();
+    }
+
+    // Exit program after printing the findings of analyzing the rules in the user settings.
+    if argument_options.is_check_config() {
+        return argument_options.check_config();
+    }
+
+    // Exit program after printing the report of the environment checks.
+    if argument_options.is_doctor() {
+        return argument_options.doctor();
+    }
+
+    // Exit program after listing or terminating the running instances from the registry.
+    if argument_options.is_list_instances() || argument_options.is_quit() {
+        if argument_options.is_list_instances() {
+            argument_options.print_instances();
+        }
+        if argument_options.is_quit() {
+            return argument_options.quit_instances();
+        }
+        return Ok(());
+    }
+
+    // Aborts if the following phases of the startup take too long in total.
+    let watchdog = argument_options.start_watchdog();
+    watchdog.phase("reading the user config");
     let user_config =
         Settings::new_from_config(argument_options.get_config())?;
-    let ignore_stdin: bool =
-        argument_options.is_nostdin() || user_config.is_nostdin();
-    let stdin_games = Settings::new_from_stdin(ignore_stdin)?;
+    watchdog.set_budget(argument_options.get_startup_timeout(&user_config));
+    watchdog.phase("reading stdin");
+    // Shell completion runs with the terminal as stdin, which is never a list of games.
+    let ignore_stdin: bool = argument_options.is_nostdin()
+        || user_config.is_nostdin()
+        || argument_options.is_complete_games()
+        || argument_options.is_stdin_rom();
+    // List modes write into a pipe, so stdin is read for them regardless of stdout.
+    let piped_stdout: bool = argument_options.is_fzf()
+        || argument_options.is_list_games()
+        || argument_options.is_count()
+        || argument_options.is_export_list();
+    let mut stdin_games = Settings::new_from_stdin(
+        ignore_stdin,
+        piped_stdout,
+        argument_options.get_stdin_timeout(&user_config),
+        argument_options.is_null(),
+    )?;
+    stdin_games.apply_stdin_rules(&user_config);
+    // Games from list files are merged the same way as those from stdin.
+    stdin_games.add_games_from(&argument_options)?;
+
+    // With `--diff-config` the other user config is combined with the same stdin and arguments.
+    let diff_config = argument_options.get_diff_config().clone();
+    let diff_sources = diff_config
+        .as_ref()
+        .map(|_| (stdin_games.clone(), argument_options.clone()));
 
     let mut app_settings = Settings::new();
     // Overwrite fields in app_settings only, if new fields are Some().
     app_settings.update_from(user_config);
     app_settings.update_from(stdin_games);
     app_settings.update_from(argument_options);
+    app_settings.expand_directories();
+    app_settings.dedup_games();
+    app_settings.sort_games();
+
+    // The lock is read from fixed locations, so another user config cannot replace it.
+    app_settings.read_lock()?;
+    // Disable lock mode for this run, if the password matches.
+    if app_settings.is_unlock() {
+        app_settings.unlock()?;
+    }
+    app_settings.check_locked_options()?;
+    app_settings.count_options();
 
     let mut defaults = Settings::new_from_defaults();
-    if !app_settings.is_libretro_path_available() {
+    if !app_settings.is_libretro_path_available()
+        || app_settings.is_libretro_info_directory_missing()
+    {
         // Extract keys and values from `retroarch.cfg` only if the path to `libretro` installation
-        // directory in `RetroArch` is unknown.
+        // directory in `RetroArch` is unknown, or the core info files are required.
+        watchdog.phase("reading retroarch.cfg");
         let raconfig = Settings::new_from_retroarch_config(
             app_settings.get_retroarch_config(),
+            &app_settings.appendconfigs(),
         )?;
         defaults.update_from(raconfig);
     }
+    let diff_defaults = diff_sources.as_ref().map(|_| defaults.clone());
     // Overwrite only those keys in `app_settings`, which their values are currently `None`.
+    app_settings.search_retroarch();
     app_settings.update_defaults_from(defaults);
+    app_settings.apply_relative();
+    app_settings.apply_escape();
+    // Temporary override files are removed on every return from here on, including errors.
+    let _overrides = app_settings.cleanup_overrides();
+    app_settings.clean_leftovers();
+
+    // With `--stdin-rom` the game is saved next to the override files, so it is removed too.
+    if app_settings.is_stdin_rom() {
+        watchdog.phase("reading the game from stdin");
+        app_settings.read_stdin_rom()?;
+    }
+    // Other modes than launching a game may run for long.
+    watchdog.disarm();
+
+    if app_settings.is_serve() {
+        return app_settings.serve();
+    }
+    if app_settings.is_hotkey_listen() {
+        return app_settings.hotkey_listen();
+    }
+
+    // Exit program after listing the games for shell completion.
+    if app_settings.is_complete_games() {
+        app_settings.print_complete_games();
+        return Ok(());
+    }
+
+    // Exit program after listing the games or printing the shell function for `fzf`.
+    if app_settings.is_fzf_snippet() {
+        return app_settings.print_fzf_snippet();
+    }
+    if app_settings.is_fzf() {
+        app_settings.print_fzf();
+        return Ok(());
+    }
+    app_settings.apply_fzf_selection();
+
+    // Exit program after comparing how the games resolve under another user config.
+    if let (Some((stdin_games, argument_options)), Some(defaults)) =
+        (diff_sources, diff_defaults)
+    {
+        let mut other = Settings::new();
+        other.update_from(Settings::new_from_config(&diff_config)?);
+        other.update_from(stdin_games);
+        other.update_from(argument_options);
+        other.dedup_games();
+        other.sort_games();
+        other.search_retroarch();
+        other.update_defaults_from(defaults);
+        return app_settings.diff_config(&other);
+    }
 
-    if app_settings.is_game_available() || app_settings.is_norun() {
+    // Exit program after printing the matching games.
+    if app_settings.is_list_games() {
+        app_settings.print_list_games();
+        return Ok(());
+    }
+
+    // Exit program after printing the number of matching games.
+    if app_settings.is_count() {
+        return app_settings.print_count();
+    }
+
+    // Exit program after exporting the games with their resolved core.
+    if app_settings.is_export_list() {
+        return app_settings.print_export_list();
+    }
+
+    // Exit program after printing the local usage counters.
+    if app_settings.is_usage_report() {
+        return app_settings.print_usage_report();
+    }
+
+    // Exit program after printing the history of launched games or its statistics.
+    if app_settings.is_history_list() {
+        return app_settings.print_history_list();
+    }
+    if app_settings.is_stats() {
+        return app_settings.print_stats();
+    }
+
+    // Exit program after comparing the cores with their hashes in the history.
+    if app_settings.is_verify_cores() {
+        return app_settings.verify_cores();
+    }
+
+    // Exit program after printing the extensions supported by the cores.
+    if app_settings.is_matrix() {
+        return app_settings.print_matrix();
+    }
+
+    // Exit program after printing the chosen `RetroArch` executable.
+    if app_settings.is_which_retroarch() {
+        return app_settings.print_which_retroarch();
+    }
+
+    // Exit program after printing the directory the core of the selected game is found in.
+    if app_settings.is_which_core() {
+        return app_settings.print_which_core();
+    }
+
+    // Exit program after removing or suggesting duplicate builds of cores.
+    if app_settings.is_prune_cores() {
+        return app_settings.prune_cores();
+    }
+
+    // Exit program after linking all games into folders for each system.
+    if app_settings.is_organize() {
+        return app_settings.organize();
+    }
+
+    // Exit program after renaming or previewing the names of games from the title database.
+    if app_settings.is_rename() {
+        return app_settings.rename_games();
+    }
+
+    if app_settings.is_resume_queue() {
+        app_settings.load_queue_progress()?;
+    }
+    if app_settings.is_queue() {
+        if app_settings.is_debounced() {
+            eprintln!("Launch ignored, another one just started.");
+        } else if app_settings.there_can_only_be_one() {
+            eprintln!(
+                "retroarch process already running. There Can Be Only One!"
+            );
+        } else {
+            let summary = app_settings.run_queue();
+            app_settings.print_queue_summary(&summary);
+            app_settings.check_queue_summary(&summary)?;
+        }
+    } else if app_settings.is_game_available() || app_settings.is_norun() {
+        if app_settings.is_interactive() {
+            app_settings.pick_game()?;
+        }
+        watchdog.phase("building the command");
         let mut run: RunCommand = app_settings.build_command()?;
+        watchdog.disarm();
 
         if !app_settings.is_norun() {
-            if app_settings.there_can_only_be_one() {
+            if app_settings.is_debounced() {
+                eprintln!("Launch ignored, another one just started.");
+            } else if app_settings.there_can_only_be_one() {
                 eprintln!(
                     "retroarch process already running. There Can Be Only One!"
                 );
             } else {
-                run.output = app_settings.run(&mut run.cmdline);
+                if let Some(report) = app_settings.launch(&mut run)? {
+                    app_settings.print_launch_report(&run, &report);
+                }
             }
         }
         if app_settings.is_list_cores() {
-            for core in app_settings.find_core_match(&run.libretro) {
-                println!("{core}");
-            }
+            let cores: Vec<String> =
+                app_settings.find_core_match(&run.libretro);
+            app_settings.print_core_list(&cores)?;
+        }
+        if app_settings.is_fingerprint() {
+            app_settings.print_fingerprint(&run);
+        }
+        if app_settings.is_print_env() {
+            app_settings.print_env(&run);
         }
         if app_settings.is_which_command() {
             print_cmdline(&run.cmdline);
         } else {
-            app_settings.print_which(run.game);
+            app_settings.print_which(&run)?;
         }
     } else if app_settings.is_list_cores() {
-        app_settings.print_cores();
+        app_settings.print_cores()?;
     } else {
         return Err("A path to game is required.".into());
     }
-
     Ok(())
 }
 
@@ -1,41 +1,129 @@
+mod aiservice;
+mod anchor;
 mod arguments;
+mod audio;
+mod backups;
+mod clock;
+mod commands;
+mod compressed;
+mod configdiff;
+mod coreenv;
+mod coreinfo;
+mod coresets;
+mod debounce;
+mod discovery;
+mod doctor;
+mod escape;
+#[cfg(feature = "events")]
+mod events;
+mod export;
 mod file;
+mod fuzzy;
+mod history;
+mod hotkey;
+mod inisyntax;
 mod inoutput;
+mod instances;
+mod interpolate;
+mod isolation;
+mod janitor;
+pub mod launch;
+mod lint;
+mod lock;
+mod matrix;
+mod migration;
+mod mounts;
+#[cfg(feature = "notify")]
+mod notification;
+mod organize;
+mod overrides;
+mod passthrough;
+mod picker;
+mod playlist;
+mod policy;
+mod preflight;
+mod presets;
+mod process;
+mod profiles;
+mod program;
+mod provider;
+mod prune;
+mod queue;
+mod remotes;
+#[cfg(feature = "database")]
+mod rename;
+mod resources;
 mod retroarch;
+mod romsets;
+mod rules;
+mod savestates;
+mod scan;
+mod scheduling;
+#[cfg(feature = "serve")]
+mod server;
+mod sort;
+mod spawner;
+mod speech;
+mod stdinmap;
+mod suggest;
+#[cfg(feature = "tokio")]
+mod supervisor;
+#[cfg(feature = "suspend")]
+mod suspend;
+mod template;
+#[cfg(feature = "database")]
+mod titles;
+#[cfg(feature = "tui")]
+mod tui;
+mod usage;
+mod watchdog;
+mod wizard;
 
 use arguments::Opt;
 
+use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::collections::HashSet;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
 use std::process::Output;
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
 
+use clap::CommandFactory;
 use clap::Parser;
 use configparser::ini;
 use indexmap::map::IndexMap;
+use rayon::prelude::*;
 use wildmatch::WildMatch;
 
 type Result<T = (), E = Box<dyn std::error::Error>> =
     std::result::Result<T, E>;
 
 /// The final `process::Command` to execute and run `retroarch`.  It bundles related information
-/// such as paths and the `output` from stdout.  The additional path data should be manually set
-/// when building the main `cmdline`, to stay in sync.  The `output` must be set manually after
-/// executing `cmdline` process.
+/// such as paths.  The additional path data should be manually set when building the main
+/// `cmdline`, to stay in sync.  How the execution ended is reported by `Settings::launch()`.
 #[derive(Debug)]
 pub struct RunCommand {
     pub cmdline: Command,
     pub game: PathBuf,
     pub libretro: PathBuf,
-    pub output: Option<Output>,
+    /// Priority and CPUs of the started process.
+    pub scheduling: scheduling::Scheduling,
 }
 
 /// Configuration of the main program.  The intended use case is to create multiple `Settings` data
 /// from various places like commandline arguments or user configuration file.  Then all those
 /// `Settings` data should be merged into a single one, which will be used as the source when
 /// finally building the `RunCommand`.  Which is then used to execute `retroarch` program itself.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Settings {
     games: Vec<PathBuf>,
     retroarch_arguments: Vec<String>,
@@ -43,7 +131,7 @@ pub struct Settings {
     retroarch: Option<PathBuf>,
     retroarch_config: Option<PathBuf>,
     libretro: Option<PathBuf>,
-    libretro_directory: Option<PathBuf>,
+    libretro_directory: Option<Vec<PathBuf>>,
     core: Option<String>,
     filter: Option<Vec<String>>,
     strict: Option<bool>,
@@ -60,6 +148,130 @@ pub struct Settings {
     cores_rules: Option<IndexMap<String, PathBuf>>,
     extension_rules: Option<IndexMap<String, PathBuf>>,
     directory_rules: Option<IndexMap<String, PathBuf>>,
+    doctor: Option<bool>,
+    libretro_info_directory: Option<PathBuf>,
+    long: Option<bool>,
+    queue: Option<bool>,
+    json: Option<bool>,
+    workdir: Option<PathBuf>,
+    env: Option<IndexMap<String, String>>,
+    rule_options: Option<IndexMap<String, rules::RuleOptions>>,
+    isolated: Option<PathBuf>,
+    retroarch_overrides: Option<IndexMap<String, String>>,
+    keep_overrides: Option<bool>,
+    highlander_scope: Option<process::Scope>,
+    list_instances: Option<bool>,
+    quit: Option<bool>,
+    notify: Option<bool>,
+    events: Option<IndexMap<String, String>>,
+    serve: Option<Option<u16>>,
+    token_file: Option<PathBuf>,
+    print_env: Option<bool>,
+    retroarch_stub: Option<PathBuf>,
+    fingerprint: Option<bool>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    core_labels: Option<IndexMap<String, String>>,
+    migrate_config: Option<bool>,
+    rom_roots: Option<Vec<PathBuf>>,
+    organize: Option<PathBuf>,
+    databases: Option<Vec<PathBuf>>,
+    rename_preview: Option<bool>,
+    rename: Option<bool>,
+    yes: Option<bool>,
+    format: Option<String>,
+    fzf: Option<bool>,
+    fzf_snippet: Option<bool>,
+    launch_from_fzf: Option<String>,
+    hotkey_listen: Option<bool>,
+    hotkey_device: Option<PathBuf>,
+    hotkey_buttons: Option<String>,
+    pause_on_suspend: Option<bool>,
+    preflight: Option<preflight::Checks>,
+    no_preflight: Option<bool>,
+    profiles: Option<Vec<profiles::Profile>>,
+    profile: Option<Vec<String>>,
+    check_config: Option<bool>,
+    policy: Option<policy::Policy>,
+    ignore_policy: Option<bool>,
+    lock: Option<lock::Lock>,
+    locked: Option<bool>,
+    unlock: Option<bool>,
+    // Computed once by `rom_extensions()`, reset whenever the settings are updated.
+    known_extensions: OnceLock<HashSet<String>>,
+    complete_games: Option<String>,
+    which_core: Option<bool>,
+    relative: Option<String>,
+    resume_queue: Option<bool>,
+    fail_fast: Option<bool>,
+    exit_policy: Option<queue::ExitPolicy>,
+    stdin_timeout: Option<u64>,
+    keep_duplicates: Option<bool>,
+    coreset: Option<String>,
+    coresets: Option<Vec<coresets::CoreSet>>,
+    diff_config: Option<PathBuf>,
+    usage_counters: Option<bool>,
+    usage_report: Option<bool>,
+    title: Option<String>,
+    fallback_core: Option<String>,
+    menu_load: Option<bool>,
+    presets: Option<Vec<presets::Preset>>,
+    preset: Option<Vec<String>>,
+    history: Option<bool>,
+    core_lock: Option<bool>,
+    ignore_core_lock: Option<bool>,
+    verify_cores: Option<bool>,
+    tag: Option<Vec<String>>,
+    note: Option<String>,
+    history_list: Option<bool>,
+    stats: Option<bool>,
+    export_list: Option<export::Format>,
+    prune_cores: Option<bool>,
+    remote: Option<String>,
+    remotes: Option<Vec<remotes::Remote>>,
+    romsets: Option<IndexMap<String, PathBuf>>,
+    stdin_rom: Option<String>,
+    startup_timeout: Option<u64>,
+    retroarch_origin: Option<discovery::Origin>,
+    retroarch_directory: Option<Vec<PathBuf>>,
+    which_retroarch: Option<bool>,
+    suggestions: Option<u64>,
+    timeout: Option<u64>,
+    stdin_rules: Option<stdinmap::StdinRules>,
+    tts_command: Option<String>,
+    nice: Option<String>,
+    cpus: Option<String>,
+    core_options: Option<IndexMap<String, rules::RuleOptions>>,
+    cache_days: Option<u64>,
+    match_anchor: Option<anchor::Anchor>,
+    interactive: Option<bool>,
+    fuzzy: Option<bool>,
+    selector_command: Option<String>,
+    matrix: Option<bool>,
+    index: Option<isize>,
+    exclude: Option<Vec<String>>,
+    undo_config: Option<bool>,
+    spawner: Option<spawner::Spawner>,
+    load_state: Option<savestates::Slot>,
+    choose: Option<bool>,
+    list_games: Option<bool>,
+    core_env: Option<IndexMap<String, IndexMap<String, String>>>,
+    env_defaults: Option<bool>,
+    count: Option<bool>,
+    null: Option<bool>,
+    escape: Option<escape::Escape>,
+    games_from: Option<Vec<PathBuf>>,
+    ai_service: Option<bool>,
+    ai_service_mode: Option<aiservice::Mode>,
+    ai_service_url: Option<String>,
+    recursive: Option<bool>,
+    force: Option<bool>,
+    sort: Option<sort::Sort>,
+    unique: Option<bool>,
+    debounce: Option<u64>,
+    volume: Option<f32>,
+    mute: Option<bool>,
+    mixer_restore_command: Option<String>,
 }
 
 impl Default for Settings {
@@ -95,17 +307,145 @@ impl Settings {
             cores_rules: None,
             extension_rules: None,
             directory_rules: None,
+            doctor: None,
+            libretro_info_directory: None,
+            long: None,
+            queue: None,
+            json: None,
+            workdir: None,
+            env: None,
+            rule_options: None,
+            isolated: None,
+            retroarch_overrides: None,
+            keep_overrides: None,
+            highlander_scope: None,
+            list_instances: None,
+            quit: None,
+            notify: None,
+            events: None,
+            serve: None,
+            token_file: None,
+            print_env: None,
+            retroarch_stub: None,
+            fingerprint: None,
+            limit: None,
+            offset: None,
+            core_labels: None,
+            migrate_config: None,
+            rom_roots: None,
+            organize: None,
+            databases: None,
+            rename_preview: None,
+            rename: None,
+            yes: None,
+            format: None,
+            fzf: None,
+            fzf_snippet: None,
+            launch_from_fzf: None,
+            hotkey_listen: None,
+            hotkey_device: None,
+            hotkey_buttons: None,
+            pause_on_suspend: None,
+            preflight: None,
+            no_preflight: None,
+            profiles: None,
+            profile: None,
+            check_config: None,
+            policy: None,
+            ignore_policy: None,
+            lock: None,
+            locked: None,
+            unlock: None,
+            known_extensions: OnceLock::new(),
+            complete_games: None,
+            which_core: None,
+            relative: None,
+            resume_queue: None,
+            fail_fast: None,
+            exit_policy: None,
+            stdin_timeout: None,
+            keep_duplicates: None,
+            coreset: None,
+            coresets: None,
+            diff_config: None,
+            usage_counters: None,
+            usage_report: None,
+            title: None,
+            fallback_core: None,
+            menu_load: None,
+            presets: None,
+            preset: None,
+            history: None,
+            core_lock: None,
+            ignore_core_lock: None,
+            verify_cores: None,
+            tag: None,
+            note: None,
+            history_list: None,
+            stats: None,
+            export_list: None,
+            prune_cores: None,
+            remote: None,
+            remotes: None,
+            romsets: None,
+            stdin_rom: None,
+            startup_timeout: None,
+            retroarch_origin: None,
+            retroarch_directory: None,
+            which_retroarch: None,
+            suggestions: None,
+            timeout: None,
+            stdin_rules: None,
+            tts_command: None,
+            nice: None,
+            cpus: None,
+            core_options: None,
+            cache_days: None,
+            match_anchor: None,
+            interactive: None,
+            fuzzy: None,
+            selector_command: None,
+            matrix: None,
+            index: None,
+            exclude: None,
+            undo_config: None,
+            spawner: None,
+            load_state: None,
+            choose: None,
+            list_games: None,
+            core_env: None,
+            env_defaults: None,
+            count: None,
+            null: None,
+            escape: None,
+            games_from: None,
+            ai_service: None,
+            ai_service_mode: None,
+            ai_service_url: None,
+            recursive: None,
+            force: None,
+            sort: None,
+            unique: None,
+            debounce: None,
+            volume: None,
+            mute: None,
+            mixer_restore_command: None,
         }
     }
 
     /// Read each line from stdin stream and convert it to paths.  Create a new struct with games
-    /// out of it.
-    pub fn new_from_stdin(nostdin: bool) -> Result<Self> {
+    /// out of it.  See `inoutput::list_from_stdin()` for `piped_stdout` and `null`.
+    pub fn new_from_stdin(
+        nostdin: bool,
+        piped_stdout: bool,
+        timeout: Option<Duration>,
+        null: bool,
+    ) -> Result<Self> {
         let mut settings: Self = Self::new();
 
         if !nostdin {
-            let list = inoutput::list_from_stdin()?;
-            settings.games = list.iter().map(PathBuf::from).collect();
+            settings.games =
+                inoutput::list_from_stdin(piped_stdout, timeout, null)?;
         }
 
         Ok(settings)
@@ -116,17 +456,26 @@ impl Settings {
         let mut settings: Self = Self::new();
 
         settings.retroarch = Some(PathBuf::from("retroarch"));
+        settings.history = Some(true);
 
         settings
     }
 
-    /// Parse own commandline arguments and create a new Settings struct out of it.
+    /// Parse own commandline arguments and create a new Settings struct out of it.  Command
+    /// presets like `@tv` are expanded first, see `expand_command_presets()`.
     pub fn new_from_cmdline(options: Option<Vec<String>>) -> Self {
         let mut settings: Self = Self::new();
 
         let args: Opt = match options {
             Some(opt) => Opt::from_iter(opt.iter()),
-            None => Opt::parse(),
+            None => {
+                match Self::expand_command_presets(env::args_os().collect()) {
+                    Ok(args) => Opt::parse_from(args),
+                    Err(e) => Opt::command()
+                        .error(clap::ErrorKind::InvalidValue, e)
+                        .exit(),
+                }
+            }
         };
 
         // default_value
@@ -141,11 +490,64 @@ impl Settings {
         // Option
         // Take them, as they are optional anyway.
         settings.retroarch = args.retroarch;
+        if settings.retroarch.is_some() {
+            settings.retroarch_origin = Some(discovery::Origin::Option);
+        } else if let Some(path) = discovery::from_env() {
+            settings.retroarch = Some(path);
+            settings.retroarch_origin = Some(discovery::Origin::Environment);
+        }
         settings.retroarch_config = args.retroarch_config;
         settings.libretro = args.libretro;
-        settings.libretro_directory = args.libretro_directory;
+        settings.libretro_directory = args
+            .libretro_directory
+            .map(|dirs| dirs.iter().flat_map(env::split_paths).collect());
+        settings.libretro_info_directory = args.libretro_info_directory;
         settings.core = args.core;
+        settings.profile = args.profile;
+        settings.title = args.title;
+        settings.preset = args.preset;
+        settings.tag = args.tag;
+        settings.note = args.note;
         settings.filter = args.filter;
+        settings.exclude = args.exclude;
+        settings.games_from = args.games_from;
+        settings.limit = args.limit;
+        settings.offset = args.offset;
+        settings.index = args.index;
+        settings.workdir = args.workdir;
+        settings.coreset = args.coreset;
+        settings.nice = args.nice;
+        settings.cpus = args.cpus;
+        settings.remote = args.remote;
+        settings.stdin_rom = args.ext.filter(|_| args.stdin_rom);
+        settings.retroarch_stub = args.retroarch_stub;
+        settings.serve = args.serve;
+        settings.token_file = args.token_file;
+        if args.hotkey_listen {
+            settings.hotkey_listen = Some(true);
+        }
+        settings.hotkey_device = args.hotkey_device;
+        settings.hotkey_buttons = args.hotkey_buttons;
+        settings.highlander_scope =
+            args.highlander_scope.and_then(|scope| scope.parse().ok());
+        settings.match_anchor =
+            args.match_anchor.and_then(|anchor| anchor.parse().ok());
+        settings.escape = args.escape.and_then(|escape| escape.parse().ok());
+        settings.sort = args.sort.and_then(|sort| sort.parse().ok());
+        settings.ai_service = args.ai_service.map(|value| value == "on");
+        settings.volume = args.volume;
+        if args.mute {
+            settings.mute = Some(true);
+        }
+        settings.isolated = args
+            .isolated
+            .map(|dir| dir.unwrap_or_else(isolation::default_dir));
+        settings.load_state = args.load_state.map(|slot| {
+            slot.map_or(savestates::Slot::Newest, savestates::Slot::Number)
+        });
+        settings.env = args
+            .env
+            .map(|assignments| assignments.into_iter().collect());
 
         // bool
         // Only set it to `true`, if the option is found in arguments.
@@ -161,9 +563,58 @@ impl Settings {
         if args.list_cores {
             settings.list_cores = Some(true);
         }
+        if args.long {
+            settings.long = Some(true);
+        }
+        if args.format.is_some() {
+            settings.format = args.format;
+        }
+        if args.interactive {
+            settings.interactive = Some(true);
+        }
+        if args.choose {
+            settings.choose = Some(true);
+        }
+        if args.fuzzy {
+            settings.fuzzy = Some(true);
+        }
+        if args.fzf {
+            settings.fzf = Some(true);
+        }
+        if args.list_games {
+            settings.list_games = Some(true);
+        }
+        if args.count {
+            settings.count = Some(true);
+        }
+        if args.relative.is_some() {
+            settings.relative =
+                args.relative.map(|base| base.unwrap_or_else(|| "~".into()));
+        }
+        if args.which_retroarch {
+            settings.which_retroarch = Some(true);
+        }
+        if args.matrix {
+            settings.matrix = Some(true);
+        }
+        if args.which_core {
+            settings.which_core = Some(true);
+        }
+        if args.complete_games.is_some() {
+            settings.complete_games = args.complete_games;
+        }
+        if args.fzf_snippet {
+            settings.fzf_snippet = Some(true);
+        }
+        if args.launch_from_fzf.is_some() {
+            settings.launch_from_fzf = args.launch_from_fzf;
+        }
         if args.fullscreen {
             settings.fullscreen = Some(true);
         }
+        if args.menu_load {
+            settings.menu_load = Some(true);
+        }
         if args.highlander {
             settings.highlander = Some(true);
         }
@@ -173,6 +624,29 @@ impl Settings {
         if args.config_path {
             settings.config_path = Some(true);
         }
+        if args.organize.is_some() {
+            settings.organize = args.organize;
+        }
+        if args.rename_preview {
+            settings.rename_preview = Some(true);
+        }
+        if args.rename {
+            settings.rename = Some(true);
+        }
+        if args.yes {
+            settings.yes = Some(true);
+        }
+        settings.databases = args.database;
+        settings.diff_config = args.diff_config;
+        if args.check_config {
+            settings.check_config = Some(true);
+        }
+        if args.migrate_config {
+            settings.migrate_config = Some(true);
+        }
+        if args.undo_config {
+            settings.undo_config = Some(true);
+        }
         if args.noconfig {
             settings.noconfig = Some(true);
         }
@@ -182,13 +656,117 @@ impl Settings {
         if args.nostdin {
             settings.nostdin = Some(true);
         }
+        if args.null {
+            settings.null = Some(true);
+        }
+        if args.recursive {
+            settings.recursive = Some(true);
+        }
+        if args.stdin_timeout.is_some() {
+            settings.stdin_timeout = args.stdin_timeout;
+        }
+        if args.timeout.is_some() {
+            settings.timeout = args.timeout;
+        }
+        if args.startup_timeout.is_some() {
+            settings.startup_timeout = args.startup_timeout;
+        }
+        if args.debounce.is_some() {
+            settings.debounce = args.debounce;
+        }
+        if args.keep_duplicates {
+            settings.keep_duplicates = Some(true);
+        }
+        if args.unique {
+            settings.unique = Some(true);
+        }
+        if args.doctor {
+            settings.doctor = Some(true);
+        }
+        if args.usage_report {
+            settings.usage_report = Some(true);
+        }
+        if args.verify_cores {
+            settings.verify_cores = Some(true);
+        }
+        if args.prune_cores {
+            settings.prune_cores = Some(true);
+        }
+        if args.history_list {
+            settings.history_list = Some(true);
+        }
+        if args.stats {
+            settings.stats = Some(true);
+        }
+        if args.queue {
+            settings.queue = Some(true);
+        }
+        if args.resume_queue {
+            settings.resume_queue = Some(true);
+        }
+        if args.fail_fast {
+            settings.fail_fast = Some(true);
+        }
+        if args.keep_going {
+            settings.fail_fast = Some(false);
+        }
+        settings.exit_policy =
+            args.exit_policy.and_then(|policy| policy.parse().ok());
+        settings.export_list =
+            args.export_list.and_then(|format| format.parse().ok());
+        if args.json {
+            settings.json = Some(true);
+        }
+        if args.list_instances {
+            settings.list_instances = Some(true);
+        }
+        if args.quit {
+            settings.quit = Some(true);
+        }
+        if args.fingerprint {
+            settings.fingerprint = Some(true);
+            settings.norun = Some(true);
+        }
+        if args.print_env {
+            settings.print_env = Some(true);
+        }
+        if args.notify {
+            settings.notify = Some(true);
+        }
+        if args.pause_on_suspend {
+            settings.pause_on_suspend = Some(true);
+        }
+        if args.no_preflight {
+            settings.no_preflight = Some(true);
+        }
+        if args.force {
+            settings.force = Some(true);
+        }
+        if args.ignore_policy {
+            settings.ignore_policy = Some(true);
+        }
+        if args.ignore_core_lock {
+            settings.ignore_core_lock = Some(true);
+        }
+        if args.locked {
+            settings.locked = Some(true);
+        }
+        if args.unlock {
+            settings.unlock = Some(true);
+        }
+        if args.keep_overrides {
+            settings.keep_overrides = Some(true);
+        }
 
         settings
     }
 
     /// Parse `retroarch.cfg` the own configuration file of `RetroArch` itself and create a new
-    /// `Settings` struct out of it.
-    pub fn new_from_retroarch_config(file: &Option<PathBuf>) -> Result<Self> {
+    /// `Settings` struct out of it.  The files in `appendconfigs` are applied on top of it.
+    pub fn new_from_retroarch_config(
+        file: &Option<PathBuf>,
+        appendconfigs: &[PathBuf],
+    ) -> Result<Self> {
         let mut settings: Self = Self::new();
 
         // If no file was given, then search at `RetroArch` default locations for the file `retroarch.cfg`.
@@ -200,15 +778,20 @@ impl Settings {
         // The list of key names to search and extract.  Ignore all other.
         let mut keys_to_get: HashSet<String> = HashSet::new();
         keys_to_get.insert("libretro_directory".to_string());
+        keys_to_get.insert("libretro_info_path".to_string());
 
         let retroarch_config_map = retroarch::parse_retroarch_config(
             &settings.retroarch_config,
+            appendconfigs,
             &keys_to_get,
         )?;
 
         // Extract values.
         if let Some(value) = retroarch_config_map.get("libretro_directory") {
-            settings.libretro_directory = Some(PathBuf::from(value));
+            settings.libretro_directory = Some(vec![PathBuf::from(value)]);
+        }
+        if let Some(value) = retroarch_config_map.get("libretro_info_path") {
+            settings.libretro_info_directory = Some(PathBuf::from(value));
         }
 
         Ok(settings)
@@ -258,9 +841,8 @@ impl Settings {
             }
         };
 
-        let mut ini: ini::Ini = ini::Ini::new_cs();
-        ini.load(&file::to_str(settings.config.as_ref()))
-            .expect("Error in loading configuration file.");
+        let ini: ini::Ini =
+            Self::read_config_ini(settings.config.as_deref().unwrap())?;
 
         let section_names: Vec<String> = ini.sections();
 
@@ -275,20 +857,212 @@ impl Settings {
         if !cores_rules.is_empty() {
             settings.cores_rules.replace(cores_rules);
         }
+        let core_labels: IndexMap<String, String> =
+            Self::read_config_core_entries(&ini)
+                .into_iter()
+                .filter_map(|(alias, entry)| entry.label.map(|l| (alias, l)))
+                .collect();
+        if !core_labels.is_empty() {
+            settings.core_labels.replace(core_labels);
+        }
+        // ps2 = { libretro = "pcsx2", nice = -5 }
+        let core_options: IndexMap<String, rules::RuleOptions> =
+            Self::read_config_core_entries(&ini)
+                .into_values()
+                .filter(|entry| entry.nice.is_some() || entry.cpus.is_some())
+                .map(|entry| {
+                    (
+                        coreinfo::core_name(&entry.libretro),
+                        rules::RuleOptions {
+                            nice: entry.nice,
+                            cpus: entry.cpus,
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect();
+        if !core_options.is_empty() {
+            settings.core_options.replace(core_options);
+        }
+
+        // [romsets]
+        // mslug = fbneo
+        let romsets: IndexMap<String, PathBuf> =
+            Self::read_config_romsets(&settings.cores_rules, &ini)?;
+        if !romsets.is_empty() {
+            settings.romsets.replace(romsets);
+        }
 
         // [.smc .sfc]
         // core = snes
         // libretro = snes9x
-        let extension_rules: IndexMap<String, PathBuf> =
-            Self::read_config_extension_rules(
-                &settings.cores_rules,
-                &ini,
-                &section_names,
-            );
+        let mut extension_rules: IndexMap<String, PathBuf> =
+            Self::read_config_cores_extension_rules(&ini);
+        extension_rules.extend(Self::read_config_extension_rules(
+            &settings.cores_rules,
+            &ini,
+            &section_names,
+        ));
         if !extension_rules.is_empty() {
             settings.extension_rules.replace(extension_rules);
         }
 
+        // [retroarch]
+        // video_driver = vulkan
+        let retroarch_overrides: IndexMap<String, String> =
+            Self::read_config_section(&ini, "retroarch");
+        if !retroarch_overrides.is_empty() {
+            settings.retroarch_overrides.replace(retroarch_overrides);
+        }
+
+        // [preflight]
+        // save_directories = fail
+        let preflight: IndexMap<String, String> =
+            Self::read_config_section(&ini, "preflight");
+        if !preflight.is_empty() {
+            settings
+                .preflight
+                .replace(preflight::Checks::from_section(&preflight)?);
+        }
+
+        // [policy]
+        // blocked_cores = psx
+        let policy: IndexMap<String, String> =
+            Self::read_config_section(&ini, "policy");
+        if !policy.is_empty() {
+            settings
+                .policy
+                .replace(policy::Policy::from_section(&policy)?);
+        }
+
+        // [lock]
+        // directories = ~/roms/kids
+        let lock: IndexMap<String, String> =
+            Self::read_config_section(&ini, "lock");
+        if !lock.is_empty() {
+            settings.lock.replace(lock::Lock::from_section(&lock)?);
+        }
+
+        // [profile:night]
+        // when = "hour>=22 or hour<6"
+        let profiles: Vec<profiles::Profile> = section_names
+            .iter()
+            .filter_map(|section| {
+                section.strip_prefix(profiles::SECTION_PREFIX).map(|name| {
+                    profiles::Profile::from_section(
+                        name,
+                        &Self::read_config_section(&ini, section),
+                    )
+                })
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        if !profiles.is_empty() {
+            settings.profiles.replace(profiles);
+        }
+
+        // [preset:sharp-pixels]
+        // snes9x_overscan = disabled
+        let presets: Vec<presets::Preset> = section_names
+            .iter()
+            .filter_map(|section| {
+                section.strip_prefix(presets::SECTION_PREFIX).map(|name| {
+                    presets::Preset::from_section(
+                        name,
+                        &Self::read_config_section(&ini, section),
+                    )
+                })
+            })
+            .collect();
+        if !presets.is_empty() {
+            settings.presets.replace(presets);
+        }
+
+        // [coreset:nightly]
+        // libretro_directory = ~/.config/retroarch-nightly/cores
+        let coresets: Vec<coresets::CoreSet> = section_names
+            .iter()
+            .filter_map(|section| {
+                section.strip_prefix(coresets::SECTION_PREFIX).map(|name| {
+                    coresets::CoreSet::from_section(
+                        name,
+                        &Self::read_config_section(&ini, section),
+                    )
+                })
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        if !coresets.is_empty() {
+            settings.coresets.replace(coresets);
+        }
+
+        // [remote:htpc]
+        // map./home/user/roms = /media/roms
+        let remotes: Vec<remotes::Remote> = section_names
+            .iter()
+            .filter_map(|section| {
+                section.strip_prefix(remotes::SECTION_PREFIX).map(|host| {
+                    remotes::Remote::from_section(
+                        host,
+                        &Self::read_config_section(&ini, section),
+                    )
+                })
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        if !remotes.is_empty() {
+            settings.remotes.replace(remotes);
+        }
+
+        // [env:mupen64plus_next]
+        // __GL_THREADED_OPTIMIZATIONS = 0
+        let core_env: IndexMap<String, IndexMap<String, String>> =
+            section_names
+                .iter()
+                .filter_map(|section| {
+                    section.strip_prefix(coreenv::SECTION_PREFIX).map(|core| {
+                        (
+                            core.to_string(),
+                            Self::read_config_section(&ini, section),
+                        )
+                    })
+                })
+                .collect();
+        if !core_env.is_empty() {
+            settings.core_env.replace(core_env);
+        }
+
+        // [stdin]
+        // strip_prefix = ./
+        let stdin: IndexMap<String, String> =
+            Self::read_config_section(&ini, stdinmap::SECTION);
+        if !stdin.is_empty() {
+            settings
+                .stdin_rules
+                .replace(stdinmap::StdinRules::from_section(&stdin)?);
+        }
+
+        // [events]
+        // mqtt_host = localhost
+        let events: IndexMap<String, String> =
+            Self::read_config_section(&ini, "events");
+        if !events.is_empty() {
+            settings.events.replace(events);
+        }
+
+        // [databases]
+        // snes = ~/libretro-database/metadat/no-intro/snes.dat
+        let databases: Vec<PathBuf> =
+            Self::read_config_section(&ini, "databases")
+                .values()
+                .filter(|file| !file.is_empty())
+                .map(|file| file::tilde(Path::new(file)))
+                .collect();
+        if !databases.is_empty() {
+            settings.databases.replace(databases);
+        }
+
+        // [paths]
+        // roms = ~/roms
+        let rom_roots: Vec<PathBuf> = Self::read_config_rom_roots(&ini);
+
         // [/home/user/roms/genesis_wide]
         // core = mdwide
         let directory_rules: IndexMap<String, PathBuf> =
@@ -296,11 +1070,24 @@ impl Settings {
                 &settings.cores_rules,
                 &ini,
                 &section_names,
+                &rom_roots,
             );
         if !directory_rules.is_empty() {
             settings.directory_rules.replace(directory_rules);
         }
 
+        // [.n64 .z64]
+        // workdir = ~/roms/n64
+        // env.MESA_LOADER_DRIVER_OVERRIDE = zink
+        let rule_options: IndexMap<String, rules::RuleOptions> =
+            Self::read_config_rule_options(&ini, &section_names, &rom_roots);
+        if !rule_options.is_empty() {
+            settings.rule_options.replace(rule_options);
+        }
+        if !rom_roots.is_empty() {
+            settings.rom_roots.replace(rom_roots);
+        }
+
         Ok(settings)
     }
 
@@ -343,6 +1130,12 @@ impl Settings {
             }
             if let Some(value) = ini.get("options", "retroarch") {
                 settings.retroarch = Some(PathBuf::from(value));
+                settings.retroarch_origin = Some(discovery::Origin::Config);
+            }
+            // Searched for the executable, if `retroarch` is not set.
+            if let Some(value) = ini.get("options", "retroarch_directory") {
+                settings.retroarch_directory =
+                    Some(env::split_paths(&value).collect());
             }
             if let Some(value) = ini.get("options", "retroarch_config") {
                 settings.retroarch_config = Some(PathBuf::from(value));
@@ -350,15 +1143,131 @@ impl Settings {
             if let Some(value) = ini.get("options", "libretro") {
                 settings.libretro = Some(PathBuf::from(value));
             }
+            // Multiple directories are separated by colons like in `$PATH`.
             if let Some(value) = ini.get("options", "libretro_directory") {
-                settings.libretro_directory = Some(PathBuf::from(value));
+                settings.libretro_directory =
+                    Some(env::split_paths(&value).collect());
+            }
+            if let Some(value) = ini.get("options", "libretro_info_directory")
+            {
+                settings.libretro_info_directory = Some(PathBuf::from(value));
             }
             if let Some(value) = ini.get("options", "core") {
                 settings.core = Some(value);
             }
+            if let Some(value) = ini.get("options", "fallback_core") {
+                settings.fallback_core = Some(value);
+            }
             if let Some(value) = ini.get("options", "filter") {
                 settings.filter = Some(vec![value]);
             }
+            if let Some(value) = ini.get("options", "exclude") {
+                settings.exclude = Some(vec![value]);
+            }
+            if let Some(value) = ini.getuint("options", "limit")? {
+                settings.limit = Some(usize::try_from(value)?);
+            }
+            if let Some(value) = ini.getuint("options", "offset")? {
+                settings.offset = Some(usize::try_from(value)?);
+            }
+            if let Some(value) = ini.get("options", "highlander_scope") {
+                settings.highlander_scope = Some(value.parse()?);
+            }
+            if let Some(value) = ini.get("options", "match") {
+                settings.match_anchor = Some(value.parse()?);
+            }
+            if let Some(value) = ini.get("options", "escape") {
+                settings.escape = Some(value.parse()?);
+            }
+            if let Some(value) = ini.get("options", "sort") {
+                settings.sort = Some(value.parse()?);
+            }
+            if let Some(value) = ini.getboolcoerce("options", "ai_service")? {
+                settings.ai_service = Some(value);
+            }
+            if let Some(value) = ini.get("options", "ai_service_mode") {
+                settings.ai_service_mode = Some(value.parse()?);
+            }
+            if let Some(value) = ini.get("options", "ai_service_url") {
+                settings.ai_service_url = Some(value);
+            }
+            if let Some(value) = ini.get("options", "volume") {
+                settings.volume = Some(audio::parse_volume(&value)?);
+            }
+            if let Some(value) = ini.getboolcoerce("options", "mute")? {
+                settings.mute = Some(value);
+            }
+            if let Some(value) = ini.get("options", "mixer_restore_command") {
+                settings.mixer_restore_command = Some(value);
+            }
+            if let Some(value) = ini.getboolcoerce("options", "fuzzy")? {
+                settings.fuzzy = Some(value);
+            }
+            if let Some(value) = ini.getboolcoerce("options", "fail_fast")? {
+                settings.fail_fast = Some(value);
+            }
+            if let Some(value) = ini.get("options", "exit_policy") {
+                settings.exit_policy = Some(value.parse()?);
+            }
+            if let Some(value) = ini.get("options", "token_file") {
+                settings.token_file = Some(PathBuf::from(value));
+            }
+            if let Some(value) = ini.get("options", "hotkey_device") {
+                settings.hotkey_device = Some(PathBuf::from(value));
+            }
+            if let Some(value) = ini.get("options", "hotkey_buttons") {
+                settings.hotkey_buttons = Some(value);
+            }
+            if let Some(value) = ini.get("options", "tts_command") {
+                settings.tts_command = Some(value);
+            }
+            if let Some(value) =
+                ini.getboolcoerce("options", "env_defaults")?
+            {
+                settings.env_defaults = Some(value);
+            }
+            if let Some(value) = ini.get("options", "selector_command") {
+                settings.selector_command = Some(value);
+            }
+            let spawner = spawner::Spawner {
+                kind: match ini.get("options", "spawn") {
+                    Some(value) => value.parse()?,
+                    None => spawner::Kind::default(),
+                },
+                memory_max: ini.get("options", "memory_max"),
+                cpu_quota: ini.get("options", "cpu_quota"),
+                slice: ini.get("options", "slice"),
+            };
+            if spawner.kind == spawner::Kind::Direct && spawner.has_limits() {
+                return Err(
+                    "Options memory_max, cpu_quota and slice require \
+                            spawn = systemd-run."
+                        .into(),
+                );
+            }
+            if ini.get("options", "spawn").is_some() || spawner.has_limits() {
+                settings.spawner = Some(spawner);
+            }
+            if let Some(value) = ini.get("options", "isolated") {
+                settings.isolated = Some(PathBuf::from(value));
+            }
+            let global_options =
+                rules::RuleOptions::from_section(ini, "options");
+            if global_options.workdir.is_some() {
+                settings.workdir = global_options.workdir;
+            }
+            if global_options.coreset.is_some() {
+                settings.coreset = global_options.coreset;
+            }
+            if global_options.nice.is_some() {
+                settings.nice = global_options.nice;
+            }
+            if global_options.cpus.is_some() {
+                settings.cpus = global_options.cpus;
+            }
+            if !global_options.env.is_empty() {
+                settings.env = Some(global_options.env);
+            }
             if let Some(value) = ini.getboolcoerce("options", "strict")? {
                 settings.strict = Some(value);
             }
@@ -373,6 +1282,12 @@ impl Settings {
             if let Some(value) = ini.getboolcoerce("options", "list_cores")? {
                 settings.list_cores = Some(value);
             }
+            if let Some(value) = ini.getboolcoerce("options", "long")? {
+                settings.long = Some(value);
+            }
+            if let Some(value) = ini.get("options", "format") {
+                settings.format = Some(value);
+            }
             if let Some(value) = ini.getboolcoerce("options", "fullscreen")? {
                 settings.fullscreen = Some(value);
             }
@@ -385,6 +1300,59 @@ impl Settings {
             if let Some(value) = ini.getboolcoerce("options", "nostdin")? {
                 settings.nostdin = Some(value);
             }
+            if let Some(value) = ini.getuint("options", "stdin_timeout")? {
+                settings.stdin_timeout = Some(value);
+            }
+            if let Some(value) = ini.getuint("options", "startup_timeout")? {
+                settings.startup_timeout = Some(value);
+            }
+            if let Some(value) = ini.getuint("options", "debounce")? {
+                settings.debounce = Some(value);
+            }
+            if let Some(value) = ini.getuint("options", "timeout")? {
+                settings.timeout = Some(value);
+            }
+            if let Some(value) = ini.getuint("options", "cache_days")? {
+                settings.cache_days = Some(value);
+            }
+            if let Some(value) = ini.getuint("options", "suggestions")? {
+                settings.suggestions = Some(value);
+            }
+            if let Some(value) =
+                ini.getboolcoerce("options", "keep_duplicates")?
+            {
+                settings.keep_duplicates = Some(value);
+            }
+            if let Some(value) = ini.getboolcoerce("options", "unique")? {
+                settings.unique = Some(value);
+            }
+            if let Some(value) =
+                ini.getboolcoerce("options", "usage_counters")?
+            {
+                settings.usage_counters = Some(value);
+            }
+            if let Some(value) = ini.getboolcoerce("options", "history")? {
+                settings.history = Some(value);
+            }
+            if let Some(value) = ini.getboolcoerce("options", "core_lock")? {
+                settings.core_lock = Some(value);
+            }
+            if let Some(value) = ini.getboolcoerce("options", "json")? {
+                settings.json = Some(value);
+            }
+            if let Some(value) = ini.getboolcoerce("options", "notify")? {
+                settings.notify = Some(value);
+            }
+            if let Some(value) =
+                ini.getboolcoerce("options", "pause_on_suspend")?
+            {
+                settings.pause_on_suspend = Some(value);
+            }
+            if let Some(value) =
+                ini.getboolcoerce("options", "keep_overrides")?
+            {
+                settings.keep_overrides = Some(value);
+            }
         }
 
         Ok(())
@@ -398,27 +1366,197 @@ impl Settings {
     /// snes = snes9x
     /// ```
     fn read_config_cores_rules(ini: &ini::Ini) -> IndexMap<String, PathBuf> {
-        let mut cores_rules: IndexMap<String, PathBuf> = IndexMap::new();
+        Self::read_config_core_entries(ini)
+            .into_iter()
+            .map(|(alias, entry)| (alias, entry.libretro))
+            .collect()
+    }
 
-        if let Some(cores) = ini.get_map().unwrap_or_default().get("cores") {
-            // Get valid entries only and convert to `(String, String)`.
-            for (core_alias, libretro_path) in cores
-                .iter()
-                .filter(|(_, v)| {
-                    !v.as_ref().unwrap_or(&"".to_string()).is_empty()
-                })
-                .map(|(k, v)| (k.to_string(), v.as_ref().unwrap()))
-            {
+    /// Replace the names of command presets like `@tv` in the commandline arguments `args` with
+    /// the arguments from section `[commands]` of the user config.  The user config is only read
+    /// if any preset is given.
+    ///
+    /// ```ini
+    /// [commands]
+    /// tv = --fullscreen --profile tv
+    /// ```
+    fn expand_command_presets(args: Vec<OsString>) -> Result<Vec<OsString>> {
+        if !commands::has_presets(&args) {
+            return Ok(args);
+        }
+
+        let presets: IndexMap<String, String> = match commands::config_path(
+            &args,
+        )
+        .map(|path| file::tilde(&path))
+        {
+            Some(path) if path.is_file() => Self::read_config_section(
+                &Self::read_config_ini(&path)?,
+                commands::SECTION,
+            ),
+            _ => IndexMap::new(),
+        };
+
+        Ok(commands::expand(args, &presets)?)
+    }
+
+    /// Load the user config INI file.  Files of an older schema version are converted in memory,
+    /// comments are removed and variables like `${HOME}` are replaced by their value in the
+    /// environment.
+    fn read_config_ini(path: &Path) -> Result<ini::Ini> {
+        let text: String = fs::read_to_string(path)?;
+        let migrated: migration::Migrated = migration::migrate(&text)?;
+        let text: String = inisyntax::strip_comments(&migrated.text);
+        let expanded: String =
+            interpolate::expand(&text, |name| env::var(name).ok())
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        // Comments are removed already, a `#` or `;` which is left is part of a value.
+        let mut ini: ini::Ini = ini::Ini::new_cs();
+        ini.set_comment_symbols(&[]);
+        ini.read(expanded)?;
+        for value in ini
+            .get_mut_map()
+            .values_mut()
+            .flat_map(|section| section.values_mut())
+            .flatten()
+        {
+            *value = inisyntax::unquote(value).to_string();
+        }
+
+        Ok(ini)
+    }
+
+    /// Read all aliases in section `[cores]` including their optional label and extensions from
+    /// inline tables.  Invalid entries are skipped.
+    ///
+    /// ```ini
+    /// [cores]
+    /// snes = { libretro = "snes9x", label = "Super Nintendo", extensions = "smc sfc" }
+    /// ```
+    fn read_config_core_entries(
+        ini: &ini::Ini,
+    ) -> IndexMap<String, rules::CoreEntry> {
+        let mut entries: IndexMap<String, rules::CoreEntry> = IndexMap::new();
+
+        if let Some(cores) = ini.get_map_ref().get("cores") {
+            // Get valid entries only and convert to `(String, CoreEntry)`.
+            for (core_alias, entry) in cores.iter().filter_map(|(k, v)| {
+                v.as_deref()
+                    .and_then(rules::CoreEntry::parse)
+                    .map(|e| (k, e))
+            }) {
                 for alias in core_alias.split_whitespace() {
-                    cores_rules.insert(
-                        alias.to_string(),
-                        PathBuf::from(libretro_path),
-                    );
+                    entries.insert(alias.to_string(), entry.clone());
                 }
             }
         }
 
-        cores_rules
+        entries
+    }
+
+    /// Build extension rules from the `extensions` declared by aliases in `[cores]`.  These have
+    /// lower priority than the regular extension rules in sections like `[.smc .sfc]`.
+    fn read_config_cores_extension_rules(
+        ini: &ini::Ini,
+    ) -> IndexMap<String, PathBuf> {
+        let mut extension_rules: IndexMap<String, PathBuf> = IndexMap::new();
+
+        for entry in Self::read_config_core_entries(ini).values() {
+            for extension in &entry.extensions {
+                extension_rules
+                    .entry(extension.to_string())
+                    .or_insert_with(|| entry.libretro.clone());
+            }
+        }
+
+        extension_rules
+    }
+
+    /// Read the cores for single games by their name from section `[romsets]`, such as for arcade
+    /// sets which only run in some core.  The entries of a compatibility list in `romsets_file` of
+    /// `[options]` are read first, so the section can correct them.  A core is an alias from
+    /// `[cores]` or a `libretro` itself.
+    ///
+    /// ```ini
+    /// [romsets]
+    /// mslug = fbneo
+    /// ```
+    fn read_config_romsets(
+        cores_rules: &Option<IndexMap<String, PathBuf>>,
+        ini: &ini::Ini,
+    ) -> Result<IndexMap<String, PathBuf>> {
+        let mut romsets: IndexMap<String, String> = IndexMap::new();
+        if let Some(list) = ini.get("options", "romsets_file") {
+            let list: PathBuf = file::tilde(Path::new(&list));
+            let content: String = fs::read_to_string(&list).map_err(|e| {
+                format!(
+                    "Could not read romsets_file {}: {}",
+                    list.display(),
+                    e
+                )
+            })?;
+            romsets.extend(romsets::parse_list(&content));
+        }
+        romsets.extend(
+            Self::read_config_section(ini, "romsets")
+                .into_iter()
+                .map(|(name, core)| (name.to_lowercase(), core)),
+        );
+
+        Ok(romsets
+            .into_iter()
+            .map(|(name, core)| {
+                let libretro: PathBuf = cores_rules
+                    .as_ref()
+                    .and_then(|rules| rules.get(&core))
+                    .cloned()
+                    .unwrap_or_else(|| PathBuf::from(core));
+                (name, libretro)
+            })
+            .collect())
+    }
+
+    /// Read all keys of a plain section like `[retroarch]` or `[events]` from ini.  Keys without a
+    /// value are ignored.  Keys in `[retroarch]` are settings in the format of `retroarch.cfg`,
+    /// which are applied on top of the regular `RetroArch` configuration for each run.
+    ///
+    /// ```ini
+    /// [retroarch]
+    /// video_driver = vulkan
+    /// ```
+    fn read_config_section(
+        ini: &ini::Ini,
+        section: &str,
+    ) -> IndexMap<String, String> {
+        ini.get_map_ref()
+            .get(section)
+            .map(|keys| {
+                keys.iter()
+                    .filter_map(|(k, v)| {
+                        v.as_ref().map(|v| (k.to_string(), v.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Read the directories of the ROM collection from section `[paths]`.  Each key names one root
+    /// and the order is kept.  The tilde is expanded.  Relative directory rules like `[snes/]` are
+    /// resolved against each of these roots, so the same config works on machines where the
+    /// collection lives at different places.
+    ///
+    /// ```ini
+    /// [paths]
+    /// roms = ~/roms
+    /// nas = /mnt/nas/roms
+    /// ```
+    fn read_config_rom_roots(ini: &ini::Ini) -> Vec<PathBuf> {
+        Self::read_config_section(ini, "paths")
+            .values()
+            .filter(|root| !root.is_empty())
+            .map(|root| file::tilde(Path::new(root)))
+            .collect()
     }
 
     /// Read in all rules for the extensions from ini.  `extension_rules` start with a dot in their
@@ -475,7 +1613,8 @@ impl Settings {
     /// somewhere in their section name like `[/emulators/roms/psx]`.  The starting tilde will be
     /// expanded to users home directory.  Any `core` rule will be resolved to a `libretro` path by
     /// looking up corresponding alias in `cores_rules`.  An existing `libretro` rule have higher
-    /// priority over `core` rule.
+    /// priority over `core` rule.  Relative directories like `[snes/]` are added once for each of
+    /// the `roots` from section `[paths]`.
     ///
     /// ```ini
     /// [/home/user/roms/genesis_wide]
@@ -485,6 +1624,7 @@ impl Settings {
         cores_rules: &Option<IndexMap<String, PathBuf>>,
         ini: &ini::Ini,
         section_names: &[String],
+        roots: &[PathBuf],
     ) -> IndexMap<String, PathBuf> {
         let mut directory_rules: IndexMap<String, PathBuf> = IndexMap::new();
 
@@ -495,7 +1635,11 @@ impl Settings {
         let dir_pattern: Vec<(String, String)> = section_names
             .iter()
             .filter(|dir| dir.contains('/'))
-            .map(|dir| (dir.to_string(), shellexpand::tilde(dir).to_string()))
+            .flat_map(|dir| {
+                directory_patterns(dir, roots)
+                    .into_iter()
+                    .map(|expanded| (dir.to_string(), expanded))
+            })
             .collect();
 
         // [/home/user/roms/genesis_wide*]
@@ -521,12 +1665,53 @@ impl Settings {
         directory_rules
     }
 
-    /// Merge current `Settings` with a new one.  Overwrite values only, if the new value is
-    /// `Some`. The `games` key is different, as the new list in `games` will be prepended to
-    /// current existing list.
-    pub fn update_from(&mut self, overwrite: Self) {
-        if !overwrite.games.is_empty() {
-            if self.games.is_empty() {
+    /// Read the additional options of all extension and directory rules, like `workdir` or
+    /// `env.KEY`.  The keys of the returned map are the same as in `extension_rules` and
+    /// `directory_rules`, which is the extension without leading dot or the expanded directory.
+    /// Rules without any of these options are not included.
+    ///
+    /// ```ini
+    /// [.n64 .z64]
+    /// env.MESA_LOADER_DRIVER_OVERRIDE = zink
+    /// ```
+    fn read_config_rule_options(
+        ini: &ini::Ini,
+        section_names: &[String],
+        roots: &[PathBuf],
+    ) -> IndexMap<String, rules::RuleOptions> {
+        let mut rule_options: IndexMap<String, rules::RuleOptions> =
+            IndexMap::new();
+
+        for section in section_names {
+            let options = rules::RuleOptions::from_section(ini, section);
+            if options.is_empty() {
+                continue;
+            }
+
+            if section.starts_with('.') {
+                for ext_pattern in section
+                    .split_whitespace()
+                    .map(|e| e.split_at(1).1.to_string())
+                {
+                    rule_options.insert(ext_pattern, options.clone());
+                }
+            } else if section.contains('/') {
+                for pattern in directory_patterns(section, roots) {
+                    rule_options.insert(pattern, options.clone());
+                }
+            }
+        }
+
+        rule_options
+    }
+
+    /// Merge current `Settings` with a new one.  Overwrite values only, if the new value is
+    /// `Some`. The `games` key is different, as the new list in `games` will be prepended to
+    /// current existing list.
+    pub fn update_from(&mut self, overwrite: Self) {
+        self.known_extensions = OnceLock::new();
+        if !overwrite.games.is_empty() {
+            if self.games.is_empty() {
                 self.games = overwrite.games;
             } else {
                 let mut combined: Vec<PathBuf> = overwrite.games;
@@ -549,6 +1734,10 @@ impl Settings {
         }
         if overwrite.retroarch.is_some() {
             self.retroarch = overwrite.retroarch;
+            self.retroarch_origin = overwrite.retroarch_origin;
+        }
+        if overwrite.retroarch_directory.is_some() {
+            self.retroarch_directory = overwrite.retroarch_directory;
         }
         if overwrite.retroarch_config.is_some() {
             self.retroarch_config = overwrite.retroarch_config;
@@ -559,12 +1748,67 @@ impl Settings {
         if overwrite.libretro_directory.is_some() {
             self.libretro_directory = overwrite.libretro_directory;
         }
+        if overwrite.libretro_info_directory.is_some() {
+            self.libretro_info_directory = overwrite.libretro_info_directory;
+        }
         if overwrite.core.is_some() {
             self.core = overwrite.core;
         }
         if overwrite.filter.is_some() {
             self.filter = overwrite.filter;
         }
+        if overwrite.exclude.is_some() {
+            self.exclude = overwrite.exclude;
+        }
+        if overwrite.workdir.is_some() {
+            self.workdir = overwrite.workdir;
+        }
+        if overwrite.nice.is_some() {
+            self.nice = overwrite.nice;
+        }
+        if overwrite.cpus.is_some() {
+            self.cpus = overwrite.cpus;
+        }
+        if overwrite.core_options.is_some() {
+            self.core_options = overwrite.core_options;
+        }
+        if overwrite.match_anchor.is_some() {
+            self.match_anchor = overwrite.match_anchor;
+        }
+        if overwrite.highlander_scope.is_some() {
+            self.highlander_scope = overwrite.highlander_scope;
+        }
+        if overwrite.serve.is_some() {
+            self.serve = overwrite.serve;
+        }
+        if overwrite.token_file.is_some() {
+            self.token_file = overwrite.token_file;
+        }
+        if overwrite.hotkey_listen.is_some() {
+            self.hotkey_listen = overwrite.hotkey_listen;
+        }
+        if overwrite.hotkey_device.is_some() {
+            self.hotkey_device = overwrite.hotkey_device;
+        }
+        if overwrite.hotkey_buttons.is_some() {
+            self.hotkey_buttons = overwrite.hotkey_buttons;
+        }
+        if overwrite.selector_command.is_some() {
+            self.selector_command = overwrite.selector_command;
+        }
+        if overwrite.tts_command.is_some() {
+            self.tts_command = overwrite.tts_command;
+        }
+        if overwrite.retroarch_stub.is_some() {
+            self.retroarch_stub = overwrite.retroarch_stub;
+        }
+        if overwrite.isolated.is_some() {
+            self.isolated = overwrite.isolated;
+        }
+        // Environment variables are merged per key, where the new value wins.
+        if let Some(env) = overwrite.env {
+            self.env.get_or_insert_with(IndexMap::new).extend(env);
+        }
         if overwrite.strict.is_some() {
             self.strict = overwrite.strict;
         }
@@ -577,9 +1821,84 @@ impl Settings {
         if overwrite.list_cores.is_some() {
             self.list_cores = overwrite.list_cores;
         }
+        if overwrite.long.is_some() {
+            self.long = overwrite.long;
+        }
+        if overwrite.format.is_some() {
+            self.format = overwrite.format;
+        }
+        if overwrite.index.is_some() {
+            self.index = overwrite.index;
+        }
+        if overwrite.fuzzy.is_some() {
+            self.fuzzy = overwrite.fuzzy;
+        }
+        if overwrite.interactive.is_some() {
+            self.interactive = overwrite.interactive;
+        }
+        if overwrite.choose.is_some() {
+            self.choose = overwrite.choose;
+        }
+        if overwrite.fzf.is_some() {
+            self.fzf = overwrite.fzf;
+        }
+        if overwrite.list_games.is_some() {
+            self.list_games = overwrite.list_games;
+        }
+        if overwrite.count.is_some() {
+            self.count = overwrite.count;
+        }
+        if overwrite.relative.is_some() {
+            self.relative = overwrite.relative;
+        }
+        if overwrite.escape.is_some() {
+            self.escape = overwrite.escape;
+        }
+        if overwrite.sort.is_some() {
+            self.sort = overwrite.sort;
+        }
+        if overwrite.ai_service.is_some() {
+            self.ai_service = overwrite.ai_service;
+        }
+        if overwrite.ai_service_mode.is_some() {
+            self.ai_service_mode = overwrite.ai_service_mode;
+        }
+        if overwrite.ai_service_url.is_some() {
+            self.ai_service_url = overwrite.ai_service_url;
+        }
+        if overwrite.volume.is_some() {
+            self.volume = overwrite.volume;
+        }
+        if overwrite.mute.is_some() {
+            self.mute = overwrite.mute;
+        }
+        if overwrite.mixer_restore_command.is_some() {
+            self.mixer_restore_command = overwrite.mixer_restore_command;
+        }
+        if overwrite.matrix.is_some() {
+            self.matrix = overwrite.matrix;
+        }
+        if overwrite.which_retroarch.is_some() {
+            self.which_retroarch = overwrite.which_retroarch;
+        }
+        if overwrite.which_core.is_some() {
+            self.which_core = overwrite.which_core;
+        }
+        if overwrite.complete_games.is_some() {
+            self.complete_games = overwrite.complete_games;
+        }
+        if overwrite.fzf_snippet.is_some() {
+            self.fzf_snippet = overwrite.fzf_snippet;
+        }
+        if overwrite.launch_from_fzf.is_some() {
+            self.launch_from_fzf = overwrite.launch_from_fzf;
+        }
         if overwrite.fullscreen.is_some() {
             self.fullscreen = overwrite.fullscreen;
         }
+        if overwrite.menu_load.is_some() {
+            self.menu_load = overwrite.menu_load;
+        }
         if overwrite.highlander.is_some() {
             self.highlander = overwrite.highlander;
         }
@@ -589,6 +1908,65 @@ impl Settings {
         if overwrite.config_path.is_some() {
             self.config_path = overwrite.config_path;
         }
+        if overwrite.organize.is_some() {
+            self.organize = overwrite.organize;
+        }
+        if overwrite.usage_counters.is_some() {
+            self.usage_counters = overwrite.usage_counters;
+        }
+        if overwrite.usage_report.is_some() {
+            self.usage_report = overwrite.usage_report;
+        }
+        if overwrite.history.is_some() {
+            self.history = overwrite.history;
+        }
+        if overwrite.core_lock.is_some() {
+            self.core_lock = overwrite.core_lock;
+        }
+        if overwrite.ignore_core_lock.is_some() {
+            self.ignore_core_lock = overwrite.ignore_core_lock;
+        }
+        if overwrite.verify_cores.is_some() {
+            self.verify_cores = overwrite.verify_cores;
+        }
+        if overwrite.prune_cores.is_some() {
+            self.prune_cores = overwrite.prune_cores;
+        }
+        if overwrite.tag.is_some() {
+            self.tag = overwrite.tag;
+        }
+        if overwrite.note.is_some() {
+            self.note = overwrite.note;
+        }
+        if overwrite.load_state.is_some() {
+            self.load_state = overwrite.load_state;
+        }
+        if overwrite.history_list.is_some() {
+            self.history_list = overwrite.history_list;
+        }
+        if overwrite.stats.is_some() {
+            self.stats = overwrite.stats;
+        }
+        if overwrite.rename_preview.is_some() {
+            self.rename_preview = overwrite.rename_preview;
+        }
+        if overwrite.rename.is_some() {
+            self.rename = overwrite.rename;
+        }
+        if overwrite.yes.is_some() {
+            self.yes = overwrite.yes;
+        }
+        if let Some(databases) = overwrite.databases {
+            self.databases
+                .get_or_insert_with(Vec::new)
+                .extend(databases);
+        }
+        if overwrite.migrate_config.is_some() {
+            self.migrate_config = overwrite.migrate_config;
+        }
+        if overwrite.undo_config.is_some() {
+            self.undo_config = overwrite.undo_config;
+        }
         if overwrite.noconfig.is_some() {
             self.noconfig = overwrite.noconfig;
         }
@@ -598,26 +1976,190 @@ impl Settings {
         if overwrite.nostdin.is_some() {
             self.nostdin = overwrite.nostdin;
         }
+        if overwrite.null.is_some() {
+            self.null = overwrite.null;
+        }
+        if overwrite.recursive.is_some() {
+            self.recursive = overwrite.recursive;
+        }
+        if overwrite.games_from.is_some() {
+            self.games_from = overwrite.games_from;
+        }
+        if overwrite.stdin_timeout.is_some() {
+            self.stdin_timeout = overwrite.stdin_timeout;
+        }
+        if overwrite.startup_timeout.is_some() {
+            self.startup_timeout = overwrite.startup_timeout;
+        }
+        if overwrite.debounce.is_some() {
+            self.debounce = overwrite.debounce;
+        }
+        if overwrite.timeout.is_some() {
+            self.timeout = overwrite.timeout;
+        }
+        if overwrite.cache_days.is_some() {
+            self.cache_days = overwrite.cache_days;
+        }
+        if overwrite.suggestions.is_some() {
+            self.suggestions = overwrite.suggestions;
+        }
+        if overwrite.keep_duplicates.is_some() {
+            self.keep_duplicates = overwrite.keep_duplicates;
+        }
+        if overwrite.unique.is_some() {
+            self.unique = overwrite.unique;
+        }
+        if overwrite.doctor.is_some() {
+            self.doctor = overwrite.doctor;
+        }
+        if overwrite.queue.is_some() {
+            self.queue = overwrite.queue;
+        }
+        if overwrite.resume_queue.is_some() {
+            self.resume_queue = overwrite.resume_queue;
+        }
+        if overwrite.fail_fast.is_some() {
+            self.fail_fast = overwrite.fail_fast;
+        }
+        if overwrite.exit_policy.is_some() {
+            self.exit_policy = overwrite.exit_policy;
+        }
+        if overwrite.export_list.is_some() {
+            self.export_list = overwrite.export_list;
+        }
+        if overwrite.json.is_some() {
+            self.json = overwrite.json;
+        }
+        if overwrite.limit.is_some() {
+            self.limit = overwrite.limit;
+        }
+        if overwrite.offset.is_some() {
+            self.offset = overwrite.offset;
+        }
+        if overwrite.fingerprint.is_some() {
+            self.fingerprint = overwrite.fingerprint;
+        }
+        if overwrite.print_env.is_some() {
+            self.print_env = overwrite.print_env;
+        }
+        if overwrite.notify.is_some() {
+            self.notify = overwrite.notify;
+        }
+        if overwrite.pause_on_suspend.is_some() {
+            self.pause_on_suspend = overwrite.pause_on_suspend;
+        }
+        if overwrite.keep_overrides.is_some() {
+            self.keep_overrides = overwrite.keep_overrides;
+        }
+        if overwrite.events.is_some() {
+            self.events = overwrite.events;
+        }
+        if overwrite.preflight.is_some() {
+            self.preflight = overwrite.preflight;
+        }
+        if overwrite.no_preflight.is_some() {
+            self.no_preflight = overwrite.no_preflight;
+        }
+        if overwrite.policy.is_some() {
+            self.policy = overwrite.policy;
+        }
+        if overwrite.force.is_some() {
+            self.force = overwrite.force;
+        }
+        if overwrite.ignore_policy.is_some() {
+            self.ignore_policy = overwrite.ignore_policy;
+        }
+        if overwrite.lock.is_some() {
+            self.lock = overwrite.lock;
+        }
+        if overwrite.locked.is_some() {
+            self.locked = overwrite.locked;
+        }
+        if overwrite.unlock.is_some() {
+            self.unlock = overwrite.unlock;
+        }
+        if overwrite.profiles.is_some() {
+            self.profiles = overwrite.profiles;
+        }
+        if overwrite.coreset.is_some() {
+            self.coreset = overwrite.coreset;
+        }
+        if overwrite.romsets.is_some() {
+            self.romsets = overwrite.romsets;
+        }
+        if overwrite.fallback_core.is_some() {
+            self.fallback_core = overwrite.fallback_core;
+        }
+        if overwrite.coresets.is_some() {
+            self.coresets = overwrite.coresets;
+        }
+        if overwrite.stdin_rom.is_some() {
+            self.stdin_rom = overwrite.stdin_rom;
+        }
+        if overwrite.remote.is_some() {
+            self.remote = overwrite.remote;
+        }
+        if overwrite.spawner.is_some() {
+            self.spawner = overwrite.spawner;
+        }
+        if overwrite.remotes.is_some() {
+            self.remotes = overwrite.remotes;
+        }
+        if overwrite.core_env.is_some() {
+            self.core_env = overwrite.core_env;
+        }
+        if overwrite.env_defaults.is_some() {
+            self.env_defaults = overwrite.env_defaults;
+        }
+        if overwrite.stdin_rules.is_some() {
+            self.stdin_rules = overwrite.stdin_rules;
+        }
+        if overwrite.profile.is_some() {
+            self.profile = overwrite.profile;
+        }
+        if overwrite.title.is_some() {
+            self.title = overwrite.title;
+        }
+        if overwrite.preset.is_some() {
+            self.preset = overwrite.preset;
+        }
+        if overwrite.presets.is_some() {
+            self.presets = overwrite.presets;
+        }
+        if overwrite.retroarch_overrides.is_some() {
+            self.retroarch_overrides = overwrite.retroarch_overrides;
+        }
 
         // Currenty, the IndexMap rules are just replaced.  In future they will be possibly
         // extended instead.
         if overwrite.cores_rules.is_some() {
             self.cores_rules = overwrite.cores_rules;
         }
+        if overwrite.core_labels.is_some() {
+            self.core_labels = overwrite.core_labels;
+        }
         if overwrite.extension_rules.is_some() {
             self.extension_rules = overwrite.extension_rules;
         }
         if overwrite.directory_rules.is_some() {
             self.directory_rules = overwrite.directory_rules;
         }
+        if overwrite.rule_options.is_some() {
+            self.rule_options = overwrite.rule_options;
+        }
+        if overwrite.rom_roots.is_some() {
+            self.rom_roots = overwrite.rom_roots;
+        }
     }
 
     /// Update current Settings from new Settings.  Replace the content only, if the old value is
     /// `None`.  Only a few keys are affected, currently `retroarch`, `retroarch_config`,
-    /// `libretro` and `libretro_directory`.
+    /// `libretro`, `libretro_directory` and `libretro_info_directory`.
     pub fn update_defaults_from(&mut self, overwrite: Self) {
+        self.known_extensions = OnceLock::new();
         if self.retroarch.is_none() {
             self.retroarch = overwrite.retroarch;
+            self.retroarch_origin = Some(discovery::Origin::Default);
         }
         if self.retroarch_config.is_none() {
             self.retroarch_config = overwrite.retroarch_config;
@@ -628,6 +2170,12 @@ impl Settings {
         if self.libretro_directory.is_none() {
             self.libretro_directory = overwrite.libretro_directory;
         }
+        if self.libretro_info_directory.is_none() {
+            self.libretro_info_directory = overwrite.libretro_info_directory;
+        }
+        if self.history.is_none() {
+            self.history = overwrite.history;
+        }
     }
 
     /// Build up the final `RetroArch` run command from the current Settings.  This is the command
@@ -635,109 +2183,249 @@ impl Settings {
     /// be wrapped up in a separate `RunCommand` struct, which itself includes the commandline to
     /// execute and a few more data.
     pub fn build_command(&self) -> Result<RunCommand, String> {
+        self.build_command_for(self.select_game())
+    }
+
+    /// Build up the `RetroArch` run command for a specific game instead of the selected one from
+    /// `games` list.  Otherwise the same as `build_command()`.
+    pub fn build_command_for(
+        &self,
+        selected_game: Option<PathBuf>,
+    ) -> Result<RunCommand, String> {
+        // `--coreset`
+        // The rules are matched against the full path of the game, like below.
+        let coreset: Option<&coresets::CoreSet> = self.coreset_for(
+            selected_game
+                .as_ref()
+                .map(|game| file::to_fullpath(game).unwrap_or(game.clone()))
+                .as_deref(),
+        )?;
+
         // `--retroarch`
-        let mut command: Command =
-            Command::new(&file::to_str(self.retroarch.as_ref()));
+        // With `--retroarch-stub` this program itself replaces `retroarch` and only records how
+        // it was called.
+        let mut command: Command = match &self.retroarch_stub {
+            Some(record) => {
+                let exe: PathBuf = env::current_exe().map_err(|e| {
+                    format!("Could not locate own executable: {}", e)
+                })?;
+                let mut command: Command = Command::new(exe);
+                command.env(
+                    crate::stub::RECORD_VAR,
+                    env::current_dir().unwrap_or_default().join(record),
+                );
+                command
+            }
+            None => Command::new(file::to_str(Self::retroarch_of(
+                coreset,
+                self.retroarch.as_ref(),
+            ))),
+        };
 
         // `game`
         // Get first entry of all games in the list, make it a full path and check if file exists.
-        let game: Option<PathBuf> = match self.select_game() {
-            Some(selected) => {
-                let path = file::to_fullpath(&selected);
-                match path {
-                    Some(ref p) => command.arg(p),
-                    None => {
-                        if self.is_norun() {
-                            command.arg(&selected)
-                        } else {
-                            let message = format!(
-                                "game file not found: {}",
-                                selected.display()
-                            );
-                            return Err(message);
-                        }
-                    }
-                };
-
-                if path.is_some() {
-                    path
-                } else if self.is_norun() {
-                    Some(selected)
-                } else {
-                    None
+        let game: Option<PathBuf> = match selected_game {
+            Some(selected) => match file::to_fullpath(&selected) {
+                Some(path) => Some(path),
+                None if self.is_norun() => Some(selected),
+                None => {
+                    return Err(format!(
+                        "game file not found: {}{}",
+                        selected.display(),
+                        self.suggest_games(&selected)
+                    ))
                 }
-            }
-            None => {
-                if self.norun.unwrap_or(false) {
-                    command.arg("");
-                    Some(PathBuf::from("".to_string()))
+            },
+            None if self.is_norun() => Some(PathBuf::new()),
+            None => return Err("No matching game available".into()),
+        };
+
+        // `--locked`
+        if let Some(game) = &game {
+            self.check_lock(game)?;
+        }
+
+        // `--libretro`
+        let libretro: PathBuf = self.resolve_libretro(game.as_deref())?;
+
+        // `.zst`
+        // The content given to `RetroArch`, which is the game itself or its decompressed copy.
+        let content: Option<PathBuf> = match &game {
+            Some(game) => Some(self.uncompressed_game(game, &libretro)?),
+            None => None,
+        };
+
+        // `--menu-load`
+        // `RetroArch` starts in its menu instead, the game is offered in a playlist.
+        match &content {
+            Some(content) if !self.is_menu_load() => command.arg(content),
+            _ => command.arg("--menu"),
+        };
+        if !self.is_menu_load() {
+            command.arg("--libretro");
+            command.arg(&libretro);
+        }
+
+        // `--workdir` and `--env`
+        let options: rules::RuleOptions = self.rule_options_for(
+            game.as_deref().unwrap_or_else(|| Path::new("")),
+        );
+        if let Some(dir) = &options.workdir {
+            command.current_dir(file::tilde(dir));
+        }
+        // `[env:core]`
+        // Variables for the core come first, so the rules and option `--env` can replace them.
+        command.envs(coreenv::for_core(
+            &coreinfo::core_name(&libretro),
+            self.core_env.as_ref(),
+            self.env_defaults.unwrap_or(true),
+        ));
+        command.envs(&options.env);
+
+        // `--isolated`
+        // `RetroArch` gets its own home directory and a private `retroarch.cfg`, which replaces
+        // the regular `--retroarch-config`.
+        let retroarch_config: Option<PathBuf> = match &self.isolated {
+            Some(dir) => {
+                let dir: PathBuf = file::tilde(dir);
+                command.envs(isolation::env_vars(&dir));
+                if self.is_norun() {
+                    Some(isolation::config_path(&dir))
                 } else {
-                    return Err("No matching game available".into());
+                    Some(
+                        isolation::prepare(
+                            &dir,
+                            Self::libretro_directories_of(
+                                coreset,
+                                self.libretro_directories(),
+                            )
+                            .first()
+                            .map(PathBuf::as_path),
+                            self.libretro_info_directory.as_deref(),
+                        )
+                        .map_err(|e| {
+                            format!(
+                                "Could not create isolated home {}: {}",
+                                dir.display(),
+                                e
+                            )
+                        })?,
+                    )
                 }
             }
+            None => self.retroarch_config.clone(),
         };
 
-        // `--libretro`
-        let mut libretro: Option<PathBuf> = self.libretro.clone();
+        // `--retroarch-config`
+        // Resolved to a fullpath, as the working directory of `retroarch` could be different.
+        if let Some(file) = &retroarch_config {
+            command.arg("--config");
+            command
+                .arg(file::to_fullpath(file).unwrap_or_else(|| file.clone()));
+        }
 
-        // `libretro` have higher priority over `core`, if present.  Otherwise lookup `core`, if
-        // available.
-        if libretro.is_none() {
-            // `--core`
-            if let Some(core) = &self.core {
-                match &self.cores_rules {
-                    Some(rules) => libretro = rules.get(core).cloned(),
-                    None => {
-                        return Err("No core rules found in `[cores]`.".into())
-                    }
-                };
+        // `--menu-load`
+        if self.is_menu_load() && !self.is_norun() {
+            if let Some(content) = &content {
+                self.write_menu_playlist(
+                    content,
+                    &libretro,
+                    retroarch_config.as_deref(),
+                )?;
             }
+        }
 
-            // Lookup and resolve from `[/directory]` rules
-            if libretro.is_none() && self.directory_rules.is_some() {
-                libretro = self.libretro_from_dir(
-                    game
-                        .as_ref()
-                        .expect("game required when building libretro path from directory rules."),
-                );
-            };
-            // Lookup and resolve from `[.ext]` rules
-            if libretro.is_none() && self.extension_rules.is_some() {
-                libretro = self.libretro_from_ext(
-                    game
-                        .as_ref()
-                        .expect("game required when building libretro path from extension rules."),
-                );
+        // `[retroarch]`
+        // All overrides are composed into a single append-config in the temporary directory of
+        // this process.  With `--norun` the file is only written, if it should be kept anyway.
+        self.check_profile_names()?;
+        let mut overrides: overrides::Overrides = self.overrides();
+        if let Some(game) = &game {
+            self.redirect_saves(
+                &mut overrides,
+                retroarch_config.as_deref(),
+                game,
+            )?;
+            self.apply_presets(
+                &mut overrides,
+                retroarch_config.as_deref(),
+                game,
+            )?;
+            // `--ai-service`
+            if let Some(enabled) = self.rule_options_for(game).ai_service {
+                overrides.extend(&aiservice::overrides(
+                    enabled,
+                    self.ai_service_mode,
+                    self.ai_service_url.as_deref(),
+                ));
+            }
+        }
+        let mut appendconfig: Option<PathBuf> = None;
+        if !overrides.is_empty() {
+            let dir: PathBuf = overrides::temp_dir();
+            let path: PathBuf = if self.is_norun() && !self.is_keep_overrides()
+            {
+                dir.join(overrides.file_name())
+            } else {
+                overrides::create_temp_dir()
+                    .and_then(|dir| overrides.write(&dir))
+                    .map_err(|e| {
+                        format!(
+                            "Could not write override file in {}: {}",
+                            dir.display(),
+                            e
+                        )
+                    })?
             };
+            command.arg("--appendconfig");
+            command.arg(&path);
+            appendconfig = Some(path);
         }
 
-        // At this point, the `libretro` path should be available, either given directly or by
-        // resolving rules from `core`.
-        if libretro.is_none() {
-            return Err("Path to `libretro` not set.".into());
+        // `[preflight]`
+        // The save directories of a remote machine cannot be checked.
+        if !self.is_norun() && self.remote.is_none() {
+            self.preflight(
+                &overrides,
+                retroarch_config.as_deref(),
+                game.as_deref().unwrap_or_else(|| Path::new("")),
+            )?;
         }
 
-        // Combine `--libretro_directory` and `--libretro`
-        // If the `libretro` itself is a relative path, then it will be combined with the given
-        // directory.  Otherwise the directory is ignored, as a fullpath of `libretro` takes
-        // precedence.
-        match retroarch::libretro_fullpath(
-            self.libretro_directory.clone(),
-            libretro.clone(),
-            "_libretro.so",
-        ) {
-            Some(fullpath) => {
-                libretro = Some(fullpath.clone());
-                command.arg("--libretro");
-                command.arg(fullpath);
-            }
-            None => return Err("No matching libretro core found".into()),
-        };
+        // `[policy]`
+        // The battery and memory of a remote machine cannot be checked.
+        if !self.is_norun()
+            && self.remote.is_none()
+            && !self.is_ignore_policy()
+        {
+            self.check_resources(&libretro)?;
+        }
 
-        // `--retroarch-config`
-        if let Some(file) = &self.retroarch_config {
-            command.arg("--config");
-            command.arg(file);
+        // `--load-state`
+        // The savestates of a remote machine cannot be looked up, only a given slot is loaded.
+        // Older versions of `retroarch` would abort with an unknown option `--entryslot`.
+        if let (Some(slot), Some(game), Some(content)) =
+            (self.load_state, &game, &content)
+        {
+            if self.remote.is_none() {
+                self.check_entryslot(coreset)?;
+            }
+            let slot: u32 = match (&self.remote, slot) {
+                (Some(_), savestates::Slot::Number(number)) => number,
+                (Some(_), savestates::Slot::Newest) => {
+                    return Err("Option --load-state requires a slot with \
+                                option --remote."
+                        .into())
+                }
+                (None, slot) => self.savestate_slot(
+                    slot,
+                    &overrides,
+                    retroarch_config.as_deref(),
+                    (game, content),
+                    &libretro,
+                )?,
+            };
+            command.arg("--entryslot").arg(slot.to_string());
         }
 
         // `--fullscreen`
@@ -746,63 +2434,635 @@ impl Settings {
         }
 
         // `--`
+        // Flags with a value already set above would be given twice, where `retroarch` silently
+        // uses only one of them.
         if !self.retroarch_arguments.is_empty() {
-            command.args(self.retroarch_arguments.iter());
+            let set_by_enjoy: Vec<String> = command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect();
+            let version: Option<retroarch::Version> =
+                if passthrough::needs_version(&self.retroarch_arguments) {
+                    retroarch::version(
+                        Self::retroarch_of(coreset, self.retroarch.as_ref())
+                            .map_or_else(|| Path::new(""), PathBuf::as_path),
+                    )
+                } else {
+                    None
+                };
+            for warning in passthrough::check(
+                &self.retroarch_arguments,
+                &set_by_enjoy,
+                version,
+            ) {
+                eprintln!("Warning: RetroArch argument: {}", warning);
+            }
+            command.args(Self::supported_arguments(
+                &self.retroarch_arguments,
+                version,
+            ));
+        }
+
+        // `--remote`
+        if let Some(target) = &self.remote {
+            command = self.remote_command(
+                target,
+                &command,
+                appendconfig.as_deref().map(|path| (path, &overrides)),
+            );
+        }
+
+        // `spawn`
+        // A remote `RetroArch` is started by the SSH server, not by a local service manager.
+        if let (Some(spawner), None) = (&self.spawner, &self.remote) {
+            command = spawner
+                .wrap(command, game.as_deref().unwrap_or(Path::new("")));
         }
 
         // Use `run.cmdline` to get the full command with all options to be executed.  `output`
         // needs to be updated manually, by catching the output when running the `cmdline`.
+        // `--nice` and `--cpus`
+        // A remote `RetroArch` is not a local process, which could be changed.
+        let scheduling: scheduling::Scheduling = match &self.remote {
+            Some(_) => scheduling::Scheduling::default(),
+            None => self.scheduling_for(&options, &libretro)?,
+        };
+
         let run = RunCommand {
             cmdline: command,
             game: game.unwrap_or_default(),
-            libretro: libretro.unwrap_or_default(),
-            output: None,
+            libretro,
+            scheduling,
         };
 
         Ok(run)
     }
 
-    /// Find core matching the libretro to list of cores.
-    pub fn find_core_match(&self, libretro: &Path) -> Vec<String> {
-        let mut core_match: Vec<String> = vec![];
-
-        if let Some(rules) = &self.cores_rules {
-            let libretro_string = libretro
-                .to_path_buf()
-                .file_stem()
-                .unwrap()
-                .to_string_lossy()
-                .to_string();
-            for (core, path) in rules {
-                let path_string =
-                    path.file_stem().unwrap().to_string_lossy().to_string();
-                if path_string.trim_end_matches("_libretro")
-                    == libretro_string.trim_end_matches("_libretro")
-                {
-                    core_match.push(core.to_string());
-                }
-            }
+    /// Hint for a game which is not found, with the files of the ROM roots and of the folder of
+    /// the game, which have the closest names.  Empty without any close one, or if option
+    /// `suggestions` is `0`.
+    fn suggest_games(&self, game: &Path) -> String {
+        let limit: usize = self
+            .suggestions
+            .unwrap_or(suggest::DEFAULT_LIMIT)
+            .try_into()
+            .unwrap_or(usize::MAX);
+        if limit == 0 {
+            return String::new();
         }
 
-        core_match
-    }
+        let mut candidates: Vec<PathBuf> = self
+            .rom_roots
+            .iter()
+            .flatten()
+            .flat_map(|root| scan::files(&file::tilde(root)))
+            .collect();
+        // The folder of the game is not searched recursively, it could be the home directory.
+        let folder: Option<PathBuf> = game
+            .parent()
+            .map(|dir| {
+                if dir.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    dir
+                }
+            })
+            .and_then(file::to_fullpath);
+        if let Some(Ok(entries)) = folder.map(fs::read_dir) {
+            candidates.extend(
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file()),
+            );
+        }
 
-    /// Extract extension from game path and lookup the corresponding extension rule in current
-    /// settings to get the `libretro` path.
-    fn libretro_from_ext(&self, game: &Path) -> Option<PathBuf> {
-        if let Some(game_ext) = game.extension() {
-            if let Some(extension_rules) = &self.extension_rules.as_ref() {
-                if let Some(libretro) = extension_rules.get(
-                    game_ext
-                        .to_str()
-                        .expect("Non UTF-8 character in extension."),
-                ) {
-                    return Some(libretro.clone());
+        let found: Vec<String> = suggest::closest(game, &candidates, limit)
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        if found.is_empty() {
+            return String::new();
+        }
+
+        format!(", did you mean {}?", found.join(" or "))
+    }
+
+    /// The game to give to `RetroArch`.  A Zstandard-compressed game like `game.smc.zst` is
+    /// decompressed into the cache directory, unless the core lists `zst` in its supported
+    /// extensions.  With `--norun` only the path of the decompressed game is shown.
+    fn uncompressed_game(
+        &self,
+        game: &Path,
+        libretro: &Path,
+    ) -> Result<PathBuf, String> {
+        if compressed::inner(game).is_none()
+            || self.core_info(libretro).is_some_and(|info| {
+                compressed::is_supported(&info.supported_extensions)
+            })
+        {
+            return Ok(game.to_path_buf());
+        }
+
+        self.decompressed_game(game)
+    }
+
+    /// Decompressing is not part of builds without cargo feature `archive`.
+    #[cfg(not(feature = "archive"))]
+    fn decompressed_game(&self, game: &Path) -> Result<PathBuf, String> {
+        if self.is_norun() {
+            return Ok(game.to_path_buf());
+        }
+
+        Err(format!(
+            "Could not decompress game {}: needs a build with cargo feature \
+             `archive`",
+            game.display()
+        ))
+    }
+
+    /// Decompress `game` into the cache directory, see `uncompressed_game()`.
+    #[cfg(feature = "archive")]
+    fn decompressed_game(&self, game: &Path) -> Result<PathBuf, String> {
+        let target: PathBuf = match compressed::cached_path(
+            &file::cache_dir().join(compressed::CACHE_DIR),
+            game,
+        ) {
+            Ok(target) => target,
+            Err(_) if self.is_norun() => return Ok(game.to_path_buf()),
+            Err(e) => {
+                return Err(format!(
+                    "Could not read game {}: {}",
+                    game.display(),
+                    e
+                ))
+            }
+        };
+        if !self.is_norun() {
+            compressed::decompress(game, &target).map_err(|e| {
+                format!("Could not decompress game {}: {}", game.display(), e)
+            })?;
+        }
+
+        Ok(target)
+    }
+
+    /// Check if `RetroArch` should start in its menu with the game in a playlist.
+    pub fn is_menu_load(&self) -> bool {
+        self.menu_load.unwrap_or(false)
+    }
+
+    /// Put the game with its core as the only entry into the playlist for `--menu-load`.  The
+    /// playlist is in the `playlist_directory` of `retroarch.cfg`.
+    fn write_menu_playlist(
+        &self,
+        game: &Path,
+        libretro: &Path,
+        retroarch_config: Option<&Path>,
+    ) -> Result<PathBuf, String> {
+        let key: String = "playlist_directory".to_string();
+        let dir: PathBuf = retroarch_config
+            .and_then(|config| {
+                retroarch::parse_retroarch_config(
+                    &Some(config.into()),
+                    &self.appendconfigs(),
+                    &HashSet::from([key.clone()]),
+                )
+                .ok()
+            })
+            .and_then(|config| config.get(&key).cloned())
+            .filter(|dir| !dir.is_empty() && dir != "default")
+            .map_or_else(
+                || playlist::default_directory(retroarch_config),
+                |dir| file::tilde(Path::new(&dir)),
+            );
+        let core_name: String = self
+            .core_info(libretro)
+            .and_then(|info| info.display_name)
+            .unwrap_or_else(|| coreinfo::core_name(libretro));
+        let content: String = playlist::to_json(
+            game,
+            &self.display_title(game),
+            libretro,
+            &core_name,
+        );
+
+        playlist::write(&dir, &content).map_err(|e| {
+            format!("Could not write playlist in {}: {}", dir.display(), e)
+        })
+    }
+
+    /// Remove arguments for `retroarch`, which are not supported by the installed `version`.  A
+    /// warning is printed for the removed arguments.
+    fn supported_arguments(
+        arguments: &[String],
+        version: Option<retroarch::Version>,
+    ) -> Vec<String> {
+        let (supported, removed) =
+            retroarch::filter_unsupported(arguments, version);
+        if !removed.is_empty() {
+            eprintln!(
+                "RetroArch {} does not support, ignoring: {}",
+                version.unwrap_or(retroarch::Version(0, 0, 0)),
+                removed.join(" ")
+            );
+        }
+
+        supported
+    }
+
+    /// Resolve the full path of the `libretro` core for a game.  A given `libretro` has the highest
+    /// priority, followed by `core`, then directory rules, extension rules and at last
+    /// `fallback_core`.  This has no side effects, so it can be used to look up many games without
+    /// running them.
+    fn resolve_libretro(
+        &self,
+        game: Option<&Path>,
+    ) -> Result<PathBuf, String> {
+        self.resolve_libretro_with(&provider::RealFs, game)
+    }
+
+    /// Same as `resolve_libretro()`, but the core is looked up in the given filesystem `fs`.
+    fn resolve_libretro_with(
+        &self,
+        fs: &dyn provider::FsProvider,
+        game: Option<&Path>,
+    ) -> Result<PathBuf, String> {
+        let libretro: PathBuf = self.libretro_for(game)?;
+        let coreset: Option<&coresets::CoreSet> = self.coreset_for(game)?;
+
+        // Combine `--libretro_directory` and `--libretro`
+        // If the `libretro` itself is a relative path, then it will be combined with each of the
+        // given directories in order, until the core is found.  Otherwise the directories are
+        // ignored, as a fullpath of `libretro` takes precedence.
+        let fullpath: PathBuf = retroarch::libretro_fullpath(
+            fs,
+            Self::libretro_directories_of(
+                coreset,
+                self.libretro_directories(),
+            ),
+            Some(libretro),
+            "_libretro.so",
+        )
+        .ok_or("No matching libretro core found")?;
+
+        // `[policy]`
+        if let Some(policy) = &self.policy {
+            if !self.is_ignore_policy() {
+                policy
+                    .check(&fullpath, &self.find_core_match(&fullpath))
+                    .map_err(|e| {
+                        format!("{} Use --ignore-policy to override.", e)
+                    })?;
+            }
+        }
+
+        Ok(fullpath)
+    }
+
+    /// The `libretro` core for a game as given or found by the rules, before it is combined with
+    /// `libretro_directory`.
+    fn libretro_for(&self, game: Option<&Path>) -> Result<PathBuf, String> {
+        let mut libretro: Option<PathBuf> = self.libretro.clone();
+
+        // `libretro` have higher priority over `core`, if present.  Otherwise lookup `core`, if
+        // available.
+        if libretro.is_none() {
+            // `--core`
+            if let Some(core) = &self.core {
+                match &self.cores_rules {
+                    Some(rules) => libretro = rules.get(core).cloned(),
+                    None => {
+                        return Err("No core rules found in `[cores]`.".into())
+                    }
+                };
+            }
+
+            // Lookup from `[romsets]` by the name of the game, which is more specific than any
+            // directory or extension.
+            if libretro.is_none() {
+                libretro =
+                    game.and_then(|game| self.libretro_from_romset(game));
+            }
+            // Lookup and resolve from `[/directory]` rules
+            if libretro.is_none() && self.directory_rules.is_some() {
+                libretro = self.libretro_from_dir(
+                    game.expect("game required when building libretro path from directory rules."),
+                );
+            };
+            // Lookup and resolve from `[.ext]` rules
+            if libretro.is_none() && self.extension_rules.is_some() {
+                libretro = self.libretro_from_ext(
+                    game.expect("game required when building libretro path from extension rules."),
+                );
+            };
+            // `fallback_core` is an alias from `[cores]` or a `libretro` itself.
+            if libretro.is_none() {
+                libretro = self.fallback_core.as_ref().map(|fallback| {
+                    self.cores_rules
+                        .as_ref()
+                        .and_then(|rules| rules.get(fallback))
+                        .cloned()
+                        .unwrap_or_else(|| PathBuf::from(fallback))
+                });
+            }
+        }
+
+        // At this point, the `libretro` path should be available, either given directly or by
+        // resolving rules from `core`.
+        libretro.ok_or_else(|| "Path to `libretro` not set.".into())
+    }
+
+    /// All directories to search for cores in order.  Empty if not set.
+    fn libretro_directories(&self) -> &[PathBuf] {
+        self.libretro_directory.as_deref().unwrap_or_default()
+    }
+
+    /// The core set of a game from option `coreset` or the rules of the game, which take
+    /// precedence.  `None` if no core set is used, an error if it is not defined in the user
+    /// config.
+    fn coreset_for(
+        &self,
+        game: Option<&Path>,
+    ) -> Result<Option<&coresets::CoreSet>, String> {
+        let name: Option<String> = match game {
+            Some(game) => self.rule_options_for(game).coreset,
+            None => self.coreset.clone(),
+        };
+
+        name.map(|name| {
+            self.coresets
+                .iter()
+                .flatten()
+                .find(|set| set.name == name)
+                .ok_or_else(|| {
+                    format!(
+                        "Core set not found in user config: [{}{}]",
+                        coresets::SECTION_PREFIX,
+                        name
+                    )
+                })
+        })
+        .transpose()
+    }
+
+    /// Wrap `command` to run on the SSH `target` instead, with the paths mapped by its section
+    /// `[remote:HOST]`.  The content of the append-config file is sent along.
+    fn remote_command(
+        &self,
+        target: &str,
+        command: &Command,
+        appendconfig: Option<(&Path, &overrides::Overrides)>,
+    ) -> Command {
+        let host: &str = remotes::host_of(target);
+        let remote: remotes::Remote = self
+            .remotes
+            .iter()
+            .flatten()
+            .find(|remote| remote.host == host)
+            .cloned()
+            .unwrap_or_else(|| remotes::Remote::new(host));
+
+        let text = |text: &std::ffi::OsStr| text.to_string_lossy().to_string();
+        let arguments: Vec<String> = command.get_args().map(text).collect();
+        let env: Vec<(String, String)> = command
+            .get_envs()
+            .filter_map(|(key, value)| {
+                value.map(|value| (text(key), text(value)))
+            })
+            .collect();
+        let appendconfig: Option<(String, String)> =
+            appendconfig.map(|(path, overrides)| {
+                (path.display().to_string(), overrides.to_cfg())
+            });
+
+        let mut ssh: Command = Command::new(remotes::SSH);
+        ssh.arg(target).arg(
+            remote.script(
+                &text(command.get_program()),
+                &arguments,
+                &env,
+                command.get_current_dir(),
+                appendconfig
+                    .as_ref()
+                    .map(|(path, content)| (path.as_str(), content.as_str())),
+            ),
+        );
+
+        ssh
+    }
+
+    /// Directories to search for cores of a `coreset`, or the `default` ones if the set has
+    /// none.
+    fn libretro_directories_of<'a>(
+        coreset: Option<&'a coresets::CoreSet>,
+        default: &'a [PathBuf],
+    ) -> &'a [PathBuf] {
+        match coreset {
+            Some(set) if !set.libretro_directory.is_empty() => {
+                &set.libretro_directory
+            }
+            _ => default,
+        }
+    }
+
+    /// Fail with a clear error, if the `RetroArch` of the `coreset` or option `retroarch` is
+    /// known to be too old for `--entryslot` of option `load_state`.
+    fn check_entryslot(
+        &self,
+        coreset: Option<&coresets::CoreSet>,
+    ) -> Result<(), String> {
+        let Some(entryslot) = passthrough::find("--entryslot") else {
+            return Ok(());
+        };
+        let retroarch: &Path =
+            Self::retroarch_of(coreset, self.retroarch.as_ref())
+                .map_or_else(|| Path::new(""), PathBuf::as_path);
+        let version: Option<retroarch::Version> =
+            retroarch::version(retroarch);
+
+        match (version, entryslot.since) {
+            (Some(version), Some(since)) if version < since => Err(format!(
+                "Option --load-state requires RetroArch {} or newer for \
+                     --entryslot, but {} is version {}.",
+                since,
+                retroarch.display(),
+                version
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The `retroarch` of a `coreset`, or the `default` one if the set has none.
+    fn retroarch_of<'a>(
+        coreset: Option<&'a coresets::CoreSet>,
+        default: Option<&'a PathBuf>,
+    ) -> Option<&'a PathBuf> {
+        coreset.and_then(|set| set.retroarch.as_ref()).or(default)
+    }
+
+    /// Search for the `RetroArch` executable in `retroarch_directory` and the systems `$PATH`, if it
+    /// is not set by option, environment or user config.  Otherwise the default is used later.
+    pub fn search_retroarch(&mut self) {
+        if self.retroarch.is_some() {
+            return;
+        }
+        if let Some(path) = discovery::search(
+            self.retroarch_directory.as_deref().unwrap_or_default(),
+        ) {
+            self.retroarch = Some(path);
+            self.retroarch_origin = Some(discovery::Origin::Search);
+        }
+    }
+
+    /// Check if the chosen `RetroArch` executable should be reported.
+    pub fn is_which_retroarch(&self) -> bool {
+        self.which_retroarch.unwrap_or(false)
+    }
+
+    /// Print the `RetroArch` executable to run and where it was taken from.
+    pub fn print_which_retroarch(&self) -> Result {
+        let retroarch: &Path =
+            self.retroarch.as_deref().unwrap_or_else(|| Path::new(""));
+        let origin: discovery::Origin =
+            self.retroarch_origin.unwrap_or(discovery::Origin::Default);
+
+        match file::which(retroarch) {
+            Some(path) => {
+                println!("{} ({})", path.display(), origin);
+                Ok(())
+            }
+            None => Err(format!(
+                "RetroArch executable not found: {} ({})",
+                retroarch.display(),
+                origin
+            )
+            .into()),
+        }
+    }
+
+    /// Check if the directory of the resolved core should be reported.
+    pub fn is_which_core(&self) -> bool {
+        self.which_core.unwrap_or(false)
+    }
+
+    /// Print where the core of the selected game is looked up, one line for each directory of
+    /// `libretro_directory` in order.  The first existing core is `used`, any other existing one
+    /// is `shadowed` by it and the others are `missing`.
+    pub fn print_which_core(&self) -> Result {
+        let game: Option<PathBuf> = self
+            .select_game()
+            .map(|game| file::to_fullpath(&game).unwrap_or(game));
+        let libretro: PathBuf = self.libretro_for(game.as_deref())?;
+        let coreset: Option<&coresets::CoreSet> =
+            self.coreset_for(game.as_deref())?;
+        let candidates: Vec<PathBuf> = retroarch::libretro_candidates(
+            Self::libretro_directories_of(
+                coreset,
+                self.libretro_directories(),
+            ),
+            &libretro,
+            "_libretro.so",
+        );
+
+        let mut found: bool = false;
+        for candidate in candidates {
+            let status: &str = match file::to_fullpath(&candidate) {
+                Some(_) if found => "shadowed",
+                Some(_) => {
+                    found = true;
+                    "used"
+                }
+                None => "missing",
+            };
+            println!("{:<8} {}", status, candidate.display());
+        }
+
+        if found {
+            Ok(())
+        } else {
+            Err("No matching libretro core found".into())
+        }
+    }
+
+    /// Find core matching the libretro to list of cores.
+    pub fn find_core_match(&self, libretro: &Path) -> Vec<String> {
+        let mut core_match: Vec<String> = vec![];
+
+        if let Some(rules) = &self.cores_rules {
+            let libretro_string = libretro
+                .to_path_buf()
+                .file_stem()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            for (core, path) in rules {
+                let path_string =
+                    path.file_stem().unwrap().to_string_lossy().to_string();
+                if path_string.trim_end_matches("_libretro")
+                    == libretro_string.trim_end_matches("_libretro")
+                {
+                    core_match.push(core.to_string());
                 }
             }
         }
 
-        None
+        core_match
+    }
+
+    /// Extract extension from game path and lookup the corresponding extension rule in current
+    /// settings to get the `libretro` path.  Compound extensions like `tar.gz` are matched before
+    /// the last extension alone.
+    fn libretro_from_ext(&self, game: &Path) -> Option<PathBuf> {
+        self.extension_rules
+            .as_ref()
+            .and_then(|extension_rules| {
+                find_by_extension(extension_rules, game)
+            })
+            .cloned()
+    }
+
+    /// Extensions of ROM files in lowercase, which are known from the extension rules and from
+    /// the core info files of all cores in `[cores]` and `libretro`.  The info files are only
+    /// read on the first call.
+    fn rom_extensions(&self) -> &HashSet<String> {
+        self.known_extensions
+            .get_or_init(|| self.read_rom_extensions())
+    }
+
+    fn read_rom_extensions(&self) -> HashSet<String> {
+        let mut known: HashSet<String> = self
+            .extension_rules
+            .iter()
+            .flat_map(|rules| rules.keys())
+            .map(|extension| extension.to_lowercase())
+            .collect();
+
+        let cores = self
+            .cores_rules
+            .iter()
+            .flat_map(|rules| rules.values())
+            .chain(&self.libretro);
+        for info in cores.filter_map(|core| self.core_info(core)) {
+            known.extend(
+                info.supported_extensions
+                    .iter()
+                    .map(|extension| extension.to_lowercase()),
+            );
+        }
+
+        known
+    }
+
+    /// Lookup the `libretro` path for the name of a game in `[romsets]`.
+    fn libretro_from_romset(&self, game: &Path) -> Option<PathBuf> {
+        let romsets = self.romsets.as_ref()?;
+
+        // A compressed game has the name of its content, like `mslug` for `mslug.zip.zst`.
+        let game: PathBuf =
+            compressed::inner(game).unwrap_or_else(|| game.to_path_buf());
+
+        romsets::romname(&game).and_then(|name| romsets.get(&name).cloned())
     }
 
     /// Extract parent folder from game path and lookup the corresponding directory rule in current
@@ -810,18 +3070,9 @@ impl Settings {
     fn libretro_from_dir(&self, game: &Path) -> Option<PathBuf> {
         if let Some(game_parent) = game.parent() {
             if let Some(directory_rules) = &self.directory_rules.as_ref() {
-                if let Some(rule) =
-                    directory_rules.iter().find(|(directory, _)| {
-                        WildMatch::new(&file::trim_last_slash(
-                            (*directory).to_string(),
-                        ))
-                        .matches(
-                            game_parent
-                                .as_os_str()
-                                .to_str()
-                                .expect("game folder as valid string"),
-                        )
-                    })
+                if let Some(rule) = directory_rules
+                    .iter()
+                    .find(|(directory, _)| dir_matches(directory, game_parent))
                 {
                     return Some(rule.1.clone());
                 }
@@ -831,46 +3082,226 @@ impl Settings {
         None
     }
 
+    /// Title of a game for display, which is option `title`, the `title` of its rules or else its
+    /// filename without ROM extension.
+    fn display_title(&self, game: &Path) -> String {
+        self.title
+            .clone()
+            .or_else(|| self.rule_options_for(game).title)
+            .unwrap_or_else(|| {
+                game_title(game, self.rom_extensions()).to_string()
+            })
+    }
+
+    /// Speak the title of a game with option `tts_command`, when launching it without looking at
+    /// a screen, such as with the gamepad hotkey.
+    fn announce_title(&self, game: &Path) {
+        if let Some(tts_command) = &self.tts_command {
+            speech::announce(tts_command, &self.display_title(game));
+        }
+    }
+
+    /// Priority and CPUs for running the `libretro` core, from the rule `options` of the game, the
+    /// entry of the core in `[cores]` and the global options, in that order.
+    fn scheduling_for(
+        &self,
+        options: &rules::RuleOptions,
+        libretro: &Path,
+    ) -> Result<scheduling::Scheduling, String> {
+        // Invalid global options are reported, even if a rule replaces them.
+        scheduling::Scheduling::parse(
+            self.nice.as_deref(),
+            self.cpus.as_deref(),
+        )?;
+        let core: Option<&rules::RuleOptions> = self
+            .core_options
+            .as_ref()
+            .and_then(|cores| cores.get(&coreinfo::core_name(libretro)));
+        let value = |rule: &Option<String>,
+                     core: Option<&Option<String>>,
+                     global: &Option<String>| {
+            rule.clone()
+                .or_else(|| core.cloned().flatten())
+                .or_else(|| global.clone())
+        };
+
+        scheduling::Scheduling::parse(
+            value(&options.nice, core.map(|c| &c.nice), &self.nice).as_deref(),
+            value(&options.cpus, core.map(|c| &c.cpus), &self.cpus).as_deref(),
+        )
+    }
+
+    /// Combine the global `workdir` and `env` options with those from the matching extension and
+    /// directory rules of a game.  Extension rules take precedence over the global options and
+    /// directory rules over the extension rules.
+    fn rule_options_for(&self, game: &Path) -> rules::RuleOptions {
+        let mut options = rules::RuleOptions {
+            workdir: self.workdir.clone(),
+            coreset: self.coreset.clone(),
+            title: None,
+            preset: None,
+            nice: None,
+            cpus: None,
+            ai_service: self.ai_service,
+            env: self.env.clone().unwrap_or_default(),
+        };
+
+        if let Some(rule_options) = &self.rule_options {
+            if let Some(ext_options) = find_by_extension(rule_options, game) {
+                options.merge(ext_options);
+            }
+            if let Some(game_parent) = game.parent() {
+                if let Some((_, dir_options)) =
+                    rule_options.iter().find(|(directory, _)| {
+                        directory.contains('/')
+                            && dir_matches(directory, game_parent)
+                    })
+                {
+                    options.merge(dir_options);
+                }
+            }
+        }
+
+        options
+    }
+
     /// Extract the first game entry from current Settings `games` list.  If any filter is
     /// available, then apply it before extraction.  The comparison is always in lowercase.
     /// Supported special characters are only the star "*", for matching anything and questionmark
     /// "?", for matching a single character.  The filter will be enclosed by stars automatically.
+    /// With option `fuzzy` the game with the best score is taken, the first one of those with the
+    /// same score and shortest title.  Option `index` takes the Nth game instead of the first,
+    /// counted from the end if negative.
     fn select_game(&self) -> Option<PathBuf> {
-        match &self.filter {
-            Some(filter) => {
-                let pattern_wildmatch = self.pattern_list_wildmatch(filter);
-
-                for game in &self.games {
-                    let gstring: String = self.to_lowercase(
-                        &game
-                            .file_stem()
-                            .unwrap()
-                            .to_str()
-                            .unwrap_or_default()
-                            .to_owned(),
-                    );
+        let index: isize = self.index.unwrap_or(1);
 
-                    if pattern_wildmatch
-                        .iter()
-                        .all(|pattern| pattern.matches(&gstring))
-                    {
-                        return Some(game.clone());
-                    }
-                }
+        if self.is_fuzzy() && self.filter.is_some() {
+            nth_game(&self.ranked_games(), index)
+        } else if let Ok(index) = usize::try_from(index) {
+            self.filtered_games().nth(index.checked_sub(1)?).cloned()
+        } else {
+            nth_game(&self.filtered_games().collect::<Vec<&PathBuf>>(), index)
+        }
+    }
 
-                None
-            }
-            None => self.games.first().cloned(),
+    /// All games matching the filter, ordered by their fuzzy score from best to worst.  Games of
+    /// the same score are ordered by the length of their title, then by their position.
+    fn ranked_games(&self) -> Vec<&PathBuf> {
+        let mut games: Vec<&PathBuf> = self.filtered_games().collect();
+        games.sort_by_cached_key(|game| {
+            (
+                Reverse(self.fuzzy_score(game)),
+                game_title(game, self.rom_extensions()).chars().count(),
+            )
+        });
+
+        games
+    }
+
+    /// Iterate over all games from current Settings `games` list, which match the filter and none
+    /// of the `exclude` patterns.  Without a filter all games are included.  The first `offset`
+    /// matches are skipped and at most `limit` matches are returned.  The games are evaluated
+    /// lazily, so taking the first entry does not compare the rest of the list.
+    fn filtered_games(&self) -> impl Iterator<Item = &PathBuf> + '_ {
+        let pattern_wildmatch: Vec<WildMatch> = self.filter_patterns();
+        let exclude_wildmatch: Vec<WildMatch> = self.exclude_patterns();
+
+        self.games
+            .iter()
+            .filter(move |game| {
+                self.matches_filter(
+                    game,
+                    &pattern_wildmatch,
+                    &exclude_wildmatch,
+                )
+            })
+            .skip(self.offset.unwrap_or(0))
+            .take(self.limit.unwrap_or(usize::MAX))
+    }
+
+    /// Positions of all games in `games`, which match the filter, with `offset` and `limit`
+    /// applied.  Unlike `filtered_games()` the whole list is compared in parallel, which is faster
+    /// if all matches are needed anyway.
+    fn filtered_indices(&self) -> Vec<usize> {
+        let pattern_wildmatch: Vec<WildMatch> = self.filter_patterns();
+        let exclude_wildmatch: Vec<WildMatch> = self.exclude_patterns();
+
+        let indices: Vec<usize> = self
+            .games
+            .par_iter()
+            .enumerate()
+            .filter(|(_, game)| {
+                self.matches_filter(
+                    game,
+                    &pattern_wildmatch,
+                    &exclude_wildmatch,
+                )
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        indices
+            .into_iter()
+            .skip(self.offset.unwrap_or(0))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// All wildmatch patterns of the `filter` option, built once for the whole list.
+    fn filter_patterns(&self) -> Vec<WildMatch> {
+        match &self.filter {
+            Some(filter) => self.pattern_list_wildmatch(filter),
+            None => vec![],
         }
     }
 
-    /// Simply convert a String to lowercase if `strict` mode is off.
-    fn to_lowercase(&self, text: &String) -> String {
-        if self.is_strict() {
-            text.to_string()
-        } else {
-            text.to_lowercase()
+    /// All wildmatch patterns of the `exclude` option, built once for the whole list.
+    fn exclude_patterns(&self) -> Vec<WildMatch> {
+        match &self.exclude {
+            Some(exclude) => self.pattern_list_wildmatch(exclude),
+            None => vec![],
+        }
+    }
+
+    /// Check if the title of a game matches all `patterns` and none of the `excludes`.  Empty
+    /// lists match any game, without looking up the known ROM extensions for the title.
+    fn matches_filter(
+        &self,
+        game: &Path,
+        patterns: &[WildMatch],
+        excludes: &[WildMatch],
+    ) -> bool {
+        if patterns.is_empty() && excludes.is_empty() {
+            return true;
         }
+
+        let stem: &str = game_title(game, self.rom_extensions());
+        let gstring: Cow<str> = if self.is_strict() {
+            Cow::Borrowed(stem)
+        } else {
+            Cow::Owned(stem.to_lowercase())
+        };
+        let included: bool = if patterns.is_empty() {
+            true
+        } else if self.is_fuzzy() {
+            self.fuzzy_score(game).is_some()
+        } else {
+            patterns.iter().all(|pattern| pattern.matches(&gstring))
+        };
+
+        included && !excludes.iter().any(|pattern| pattern.matches(&gstring))
+    }
+
+    /// Sum of the fuzzy scores of all `filter` patterns for the title of a game.  `None` if any
+    /// of them does not match.
+    fn fuzzy_score(&self, game: &Path) -> Option<i64> {
+        let title: &str = game_title(game, self.rom_extensions());
+
+        self.filter
+            .iter()
+            .flatten()
+            .map(|pattern| fuzzy::score(pattern, title))
+            .sum()
     }
 
     /// Build the list of patterns by wildcard filter.
@@ -881,16 +3312,30 @@ impl Settings {
             .collect()
     }
 
-    /// Build the wildmatch pattern based on `strict` mode of `filter` option.
+    /// Build the wildmatch pattern based on `strict` mode and `match` anchor of `filter` option.
     fn wildmatch_pattern(&self, pattern: &str) -> WildMatch {
         if self.is_strict() {
-            WildMatch::new(pattern)
+            WildMatch::new(&self.get_match_anchor().wildcard(pattern))
         } else {
-            WildMatch::new(&format!("*{}*", pattern.to_lowercase()))
+            WildMatch::new(
+                &self.get_match_anchor().wildcard(&pattern.to_lowercase()),
+            )
         }
     }
 
-    /// Opens the current `config` file with the associated default application.
+    /// Where the patterns of `filter` must match the title.  Without option `match` the whole
+    /// title in `strict` mode, otherwise any part.
+    pub fn get_match_anchor(&self) -> anchor::Anchor {
+        self.match_anchor.unwrap_or(if self.is_strict() {
+            anchor::Anchor::Exact
+        } else {
+            anchor::Anchor::Contains
+        })
+    }
+
+    /// Opens the current `config` file with the program of option `opener`, which is `xdg-open`
+    /// by default.  This runs before the user settings are read, so the option is looked up in
+    /// the file itself, which may still be broken.
     pub fn open_config(&self) -> Result<bool> {
         if self.open_config.unwrap_or(false) {
             let config_path: &PathBuf = self
@@ -900,7 +3345,14 @@ impl Settings {
 
             match file::to_fullpath(config_path) {
                 Some(ref path) => {
-                    file::open_with_default(path)?;
+                    let opener: Option<String> = Self::read_config_ini(path)
+                        .ok()
+                        .and_then(|ini| ini.get("options", "opener"));
+                    let opener: program::Program = program::Program::parse(
+                        "opener",
+                        opener.as_deref().unwrap_or(program::DEFAULT_OPENER),
+                    )?;
+                    file::open_with(&opener, path)?;
                 }
                 None => (),
             }
@@ -922,6 +3374,13 @@ impl Settings {
         }
     }
 
+    /// Files given with `--appendconfig` in the arguments for `retroarch`, which `RetroArch`
+    /// applies on top of `retroarch.cfg`.
+    #[must_use]
+    pub fn appendconfigs(&self) -> Vec<PathBuf> {
+        retroarch::appendconfigs(&self.retroarch_arguments)
+    }
+
     /// Get the `RetroArchs` own `retroarch.cfg` configuration file path from current Settings.
     #[must_use]
     pub const fn get_retroarch_config(&self) -> &Option<PathBuf> {
@@ -945,26 +3404,508 @@ impl Settings {
         self.libretro_directory.is_some()
     }
 
-    /// Check if the `strict` option is set, so the `filter` command will go into strict mode.
-    pub fn is_strict(&self) -> bool {
-        self.strict.unwrap_or(false)
+    /// Check if the directory of libretro core info files is required, but not set yet.  It is
+    /// needed to print the system names of cores, also in a `format`.
+    #[must_use]
+    pub fn is_libretro_info_directory_missing(&self) -> bool {
+        self.libretro_info_directory.is_none()
+            && (self.is_long() || self.is_organize() || self.format.is_some())
     }
 
-    /// Check if the `stdin` stream should be ignored.
-    #[must_use]
-    pub fn is_nostdin(&self) -> bool {
-        self.nostdin.unwrap_or(false)
+    /// Check if the `filter` patterns are matched fuzzy instead of by wildcards.
+    pub fn is_fuzzy(&self) -> bool {
+        self.fuzzy.unwrap_or(false)
     }
 
-    /// Check if the `norun` option is set, so `RetroArch` command will not be executed.
+    /// Check if the game should be chosen by the user, from a list on the terminal or with the
+    /// `selector_command`.
+    pub fn is_interactive(&self) -> bool {
+        self.interactive.unwrap_or(false)
+            || self.selector_command.is_some()
+            || self.is_choose()
+    }
+
+    /// Check if the `choose` option is set, to choose the game by number from a list.
+    pub fn is_choose(&self) -> bool {
+        self.choose.unwrap_or(false)
+    }
+
+    /// Let the user choose the game to launch from all games matching the filter, by number with
+    /// option `choose`, with the `selector_command` or else in a list on the terminal.  Builds
+    /// without cargo feature `tui` ask by number instead of the list.  The chosen game replaces
+    /// the list of games, so it is the selected one from now on.  A single match is taken without
+    /// asking, and so is the first one with `choose` if stdout is no terminal.
+    pub fn pick_game(&mut self) -> Result {
+        let games: Vec<PathBuf> = self.filtered_games().cloned().collect();
+        if games.len() < 2
+            || (self.is_choose() && !atty::is(atty::Stream::Stdout))
+        {
+            return Ok(());
+        }
+        let items: Vec<String> = games
+            .iter()
+            .map(|game| game.display().to_string())
+            .collect();
+
+        let chosen: Option<usize> = match &self.selector_command {
+            _ if self.is_choose() => picker::choose_on_tty(&items)?,
+            Some(selector_command) => {
+                picker::select_with(selector_command, &items)?
+            }
+            #[cfg(feature = "tui")]
+            None => tui::pick(&items)?,
+            #[cfg(not(feature = "tui"))]
+            None => picker::choose_on_tty(&items)?,
+        };
+        match chosen {
+            Some(index) => {
+                self.games = vec![games[index].clone()];
+                self.filter = None;
+                self.exclude = None;
+                self.offset = None;
+                self.limit = None;
+                self.index = None;
+                self.fuzzy = None;
+                self.announce_title(&self.games[0]);
+                Ok(())
+            }
+            None => Err("No game chosen".into()),
+        }
+    }
+
+    /// Check if the `strict` option is set, so the `filter` command will go into strict mode.
+    pub fn is_strict(&self) -> bool {
+        self.strict.unwrap_or(false)
+    }
+
+    /// Check if the `stdin` stream should be ignored.
+    #[must_use]
+    pub fn is_nostdin(&self) -> bool {
+        self.nostdin.unwrap_or(false)
+    }
+
+    /// Games on stdin are separated by NUL instead of newlines.
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        self.null.unwrap_or(false)
+    }
+
+    /// Time to wait for more data on stdin, before reading stops.  Stdin is read before all
+    /// settings are merged, so the option from `config` is used, if not set in `self`.  Defaults
+    /// to 2 seconds, `0` waits until stdin is closed and returns `None`.
+    #[must_use]
+    pub fn get_stdin_timeout(&self, config: &Self) -> Option<Duration> {
+        match self.stdin_timeout.or(config.stdin_timeout).unwrap_or(2000) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    /// Rewrite the games read from stdin with the rules of section `[stdin]` of the `config`.
+    /// Stdin is read before all settings are merged, so this is applied to them alone.
+    pub fn apply_stdin_rules(&mut self, config: &Self) {
+        if let Some(rules) = &config.stdin_rules {
+            for game in &mut self.games {
+                *game = rules.apply(game);
+            }
+        }
+    }
+
+    /// Add the games of each list file from option `games_from` to the games of `self`, after
+    /// those already in it.  See `inoutput::list_from_file()`.
+    pub fn add_games_from(&mut self, options: &Self) -> Result {
+        for list in options.games_from.iter().flatten() {
+            self.games.extend(inoutput::list_from_file(list)?);
+        }
+
+        Ok(())
+    }
+
+    /// Time the startup may take in total, before it is aborted.  The user config is read during
+    /// the startup, so the option from `config` is used, if not set in `self`.  Off by default and
+    /// with `0`, which returns `None`.
+    #[must_use]
+    pub fn get_startup_timeout(&self, config: &Self) -> Option<Duration> {
+        match self.startup_timeout.or(config.startup_timeout).unwrap_or(0) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    /// Start the watchdog of the startup with the budget of option `startup_timeout`.  The budget
+    /// of the user config is only known after reading it, see `watchdog::Watchdog::set_budget()`.
+    #[must_use]
+    pub fn start_watchdog(&self) -> watchdog::Watchdog {
+        watchdog::Watchdog::start(self.get_startup_timeout(&Self::new()))
+    }
+
+    /// Check if games given more than once should stay in the list.
+    #[must_use]
+    pub fn is_keep_duplicates(&self) -> bool {
+        self.keep_duplicates.unwrap_or(false)
+    }
+
+    /// Check if games with the same filename in different directories count as duplicates.
+    #[must_use]
+    pub fn is_unique(&self) -> bool {
+        self.unique.unwrap_or(false)
+    }
+
+    /// Replace each game, which is a directory, by the files in it with a known ROM extension,
+    /// sorted by path.  Subdirectories are scanned too with option `recursive`.  Hidden and
+    /// ignored files are skipped, see `scan::files()`.  Any file is a game, if no extension is
+    /// known.
+    pub fn expand_directories(&mut self) {
+        if !self.games.iter().any(|game| file::tilde(game).is_dir()) {
+            return;
+        }
+        let known: HashSet<String> = self.rom_extensions().clone();
+        let recursive: bool = self.recursive.unwrap_or(false);
+
+        self.games = std::mem::take(&mut self.games)
+            .into_iter()
+            .flat_map(|game| {
+                let dir: PathBuf = file::tilde(&game);
+                if !dir.is_dir() {
+                    return vec![game];
+                }
+                let files: Vec<PathBuf> = match recursive {
+                    true => scan::files(&dir),
+                    false => scan::top_files(&dir),
+                };
+                files
+                    .into_iter()
+                    .filter(|file| is_known_game(file, &known))
+                    .collect()
+            })
+            .collect();
+    }
+
+    /// Remove games which are given more than once, such as from the commandline and from stdin
+    /// at the same time.  Only the first of each game is kept, in the original order.  Games are
+    /// compared by their full path with symlinks resolved, or as given if the file does not
+    /// exist.  Nothing is removed with option `keep_duplicates`.  With option `unique` games with
+    /// the same filename are removed too, even in different directories.
+    pub fn dedup_games(&mut self) {
+        if self.games.len() < 2 {
+            return;
+        }
+        if !self.is_keep_duplicates() {
+            let mut seen: HashSet<PathBuf> = HashSet::new();
+            self.games.retain(|game| {
+                seen.insert(
+                    file::to_fullpath(game).unwrap_or_else(|| game.clone()),
+                )
+            });
+        }
+        if self.is_unique() {
+            let mut seen: HashSet<OsString> = HashSet::new();
+            self.games.retain(|game| {
+                seen.insert(
+                    game.file_name()
+                        .unwrap_or(game.as_os_str())
+                        .to_os_string(),
+                )
+            });
+        }
+    }
+
+    /// Order the games as with option `sort`, before any filter or selection.
+    pub fn sort_games(&mut self) {
+        self.sort.unwrap_or_default().apply(&mut self.games);
+    }
+
+    /// Check if the `norun` option is set, so `RetroArch` command will not be executed.
     pub fn is_norun(&self) -> bool {
         self.norun.unwrap_or(false)
     }
 
     /// Print the given `path`, if current Settings include the option `which`.
-    pub fn print_which(&self, path: PathBuf) {
+    pub fn print_which(&self, run: &RunCommand) -> Result {
         if self.which.unwrap_or(false) {
-            inoutput::print_path(&Some(path));
+            match self.format_template()? {
+                Some(template) => {
+                    println!("{}", template.render(&self.game_fields(run)));
+                }
+                None => inoutput::print_path(&Some(run.game.clone())),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parse the `format` option into a template.  `None` if no format is set.
+    fn format_template(&self) -> Result<Option<template::Template>> {
+        Ok(self.format.as_deref().map(str::parse).transpose()?)
+    }
+
+    /// Values of the template fields for the game and core of a resolved command.
+    fn game_fields(&self, run: &RunCommand) -> IndexMap<&'static str, String> {
+        self.game_fields_for(&run.game, &run.libretro)
+    }
+
+    /// Values of the template fields for a game and the fullpath of its `libretro` core.
+    fn game_fields_for(
+        &self,
+        game: &Path,
+        libretro: &Path,
+    ) -> IndexMap<&'static str, String> {
+        let text = |part: Option<&std::ffi::OsStr>| {
+            part.unwrap_or_default().to_string_lossy().to_string()
+        };
+        let mut fields: IndexMap<&'static str, String> = IndexMap::from([
+            ("path", inoutput::display_path(game)),
+            ("name", text(game.file_name())),
+            ("title", self.display_title(game)),
+            ("ext", text(game.extension())),
+            (
+                "dir",
+                game.parent()
+                    .map(inoutput::display_path)
+                    .unwrap_or_default(),
+            ),
+        ]);
+
+        // An empty `libretro` stands for a game without any matching core.
+        if libretro.file_stem().is_some() {
+            if let Some(core) = self.find_core_match(libretro).first() {
+                fields.extend(self.core_fields(core));
+            }
+        }
+        // The fullpath of the resolved core instead of the value in `[cores]`.
+        fields.insert("libretro", inoutput::display_path(libretro));
+
+        fields
+    }
+
+    /// Check if games should be listed for `fzf` instead of running one.
+    pub fn is_fzf(&self) -> bool {
+        self.fzf.unwrap_or(false)
+    }
+
+    /// Check if the example shell function for `fzf` should be printed.
+    pub fn is_fzf_snippet(&self) -> bool {
+        self.fzf_snippet.unwrap_or(false)
+    }
+
+    /// Print each game matching the filter as a line of tab separated `path`, `title` and
+    /// `core`, to be picked from in `fzf`.  Games without a matching core have an empty `core`.
+    pub fn print_fzf(&self) {
+        for game in self.filtered_games() {
+            let game: PathBuf =
+                file::to_fullpath(game).unwrap_or_else(|| game.clone());
+            let mut fields = match self.resolve_libretro(Some(&game)) {
+                Ok(libretro) => {
+                    let mut fields = self.game_fields_for(&game, &libretro);
+                    if !fields.contains_key("core") {
+                        fields.insert("core", coreinfo::core_name(&libretro));
+                    }
+                    fields
+                }
+                Err(_) => self.game_fields_for(&game, Path::new("")),
+            };
+            fields.entry("core").or_default();
+
+            println!(
+                "{}\t{}\t{}",
+                fields["path"], fields["title"], fields["core"]
+            );
+        }
+    }
+
+    /// Make all printed paths relative to the base of option `relative` from now on, if set.
+    pub fn apply_relative(&self) {
+        if let Some(base) = &self.relative {
+            inoutput::set_relative(inoutput::Relative::parse(
+                base,
+                self.rom_roots.as_deref().unwrap_or_default(),
+            ));
+        }
+    }
+
+    /// Escape all printed paths as with option `escape` from now on, if set.
+    pub fn apply_escape(&self) {
+        if let Some(escape) = self.escape {
+            inoutput::set_escape(escape);
+        }
+    }
+
+    /// Check if the games matching a prefix should be listed for shell completion.
+    pub fn is_complete_games(&self) -> bool {
+        self.complete_games.is_some()
+    }
+
+    /// Print the fullpath of each game in the ROM roots from `[paths]`, whose title or path
+    /// starts with `complete_games`, for completion in the shell.  The title is compared in
+    /// lowercase.  Only files with a known ROM extension are games, if any extension is known.
+    pub fn print_complete_games(&self) {
+        let prefix: &str = self.complete_games.as_deref().unwrap_or_default();
+        let lowercase: String = prefix.to_lowercase();
+        let known: &HashSet<String> = self.rom_extensions();
+
+        let games = self.rom_root_games(known).into_iter().filter(|game| {
+            game_title(game, known)
+                .to_lowercase()
+                .starts_with(&lowercase)
+                || game.display().to_string().starts_with(prefix)
+        });
+
+        for game in games {
+            println!("{}", game.display());
+        }
+    }
+
+    /// Print a shell function, which lists the games with `--fzf`, lets the user pick one in
+    /// `fzf` and launches it with `--launch-from-fzf`.  The ROM roots from `[paths]` are searched
+    /// for games, or `~/roms` without them.  Fails if a root cannot be quoted for the shell.
+    pub fn print_fzf_snippet(&self) -> Result {
+        let roots: String = match &self.rom_roots {
+            Some(roots) if !roots.is_empty() => roots
+                .iter()
+                .map(|root| {
+                    let root: String = root.display().to_string();
+                    shlex::try_quote(&root).map(Cow::into_owned).map_err(
+                        |error| {
+                            format!(
+                                "Could not quote ROM root {root:?}: {error}"
+                            )
+                        },
+                    )
+                })
+                .collect::<Result<Vec<String>, String>>()?
+                .join(" "),
+            _ => "~/roms".to_string(),
+        };
+
+        println!(
+            "# Pick a game in fzf and play it with enjoy.  Add this to ~/.bashrc or ~/.zshrc.
+# Bind it to Alt+E in bash with:  bind -x '\"\\ee\": enjoy-fzf'
+enjoy-fzf() {{
+    local line
+    line=\"$(find {} -type f | enjoy --fzf \\
+        | fzf --delimiter '\\t' --with-nth 2,3 --preview 'echo {{1}}')\" \\
+        && enjoy --launch-from-fzf \"$line\"
+}}",
+            roots
+        );
+
+        Ok(())
+    }
+
+    /// Replace the list of games by the path of a line selected in `fzf`, if option
+    /// `launch_from_fzf` is set.  The path is the first tab separated field of the line.
+    pub fn apply_fzf_selection(&mut self) {
+        if let Some(line) = self.launch_from_fzf.take() {
+            let path: &str = line.split('\t').next().unwrap_or_default();
+            self.games =
+                vec![PathBuf::from(path.trim_end_matches(['\r', '\n']))];
+            self.filter = None;
+            self.exclude = None;
+            self.limit = None;
+            self.offset = None;
+        }
+    }
+
+    /// Values of the template fields for a core alias from section `[cores]`.
+    fn core_fields(&self, core: &str) -> IndexMap<&'static str, String> {
+        let libretro: PathBuf = self
+            .cores_rules
+            .as_ref()
+            .and_then(|rules| rules.get(core))
+            .cloned()
+            .unwrap_or_default();
+        let label: String = self
+            .core_labels
+            .as_ref()
+            .and_then(|labels| labels.get(core))
+            .cloned()
+            .unwrap_or_default();
+        let system: String = self
+            .core_info(&libretro)
+            .and_then(|info| info.system())
+            .unwrap_or_default();
+
+        IndexMap::from([
+            ("core", core.to_string()),
+            ("libretro", libretro.display().to_string()),
+            ("label", label),
+            ("system", system),
+        ])
+    }
+
+    /// Check if option to print the fingerprint of the resolved command is set.
+    pub fn is_fingerprint(&self) -> bool {
+        self.fingerprint.unwrap_or(false)
+    }
+
+    /// Print a stable hash of the resolved command in a `RunCommand`, followed by the game.
+    pub fn print_fingerprint(&self, run: &RunCommand) {
+        let hash: u64 = command_fingerprint(
+            &run.cmdline,
+            &self.overrides().to_cfg(),
+            &overrides::temp_dir(),
+        );
+
+        println!("{:016x}  {}", hash, run.game.display());
+    }
+
+    /// Check if option to print the resolved settings as shell exports is set.
+    pub fn is_print_env(&self) -> bool {
+        self.print_env.unwrap_or(false)
+    }
+
+    /// Print the resolved settings of a `RunCommand` as `ENJOY_*` shell export lines, to be
+    /// sourced by wrapper scripts.  Variables without a value are exported as empty.
+    pub fn print_env(&self, run: &RunCommand) {
+        let path = |path: Option<&PathBuf>| {
+            path.map(|p| file::to_fullpath(p).unwrap_or_else(|| p.clone()))
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default()
+        };
+        // The core set was already checked when building the command of `run`.
+        let coreset: Option<&coresets::CoreSet> =
+            self.coreset_for(Some(&run.game)).ok().flatten();
+        let retroarch: Option<&PathBuf> =
+            Self::retroarch_of(coreset, self.retroarch.as_ref());
+        let retroarch: String = retroarch
+            .and_then(|p| file::which(p))
+            .or_else(|| retroarch.cloned())
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+
+        let exports: [(&str, String); 8] = [
+            ("ENJOY_GAME", run.game.display().to_string()),
+            ("ENJOY_CORE", coreinfo::core_name(&run.libretro)),
+            ("ENJOY_LIBRETRO", run.libretro.display().to_string()),
+            ("ENJOY_RETROARCH", retroarch),
+            (
+                "ENJOY_RETROARCH_CONFIG",
+                path(self.retroarch_config.as_ref()),
+            ),
+            (
+                "ENJOY_LIBRETRO_DIRECTORY",
+                env::join_paths(
+                    Self::libretro_directories_of(
+                        coreset,
+                        self.libretro_directories(),
+                    )
+                    .iter()
+                    .map(|dir| {
+                        file::to_fullpath(dir).unwrap_or_else(|| dir.clone())
+                    }),
+                )
+                .map(|dirs| dirs.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            ),
+            ("ENJOY_CONFIG", path(self.config.as_ref())),
+            (
+                "ENJOY_WORKDIR",
+                path(self.rule_options_for(&run.game).workdir.as_ref()),
+            ),
+        ];
+        for (name, value) in exports {
+            println!("{}", inoutput::shell_export(name, &value));
         }
     }
 
@@ -978,61 +3919,2600 @@ impl Settings {
         false
     }
 
-    /// Check if option to print entire command is set.
-    pub fn is_which_command(&self) -> bool {
-        self.which_command.unwrap_or(false)
+    /// Check if the `migrate_config` option is set, to upgrade the user config file only.
+    pub fn is_migrate_config(&self) -> bool {
+        self.migrate_config.unwrap_or(false)
     }
 
-    /// Check if option to print cores is set.
-    pub fn is_list_cores(&self) -> bool {
-        self.list_cores.unwrap_or(false)
+    /// Check if the `undo_config` option is set, to restore the user config file only.
+    pub fn is_undo_config(&self) -> bool {
+        self.undo_config.unwrap_or(false)
     }
 
-    /// Print all name of cores defined in the section \[cores\] in the config file.
-    pub fn print_cores(&self) {
-        if let Some(rules) = self.cores_rules.as_ref() {
-            let mut keys: Vec<String> = rules.clone().into_keys().collect();
-            keys.sort_unstable();
-            for core in keys {
-                println!("{core}");
+    /// Check if the user config should be analyzed for rules, which never apply.
+    pub fn is_check_config(&self) -> bool {
+        self.check_config.unwrap_or(false)
+    }
+
+    /// The other user settings file to compare with, if option `diff_config` is set.
+    pub fn get_diff_config(&self) -> &Option<PathBuf> {
+        &self.diff_config
+    }
+
+    /// Print how each game resolves differently under `other`, which are the same settings but
+    /// from another user config.  The games are the filtered games, or all games in the ROM roots
+    /// without any.  Games without a difference are left out, the overrides of `retroarch.cfg`
+    /// are compared once for all games.  Nothing is run or written.
+    pub fn diff_config(&self, other: &Self) -> Result {
+        let mut old: Self = self.clone();
+        old.norun = Some(true);
+        let mut new: Self = other.clone();
+        new.norun = Some(true);
+
+        let games: Vec<PathBuf> = self.collection_games();
+        if games.is_empty() {
+            return Err("No game to compare.  Give a game or set the ROM \
+                 roots in section `[paths]` of the user config."
+                .into());
+        }
+
+        let overrides: Vec<String> = configdiff::compare_overrides(
+            &old.overrides().entries,
+            &new.overrides().entries,
+        );
+        if !overrides.is_empty() {
+            println!("retroarch.cfg overrides");
+            for line in overrides {
+                println!("  {}", line);
+            }
+        }
+
+        let resolve = |settings: &Self, game: &Path| {
+            settings
+                .build_command_for(Some(game.to_path_buf()))
+                .map(|run| {
+                    configdiff::Resolved::new(
+                        &run.libretro,
+                        run.cmdline
+                            .get_program()
+                            .to_string_lossy()
+                            .to_string(),
+                        run.cmdline
+                            .get_args()
+                            .map(|arg| arg.to_string_lossy().to_string())
+                            .collect(),
+                    )
+                })
+        };
+        let mut changed: usize = 0;
+        for game in &games {
+            let lines: Vec<String> = configdiff::compare(
+                &resolve(&old, game),
+                &resolve(&new, game),
+            );
+            if !lines.is_empty() {
+                changed += 1;
+                println!("{}", game.display());
+                for line in lines {
+                    println!("  {}", line);
+                }
+            }
+        }
+        eprintln!("{} of {} games differ.", changed, games.len());
+
+        Ok(())
+    }
+
+    /// Fullpaths of the given games matching the filter, or all games in the ROM roots from
+    /// `[paths]` without any given game.
+    fn collection_games(&self) -> Vec<PathBuf> {
+        if self.games.is_empty() {
+            self.rom_root_games(self.rom_extensions())
+        } else {
+            self.filtered_games()
+                .map(|game| file::to_fullpath(game).unwrap_or(game.clone()))
+                .collect()
+        }
+    }
+
+    /// Check if the matching games should be printed instead of running one.
+    pub fn is_list_games(&self) -> bool {
+        self.list_games.unwrap_or(false)
+    }
+
+    /// Print the fullpath of each game of the collection, one per line.
+    pub fn print_list_games(&self) {
+        for game in self.collection_games() {
+            println!("{}", inoutput::display_path(&game));
+        }
+    }
+
+    /// Check if the number of matching games should be printed instead of running one.
+    pub fn is_count(&self) -> bool {
+        self.count.unwrap_or(false)
+    }
+
+    /// Print the number of games of the collection.  No game at all is an error, so scripts can
+    /// check the exit code.
+    pub fn print_count(&self) -> Result {
+        let count: usize = self.collection_games().len();
+        println!("{}", count);
+
+        if count == 0 {
+            return Err("No matching game available".into());
+        }
+
+        Ok(())
+    }
+
+    /// Check if the games should be exported with their resolved core instead of running one.
+    pub fn is_export_list(&self) -> bool {
+        self.export_list.is_some()
+    }
+
+    /// Print the path, title, system, resolved core, size and hash of each game in the format of
+    /// option `export_list`.  The games are read and resolved in parallel.
+    pub fn print_export_list(&self) -> Result {
+        let format: export::Format =
+            self.export_list.expect("export format required");
+        let games: Vec<PathBuf> = self.collection_games();
+        if games.is_empty() {
+            return Err("No game to export.  Give a game or set the ROM \
+                 roots in section `[paths]` of the user config."
+                .into());
+        }
+
+        let rows: Vec<export::Row> =
+            games.par_iter().map(|game| self.export_row(game)).collect();
+        print!("{}", format.render(&rows));
+
+        Ok(())
+    }
+
+    /// Values of the exported columns for a single game.
+    fn export_row(&self, game: &Path) -> export::Row {
+        let libretro: Option<PathBuf> = self.resolve_libretro(Some(game)).ok();
+        let system: Option<String> = libretro
+            .as_deref()
+            .and_then(|libretro| self.core_info(libretro))
+            .and_then(|info| info.system());
+
+        export::Row::from([
+            ("path", inoutput::display_path(game)),
+            ("title", self.display_title(game)),
+            ("system", system.unwrap_or_default()),
+            (
+                "core",
+                libretro
+                    .as_deref()
+                    .map(inoutput::display_path)
+                    .unwrap_or_default(),
+            ),
+            (
+                "size",
+                fs::metadata(game)
+                    .map(|metadata| metadata.len().to_string())
+                    .unwrap_or_default(),
+            ),
+            (
+                "hash",
+                fs::read(game)
+                    .map(|content| format!("{:016x}", file::fnv1a(&content)))
+                    .unwrap_or_default(),
+            ),
+        ])
+    }
+
+    /// Analyze how the rules of the user settings file defined in `config` interact and print
+    /// findings like shadowed or dead rules, highest severity first.  A core set by option
+    /// `core` or `libretro` on the commandline is taken into account.  Returns an error, if any
+    /// finding has a high severity.
+    pub fn check_config(&self) -> Result {
+        let path: PathBuf = self
+            .get_config()
+            .as_ref()
+            .and_then(|config| file::to_fullpath(config))
+            .ok_or("User config ini file not found.")?;
+        let ini: ini::Ini = Self::read_config_ini(&path)?;
+
+        let entries: IndexMap<String, rules::CoreEntry> =
+            Self::read_config_core_entries(&ini);
+        let rule = |section: &String| lint::Rule {
+            section: section.to_string(),
+            core: ini.get(section, "core"),
+            libretro: ini.get(section, "libretro").is_some(),
+        };
+        let sections: Vec<String> = ini.sections();
+        let model = lint::Model {
+            aliases: entries.keys().cloned().collect(),
+            aliases_with_extensions: entries
+                .iter()
+                .filter(|(_, entry)| !entry.extensions.is_empty())
+                .map(|(alias, _)| alias.to_string())
+                .collect(),
+            directory_rules: sections
+                .iter()
+                .filter(|section| section.contains('/'))
+                .map(rule)
+                .collect(),
+            extension_rules: sections
+                .iter()
+                .filter(|section| section.starts_with('.'))
+                .map(rule)
+                .collect(),
+            global_core: self
+                .libretro
+                .as_ref()
+                .map(|libretro| libretro.display().to_string())
+                .or_else(|| self.core.clone())
+                .or_else(|| ini.get("options", "libretro"))
+                .or_else(|| ini.get("options", "core")),
+        };
+
+        let findings: Vec<lint::Finding> = lint::lint(&model);
+        for finding in &findings {
+            println!("{}", finding);
+        }
+        println!("{} findings: {}", findings.len(), path.display());
+
+        match findings
+            .iter()
+            .filter(|finding| finding.severity == lint::Severity::High)
+            .count()
+        {
+            0 => Ok(()),
+            high => {
+                Err(format!("{} findings with high severity.", high).into())
+            }
+        }
+    }
+
+    /// Rewrite the user settings file defined in `config` to the current schema version.  A
+    /// numbered backup of the original file is saved first, see `undo_config()`.
+    pub fn migrate_config(&self) -> Result {
+        let path: PathBuf = self
+            .get_config()
+            .as_ref()
+            .and_then(|config| file::to_fullpath(config))
+            .ok_or("User config ini file not found.")?;
+
+        let migrated: migration::Migrated =
+            migration::migrate(&fs::read_to_string(&path)?)?;
+        if migrated.is_current() {
+            println!(
+                "Config is already at version {}: {}",
+                migration::CURRENT_VERSION,
+                path.display()
+            );
+            return Ok(());
+        }
+
+        let backup: PathBuf = backups::write(
+            &backups::backup_dir(&path),
+            &path,
+            &migrated.text,
+        )?;
+
+        println!(
+            "Migrated config from version {} to {}: {}",
+            migrated.from,
+            migration::CURRENT_VERSION,
+            path.display()
+        );
+        for description in migrated.applied {
+            println!("  - {}", description);
+        }
+        println!("Backup of the original: {}", backup.display());
+
+        Ok(())
+    }
+
+    /// Check if the guided setup should create the user config: `enjoy` was started from a
+    /// terminal without any arguments and the user config does not exist yet.
+    pub fn is_first_run(&self) -> bool {
+        env::args_os().count() == 1
+            && atty::is(atty::Stream::Stdin)
+            && atty::is(atty::Stream::Stdout)
+            && self
+                .get_config()
+                .as_ref()
+                .is_some_and(|config| !file::tilde(config).exists())
+    }
+
+    /// Create the user config defined in `config` with a guided setup in the terminal, from the
+    /// `RetroArch` installation and the cores found on the system.
+    pub fn run_wizard(&self) -> Result {
+        let path: PathBuf = self
+            .get_config()
+            .as_ref()
+            .map(|config| file::tilde(config))
+            .ok_or("User config ini file required.")?;
+
+        let config: Option<String> = wizard::run(
+            &wizard::detect(),
+            &mut std::io::stdin().lock(),
+            &mut std::io::stdout(),
+        )?;
+        match config {
+            Some(config) => {
+                backups::write(&backups::backup_dir(&path), &path, &config)?;
+                println!("Config written: {}", path.display());
+            }
+            None => println!("No config written."),
+        }
+
+        Ok(())
+    }
+
+    /// Restore the user settings file defined in `config` to the state before the last change
+    /// made by `enjoy`, from the newest numbered backup.  Each call steps back one more change.
+    pub fn undo_config(&self) -> Result {
+        let path: PathBuf = self
+            .get_config()
+            .as_ref()
+            .map(|config| file::tilde(config))
+            .ok_or("User config ini file required.")?;
+
+        match backups::undo(&backups::backup_dir(&path), &path)? {
+            Some(backup) if path.exists() => {
+                println!(
+                    "Config restored from {}: {}",
+                    backup.display(),
+                    path.display()
+                );
+            }
+            Some(_) => println!(
+                "Config removed, it did not exist before: {}",
+                path.display()
+            ),
+            None => {
+                return Err(
+                    format!("No backup of config: {}", path.display()).into()
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if the `doctor` option is set, to run the environment checks only.
+    pub fn is_doctor(&self) -> bool {
+        self.doctor.unwrap_or(false)
+    }
+
+    /// Run a suite of environment checks and print a structured report of them to stdout.  The
+    /// user config is loaded and merged in here, so that problems in it are reported instead of
+    /// aborting the program.  Returns an error, if any of the checks failed.
+    pub fn doctor(self) -> Result {
+        let mut report = doctor::Report::new();
+        let mut settings: Self = Self::new();
+
+        // User config
+        match self.get_config() {
+            Some(path) => match Self::new_from_config(&Some(path.clone())) {
+                Ok(config) => {
+                    report.pass(
+                        "user config",
+                        &file::to_str(config.config.as_ref()),
+                    );
+                    settings.update_from(config);
+                }
+                Err(e) => report.fail("user config", &e.to_string()),
+            },
+            None => report.warn("user config", "ignored by option --noconfig"),
+        }
+        settings.update_from(self);
+        settings.search_retroarch();
+        settings.update_defaults_from(Self::new_from_defaults());
+
+        // `retroarch`
+        doctor::check_retroarch(
+            &mut report,
+            settings
+                .retroarch
+                .as_deref()
+                .unwrap_or_else(|| Path::new("")),
+        );
+
+        // `retroarch.cfg`
+        let mut keys_to_get: HashSet<String> = HashSet::new();
+        keys_to_get.insert("libretro_directory".to_string());
+        keys_to_get.insert("savefile_directory".to_string());
+        keys_to_get.insert("savestate_directory".to_string());
+
+        let retroarch_config: Option<PathBuf> =
+            match &settings.retroarch_config {
+                Some(p) => file::to_fullpath(p),
+                None => retroarch::search_default_config(),
+            };
+        let retroarch_config_map: IndexMap<String, String> =
+            match retroarch_config {
+                Some(path) => {
+                    let path = Some(path);
+                    match retroarch::parse_retroarch_config(
+                        &path,
+                        &settings.appendconfigs(),
+                        &keys_to_get,
+                    ) {
+                        Ok(map) => {
+                            report.pass(
+                                "retroarch.cfg",
+                                &file::to_str(path.as_ref()),
+                            );
+                            map
+                        }
+                        Err(e) => {
+                            report.fail("retroarch.cfg", &e.to_string());
+                            IndexMap::new()
+                        }
+                    }
+                }
+                None => {
+                    report.fail("retroarch.cfg", "not found");
+                    IndexMap::new()
+                }
+            };
+
+        // `libretro_directory`
+        let libretro_directories: Vec<PathBuf> = settings
+            .libretro_directory
+            .clone()
+            .or_else(|| {
+                retroarch_config_map
+                    .get("libretro_directory")
+                    .map(|dir| vec![PathBuf::from(dir)])
+            })
+            .unwrap_or_default();
+        if libretro_directories.is_empty() {
+            doctor::check_libretro_directory(&mut report, None);
+        }
+        for dir in &libretro_directories {
+            doctor::check_libretro_directory(&mut report, Some(dir));
+        }
+
+        // Save data directories
+        doctor::check_writable_directory(
+            &mut report,
+            "savefile directory",
+            retroarch_config_map.get("savefile_directory"),
+        );
+        doctor::check_writable_directory(
+            &mut report,
+            "savestate directory",
+            retroarch_config_map.get("savestate_directory"),
+        );
+
+        report.print();
+
+        match report.count(doctor::Status::Fail) {
+            0 => Ok(()),
+            failed => Err(format!("{} checks failed.", failed).into()),
+        }
+    }
+
+    /// Check if the `queue` option is set, to run all games one after another.  Resuming a queue
+    /// runs one too.
+    pub fn is_queue(&self) -> bool {
+        self.queue.unwrap_or(false) || self.is_resume_queue()
+    }
+
+    /// Check if an interrupted queue should be continued.
+    pub fn is_resume_queue(&self) -> bool {
+        self.resume_queue.unwrap_or(false)
+    }
+
+    /// Replace the games by those of the interrupted queue, which are not finished yet.  The
+    /// filter, offset and limit were already applied when the queue started.
+    pub fn load_queue_progress(&mut self) -> Result {
+        let progress: queue::Progress =
+            queue::Progress::load(&queue::progress_file())?
+                .ok_or("No interrupted queue to resume.")?;
+        let remaining: Vec<PathBuf> = progress.remaining();
+
+        eprintln!(
+            "Resuming queue, skipping {} of {} games already finished.",
+            progress.games.len() - remaining.len(),
+            progress.games.len()
+        );
+        self.games = remaining;
+        self.filter = None;
+        self.exclude = None;
+        self.offset = None;
+        self.limit = None;
+
+        Ok(())
+    }
+
+    /// Check if the queue should stop at the first failed game.
+    pub fn is_fail_fast(&self) -> bool {
+        self.fail_fast.unwrap_or(false)
+    }
+
+    /// Check the `summary` of a queue against the exit policy.  An error makes the program exit
+    /// with a nonzero code.
+    pub fn check_queue_summary(&self, summary: &queue::Summary) -> Result {
+        if self
+            .exit_policy
+            .unwrap_or(queue::ExitPolicy::Never)
+            .is_failure(summary)
+        {
+            return Err(format!(
+                "{} of {} games in the queue failed.",
+                summary.failed(),
+                summary.entries.len()
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Check if output should be formatted as JSON.
+    pub fn is_json(&self) -> bool {
+        self.json.unwrap_or(false)
+    }
+
+    /// Run all games matching the filter back-to-back and collect their exit codes and durations.
+    /// A game failing to build its command or to start is recorded with the error and does not
+    /// stop the queue.
+    /// The progress is saved to `queue::progress_file()` and removed after the last game.
+    pub fn run_queue(&self) -> queue::Summary {
+        self.run_queue_with(
+            &provider::RealProcess,
+            Some(&queue::progress_file()),
+        )
+    }
+
+    /// Same as `run_queue()`, but the games are started by the given `runner` and the progress
+    /// is saved to `progress`, if any.
+    pub fn run_queue_with(
+        &self,
+        runner: &dyn provider::ProcessRunner,
+        progress: Option<&Path>,
+    ) -> queue::Summary {
+        let mut summary = queue::Summary::new();
+        let games: Vec<PathBuf> = self
+            .filtered_indices()
+            .into_iter()
+            .map(|index| self.games[index].clone())
+            .collect();
+
+        // Saved as fullpaths, so the queue can be resumed from another directory.
+        let tracked: Vec<PathBuf> = games
+            .iter()
+            .map(|game| {
+                file::to_fullpath(game).unwrap_or_else(|| game.clone())
+            })
+            .collect();
+        let mut progress: Option<&Path> = progress;
+        if let Some(file) = progress {
+            if let Err(error) = queue::start_progress(file, &tracked) {
+                eprintln!(
+                    "Warning: Could not save queue progress to {}: {}",
+                    file.display(),
+                    error
+                );
+                progress = None;
+            }
+        }
+
+        for (game, tracked) in games.iter().zip(&tracked) {
+            let started: Instant = Instant::now();
+            let entry: queue::QueueEntry =
+                match self.build_command_for(Some(game.clone())) {
+                    Ok(mut run) => match self.run_with(runner, &mut run) {
+                        Ok(report) => {
+                            self.count_launch(&run);
+                            queue::QueueEntry {
+                                game: run.game,
+                                exit_code: report
+                                    .as_ref()
+                                    .and_then(|report| report.status.code()),
+                                duration: report.map_or_else(
+                                    || started.elapsed(),
+                                    |report| report.duration,
+                                ),
+                                error: None,
+                            }
+                        }
+                        Err(error) => queue::QueueEntry {
+                            game: run.game,
+                            exit_code: None,
+                            duration: started.elapsed(),
+                            error: Some(error),
+                        },
+                    },
+                    Err(error) => queue::QueueEntry {
+                        game: game.clone(),
+                        exit_code: None,
+                        duration: Duration::ZERO,
+                        error: Some(error),
+                    },
+                };
+            if let Some(file) = progress {
+                queue::mark_done(file, tracked).ok();
+            }
+            let failed: bool = !entry.is_success();
+            summary.entries.push(entry);
+            if failed && self.is_fail_fast() {
+                break;
+            }
+        }
+        // With fail fast the rest is not run, but still part of the summary.  The progress is
+        // kept, so the rest can be run with `--resume-queue`.
+        let stopped: bool = summary.entries.len() < games.len();
+        for game in tracked.iter().skip(summary.entries.len()) {
+            summary.entries.push(queue::QueueEntry {
+                game: game.clone(),
+                exit_code: None,
+                duration: Duration::ZERO,
+                error: None,
+            });
+        }
+        if let Some(file) = progress.filter(|_| !stopped) {
+            fs::remove_file(file).ok();
+        }
+
+        #[cfg(feature = "notify")]
+        if self.is_notify() {
+            let (title, body) = notification::queue_message(&summary);
+            self.send_notification(&title, &body);
+        }
+
+        summary
+    }
+
+    /// Check if the usage of options and rules should be counted.
+    pub fn is_usage_counters(&self) -> bool {
+        self.usage_counters.unwrap_or(false)
+    }
+
+    /// Check if the usage counters should be printed.
+    pub fn is_usage_report(&self) -> bool {
+        self.usage_report.unwrap_or(false)
+    }
+
+    /// Count the options given on the commandline of this program, if `usage_counters` is
+    /// enabled.  Only the names of the options are counted, never their values.
+    pub fn count_options(&self) {
+        let args: Vec<String> = env::args().skip(1).collect();
+        self.count_usage(&usage::options(&args));
+    }
+
+    /// Count the core and the rule which resolved it for a launched game, if `usage_counters` is
+    /// enabled.
+    pub fn count_launch(&self, run: &RunCommand) {
+        let mut names: Vec<String> =
+            vec![format!("core:{}", coreinfo::core_name(&run.libretro))];
+        names.extend(self.matched_rule(&run.game));
+        self.count_usage(&names);
+    }
+
+    /// Add the names to the usage counters.  Failing to do so is only reported, as it should never
+    /// prevent a game from running.
+    fn count_usage(&self, names: &[String]) {
+        if !self.is_usage_counters() {
+            return;
+        }
+        let file: PathBuf = usage::usage_file();
+        if let Err(e) = usage::record(&file, names) {
+            eprintln!(
+                "Warning: Could not update usage counters {}: {}",
+                file.display(),
+                e
+            );
+        }
+    }
+
+    /// Name of the directory or extension rule, which resolves the core of a game, like
+    /// `rule:[.smc]`.  `None` if the core is given by option `libretro` or `core` instead.
+    fn matched_rule(&self, game: &Path) -> Option<String> {
+        if self.libretro.is_some() || self.core.is_some() {
+            return None;
+        }
+        if self.libretro_from_romset(game).is_some() {
+            return romsets::romname(game)
+                .map(|name| format!("rule:[romsets] {}", name));
+        }
+        let directory: Option<&String> = game.parent().and_then(|parent| {
+            self.directory_rules.as_ref().and_then(|rules| {
+                rules
+                    .keys()
+                    .find(|directory| dir_matches(directory, parent))
+            })
+        });
+        let extension = || {
+            self.extension_rules.as_ref().and_then(|rules| {
+                compound_extensions(game)
+                    .into_iter()
+                    .find(|extension| rules.contains_key(*extension))
+                    .map(|extension| format!(".{}", extension))
+            })
+        };
+
+        directory
+            .cloned()
+            .or_else(extension)
+            .map(|rule| format!("rule:[{}]", rule))
+    }
+
+    /// Print the usage counters, most used first, followed by the rules of the user config which
+    /// were never counted.
+    pub fn print_usage_report(&self) -> Result {
+        let file: PathBuf = usage::usage_file();
+        let counters: usage::Counters =
+            usage::Counters::load(&file).map_err(|e| {
+                format!("Could not read {}: {}", file.display(), e)
+            })?;
+        if !self.is_usage_counters() {
+            eprintln!(
+                "Usage counters are disabled.  Enable them with \
+                 `usage_counters = 1` in section `[options]` of the user config."
+            );
+        }
+
+        for (name, count) in counters.sorted() {
+            println!("{:>6}  {}", count, name);
+        }
+
+        let unused: Vec<String> = self
+            .directory_rules
+            .iter()
+            .flat_map(|rules| rules.keys().cloned())
+            .chain(
+                self.extension_rules
+                    .iter()
+                    .flat_map(|rules| rules.keys())
+                    .map(|extension| format!(".{}", extension)),
+            )
+            .map(|rule| format!("rule:[{}]", rule))
+            .filter(|rule| !counters.counts.contains_key(rule))
+            .collect();
+        if !unused.is_empty() {
+            println!();
+            println!("Never used:");
+            for rule in unused {
+                println!("        {}", rule);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if games are recorded in the history of launched games.  Enabled by default.
+    pub fn is_history(&self) -> bool {
+        self.history.unwrap_or(false)
+    }
+
+    /// Check if games should not be launched with a core, which changed since its last run.
+    pub fn is_core_lock(&self) -> bool {
+        self.core_lock.unwrap_or(false)
+            && !self.ignore_core_lock.unwrap_or(false)
+    }
+
+    /// Check if the cores in the history should be verified instead of running a game.
+    pub fn is_verify_cores(&self) -> bool {
+        self.verify_cores.unwrap_or(false)
+    }
+
+    /// Hash of the `libretro` core to launch, compared with the one recorded at its last launch
+    /// in the history.  A changed core is an error with `core_lock` and a warning otherwise.
+    /// `None` if the history is disabled or the core cannot be read, which `RetroArch` reports
+    /// itself.
+    fn check_core(&self, libretro: &Path) -> Result<Option<u64>, String> {
+        if !self.is_history() {
+            return Ok(None);
+        }
+        let Ok(hash) = history::hash_file(libretro) else {
+            return Ok(None);
+        };
+        let entries: Vec<history::Entry> =
+            history::load(&history::history_file()).unwrap_or_default();
+
+        match history::last_hash(&entries, libretro) {
+            Some(last) if last != hash && self.is_core_lock() => Err(format!(
+                "Core changed since its last run, launch with option \
+                 --ignore-core-lock to accept it: {}",
+                libretro.display()
+            )),
+            Some(last) if last != hash => {
+                eprintln!(
+                    "Warning: Core changed since its last run: {}",
+                    libretro.display()
+                );
+                Ok(Some(hash))
+            }
+            _ => Ok(Some(hash)),
+        }
+    }
+
+    /// Slot of the savestate to load with option `load_state`, for the `game` and the `content`
+    /// given to `RetroArch`.  If the history shows a different build of `libretro` at the launch
+    /// of the game the savestate was written in, a warning is printed, as the savestate might not
+    /// work with the current one.
+    fn savestate_slot(
+        &self,
+        slot: savestates::Slot,
+        overrides: &overrides::Overrides,
+        retroarch_config: Option<&Path>,
+        (game, content): (&Path, &Path),
+        libretro: &Path,
+    ) -> Result<u32, String> {
+        let dir: PathBuf = self
+            .save_directories(overrides, retroarch_config, content)
+            .into_iter()
+            .find(|(key, _)| *key == "savestate_directory")
+            .map(|(_, dir)| dir)
+            .unwrap_or_default();
+        let states: Vec<savestates::State> = savestates::find(&dir, content);
+        let state: &savestates::State = savestates::select(&states, slot)
+            .ok_or_else(|| {
+                format!("No savestate found for game: {}", content.display())
+            })?;
+
+        if self.is_history() {
+            let entries: Vec<history::Entry> =
+                history::load(&history::history_file()).unwrap_or_default();
+            let created: Option<u64> =
+                history::hash_at(&entries, libretro, game, state.modified);
+            if created.is_some_and(|created| {
+                history::hash_file(libretro).is_ok_and(|hash| hash != created)
+            }) {
+                eprintln!(
+                    "Warning: Savestate was created with a different build of \
+                     the core and might not load: {}",
+                    state.path.display()
+                );
+            }
+        }
+
+        Ok(state.slot)
+    }
+
+    /// Add a launched game to the history.  Failing to do so is only reported, as it should never
+    /// prevent a game from running.
+    fn record_history(&self, run: &RunCommand, core_hash: u64) {
+        let file: PathBuf = history::history_file();
+        let entry = history::Entry {
+            time: instances::now(),
+            core_hash,
+            core: run.libretro.clone(),
+            game: file::to_fullpath(&run.game)
+                .unwrap_or_else(|| run.game.clone()),
+            title: self.display_title(&run.game),
+            tags: self.tag.clone().unwrap_or_default(),
+            note: self.note.clone().unwrap_or_default(),
+        };
+        if let Err(e) = history::append(&file, &entry) {
+            eprintln!(
+                "Warning: Could not update history {}: {}",
+                file.display(),
+                e
+            );
+        }
+    }
+
+    /// Check if the history of launched games should be printed instead of running a game.
+    pub fn is_history_list(&self) -> bool {
+        self.history_list.unwrap_or(false)
+    }
+
+    /// Check if the number of launches of each game should be printed instead of running a game.
+    pub fn is_stats(&self) -> bool {
+        self.stats.unwrap_or(false)
+    }
+
+    /// Entries of the history with all tags of option `tag`, oldest first.
+    fn tagged_history(&self) -> Result<Vec<history::Entry>, String> {
+        let file: PathBuf = history::history_file();
+        let tags: Vec<String> = self.tag.clone().unwrap_or_default();
+
+        Ok(history::load(&file)
+            .map_err(|e| format!("Could not read {}: {}", file.display(), e))?
+            .into_iter()
+            .filter(|entry| entry.has_tags(&tags))
+            .collect())
+    }
+
+    /// Print each launch in the history with its date, title, tags and note.
+    pub fn print_history_list(&self) -> Result {
+        let offset: i64 = history::local_offset();
+
+        for entry in self.tagged_history()? {
+            let mut line: String = format!(
+                "{}  {}",
+                history::format_time(entry.time, offset),
+                entry.title
+            );
+            if !entry.tags.is_empty() {
+                line.push_str(&format!("  [{}]", entry.tags.join(", ")));
+            }
+            if !entry.note.is_empty() {
+                line.push_str(&format!("  {}", entry.note));
+            }
+            println!("{}", line);
+        }
+
+        Ok(())
+    }
+
+    /// Print the number of launches and the last launch of each game in the history.
+    pub fn print_stats(&self) -> Result {
+        let offset: i64 = history::local_offset();
+        let entries: Vec<history::Entry> = self.tagged_history()?;
+
+        for (title, count, last) in history::stats(&entries) {
+            println!(
+                "{:>6}  {}  {}",
+                count,
+                history::format_time(last, offset),
+                title
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Compare each core in the history with its hash at the last launch and print the result.
+    /// Changed or missing cores are an error.
+    pub fn verify_cores(&self) -> Result {
+        let file: PathBuf = history::history_file();
+        let entries: Vec<history::Entry> =
+            history::load(&file).map_err(|e| {
+                format!("Could not read {}: {}", file.display(), e)
+            })?;
+
+        let mut failed: usize = 0;
+        for (core, last) in history::last_hashes(&entries) {
+            let status: &str = match history::hash_file(core) {
+                Ok(hash) if hash == last => "ok",
+                Ok(_) => "changed",
+                Err(_) => "missing",
+            };
+            if status != "ok" {
+                failed += 1;
+            }
+            println!("{:<8} {}", status, core.display());
+        }
+
+        if failed > 0 {
+            return Err(format!(
+                "{} cores changed since their last run.",
+                failed
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Check if duplicate builds of cores should be looked for instead of running a game.
+    pub fn is_prune_cores(&self) -> bool {
+        self.prune_cores.unwrap_or(false)
+    }
+
+    /// Fullpaths of all cores used by the rules, `fallback_core` and `libretro` of the user
+    /// config.
+    fn referenced_cores(&self) -> HashSet<PathBuf> {
+        let rules = |rules: &Option<IndexMap<String, PathBuf>>| {
+            rules
+                .iter()
+                .flat_map(|rules| rules.values().cloned())
+                .collect::<Vec<_>>()
+        };
+        let fallback: Option<PathBuf> =
+            self.fallback_core.as_ref().map(|fallback| {
+                self.cores_rules
+                    .as_ref()
+                    .and_then(|rules| rules.get(fallback))
+                    .cloned()
+                    .unwrap_or_else(|| PathBuf::from(fallback))
+            });
+
+        rules(&self.cores_rules)
+            .into_iter()
+            .chain(rules(&self.extension_rules))
+            .chain(rules(&self.directory_rules))
+            .chain(self.libretro.clone())
+            .chain(fallback)
+            .filter_map(|libretro| {
+                retroarch::libretro_fullpath(
+                    &provider::RealFs,
+                    self.libretro_directories(),
+                    Some(libretro),
+                    "_libretro.so",
+                )
+            })
+            .collect()
+    }
+
+    /// Print the groups of duplicate builds of the same core in each `libretro_directory` with
+    /// the build to keep and those to remove.  The others are removed only with option `yes`.
+    pub fn prune_cores(&self) -> Result {
+        let referenced: HashSet<PathBuf> = self.referenced_cores();
+        let apply: bool = self.yes.unwrap_or(false);
+        let mut removed: usize = 0;
+        let mut freed: u64 = 0;
+
+        for dir in self.libretro_directories() {
+            let dir: PathBuf = file::tilde(dir);
+            let files: Vec<PathBuf> =
+                prune::core_files(&dir).map_err(|e| {
+                    format!("Could not read {}: {}", dir.display(), e)
+                })?;
+
+            for group in prune::plan(&files, &referenced) {
+                let size = |file: &Path| {
+                    fs::metadata(file)
+                        .map(|metadata| metadata.len())
+                        .unwrap_or(0)
+                };
+                if group.referenced {
+                    println!("{} (used by rules)", group.base);
+                } else {
+                    println!("{}", group.base);
+                }
+                println!(
+                    "  keep    {} ({})",
+                    group.keep.display(),
+                    prune::format_size(size(&group.keep))
+                );
+                for file in &group.remove {
+                    let bytes: u64 = size(file);
+                    let identical: &str =
+                        if prune::is_identical(file, &group.keep) {
+                            ", identical"
+                        } else {
+                            ""
+                        };
+                    println!(
+                        "  remove  {} ({}{})",
+                        file.display(),
+                        prune::format_size(bytes),
+                        identical
+                    );
+                    if apply {
+                        if let Err(e) = fs::remove_file(file) {
+                            eprintln!("Skipped {}: {}", file.display(), e);
+                            continue;
+                        }
+                    }
+                    removed += 1;
+                    freed += bytes;
+                }
+            }
+        }
+
+        if apply {
+            eprintln!(
+                "Removed {} cores, freed {}.",
+                removed,
+                prune::format_size(freed)
+            );
+        } else {
+            eprintln!(
+                "Would remove {} cores, freeing {}.  Nothing removed, use \
+                 --prune-cores --yes to apply.",
+                removed,
+                prune::format_size(freed)
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Check if games should be linked into per-system folders instead of running one.
+    pub fn is_organize(&self) -> bool {
+        self.organize.is_some()
+    }
+
+    /// Create a symlink for each game matching the filter in the `organize` directory, grouped
+    /// in folders by the system of their resolved core.  Games without a matching core or with a
+    /// conflicting file in the way are reported to stderr and skipped.
+    pub fn organize(&self) -> Result {
+        let dir: PathBuf = file::tilde(
+            self.organize.as_ref().expect("organize directory required"),
+        );
+        let mut created: usize = 0;
+        let mut skipped: usize = 0;
+
+        for game in self.filtered_games() {
+            let game: PathBuf = match file::to_fullpath(game) {
+                Some(path) if path.is_file() => path,
+                _ => {
+                    eprintln!(
+                        "Skipped, game file not found: {}",
+                        game.display()
+                    );
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let libretro: PathBuf = match self.resolve_libretro(Some(&game)) {
+                Ok(libretro) => libretro,
+                Err(error) => {
+                    eprintln!("Skipped {}: {}", game.display(), error);
+                    skipped += 1;
+                    continue;
+                }
+            };
+
+            let group: String = organize::group_name(
+                self.core_info(&libretro).and_then(|info| info.system()),
+                &libretro,
+            );
+            match organize::link(&dir, &group, &game)? {
+                (link, organize::Linked::Created) => {
+                    println!("{}", link.display());
+                    created += 1;
+                }
+                (_, organize::Linked::Unchanged) => {}
+                (link, organize::Linked::Conflict) => {
+                    eprintln!(
+                        "Skipped, file already exists: {}",
+                        link.display()
+                    );
+                    skipped += 1;
+                }
+            }
+        }
+
+        eprintln!(
+            "Created {} links in {}, skipped {} games.",
+            created,
+            dir.display(),
+            skipped
+        );
+
+        Ok(())
+    }
+
+    /// All files in the ROM roots from `[paths]` with a known ROM extension, sorted.  Any file is
+    /// a game, if no extension is `known`.
+    fn rom_root_games(&self, known: &HashSet<String>) -> Vec<PathBuf> {
+        let mut games: Vec<PathBuf> = self
+            .rom_roots
+            .iter()
+            .flatten()
+            .flat_map(|root| scan::files(root))
+            .filter(|game| is_known_game(game, known))
+            .collect();
+        games.sort();
+        games.dedup();
+
+        games
+    }
+
+    /// Check if games should be renamed after their title, or only a preview be shown.
+    pub fn is_rename(&self) -> bool {
+        self.rename_preview.unwrap_or(false) || self.rename.unwrap_or(false)
+    }
+
+    /// The title databases are not part of builds without cargo feature `database`.
+    #[cfg(not(feature = "database"))]
+    pub fn rename_games(&self) -> Result {
+        Err(
+            "Option --rename needs a build with cargo feature `database`."
+                .into(),
+        )
+    }
+
+    /// Look up the title of each game in the databases and print the proposed filename.  The
+    /// games are the filtered games, or all games in the ROM roots without any.  With `--rename`
+    /// and `--yes` the files are renamed, never replacing another file.
+    #[cfg(feature = "database")]
+    pub fn rename_games(&self) -> Result {
+        let databases: &[PathBuf] =
+            self.databases.as_deref().unwrap_or_default();
+        if databases.is_empty() {
+            return Err(
+                "No title database, add one with --database or in section \
+                 [databases]."
+                    .into(),
+            );
+        }
+        let db = titles::TitleDb::load(databases)?;
+        if db.is_empty() {
+            return Err("No game titles found in the databases.".into());
+        }
+        let known: &HashSet<String> = self.rom_extensions();
+        let games: Vec<PathBuf> = if self.games.is_empty() {
+            self.rom_root_games(known)
+        } else {
+            self.filtered_games()
+                .filter_map(|game| file::to_fullpath(game))
+                .filter(|game| game.is_file())
+                .collect()
+        };
+
+        let mut names: Vec<(PathBuf, Option<String>)> = Vec::new();
+        for game in games {
+            let title: Option<String> = match db.lookup(&game) {
+                Ok(title) => title.map(str::to_string),
+                Err(error) => {
+                    eprintln!("Skipped {}: {}", game.display(), error);
+                    continue;
+                }
+            };
+            let name: Option<String> = title.map(|title| {
+                let file_name: &str = game
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or_default();
+                let extension: &str =
+                    &file_name[game_title(&game, known).len()..];
+                rename::file_name(&title, extension)
+            });
+            names.push((game, name));
+        }
+
+        let apply: bool =
+            self.rename.unwrap_or(false) && self.yes.unwrap_or(false);
+        let mut renamed: usize = 0;
+        let mut skipped: usize = 0;
+        for proposal in rename::plan(names) {
+            let game: String = proposal.game.display().to_string();
+            let target: String = proposal
+                .target
+                .as_ref()
+                .map(|target| target.display().to_string())
+                .unwrap_or_default();
+            match proposal.status {
+                rename::Status::Rename if apply => {
+                    match rename::apply(&proposal.game, Path::new(&target)) {
+                        Ok(()) => {
+                            println!("{game} -> {target}");
+                            renamed += 1;
+                        }
+                        Err(error) => {
+                            eprintln!("Skipped {game}: {error}");
+                            skipped += 1;
+                        }
+                    }
+                }
+                rename::Status::Rename => {
+                    println!("{game} -> {target}");
+                    renamed += 1;
+                }
+                rename::Status::Unchanged => {}
+                rename::Status::Unknown => {
+                    eprintln!("Skipped, title not found: {game}");
+                    skipped += 1;
+                }
+                rename::Status::Collision => {
+                    eprintln!(
+                        "Skipped, name already taken: {game} -> {target}"
+                    );
+                    skipped += 1;
+                }
+            }
+        }
+
+        if apply {
+            eprintln!("Renamed {renamed} games, skipped {skipped} games.");
+        } else {
+            eprintln!(
+                "Would rename {renamed} games, skipped {skipped} games.  \
+                 Nothing renamed, use --rename --yes to apply."
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Check if desktop notifications are enabled.
+    #[cfg(feature = "notify")]
+    pub fn is_notify(&self) -> bool {
+        self.notify.unwrap_or(false)
+    }
+
+    /// Check if `RetroArch` should be paused while the system suspends.
+    #[cfg(feature = "suspend")]
+    pub fn is_pause_on_suspend(&self) -> bool {
+        self.pause_on_suspend.unwrap_or(false)
+    }
+
+    /// Start listening for suspend of the system, if requested.  The network command interface
+    /// of `RetroArch` is enabled by `overrides()` for it.
+    #[cfg(feature = "suspend")]
+    fn watch_suspend(&self) -> Option<suspend::Watcher> {
+        if !self.is_pause_on_suspend() {
+            return None;
+        }
+
+        suspend::Watcher::start(suspend::DEFAULT_PORT)
+            .map_err(|e| eprintln!("Could not listen for suspend: {}", e))
+            .ok()
+    }
+
+    /// Publish an event to all sinks configured in `[events]`.  Failures are reported, but do not
+    /// stop the program.
+    #[cfg(feature = "events")]
+    fn publish_event(&self, event: &events::Event) {
+        if let Some(config) = &self.events {
+            for sink in events::sinks(config) {
+                if let Err(e) = sink.publish(event) {
+                    eprintln!("Could not publish event: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Show a desktop notification.  A failure is reported, but does not stop the program.
+    #[cfg(feature = "notify")]
+    fn send_notification(&self, title: &str, body: &str) {
+        if let Err(e) = notification::send(title, body) {
+            eprintln!("Could not send notification: {}", e);
+        }
+    }
+
+    /// Print the summary of a queue run as a table or JSON, depending on the `json` option.
+    pub fn print_queue_summary(&self, summary: &queue::Summary) {
+        if self.is_json() {
+            println!("{}", summary.to_json());
+        } else {
+            println!("{}", summary.to_table());
+        }
+    }
+
+    /// Print the report of a launched game as JSON with the `json` option.  Otherwise `RetroArch`
+    /// has printed its own messages already.
+    pub fn print_launch_report(
+        &self,
+        run: &RunCommand,
+        report: &launch::LaunchReport,
+    ) {
+        if self.is_json() {
+            println!("{}", report.to_json(&run.game));
+        }
+    }
+
+    /// Check if option to print entire command is set.
+    pub fn is_which_command(&self) -> bool {
+        self.which_command.unwrap_or(false)
+    }
+
+    /// Check if option to print cores is set.
+    pub fn is_list_cores(&self) -> bool {
+        self.list_cores.unwrap_or(false)
+    }
+
+    /// Compose all sources of `retroarch.cfg` overrides for the next run.
+    pub fn overrides(&self) -> overrides::Overrides {
+        let mut overrides = overrides::Overrides::new();
+
+        #[cfg(feature = "suspend")]
+        if self.is_pause_on_suspend() {
+            overrides.set("network_cmd_enable", "true");
+            overrides
+                .set("network_cmd_port", &suspend::DEFAULT_PORT.to_string());
+        }
+        if let Some(entries) = &self.retroarch_overrides {
+            overrides.extend(entries);
+        }
+        for profile in self.active_profiles() {
+            overrides.extend(&profile.overrides);
+        }
+        // `--volume` and `--mute`
+        overrides.extend(&audio::overrides(self.volume, self.is_mute()));
+
+        overrides
+    }
+
+    /// Check if the audio of `RetroArch` is muted for this run.
+    pub fn is_mute(&self) -> bool {
+        self.mute.unwrap_or(false)
+    }
+
+    /// Run the `mixer_restore_command` after `RetroArch` exited, if the audio was changed with
+    /// option `volume` or `mute`.  Such as to turn up the mixer of the system again, which was
+    /// turned down for a late-night session.  A failure is reported, but does not stop the
+    /// program.
+    fn restore_mixer(&self) {
+        let command: &str = match &self.mixer_restore_command {
+            Some(command) if self.volume.is_some() || self.is_mute() => {
+                command
+            }
+            _ => return,
+        };
+
+        let status = program::Program::parse("mixer_restore_command", command)
+            .and_then(|program| {
+                program
+                    .command()
+                    .stdin(Stdio::null())
+                    .status()
+                    .map_err(|e| {
+                        format!("Could not run mixer_restore_command: {}", e)
+                    })
+            });
+        match status {
+            Ok(status) if !status.success() => {
+                eprintln!("mixer_restore_command failed: {}", status);
+            }
+            Ok(_) => (),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    /// Profiles from the user config, which apply to the next run.  These are the ones whose
+    /// condition is met right now and those given by option `profile`, in the order of the
+    /// config.
+    pub fn active_profiles(&self) -> Vec<&profiles::Profile> {
+        let forced: &[String] = self.profile.as_deref().unwrap_or_default();
+        let environment = profiles::Environment::new();
+
+        self.profiles
+            .iter()
+            .flatten()
+            .filter(|profile| {
+                forced.contains(&profile.name)
+                    || profile.when.as_ref().is_some_and(|when| {
+                        when.matches(|key| environment.get(key))
+                    })
+            })
+            .collect()
+    }
+
+    /// Names of the presets for a game, which are those of option `preset` followed by the one of
+    /// its rules.
+    fn preset_names(&self, game: &Path) -> Vec<String> {
+        self.preset
+            .iter()
+            .flatten()
+            .cloned()
+            .chain(self.rule_options_for(game).preset)
+            .collect()
+    }
+
+    /// Let `RetroArch` use a generated core options file for `game`, which are the current core
+    /// options with the presets applied on top.  It is written to the temporary directory, like
+    /// the overrides.  Nothing happens without any preset.
+    fn apply_presets(
+        &self,
+        overrides: &mut overrides::Overrides,
+        retroarch_config: Option<&Path>,
+        game: &Path,
+    ) -> Result<(), String> {
+        let names: Vec<String> = self.preset_names(game);
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        // Core options have the same format as the overrides.
+        let mut options = overrides::Overrides::new();
+        if let Some(config) = retroarch_config {
+            let key: String = "core_options_path".to_string();
+            let current: PathBuf = retroarch::parse_retroarch_config(
+                &Some(config.into()),
+                &self.appendconfigs(),
+                &HashSet::from([key.clone()]),
+            )
+            .ok()
+            .and_then(|config| config.get(&key).cloned())
+            .filter(|path| !path.is_empty())
+            .map_or_else(
+                || presets::default_core_options(config),
+                |path| file::tilde(Path::new(&path)),
+            );
+            if let Ok(content) = fs::read_to_string(current) {
+                options.extend(&presets::parse_core_options(&content));
+            }
+        }
+        for name in &names {
+            let preset = self
+                .presets
+                .iter()
+                .flatten()
+                .find(|preset| &preset.name == name)
+                .ok_or_else(|| {
+                    format!(
+                        "Preset not found in user config: [{}{}]",
+                        presets::SECTION_PREFIX,
+                        name
+                    )
+                })?;
+            options.extend(&preset.options);
+        }
+
+        let path: PathBuf = if self.is_norun() && !self.is_keep_overrides() {
+            overrides::temp_dir()
+                .join(options.file_name_with(presets::FILE_PREFIX))
+        } else {
+            overrides::create_temp_dir()
+                .and_then(|dir| options.write_with(&dir, presets::FILE_PREFIX))
+                .map_err(|e| {
+                    format!("Could not write core options file: {}", e)
+                })?
+        };
+        overrides.set("core_options_path", &path.display().to_string());
+        overrides.set("global_core_options", "true");
+        overrides.set("game_specific_options", "false");
+
+        Ok(())
+    }
+
+    /// Check if each profile given by option `profile` is defined in the user config.
+    fn check_profile_names(&self) -> Result<(), String> {
+        for name in self.profile.iter().flatten() {
+            if !self.profiles.iter().flatten().any(|p| &p.name == name) {
+                return Err(format!(
+                    "Profile not found in user config: [{}{}]",
+                    profiles::SECTION_PREFIX,
+                    name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if only approved games of section `[lock]` can be launched.  Option `--locked`
+    /// takes precedence over key `enabled` in the user config.
+    pub fn is_locked(&self) -> bool {
+        self.locked.unwrap_or_else(|| {
+            self.lock.as_ref().is_some_and(|lock| lock.enabled)
+        })
+    }
+
+    /// Read the lock from section `[lock]` of fixed locations, which cannot be replaced with options:
+    /// the system wide `/etc/enjoy/lock.ini` or else the default user config.  A `[lock]` in a
+    /// user config given with `--config` has no effect.
+    pub fn read_lock(&mut self) -> Result {
+        self.lock = None;
+
+        for location in [lock::SYSTEM_CONFIG, arguments::DEFAULT_CONFIG] {
+            let path: PathBuf = file::tilde(Path::new(location));
+            if !path.is_file() {
+                continue;
+            }
+            let ini: ini::Ini = Self::read_config_ini(&path)?;
+            let section: IndexMap<String, String> =
+                Self::read_config_section(&ini, "lock");
+            if !section.is_empty() {
+                self.lock.replace(lock::Lock::from_section(&section)?);
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Refuse options getting around the lock while in lock mode: replacing the user config with
+    /// `--config` or `--noconfig`, and content in the arguments for `retroarch`.
+    pub fn check_locked_options(&self) -> Result {
+        if !self.is_locked() {
+            return Ok(());
+        }
+
+        let default: PathBuf =
+            file::tilde(Path::new(arguments::DEFAULT_CONFIG));
+        let is_default = |config: &Path| {
+            file::tilde(config) == default
+                || file::to_fullpath(config).is_some_and(|config| {
+                    file::to_fullpath(&default) == Some(config)
+                })
+        };
+        if self.noconfig.unwrap_or(false)
+            || !self.config.as_deref().map_or(true, is_default)
+        {
+            return Err(
+                "Locked, options --config and --noconfig are not allowed."
+                    .into(),
+            );
+        }
+        // Any other content can be loaded from the menu.
+        if self.is_menu_load() {
+            return Err("Locked, option --menu-load is not allowed.".into());
+        }
+
+        Ok(lock::check_arguments(&self.retroarch_arguments)?)
+    }
+
+    /// Check if the lock mode should be disabled with a password.
+    pub fn is_unlock(&self) -> bool {
+        self.unlock.unwrap_or(false)
+    }
+
+    /// Ask for the password of section `[lock]` on the terminal and disable lock mode for this
+    /// run, if it matches.
+    pub fn unlock(&mut self) -> Result {
+        self.lock
+            .clone()
+            .unwrap_or_default()
+            .unlock(|| lock::read_password("Password: "))?;
+        self.locked = Some(false);
+
+        Ok(())
+    }
+
+    /// Check if `game` may be launched in lock mode.  Without lock mode every game is allowed.
+    fn check_lock(&self, game: &Path) -> Result<(), String> {
+        if !self.is_locked() {
+            return Ok(());
+        }
+
+        self.lock.clone().unwrap_or_default().check(game)
+    }
+
+    /// Check if the blocked and allowed cores of section `[policy]` should be ignored.
+    pub fn is_ignore_policy(&self) -> bool {
+        self.ignore_policy.unwrap_or(false)
+    }
+
+    /// Check if the game should be launched, even if the battery or free memory is too low.
+    pub fn is_force(&self) -> bool {
+        self.force.unwrap_or(false)
+    }
+
+    /// Check the battery and free memory against the limits of section `[policy]`, before
+    /// `libretro` is launched.  Too low resources only print a warning, unless the policy aborts
+    /// with `on_low_resources = abort` and option `force` is not set.
+    fn check_resources(&self, libretro: &Path) -> Result<(), String> {
+        let Some(policy) = &self.policy else {
+            return Ok(());
+        };
+        let messages: Vec<String> = policy.low_resources(
+            libretro,
+            &self.find_core_match(libretro),
+            resources::battery(Path::new(resources::POWER_SUPPLY_DIR)),
+            fs::read_to_string(resources::MEMINFO)
+                .ok()
+                .as_deref()
+                .and_then(resources::available_memory),
+        );
+
+        if policy.on_low_resources == policy::LowResources::Abort
+            && !messages.is_empty()
+            && !self.is_force()
+        {
+            return Err(format!(
+                "{} Use --force to launch anyway.",
+                messages.join(" ")
+            ));
+        }
+        for message in messages {
+            eprintln!("Warning: {}", message);
+        }
+
+        Ok(())
+    }
+
+    /// Check if the checks of section `[preflight]` should be skipped.
+    pub fn is_no_preflight(&self) -> bool {
+        self.no_preflight.unwrap_or(false)
+    }
+
+    /// Run the checks of section `[preflight]` on the save directories for `game`, before it is
+    /// launched.  Warnings are printed to stderr, failed checks are returned as error.
+    fn preflight(
+        &self,
+        overrides: &overrides::Overrides,
+        retroarch_config: Option<&Path>,
+        game: &Path,
+    ) -> Result<(), String> {
+        if self.is_no_preflight() {
+            return Ok(());
+        }
+
+        let checks: preflight::Checks =
+            self.preflight.clone().unwrap_or_default();
+        let directories: Vec<(&str, PathBuf)> =
+            self.save_directories(overrides, retroarch_config, game);
+        let mut unique: Vec<(&str, &Path)> = vec![];
+        for (key, dir) in &directories {
+            if !unique.iter().any(|(_, known)| known == dir) {
+                unique.push((key, dir));
+            }
+        }
+
+        checks
+            .run(&unique)
+            .map_err(|e| format!("Preflight check failed: {}", e))
+    }
+
+    /// Store the saves of `game` in a writable folder of this program, if they would be stored
+    /// next to the game on a read-only mount, like a CD-ROM or squashfs image.  The redirection
+    /// is added to `overrides` and reported on stderr.  The folder is only created, if the game
+    /// is run.
+    fn redirect_saves(
+        &self,
+        overrides: &mut overrides::Overrides,
+        retroarch_config: Option<&Path>,
+        game: &Path,
+    ) -> Result<(), String> {
+        let content_dir: &Path = match game.parent() {
+            Some(dir) if game.has_root() => dir,
+            _ => return Ok(()),
+        };
+        let keys: Vec<&str> = self
+            .save_directories(overrides, retroarch_config, game)
+            .into_iter()
+            .filter(|(_, dir)| dir == content_dir)
+            .map(|(key, _)| key)
+            .collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let mount: mounts::Mount = match mounts::mount_of(content_dir) {
+            Some(mount) if mount.is_read_only() => mount,
+            _ => return Ok(()),
+        };
+
+        let dir: PathBuf = file::data_dir().join("saves").join(format!(
+            "{}-{:08x}",
+            game.file_stem().unwrap_or_default().to_string_lossy(),
+            file::fnv1a(game.as_os_str().as_encoded_bytes()) as u32
+        ));
+        if !self.is_norun() {
+            fs::create_dir_all(&dir).map_err(|e| {
+                format!("Could not create {}: {}", dir.display(), e)
+            })?;
+        }
+        for (key, in_content_dir) in retroarch::SAVE_DIRECTORY_KEYS {
+            if keys.contains(key) {
+                overrides.set(key, &dir.display().to_string());
+                overrides.set(in_content_dir, "false");
+            }
+        }
+        eprintln!(
+            "{} is on read-only mount {}, {} redirected to {}",
+            game.display(),
+            mount.point.display(),
+            keys.join(" and "),
+            dir.display()
+        );
+
+        Ok(())
+    }
+
+    /// Directories `RetroArch` writes the save data of `game` to, each with its key in
+    /// `retroarch.cfg`.  Keys in `overrides` take precedence over those in `retroarch_config`.
+    /// With the value "default" or the `*_in_content_dir` keys enabled, the files are stored next
+    /// to the game.
+    fn save_directories(
+        &self,
+        overrides: &overrides::Overrides,
+        retroarch_config: Option<&Path>,
+        game: &Path,
+    ) -> Vec<(&'static str, PathBuf)> {
+        let keys: HashSet<String> = retroarch::SAVE_DIRECTORY_KEYS
+            .iter()
+            .flat_map(|(key, in_content_dir)| [*key, *in_content_dir])
+            .map(ToString::to_string)
+            .collect();
+        let mut config: IndexMap<String, String> = match retroarch_config {
+            Some(path) => retroarch::parse_retroarch_config(
+                &Some(path.into()),
+                &self.appendconfigs(),
+                &keys,
+            )
+            .unwrap_or_default(),
+            None => IndexMap::new(),
+        };
+        config.extend(
+            overrides
+                .entries
+                .iter()
+                .filter(|(key, _)| keys.contains(key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+
+        let content_dir: PathBuf =
+            game.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut directories: Vec<(&str, PathBuf)> = vec![];
+        for &(key, in_content_dir) in retroarch::SAVE_DIRECTORY_KEYS {
+            let dir: PathBuf = match config.get(key) {
+                _ if config.get(in_content_dir).map(String::as_str)
+                    == Some("true") =>
+                {
+                    content_dir.clone()
+                }
+                Some(dir) if !dir.is_empty() && dir != "default" => {
+                    file::tilde(Path::new(dir))
+                }
+                _ => content_dir.clone(),
+            };
+            if !dir.as_os_str().is_empty() {
+                directories.push((key, dir));
+            }
+        }
+
+        directories
+    }
+
+    /// Check if temporary override files should be kept after the run.
+    pub fn is_keep_overrides(&self) -> bool {
+        self.keep_overrides.unwrap_or(false)
+    }
+
+    /// Check if the game itself is read from stdin.
+    pub fn is_stdin_rom(&self) -> bool {
+        self.stdin_rom.is_some()
+    }
+
+    /// Save the game read from stdin into the temporary directory of this process and make it
+    /// the only game.  It is removed on exit together with the override files.
+    pub fn read_stdin_rom(&mut self) -> Result {
+        let extension: &str = self.stdin_rom.as_deref().unwrap_or_default();
+        if atty::is(atty::Stream::Stdin) {
+            return Err("No game data on stdin for --stdin-rom.".into());
+        }
+
+        let path: PathBuf = overrides::create_temp_dir()
+            .and_then(|dir| {
+                inoutput::write_stdin_rom(
+                    &dir,
+                    extension,
+                    &mut std::io::stdin().lock(),
+                )
+            })
+            .map_err(|e| format!("Could not read game from stdin: {}", e))?;
+        self.games = vec![path];
+
+        Ok(())
+    }
+
+    /// Guard removing the temporary override files of this process when dropped, unless they
+    /// should be kept.
+    pub fn cleanup_overrides(&self) -> overrides::TempDirGuard {
+        overrides::TempDirGuard {
+            keep: self.is_keep_overrides(),
+        }
+    }
+
+    /// Remove temporary files and cached games left behind by earlier runs, like after a crash.
+    /// Files of runs whose `RetroArch` is still running are kept.  Returns the number of removed
+    /// files and directories.
+    pub fn clean_leftovers(&self) -> usize {
+        let running: Vec<instances::Instance> =
+            instances::list(&file::runtime_dir());
+        let owners: HashSet<u32> = running
+            .iter()
+            .filter_map(|instance| instance.owner)
+            .collect();
+        let games: HashSet<PathBuf> = running
+            .iter()
+            .map(|instance| instance.game.clone())
+            .collect();
+
+        janitor::clean(
+            &owners,
+            &games,
+            self.cache_days.unwrap_or(janitor::DEFAULT_CACHE_DAYS),
+        )
+    }
+
+    /// Check if option for long output format of lists is set.
+    pub fn is_long(&self) -> bool {
+        self.long.unwrap_or(false)
+    }
+
+    /// Print all name of cores defined in the section \[cores\] in the config file.  In `long`
+    /// mode the cores are grouped by the system name found in their core info files.
+    pub fn print_cores(&self) -> Result {
+        if let Some(rules) = self.cores_rules.as_ref() {
+            let mut keys: Vec<String> = rules.clone().into_keys().collect();
+            keys.sort_unstable();
+            self.print_core_list(&keys)?;
+        }
+
+        Ok(())
+    }
+
+    /// Print the given core names, one per line.  With a `format` each line is formatted after
+    /// the template, otherwise in `long` mode the cores are grouped by their system.
+    pub fn print_core_list(&self, cores: &[String]) -> Result {
+        if let Some(template) = self.format_template()? {
+            for core in cores {
+                println!("{}", template.render(&self.core_fields(core)));
+            }
+        } else if self.is_long() {
+            self.print_cores_long(cores);
+        } else {
+            for core in cores {
+                println!("{core}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Print given core names grouped under their system names, including the `libretro` path
+    /// each name resolves to.  Cores without info file are listed under "Unknown" at the end.
+    pub fn print_cores_long(&self, cores: &[String]) {
+        let mut groups: IndexMap<String, Vec<(String, PathBuf)>> =
+            IndexMap::new();
+
+        for core in cores {
+            let libretro: PathBuf = self
+                .cores_rules
+                .as_ref()
+                .and_then(|rules| rules.get(core))
+                .cloned()
+                .unwrap_or_default();
+            let system: String = self
+                .core_info(&libretro)
+                .and_then(|info| info.system())
+                .unwrap_or_else(|| "Unknown".to_string());
+            groups
+                .entry(system)
+                .or_default()
+                .push((core.to_string(), libretro));
+        }
+        groups.sort_by(|a, _, b, _| {
+            (a == "Unknown").cmp(&(b == "Unknown")).then(a.cmp(b))
+        });
+
+        for (system, entries) in groups {
+            println!("{system}");
+            for (core, libretro) in entries {
+                match self.core_labels.as_ref().and_then(|l| l.get(&core)) {
+                    Some(label) => println!(
+                        "  {}\t{}\t{}",
+                        core,
+                        libretro.display(),
+                        label
+                    ),
+                    None => println!("  {}\t{}", core, libretro.display()),
+                }
+            }
+        }
+    }
+
+    /// Check if the table of extensions supported by the cores should be printed.
+    pub fn is_matrix(&self) -> bool {
+        self.matrix.unwrap_or(false)
+    }
+
+    /// Print a table of all core aliases from `[cores]` against the extensions their core info
+    /// files list as supported, marking which of them the extension rules use.
+    pub fn print_matrix(&self) -> Result {
+        let columns: Vec<matrix::Column> = self
+            .cores_rules
+            .iter()
+            .flatten()
+            .map(|(alias, libretro)| {
+                matrix::column(
+                    alias,
+                    libretro,
+                    self.libretro_info_directory.as_deref(),
+                )
+            })
+            .collect();
+        let rules: IndexMap<String, PathBuf> =
+            self.extension_rules.clone().unwrap_or_default();
+
+        println!("{}", matrix::table(&columns, &rules));
+        println!("\n{}", matrix::LEGEND);
+
+        Ok(())
+    }
+
+    /// Read the core info file of a `libretro` core from the `libretro_info_directory`.
+    fn core_info(&self, libretro: &Path) -> Option<coreinfo::CoreInfo> {
+        coreinfo::load(self.libretro_info_directory.as_ref()?, libretro)
+    }
+
+    /// Check if an instance of `RetroArch` is already running, if the single instance mode
+    /// `highlander` is active.  Otherwise its always `false`.  Instances launched by this program
+    /// are looked up in the registry first.  Other processes are only counted, if they are started
+    /// from the configured `retroarch` executable and in the `highlander_scope`.
+    #[must_use]
+    pub fn there_can_only_be_one(&self) -> bool {
+        if !self.highlander.unwrap_or(false) {
+            return false;
+        }
+
+        if let Some(instance) = instances::list(&file::runtime_dir()).first() {
+            println!("{}", instance.pid);
+            return true;
+        }
+
+        retroarch::is_running(
+            "retroarch",
+            self.retroarch.as_deref(),
+            self.highlander_scope.unwrap_or(process::Scope::Any),
+            true,
+        )
+    }
+
+    /// Check if this launch follows another one within `debounce` milliseconds, such as from a
+    /// double click, so it should be skipped.  Otherwise it is recorded for the next invocation.
+    /// A failed check is reported, but does not prevent the launch.
+    #[must_use]
+    pub fn is_debounced(&self) -> bool {
+        let window: Duration = match self.debounce {
+            Some(millis) if millis > 0 => Duration::from_millis(millis),
+            _ => return false,
+        };
+
+        debounce::is_too_soon(&file::runtime_dir(), window, SystemTime::now())
+            .unwrap_or_else(|e| {
+                eprintln!(
+                    "Warning: Could not check the launch debounce: {}",
+                    e
+                );
+                false
+            })
+    }
+
+    /// Check if the gamepad hotkey mode is requested.
+    pub fn is_hotkey_listen(&self) -> bool {
+        self.hotkey_listen.unwrap_or(false)
+    }
+
+    /// Wait for the button combination from `hotkey_buttons` on the gamepad `hotkey_device`, until
+    /// the program is terminated.  Each time it is pressed and no `RetroArch` is running, the
+    /// game is launched like without this mode.
+    pub fn hotkey_listen(&self) -> Result {
+        let combo = hotkey::Combo::parse(
+            self.hotkey_buttons
+                .as_deref()
+                .unwrap_or(hotkey::DEFAULT_BUTTONS),
+        )?;
+        let device: PathBuf = self.hotkey_device.as_ref().map_or_else(
+            || PathBuf::from(hotkey::DEFAULT_DEVICE),
+            |device| file::tilde(device),
+        );
+        if !self.is_game_available() {
+            return Err("No game to launch with the hotkey.  Give a game \
+                 or set option `game` in the user config."
+                .into());
+        }
+        eprintln!("Listening on joystick: {}", device.display());
+
+        hotkey::listen(&device, combo, || {
+            if !instances::list(&file::runtime_dir()).is_empty()
+                || retroarch::is_running(
+                    "retroarch",
+                    self.retroarch.as_deref(),
+                    process::Scope::Any,
+                    false,
+                )
+            {
+                eprintln!("Hotkey ignored, retroarch already running.");
+                return;
+            }
+            match self.build_command() {
+                Ok(mut run) => {
+                    eprintln!("Hotkey: {}", run.game.display());
+                    self.announce_title(&run.game);
+                    if let Err(e) = self.run(&mut run) {
+                        eprintln!("Hotkey: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Hotkey: {}", e),
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Check if the HTTP server mode is requested.
+    pub fn is_serve(&self) -> bool {
+        self.serve.is_some()
+    }
+
+    /// The HTTP server is not part of builds without cargo feature `serve`.
+    #[cfg(not(feature = "serve"))]
+    pub fn serve(&self) -> Result {
+        Err("Option --serve needs a build with cargo feature `serve`.".into())
+    }
+
+    /// Run the HTTP server on localhost until the program is terminated.  Every request needs
+    /// the secret from the `token_file`, which is created with a random token if missing.
+    #[cfg(feature = "serve")]
+    pub fn serve(&self) -> Result {
+        let token_file: PathBuf = match &self.token_file {
+            Some(file) => file::tilde(file),
+            None => {
+                file::private_dir(&file::runtime_dir()).map_err(|e| {
+                    format!("Could not create runtime directory: {}", e)
+                })?;
+                file::runtime_dir().join("token")
+            }
+        };
+        let token: String = server::load_token(&token_file).map_err(|e| {
+            format!(
+                "Could not read token file {}: {}",
+                token_file.display(),
+                e
+            )
+        })?;
+        eprintln!("Token file: {}", token_file.display());
+
+        let port: u16 = self.serve.flatten().unwrap_or(server::DEFAULT_PORT);
+        #[cfg(feature = "tokio")]
+        {
+            let settings: Self = self.clone();
+            supervisor::block_on(server::serve_async(
+                port,
+                token,
+                move |request| settings.handle_request(request),
+            ))??;
+        }
+        #[cfg(not(feature = "tokio"))]
+        server::serve(port, &token, |request| self.handle_request(request))?;
+
+        Ok(())
+    }
+
+    /// Answer a single request of the HTTP server.
+    ///
+    /// - `POST /launch` with a body like `{"game": "/roms/mario.smc", "core": "snes"}` starts a
+    ///   game in the background.  `core` is optional.
+    /// - `POST /quit` terminates all running instances launched by this program.
+    ///
+    /// A launched game is returned as a job, which the server runs in the background.
+    #[cfg(feature = "serve")]
+    fn handle_request(&self, request: &server::Request) -> server::Reply {
+        let response: server::Response =
+            match (request.method.as_str(), request.path.as_str()) {
+                ("POST", "/launch") => {
+                    return self.handle_launch(&request.body)
+                }
+                ("POST", "/quit") => {
+                    let mut terminated: Vec<String> = vec![];
+                    for instance in instances::list(&file::runtime_dir()) {
+                        if let Ok(true) = instances::terminate(&instance) {
+                            terminated.push(instance.pid.to_string());
+                        }
+                    }
+                    server::Response::json(
+                        200,
+                        format!(
+                            "{{\"terminated\":[{}]}}",
+                            terminated.join(",")
+                        ),
+                    )
+                }
+                (_, "/launch" | "/quit") => {
+                    server::Response::error(405, "only POST is allowed")
+                }
+                _ => server::Response::error(404, "not found"),
+            };
+
+        (response, None)
+    }
+
+    #[cfg(feature = "serve")]
+    fn handle_launch(&self, body: &str) -> server::Reply {
+        let fields: IndexMap<String, String> =
+            match inoutput::parse_json_object(body) {
+                Ok(fields) => fields,
+                Err(e) => return (server::Response::error(400, &e), None),
+            };
+        if let Some(key) = fields
+            .keys()
+            .find(|key| !matches!(key.as_str(), "game" | "core"))
+        {
+            return (
+                server::Response::error(
+                    400,
+                    &format!("unknown field \"{}\"", key),
+                ),
+                None,
+            );
+        }
+        let game: PathBuf = match fields.get("game") {
+            Some(game) if !game.is_empty() => PathBuf::from(game),
+            _ => {
+                return (
+                    server::Response::error(400, "field \"game\" required"),
+                    None,
+                )
+            }
+        };
+
+        let mut settings: Self = self.clone();
+        settings.games = vec![game.clone()];
+        settings.filter = None;
+        settings.norun = None;
+        if let Some(core) = fields.get("core") {
+            settings.core = Some(core.to_string());
+            settings.libretro = None;
+        }
+        // Another request could launch right after the check, so the check and the launch are
+        // done under a claim, which is held until the game exits.
+        let claim: Option<server::LaunchClaim> =
+            if settings.highlander.unwrap_or(false) {
+                match server::LaunchClaim::acquire() {
+                    Some(claim) => Some(claim),
+                    None => {
+                        return (
+                            server::Response::error(
+                                409,
+                                "retroarch already running",
+                            ),
+                            None,
+                        )
+                    }
+                }
+            } else {
+                None
+            };
+        if settings.there_can_only_be_one() {
+            return (
+                server::Response::error(409, "retroarch already running"),
+                None,
+            );
+        }
+
+        match settings.build_command_for(Some(game)) {
+            Ok(mut run) => {
+                let body: String = format!(
+                    "{{\"status\":\"launched\",\"game\":{},\"libretro\":{}}}",
+                    inoutput::json_string(&run.game.display().to_string()),
+                    inoutput::json_string(&run.libretro.display().to_string())
+                );
+                let job: server::Job = Box::new(move || {
+                    let _claim = claim;
+                    if let Err(e) = settings.run(&mut run) {
+                        eprintln!("{}", e);
+                    }
+                });
+                (server::Response::json(202, body), Some(job))
+            }
+            Err(e) => (server::Response::error(422, &e), None),
+        }
+    }
+
+    /// Check if option to list running instances is set.
+    pub fn is_list_instances(&self) -> bool {
+        self.list_instances.unwrap_or(false)
+    }
+
+    /// Check if option to quit running instances is set.
+    pub fn is_quit(&self) -> bool {
+        self.quit.unwrap_or(false)
+    }
+
+    /// Print all running `RetroArch` instances launched by this program, as a table or JSON.
+    pub fn print_instances(&self) {
+        let running: Vec<instances::Instance> =
+            instances::list(&file::runtime_dir());
+
+        if self.is_json() {
+            let entries: Vec<String> =
+                running.iter().map(instances::Instance::to_json).collect();
+            println!("[{}]", entries.join(","));
+        } else {
+            println!("{}", instances::to_table(&running));
+        }
+    }
+
+    /// Ask all running `RetroArch` instances launched by this program to quit.  The pid of each
+    /// terminated instance is printed.
+    pub fn quit_instances(&self) -> Result {
+        let mut failed: usize = 0;
+
+        for instance in instances::list(&file::runtime_dir()) {
+            match instances::terminate(&instance) {
+                Ok(true) => println!("{}", instance.pid),
+                Ok(false) => failed += 1,
+                Err(e) => {
+                    eprintln!(
+                        "Could not terminate instance {}: {}",
+                        instance.pid, e
+                    );
+                    failed += 1;
+                }
             }
         }
+
+        if failed > 0 {
+            return Err(format!(
+                "{} instances could not be terminated.",
+                failed
+            )
+            .into());
+        }
+
+        Ok(())
     }
 
-    /// Check if an instance of `RetroArch` is already running, if the single instance mode
-    /// `highlander` is active.  Otherwise its always `false`.
-    #[must_use]
-    pub fn there_can_only_be_one(&self) -> bool {
-        self.highlander.unwrap_or(false)
-            && retroarch::is_running("retroarch", true)
+    /// Launch the game of a `RunCommand` and count its use.  This is the entrypoint for
+    /// frontends, which get a report of how `RetroArch` ended.  `None` with option `norun`.
+    pub fn launch(
+        &self,
+        run: &mut RunCommand,
+    ) -> Result<Option<launch::LaunchReport>, String> {
+        let report: Option<launch::LaunchReport> = self.run(run)?;
+        self.count_launch(run);
+
+        Ok(report)
+    }
+
+    /// Execute the `cmdline` of the given `RunCommand` to run the program with its arguments and
+    /// report how it ended.  Do not execute it, if the option `norun` is active.  While the
+    /// program runs, it is recorded in the instance registry.  Returns an error, if the program
+    /// could not be started or waited for.
+    pub fn run(
+        &self,
+        run: &mut RunCommand,
+    ) -> Result<Option<launch::LaunchReport>, String> {
+        self.run_with(&provider::RealProcess, run)
     }
 
-    /// Execute the given `Command` to run the program with its arguments and return its `output`.
-    /// Do not execute it, if the option `norun` is active.
-    pub fn run(&self, command: &mut Command) -> Option<Output> {
+    /// Same as `run()`, but the command is started by the given `runner`.
+    pub fn run_with(
+        &self,
+        runner: &dyn provider::ProcessRunner,
+        run: &mut RunCommand,
+    ) -> Result<Option<launch::LaunchReport>, String> {
         if self.norun.unwrap_or(false) {
-            None
+            Ok(None)
         } else {
-            let output: Output =
-                command.output().expect("Error! Could not run RetroArch.");
+            let core_hash: Option<u64> = self.check_core(&run.libretro)?;
+            let started: Instant = Instant::now();
+            let child = runner
+                .spawn(
+                    run.cmdline
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::piped())
+                        .stderr(Stdio::piped()),
+                )
+                .map_err(|e| format!("Could not run RetroArch: {}", e))?;
+            if !run.scheduling.is_empty() {
+                if let Err(e) = run.scheduling.apply(child.id()) {
+                    eprintln!(
+                        "Could not set CPU priority of RetroArch: {}",
+                        e
+                    );
+                }
+            }
+            if let Some(core_hash) = core_hash {
+                self.record_history(run, core_hash);
+            }
+
+            let registry: PathBuf = file::runtime_dir();
+            let instance =
+                instances::Instance::new(child.id(), &run.game, &run.libretro);
+            if let Err(e) = instances::register(&registry, &instance) {
+                eprintln!("Could not write instance record: {}", e);
+            }
+            #[cfg(feature = "events")]
+            self.publish_event(&events::Event::launch(
+                &self.display_title(&run.game),
+                &run.libretro,
+            ));
+            #[cfg(feature = "suspend")]
+            let watcher = self.watch_suspend();
+            // `--timeout`
+            let deadline: Option<launch::Deadline> =
+                self.timeout.filter(|seconds| *seconds > 0).map(|seconds| {
+                    launch::Deadline::start(
+                        instance.pid,
+                        Duration::from_secs(seconds),
+                    )
+                });
+
+            let waited = child.wait();
+            let timed_out: bool =
+                deadline.is_some_and(launch::Deadline::finish);
+            #[cfg(feature = "suspend")]
+            drop(watcher);
+            instances::unregister(&registry, instance.pid);
+            self.restore_mixer();
+            let output: Output = waited
+                .map_err(|e| format!("Could not wait for RetroArch: {}", e))?;
+            #[cfg(feature = "events")]
+            self.publish_event(&events::Event::exit(
+                &self.display_title(&run.game),
+                &run.libretro,
+                started.elapsed(),
+                output.status.code(),
+            ));
+
+            // In a queue only the summary at the end is notified.
+            #[cfg(feature = "notify")]
+            if self.is_notify() && !self.is_queue() {
+                if let Some((title, body)) = notification::run_message(
+                    &self.display_title(&run.game),
+                    &output.status,
+                    started.elapsed(),
+                ) {
+                    self.send_notification(&title, &body);
+                }
+            }
+
+            if timed_out {
+                eprintln!(
+                    "RetroArch terminated after the timeout of {} seconds.",
+                    self.timeout.unwrap_or_default()
+                );
             // if output.status.to_string() != *"exit code: 0" {
-            if output.status.to_string() != *"exit status: 0" {
+            } else if output.status.to_string() != *"exit status: 0" {
                 eprintln!("Could not run RetroArch. {}", output.status);
             }
 
-            Some(output)
+            Ok(Some(launch::LaunchReport::new(
+                output,
+                started.elapsed(),
+                timed_out,
+            )))
         }
     }
 }
 
+/// Stable hash over everything that defines a launch: program, arguments, working directory,
+/// explicitly set environment variables and the content of the override config.  The temporary
+/// directory of the current process in arguments is replaced by a placeholder, as it differs on
+/// each run.
+fn command_fingerprint(
+    command: &Command,
+    overrides_cfg: &str,
+    temp_dir: &Path,
+) -> u64 {
+    let temp_dir: String = temp_dir.display().to_string();
+    let mut text: String = command.get_program().to_string_lossy().to_string();
+
+    for arg in command.get_args() {
+        text.push('\0');
+        text.push_str(&arg.to_string_lossy().replace(&temp_dir, "$TEMP"));
+    }
+    text.push_str("\0cwd=");
+    if let Some(dir) = command.get_current_dir() {
+        text.push_str(&dir.to_string_lossy());
+    }
+    for (key, value) in command.get_envs() {
+        text.push_str(&format!(
+            "\0env={}={}",
+            key.to_string_lossy(),
+            value.unwrap_or_default().to_string_lossy()
+        ));
+    }
+    text.push_str("\0overrides=");
+    text.push_str(overrides_cfg);
+
+    file::fnv1a(text.as_bytes())
+}
+
+/// Check if `game` has one of the `known` ROM extensions, compared in lowercase.  Any file is a
+/// game, if no extension is known.
+fn is_known_game(game: &Path, known: &HashSet<String>) -> bool {
+    known.is_empty()
+        || compound_extensions(game)
+            .iter()
+            .any(|extension| known.contains(&extension.to_lowercase()))
+}
+
+/// All extensions of a game from the longest compound one down to the last extension alone, like
+/// `tar.gz` and `gz` for `game.tar.gz`.  The leading dot of a hidden file does not start an
+/// extension.
+fn compound_extensions(game: &Path) -> Vec<&str> {
+    let file_name: &str = match game.file_name().and_then(|name| name.to_str())
+    {
+        Some(name) => name,
+        None => return vec![],
+    };
+
+    file_name
+        .match_indices('.')
+        .filter(|(index, _)| *index > 0 && *index + 1 < file_name.len())
+        .map(|(index, _)| &file_name[index + 1..])
+        .collect()
+}
+
+/// The Nth game of `games` starting at 1, or counted from the end with a negative `index`.
+fn nth_game(games: &[&PathBuf], index: isize) -> Option<PathBuf> {
+    let position: usize = if index < 0 {
+        games.len().checked_sub(index.unsigned_abs())?
+    } else {
+        usize::try_from(index).ok()?.checked_sub(1)?
+    };
+
+    games.get(position).map(|game| (*game).clone())
+}
+
+/// Parse the value of option `--index`, which must not be 0.
+pub fn parse_index(text: &str) -> std::result::Result<isize, String> {
+    text.parse::<isize>()
+        .ok()
+        .filter(|index| *index != 0)
+        .ok_or_else(|| {
+            format!(
+                "Invalid index \"{}\", expected 1, 2, ... or -1, -2, ...",
+                text
+            )
+        })
+}
+
+/// Filename of a game without its ROM extension, used as its title and for filters.  Only the
+/// longest extension in `known` is removed, compared in lowercase, like `smc.gz` for
+/// `Game (USA).v1.1.smc.gz`.  Without a known one, the last extension is removed only if it looks
+/// like one: up to 5 letters and digits with at least one letter.  So `Game (USA).v1.1` keeps the
+/// version, where `Path::file_stem()` would cut it.
+fn game_title<'a>(game: &'a Path, known: &HashSet<String>) -> &'a str {
+    let file_name: &str = game
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let extensions: Vec<&str> = compound_extensions(game);
+    let is_known = |extension: &str| {
+        !known.is_empty()
+            && if extension.bytes().any(|b| b.is_ascii_uppercase()) {
+                known.contains(&extension.to_lowercase())
+            } else {
+                known.contains(extension)
+            }
+    };
+    let extension: Option<&str> = extensions
+        .iter()
+        .find(|extension| is_known(extension))
+        .or_else(|| {
+            extensions.last().filter(|extension| {
+                extension.len() <= 5
+                    && extension.chars().all(|c| c.is_ascii_alphanumeric())
+                    && extension.chars().any(|c| c.is_ascii_alphabetic())
+            })
+        })
+        .copied();
+
+    match extension {
+        Some(extension) => &file_name[..file_name.len() - extension.len() - 1],
+        None => file_name,
+    }
+}
+
+/// Lookup the rule of the longest extension of a game, that has an entry in `rules`.  A
+/// compressed game like `game.smc.zst` falls back to the extensions of `game.smc`.
+fn find_by_extension<'a, T>(
+    rules: &'a IndexMap<String, T>,
+    game: &Path,
+) -> Option<&'a T> {
+    let inner: Option<PathBuf> = compressed::inner(game);
+
+    compound_extensions(game)
+        .into_iter()
+        .chain(
+            inner
+                .as_deref()
+                .map(compound_extensions)
+                .unwrap_or_default(),
+        )
+        .find_map(|extension| rules.get(extension))
+}
+
+/// Expand the section name of a directory rule to the patterns it stands for.  Absolute paths and
+/// those starting with a tilde are a single pattern.  Relative ones like `snes/` are joined to
+/// each ROM root and match nothing without any root.
+fn directory_patterns(section: &str, roots: &[PathBuf]) -> Vec<String> {
+    if section.starts_with('/') || section.starts_with('~') {
+        return vec![shellexpand::tilde(section).to_string()];
+    }
+
+    roots
+        .iter()
+        .map(|root| root.join(section).display().to_string())
+        .collect()
+}
+
+/// Compare the directory pattern of a rule with the parent folder of a game.  The pattern
+/// supports the wildcards `*` and `?`, a trailing slash is ignored.
+fn dir_matches(directory: &str, game_parent: &Path) -> bool {
+    WildMatch::new(&file::trim_last_slash(directory.to_string())).matches(
+        game_parent
+            .as_os_str()
+            .to_str()
+            .expect("game folder as valid string"),
+    )
+}
+
 #[cfg(test)]
 mod tests {
 
+    use std::collections::HashSet;
     use std::path::PathBuf;
+    use std::sync::OnceLock;
 
     use configparser::ini;
     use indexmap::map::IndexMap;
 
+    use super::provider::fake::FakeRunner;
+    use super::provider::fake::MemFs;
+
     type Result<T = (), E = Box<dyn std::error::Error>> =
         std::result::Result<T, E>;
 
@@ -1053,6 +6533,10 @@ mod tests {
     //  - Settings::print_cores()
     //  - Settings::there_can_only_be_one()
     //  - Settings::run()
+    //  - Settings::restore_mixer()
+    //  - Settings::print_fzf()
+    //  - Settings::print_fzf_snippet()
+    //  - Settings::print_complete_games()
 
     #[test]
     fn new_from_defaults_retroarch() -> Result<()> {
@@ -1080,6 +6564,129 @@ mod tests {
             cores_rules: None,
             extension_rules: None,
             directory_rules: None,
+            doctor: None,
+            libretro_info_directory: None,
+            long: None,
+            queue: None,
+            json: None,
+            workdir: None,
+            env: None,
+            rule_options: None,
+            isolated: None,
+            retroarch_overrides: None,
+            keep_overrides: None,
+            highlander_scope: None,
+            list_instances: None,
+            quit: None,
+            notify: None,
+            events: None,
+            serve: None,
+            token_file: None,
+            print_env: None,
+            retroarch_stub: None,
+            fingerprint: None,
+            limit: None,
+            offset: None,
+            core_labels: None,
+            migrate_config: None,
+            rom_roots: None,
+            organize: None,
+            databases: None,
+            rename_preview: None,
+            rename: None,
+            yes: None,
+            format: None,
+            fzf: None,
+            fzf_snippet: None,
+            launch_from_fzf: None,
+            hotkey_listen: None,
+            hotkey_device: None,
+            hotkey_buttons: None,
+            pause_on_suspend: None,
+            preflight: None,
+            no_preflight: None,
+            profiles: None,
+            profile: None,
+            check_config: None,
+            policy: None,
+            ignore_policy: None,
+            lock: None,
+            locked: None,
+            unlock: None,
+            known_extensions: OnceLock::new(),
+            complete_games: None,
+            which_core: None,
+            relative: None,
+            resume_queue: None,
+            fail_fast: None,
+            exit_policy: None,
+            stdin_timeout: None,
+            keep_duplicates: None,
+            coreset: None,
+            coresets: None,
+            diff_config: None,
+            usage_counters: None,
+            usage_report: None,
+            title: None,
+            fallback_core: None,
+            menu_load: None,
+            presets: None,
+            preset: None,
+            history: None,
+            core_lock: None,
+            ignore_core_lock: None,
+            verify_cores: None,
+            tag: None,
+            note: None,
+            history_list: None,
+            stats: None,
+            export_list: None,
+            prune_cores: None,
+            remote: None,
+            remotes: None,
+            romsets: None,
+            stdin_rom: None,
+            startup_timeout: None,
+            retroarch_origin: None,
+            retroarch_directory: None,
+            which_retroarch: None,
+            suggestions: None,
+            timeout: None,
+            stdin_rules: None,
+            tts_command: None,
+            nice: None,
+            cpus: None,
+            core_options: None,
+            cache_days: None,
+            match_anchor: None,
+            interactive: None,
+            fuzzy: None,
+            selector_command: None,
+            matrix: None,
+            index: None,
+            exclude: None,
+            undo_config: None,
+            spawner: None,
+            load_state: None,
+            choose: None,
+            list_games: None,
+            core_env: None,
+            env_defaults: None,
+            count: None,
+            null: None,
+            escape: None,
+            games_from: None,
+            ai_service: None,
+            ai_service_mode: None,
+            ai_service_url: None,
+            recursive: None,
+            force: None,
+            sort: None,
+            unique: None,
+            debounce: None,
+            volume: None,
+            mute: None,
+            mixer_restore_command: None,
         };
 
         let defaults = super::Settings::new_from_defaults();
@@ -1158,6 +6765,7 @@ mod tests {
             md = genesis_plus_gx_libretro.so
             mdwide = genesis_plus_gx_wide
             gb gbc = sameboy
+            nes = { libretro = \"mesen\", label = \"NES\", extensions = \"nes .FDS\" }
 
 
             [/bin*]
@@ -1167,6 +6775,14 @@ mod tests {
             [path_without_slash]
             core = snes
 
+            [paths]
+            roms = /mnt/roms
+            empty =
+            nas = ~/roms
+
+            [psx/]
+            core = snes
+
             [.smc .sfc]
             core = snes
 
@@ -1233,129 +6849,656 @@ mod tests {
             Some(PathBuf::from("/usr/bin/retroarch")),
             settings.retroarch
         );
-        assert_eq!(Some(PathBuf::from("")), settings.libretro_directory);
+        assert_eq!(Some(vec![PathBuf::from("")]), settings.libretro_directory);
         assert_eq!(None, settings.retroarch_config);
 
-        Ok(())
+        Ok(())
+    }
+
+    #[test]
+    fn read_config_options_bool() -> Result<()> {
+        let mut settings = super::Settings::new();
+        let ini = test_ini_template();
+
+        super::Settings::read_config_options(
+            &mut settings,
+            &ini,
+            &["options".to_string()],
+        )?;
+
+        assert_eq!(Some(false), settings.which);
+        assert_eq!(Some(true), settings.norun);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_config_section() {
+        let ini = test_ini_template();
+
+        let overrides =
+            super::Settings::read_config_section(&ini, "retroarch");
+
+        assert_eq!(Some(&"1".to_string()), overrides.get("which"));
+        assert_eq!(Some(&"0".to_string()), overrides.get("doesexist"));
+        assert_eq!(None, overrides.get("doesnotexist"));
+    }
+
+    #[test]
+    fn read_config_cores_rules() -> Result<()> {
+        let ini = test_ini_template();
+
+        let rules = super::Settings::read_config_cores_rules(&ini);
+
+        assert_eq!(Some(&PathBuf::from("snes9x")), rules.get("snes"));
+        assert_eq!(
+            Some(&PathBuf::from("genesis_plus_gx_libretro.so")),
+            rules.get("md")
+        );
+        assert_eq!(None, rules.get("retroarch"));
+        assert_eq!(Some(&PathBuf::from("mesen")), rules.get("nes"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_config_cores_extension_rules() {
+        let ini = test_ini_template();
+
+        let ext_rules =
+            super::Settings::read_config_cores_extension_rules(&ini);
+
+        assert_eq!(Some(&PathBuf::from("mesen")), ext_rules.get("nes"));
+        assert_eq!(Some(&PathBuf::from("mesen")), ext_rules.get("fds"));
+        assert_eq!(2, ext_rules.len());
+    }
+
+    #[test]
+    fn read_config_extension_rules() {
+        let ini = test_ini_template();
+
+        let ext_rules = super::Settings::read_config_extension_rules(
+            &Some(test_ini_cores_rules_template()),
+            &ini,
+            &ini.sections(),
+        );
+
+        assert_eq!(Some(&PathBuf::from("snes9x")), ext_rules.get("sfc"));
+        assert_eq!(
+            Some(&PathBuf::from("genesis_plus_gx_wide")),
+            ext_rules.get("mdwide")
+        );
+        assert_eq!(None, ext_rules.get(""));
+    }
+
+    #[test]
+    fn read_config_directory_rules() {
+        let ini = test_ini_template();
+
+        let dir_rules = super::Settings::read_config_directory_rules(
+            &Some(test_ini_cores_rules_template()),
+            &ini,
+            &ini.sections(),
+            &[],
+        );
+
+        assert_eq!(
+            Some(&PathBuf::from("mednafen_psx_hw")),
+            dir_rules.get("/bin*")
+        );
+        assert_ne!(Some(&PathBuf::from("md")), dir_rules.get("/bin"));
+        assert_eq!(None, dir_rules.get("path_without_slash"));
+        assert_eq!(None, dir_rules.get("psx/"));
+    }
+
+    #[test]
+    fn read_config_directory_rules_relative() {
+        let ini = test_ini_template();
+        let roots = super::Settings::read_config_rom_roots(&ini);
+
+        let dir_rules = super::Settings::read_config_directory_rules(
+            &Some(test_ini_cores_rules_template()),
+            &ini,
+            &ini.sections(),
+            &roots,
+        );
+
+        assert_eq!(2, roots.len());
+        assert_eq!(PathBuf::from("/mnt/roms"), roots[0]);
+        assert!(!roots[1].starts_with("~"));
+        assert_eq!(
+            Some(&PathBuf::from("snes9x")),
+            dir_rules.get("/mnt/roms/psx/")
+        );
+        assert_eq!(
+            Some(&PathBuf::from("snes9x")),
+            dir_rules.get(&roots[1].join("psx/").display().to_string())
+        );
+    }
+
+    #[test]
+    fn libretro_from_ext() {
+        let mut ext_rules: IndexMap<String, PathBuf> = IndexMap::new();
+        ext_rules.insert("sfc".to_string(), PathBuf::from("mesen-s"));
+        ext_rules.insert("smc".to_string(), PathBuf::from("mesen-s"));
+        ext_rules.insert("gb".to_string(), PathBuf::from("sameboy"));
+        ext_rules.insert("gbc".to_string(), PathBuf::from("sameboy"));
+        ext_rules.insert("gz".to_string(), PathBuf::from("gzip"));
+        ext_rules.insert("sfc.gz".to_string(), PathBuf::from("snes9x"));
+
+        let settings = super::Settings {
+            games: vec![],
+            retroarch_arguments: vec![],
+            config: None,
+            retroarch: Some(PathBuf::from("retroarch")),
+            retroarch_config: None,
+            libretro: None,
+            libretro_directory: None,
+            core: None,
+            filter: Some(vec!["[!]".to_string()]),
+            strict: None,
+            which: None,
+            which_command: None,
+            list_cores: None,
+            fullscreen: None,
+            highlander: Some(true),
+            open_config: None,
+            config_path: None,
+            noconfig: None,
+            norun: Some(true),
+            nostdin: None,
+            cores_rules: None,
+            extension_rules: Some(ext_rules),
+            directory_rules: None,
+            doctor: None,
+            libretro_info_directory: None,
+            long: None,
+            queue: None,
+            json: None,
+            workdir: None,
+            env: None,
+            rule_options: None,
+            isolated: None,
+            retroarch_overrides: None,
+            keep_overrides: None,
+            highlander_scope: None,
+            list_instances: None,
+            quit: None,
+            notify: None,
+            events: None,
+            serve: None,
+            token_file: None,
+            print_env: None,
+            retroarch_stub: None,
+            fingerprint: None,
+            limit: None,
+            offset: None,
+            core_labels: None,
+            migrate_config: None,
+            rom_roots: None,
+            organize: None,
+            databases: None,
+            rename_preview: None,
+            rename: None,
+            yes: None,
+            format: None,
+            fzf: None,
+            fzf_snippet: None,
+            launch_from_fzf: None,
+            hotkey_listen: None,
+            hotkey_device: None,
+            hotkey_buttons: None,
+            pause_on_suspend: None,
+            preflight: None,
+            no_preflight: None,
+            profiles: None,
+            profile: None,
+            check_config: None,
+            policy: None,
+            ignore_policy: None,
+            lock: None,
+            locked: None,
+            unlock: None,
+            known_extensions: OnceLock::new(),
+            complete_games: None,
+            which_core: None,
+            relative: None,
+            resume_queue: None,
+            fail_fast: None,
+            exit_policy: None,
+            stdin_timeout: None,
+            keep_duplicates: None,
+            coreset: None,
+            coresets: None,
+            diff_config: None,
+            usage_counters: None,
+            usage_report: None,
+            title: None,
+            fallback_core: None,
+            menu_load: None,
+            presets: None,
+            preset: None,
+            history: None,
+            core_lock: None,
+            ignore_core_lock: None,
+            verify_cores: None,
+            tag: None,
+            note: None,
+            history_list: None,
+            stats: None,
+            export_list: None,
+            prune_cores: None,
+            remote: None,
+            remotes: None,
+            romsets: None,
+            stdin_rom: None,
+            startup_timeout: None,
+            retroarch_origin: None,
+            retroarch_directory: None,
+            which_retroarch: None,
+            suggestions: None,
+            timeout: None,
+            stdin_rules: None,
+            tts_command: None,
+            nice: None,
+            cpus: None,
+            core_options: None,
+            cache_days: None,
+            match_anchor: None,
+            interactive: None,
+            fuzzy: None,
+            selector_command: None,
+            matrix: None,
+            index: None,
+            exclude: None,
+            undo_config: None,
+            spawner: None,
+            load_state: None,
+            choose: None,
+            list_games: None,
+            core_env: None,
+            env_defaults: None,
+            count: None,
+            null: None,
+            escape: None,
+            games_from: None,
+            ai_service: None,
+            ai_service_mode: None,
+            ai_service_url: None,
+            recursive: None,
+            force: None,
+            sort: None,
+            unique: None,
+            debounce: None,
+            volume: None,
+            mute: None,
+            mixer_restore_command: None,
+        };
+
+        assert_eq!(
+            None,
+            settings.libretro_from_ext(&PathBuf::from("")).as_ref()
+        );
+        assert_eq!(
+            None,
+            settings.libretro_from_ext(&PathBuf::from(".sfc")).as_ref()
+        );
+        assert_eq!(
+            Some(&PathBuf::from("mesen-s")),
+            settings
+                .libretro_from_ext(&PathBuf::from("game.sfc"))
+                .as_ref()
+        );
+        assert_eq!(
+            Some(&PathBuf::from("snes9x")),
+            settings
+                .libretro_from_ext(&PathBuf::from("/roms/v1.2/game.sfc.gz"))
+                .as_ref()
+        );
+        assert_eq!(
+            Some(&PathBuf::from("gzip")),
+            settings
+                .libretro_from_ext(&PathBuf::from("game.smc.gz"))
+                .as_ref()
+        );
+        assert_eq!(
+            Some(&PathBuf::from("snes9x")),
+            settings
+                .libretro_from_ext(&PathBuf::from("game.sfc.gz.zst"))
+                .as_ref()
+        );
+        assert_eq!(
+            Some(&PathBuf::from("sameboy")),
+            settings
+                .libretro_from_ext(&PathBuf::from("game.gb.zst"))
+                .as_ref()
+        );
+    }
+
+    #[test]
+    fn resolve_libretro_with_fake() {
+        let fs = MemFs::with_files(&[
+            "/cores/snes9x_libretro.so",
+            "/cores/bsnes_libretro.so",
+            "/cores/mesen_libretro.so",
+        ]);
+        let mut settings = super::Settings::new();
+        settings.libretro_directory = Some(vec![PathBuf::from("/cores")]);
+        settings.extension_rules = Some(IndexMap::from([(
+            "smc".to_string(),
+            PathBuf::from("snes9x"),
+        )]));
+        settings.directory_rules = Some(IndexMap::from([(
+            "/roms/hacks".to_string(),
+            PathBuf::from("bsnes"),
+        )]));
+        let resolve = |settings: &super::Settings, game: &str| {
+            settings.resolve_libretro_with(&fs, Some(&PathBuf::from(game)))
+        };
+
+        assert_eq!(
+            Ok(PathBuf::from("/cores/snes9x_libretro.so")),
+            resolve(&settings, "/roms/mario.smc")
+        );
+        assert_eq!(
+            Ok(PathBuf::from("/cores/bsnes_libretro.so")),
+            resolve(&settings, "/roms/hacks/mario.smc")
+        );
+        assert!(resolve(&settings, "/roms/zelda.sfc").is_err());
+
+        settings.libretro = Some(PathBuf::from("mesen"));
+        assert_eq!(
+            Ok(PathBuf::from("/cores/mesen_libretro.so")),
+            resolve(&settings, "/roms/hacks/mario.smc")
+        );
+        settings.libretro = Some(PathBuf::from("missing"));
+        assert!(resolve(&settings, "/roms/mario.smc").is_err());
+    }
+
+    #[test]
+    fn resolve_libretro_coreset() {
+        let fs = MemFs::with_files(&[
+            "/cores/snes9x_libretro.so",
+            "/nightly/snes9x_libretro.so",
+        ]);
+        let mut settings = super::Settings::new();
+        settings.libretro_directory = Some(vec![PathBuf::from("/cores")]);
+        settings.libretro = Some(PathBuf::from("snes9x"));
+        settings.coresets = Some(vec![super::coresets::CoreSet {
+            name: "nightly".to_string(),
+            libretro_directory: vec![PathBuf::from("/nightly")],
+            retroarch: None,
+        }]);
+        settings.rule_options = Some(IndexMap::from([(
+            "/roms/nightly".to_string(),
+            super::rules::RuleOptions {
+                coreset: Some("nightly".to_string()),
+                ..Default::default()
+            },
+        )]));
+        let resolve = |settings: &super::Settings, game: &str| {
+            settings.resolve_libretro_with(&fs, Some(&PathBuf::from(game)))
+        };
+
+        assert_eq!(
+            Ok(PathBuf::from("/cores/snes9x_libretro.so")),
+            resolve(&settings, "/roms/mario.smc")
+        );
+        assert_eq!(
+            Ok(PathBuf::from("/nightly/snes9x_libretro.so")),
+            resolve(&settings, "/roms/nightly/mario.smc")
+        );
+        settings.coreset = Some("nightly".to_string());
+        assert_eq!(
+            Ok(PathBuf::from("/nightly/snes9x_libretro.so")),
+            resolve(&settings, "/roms/mario.smc")
+        );
+        settings.coreset = Some("missing".to_string());
+        assert!(resolve(&settings, "/roms/mario.smc").is_err());
+    }
+
+    #[test]
+    fn resolve_libretro_fallback() {
+        let fs = MemFs::with_files(&[
+            "/cores/snes9x_libretro.so",
+            "/cores/mame_libretro.so",
+        ]);
+        let mut settings = super::Settings::new();
+        settings.libretro_directory = Some(vec![PathBuf::from("/cores")]);
+        settings.extension_rules = Some(IndexMap::from([(
+            "smc".to_string(),
+            PathBuf::from("snes9x"),
+        )]));
+        let resolve = |settings: &super::Settings, game: &str| {
+            settings.resolve_libretro_with(&fs, Some(&PathBuf::from(game)))
+        };
+        assert!(resolve(&settings, "/roms/pacman.zip").is_err());
+
+        settings.fallback_core = Some("mame".to_string());
+        assert_eq!(
+            Ok(PathBuf::from("/cores/mame_libretro.so")),
+            resolve(&settings, "/roms/pacman.zip")
+        );
+        assert_eq!(
+            Ok(PathBuf::from("/cores/snes9x_libretro.so")),
+            resolve(&settings, "/roms/mario.smc")
+        );
+
+        settings.cores_rules = Some(IndexMap::from([(
+            "arcade".to_string(),
+            PathBuf::from("mame"),
+        )]));
+        settings.fallback_core = Some("arcade".to_string());
+        assert_eq!(
+            Ok(PathBuf::from("/cores/mame_libretro.so")),
+            resolve(&settings, "/roms/pacman.zip")
+        );
     }
 
     #[test]
-    fn read_config_options_bool() -> Result<()> {
+    fn resolve_libretro_romsets() {
+        let fs = MemFs::with_files(&[
+            "/cores/mame_libretro.so",
+            "/cores/fbneo_libretro.so",
+        ]);
         let mut settings = super::Settings::new();
-        let ini = test_ini_template();
-
-        super::Settings::read_config_options(
-            &mut settings,
-            &ini,
-            &["options".to_string()],
-        )?;
-
-        assert_eq!(Some(false), settings.which);
-        assert_eq!(Some(true), settings.norun);
+        settings.libretro_directory = Some(vec![PathBuf::from("/cores")]);
+        settings.directory_rules = Some(IndexMap::from([(
+            "/roms/arcade".to_string(),
+            PathBuf::from("mame"),
+        )]));
+        settings.romsets = Some(IndexMap::from([(
+            "mslug".to_string(),
+            PathBuf::from("fbneo"),
+        )]));
+        let resolve = |game: &str| {
+            settings.resolve_libretro_with(&fs, Some(&PathBuf::from(game)))
+        };
 
-        Ok(())
+        assert_eq!(
+            Ok(PathBuf::from("/cores/fbneo_libretro.so")),
+            resolve("/roms/arcade/MSLUG.zip")
+        );
+        assert_eq!(
+            Ok(PathBuf::from("/cores/mame_libretro.so")),
+            resolve("/roms/arcade/sf2.zip")
+        );
+        assert_eq!(
+            Some("rule:[romsets] mslug".to_string()),
+            settings.matched_rule(&PathBuf::from("/roms/arcade/mslug.zip"))
+        );
     }
 
     #[test]
-    fn read_config_cores_rules() -> Result<()> {
-        let ini = test_ini_template();
+    fn resolve_libretro_policy() {
+        let fs = MemFs::with_files(&["/cores/snes9x_libretro.so"]);
+        let mut settings = super::Settings::new();
+        settings.libretro_directory = Some(vec![PathBuf::from("/cores")]);
+        settings.libretro = Some(PathBuf::from("snes9x"));
+        settings.policy = Some(super::policy::Policy {
+            blocked_cores: vec!["snes*".to_string()],
+            ..Default::default()
+        });
+        let game = PathBuf::from("/roms/mario.smc");
 
-        let rules = super::Settings::read_config_cores_rules(&ini);
+        assert!(settings.resolve_libretro_with(&fs, Some(&game)).is_err());
 
-        assert_eq!(Some(&PathBuf::from("snes9x")), rules.get("snes"));
+        settings.ignore_policy = Some(true);
         assert_eq!(
-            Some(&PathBuf::from("genesis_plus_gx_libretro.so")),
-            rules.get("md")
+            Ok(PathBuf::from("/cores/snes9x_libretro.so")),
+            settings.resolve_libretro_with(&fs, Some(&game))
         );
-        assert_eq!(None, rules.get("retroarch"));
-
-        Ok(())
     }
 
     #[test]
-    fn read_config_extension_rules() {
-        let ini = test_ini_template();
+    fn run_with_fake() {
+        let runner = FakeRunner {
+            code: 3,
+            ..Default::default()
+        };
+        let settings = super::Settings::new();
+        let mut command = std::process::Command::new("retroarch");
+        command.arg("/roms/mario.smc");
+        let mut run = super::RunCommand {
+            cmdline: command,
+            game: PathBuf::from("/roms/mario.smc"),
+            libretro: PathBuf::from("/cores/snes9x_libretro.so"),
+            scheduling: Default::default(),
+        };
 
-        let ext_rules = super::Settings::read_config_extension_rules(
-            &Some(test_ini_cores_rules_template()),
-            &ini,
-            &ini.sections(),
-        );
+        let report = settings.run_with(&runner, &mut run).unwrap().unwrap();
 
-        assert_eq!(Some(&PathBuf::from("snes9x")), ext_rules.get("sfc"));
+        assert_eq!(Some(3), report.status.code());
+        assert!(!report.timed_out);
+        assert!(!report.is_success());
         assert_eq!(
-            Some(&PathBuf::from("genesis_plus_gx_wide")),
-            ext_rules.get("mdwide")
+            vec![vec!["retroarch".to_string(), "/roms/mario.smc".to_string()]],
+            *runner.spawned.borrow()
         );
-        assert_eq!(None, ext_rules.get(""));
     }
 
     #[test]
-    fn read_config_directory_rules() {
-        let ini = test_ini_template();
+    fn run_with_missing_program() {
+        let runner = FakeRunner {
+            missing: true,
+            ..Default::default()
+        };
+        let settings = super::Settings::new();
+        let mut run = super::RunCommand {
+            cmdline: std::process::Command::new("retroarch"),
+            game: PathBuf::from("/roms/mario.smc"),
+            libretro: PathBuf::from("/cores/snes9x_libretro.so"),
+            scheduling: Default::default(),
+        };
 
-        let dir_rules = super::Settings::read_config_directory_rules(
-            &Some(test_ini_cores_rules_template()),
-            &ini,
-            &ini.sections(),
-        );
+        assert!(settings.run_with(&runner, &mut run).is_err());
+    }
+
+    #[test]
+    fn game_title() {
+        let known: HashSet<String> =
+            HashSet::from(["smc".to_string(), "smc.gz".to_string()]);
+        let title = |game: &str, known: &HashSet<String>| {
+            super::game_title(&PathBuf::from(game), known).to_string()
+        };
 
         assert_eq!(
-            Some(&PathBuf::from("mednafen_psx_hw")),
-            dir_rules.get("/bin*")
+            "Game (USA).v1.1",
+            title("/roms/Game (USA).v1.1.smc", &known)
         );
-        assert_ne!(Some(&PathBuf::from("md")), dir_rules.get("/bin"));
-        assert_eq!(None, dir_rules.get("path_without_slash"));
+        assert_eq!("Game (USA).v1.1", title("Game (USA).v1.1.SMC.gz", &known));
+        assert_eq!("Game (USA).v1.1", title("Game (USA).v1.1", &known));
+        assert_eq!("Game (USA).v1.1", title("Game (USA).v1.1.sfc", &known));
+        assert_eq!("Game.smc", title("Game.smc.gz", &HashSet::new()));
+        assert_eq!(".hidden", title(".hidden", &known));
     }
 
     #[test]
-    fn libretro_from_ext() {
-        let mut ext_rules: IndexMap<String, PathBuf> = IndexMap::new();
-        ext_rules.insert("sfc".to_string(), PathBuf::from("mesen-s"));
-        ext_rules.insert("smc".to_string(), PathBuf::from("mesen-s"));
-        ext_rules.insert("gb".to_string(), PathBuf::from("sameboy"));
-        ext_rules.insert("gbc".to_string(), PathBuf::from("sameboy"));
+    fn display_title() {
+        let mut settings = super::Settings::new();
+        settings.rule_options = Some(IndexMap::from([(
+            "/roms/n64".to_string(),
+            super::rules::RuleOptions {
+                title: Some("Super Mario 64".to_string()),
+                ..Default::default()
+            },
+        )]));
+        let title = |settings: &super::Settings, game: &str| {
+            settings.display_title(&PathBuf::from(game))
+        };
 
-        let settings = super::Settings {
-            games: vec![],
-            retroarch_arguments: vec![],
-            config: None,
-            retroarch: Some(PathBuf::from("retroarch")),
-            retroarch_config: None,
-            libretro: None,
-            libretro_directory: None,
-            core: None,
-            filter: Some(vec!["[!]".to_string()]),
-            strict: None,
-            which: None,
-            which_command: None,
-            list_cores: None,
-            fullscreen: None,
-            highlander: Some(true),
-            open_config: None,
-            config_path: None,
-            noconfig: None,
-            norun: Some(true),
-            nostdin: None,
-            cores_rules: None,
-            extension_rules: Some(ext_rules),
-            directory_rules: None,
+        assert_eq!("sm64 (u)", title(&settings, "/roms/sm64 (u).z64"));
+        assert_eq!("Super Mario 64", title(&settings, "/roms/n64/sm64.z64"));
+        settings.title = Some("Mario".to_string());
+        assert_eq!("Mario", title(&settings, "/roms/n64/sm64.z64"));
+    }
+
+    #[test]
+    fn apply_presets() {
+        let mut settings = super::Settings::new();
+        settings.norun = Some(true);
+        settings.presets = Some(vec![super::presets::Preset {
+            name: "sharp".to_string(),
+            options: IndexMap::from([(
+                "snes9x_overscan".to_string(),
+                "disabled".to_string(),
+            )]),
+        }]);
+        settings.rule_options = Some(IndexMap::from([(
+            "/roms/snes".to_string(),
+            super::rules::RuleOptions {
+                preset: Some("crt".to_string()),
+                ..Default::default()
+            },
+        )]));
+        let apply = |settings: &super::Settings, game: &str| {
+            let mut overrides = super::overrides::Overrides::new();
+            settings
+                .apply_presets(&mut overrides, None, &PathBuf::from(game))
+                .map(|()| overrides.to_cfg())
         };
 
+        assert_eq!(Ok(String::new()), apply(&settings, "/roms/mario.smc"));
+        assert!(apply(&settings, "/roms/snes/mario.smc")
+            .unwrap_err()
+            .contains("[preset:crt]"));
+
+        settings.preset = Some(vec!["sharp".to_string()]);
+        let cfg = apply(&settings, "/roms/mario.smc").unwrap();
+        assert!(cfg.contains("core_options_path = "));
+        assert!(cfg.contains("global_core_options = \"true\""));
+        assert!(cfg.contains("game_specific_options = \"false\""));
+    }
+
+    #[test]
+    fn rom_extensions_reset_on_update() {
+        let mut settings = super::Settings::new();
+        settings.extension_rules =
+            Some(IndexMap::from([("SMC".to_string(), PathBuf::from("a"))]));
+        assert!(settings.rom_extensions().contains("smc"));
+
+        let mut update = super::Settings::new();
+        update.extension_rules =
+            Some(IndexMap::from([("gb".to_string(), PathBuf::from("b"))]));
+        settings.update_from(update);
+        assert!(settings.rom_extensions().contains("gb"));
+        assert!(!settings.rom_extensions().contains("smc"));
+    }
+
+    #[test]
+    fn compound_extensions() {
         assert_eq!(
-            None,
-            settings.libretro_from_ext(&PathBuf::from("")).as_ref()
-        );
-        assert_eq!(
-            None,
-            settings.libretro_from_ext(&PathBuf::from(".sfc")).as_ref()
+            vec!["pack.tar.gz", "tar.gz", "gz"],
+            super::compound_extensions(&PathBuf::from(
+                "/a.b/game.pack.tar.gz"
+            ))
         );
         assert_eq!(
-            Some(&PathBuf::from("mesen-s")),
-            settings
-                .libretro_from_ext(&PathBuf::from("game.sfc"))
-                .as_ref()
+            vec!["gb"],
+            super::compound_extensions(&PathBuf::from(".hidden.gb"))
         );
+        assert!(super::compound_extensions(&PathBuf::from(".gb")).is_empty());
+        assert!(super::compound_extensions(&PathBuf::from("game.")).is_empty());
     }
 
     #[test]
@@ -1394,6 +7537,129 @@ mod tests {
             cores_rules: None,
             extension_rules: None,
             directory_rules: Some(dir_rules),
+            doctor: None,
+            libretro_info_directory: None,
+            long: None,
+            queue: None,
+            json: None,
+            workdir: None,
+            env: None,
+            rule_options: None,
+            isolated: None,
+            retroarch_overrides: None,
+            keep_overrides: None,
+            highlander_scope: None,
+            list_instances: None,
+            quit: None,
+            notify: None,
+            events: None,
+            serve: None,
+            token_file: None,
+            print_env: None,
+            retroarch_stub: None,
+            fingerprint: None,
+            limit: None,
+            offset: None,
+            core_labels: None,
+            migrate_config: None,
+            rom_roots: None,
+            organize: None,
+            databases: None,
+            rename_preview: None,
+            rename: None,
+            yes: None,
+            format: None,
+            fzf: None,
+            fzf_snippet: None,
+            launch_from_fzf: None,
+            hotkey_listen: None,
+            hotkey_device: None,
+            hotkey_buttons: None,
+            pause_on_suspend: None,
+            preflight: None,
+            no_preflight: None,
+            profiles: None,
+            profile: None,
+            check_config: None,
+            policy: None,
+            ignore_policy: None,
+            lock: None,
+            locked: None,
+            unlock: None,
+            known_extensions: OnceLock::new(),
+            complete_games: None,
+            which_core: None,
+            relative: None,
+            resume_queue: None,
+            fail_fast: None,
+            exit_policy: None,
+            stdin_timeout: None,
+            keep_duplicates: None,
+            coreset: None,
+            coresets: None,
+            diff_config: None,
+            usage_counters: None,
+            usage_report: None,
+            title: None,
+            fallback_core: None,
+            menu_load: None,
+            presets: None,
+            preset: None,
+            history: None,
+            core_lock: None,
+            ignore_core_lock: None,
+            verify_cores: None,
+            tag: None,
+            note: None,
+            history_list: None,
+            stats: None,
+            export_list: None,
+            prune_cores: None,
+            remote: None,
+            remotes: None,
+            romsets: None,
+            stdin_rom: None,
+            startup_timeout: None,
+            retroarch_origin: None,
+            retroarch_directory: None,
+            which_retroarch: None,
+            suggestions: None,
+            timeout: None,
+            stdin_rules: None,
+            tts_command: None,
+            nice: None,
+            cpus: None,
+            core_options: None,
+            cache_days: None,
+            match_anchor: None,
+            interactive: None,
+            fuzzy: None,
+            selector_command: None,
+            matrix: None,
+            index: None,
+            exclude: None,
+            undo_config: None,
+            spawner: None,
+            load_state: None,
+            choose: None,
+            list_games: None,
+            core_env: None,
+            env_defaults: None,
+            count: None,
+            null: None,
+            escape: None,
+            games_from: None,
+            ai_service: None,
+            ai_service_mode: None,
+            ai_service_url: None,
+            recursive: None,
+            force: None,
+            sort: None,
+            unique: None,
+            debounce: None,
+            volume: None,
+            mute: None,
+            mixer_restore_command: None,
         };
 
         assert_eq!(
@@ -1418,6 +7684,280 @@ mod tests {
         );
     }
 
+    #[test]
+    fn read_config_rule_options() {
+        let mut ini: ini::Ini = ini::Ini::new_cs();
+        ini.read(String::from(
+            "
+            [.n64 .z64]
+            core = n64
+            env.MESA_LOADER_DRIVER_OVERRIDE = zink
+
+            [/roms/n64/hacks*]
+            workdir = /tmp
+
+            [.gb]
+            core = gb
+            ",
+        ))
+        .unwrap();
+
+        let rule_options = super::Settings::read_config_rule_options(
+            &ini,
+            &ini.sections(),
+            &[],
+        );
+
+        assert!(rule_options.contains_key("n64"));
+        assert!(rule_options.contains_key("z64"));
+        assert_eq!(
+            Some(PathBuf::from("/tmp")),
+            rule_options.get("/roms/n64/hacks*").unwrap().workdir
+        );
+        assert_eq!(None, rule_options.get("gb"));
+    }
+
+    #[test]
+    fn command_fingerprint() {
+        let temp = PathBuf::from("/tmp/enjoy/1234");
+        let mut command = std::process::Command::new("retroarch");
+        command.args(["mario.smc", "--appendconfig", "/tmp/enjoy/1234/o.cfg"]);
+        let first = super::command_fingerprint(&command, "", &temp);
+
+        let mut other_pid = std::process::Command::new("retroarch");
+        other_pid.args(["mario.smc", "--appendconfig", "/tmp/enjoy/99/o.cfg"]);
+        assert_eq!(
+            first,
+            super::command_fingerprint(
+                &other_pid,
+                "",
+                &PathBuf::from("/tmp/enjoy/99")
+            )
+        );
+
+        command.env("SDL_VIDEODRIVER", "x11");
+        let with_env = super::command_fingerprint(&command, "", &temp);
+        assert_ne!(first, with_env);
+        assert_ne!(
+            with_env,
+            super::command_fingerprint(
+                &command,
+                "video_driver = \"gl\"\n",
+                &temp
+            )
+        );
+    }
+
+    #[test]
+    fn rule_options_for() {
+        let mut ext_options = super::rules::RuleOptions {
+            workdir: Some(PathBuf::from("/ext")),
+            ..Default::default()
+        };
+        ext_options
+            .env
+            .insert("SDL_VIDEODRIVER".to_string(), "x11".to_string());
+        let dir_options = super::rules::RuleOptions {
+            workdir: Some(PathBuf::from("/dir")),
+            ..Default::default()
+        };
+
+        let mut rule_options: IndexMap<String, super::rules::RuleOptions> =
+            IndexMap::new();
+        rule_options.insert("n64".to_string(), ext_options);
+        rule_options.insert("/roms/hacks*".to_string(), dir_options);
+
+        let mut settings = super::Settings::new();
+        settings.workdir = Some(PathBuf::from("/global"));
+        settings.rule_options = Some(rule_options);
+
+        let options =
+            settings.rule_options_for(&PathBuf::from("/roms/n64/mario.n64"));
+        assert_eq!(Some(PathBuf::from("/ext")), options.workdir);
+        assert_eq!(
+            Some(&"x11".to_string()),
+            options.env.get("SDL_VIDEODRIVER")
+        );
+
+        let options = settings
+            .rule_options_for(&PathBuf::from("/roms/hacks64/mario.n64"));
+        assert_eq!(Some(PathBuf::from("/dir")), options.workdir);
+
+        let options = settings.rule_options_for(&PathBuf::from("/roms/a.gb"));
+        assert_eq!(Some(PathBuf::from("/global")), options.workdir);
+    }
+
+    #[test]
+    fn filter_match_anchor() {
+        let mut settings = super::Settings::new();
+        settings.games = ["Mario.smc", "Mario Paint.smc", "Super Mario.smc"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        settings.filter = Some(vec!["mario".to_string()]);
+        let filtered = |settings: &super::Settings| {
+            settings
+                .filtered_games()
+                .map(|game| game.display().to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(3, filtered(&settings).len());
+        settings.match_anchor = Some(super::anchor::Anchor::Start);
+        assert_eq!(vec!["Mario.smc", "Mario Paint.smc"], filtered(&settings));
+        settings.match_anchor = Some(super::anchor::Anchor::End);
+        assert_eq!(vec!["Mario.smc", "Super Mario.smc"], filtered(&settings));
+        settings.match_anchor = Some(super::anchor::Anchor::Exact);
+        assert_eq!(vec!["Mario.smc"], filtered(&settings));
+        settings.strict = Some(true);
+        assert!(filtered(&settings).is_empty());
+    }
+
+    #[test]
+    fn filter_exclude() {
+        let mut settings = super::Settings::new();
+        settings.games = [
+            "Mario (Beta).smc",
+            "Mario (Proto).smc",
+            "Mario.smc",
+            "Zelda.smc",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        settings.exclude =
+            Some(vec!["(beta)".to_string(), "(proto)".to_string()]);
+        let filtered = |settings: &super::Settings| {
+            settings
+                .filtered_games()
+                .map(|game| game.display().to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(vec!["Mario.smc", "Zelda.smc"], filtered(&settings));
+        settings.filter = Some(vec!["mario".to_string()]);
+        assert_eq!(vec!["Mario.smc"], filtered(&settings));
+        assert_eq!(vec![2], settings.filtered_indices());
+        settings.fuzzy = Some(true);
+        assert_eq!(vec!["Mario.smc"], filtered(&settings));
+    }
+
+    #[test]
+    fn pick_game_selects_chosen() {
+        let mut settings = super::Settings::new();
+        settings.games =
+            ["Mario 1.smc", "Mario 2.smc", "Mario 3.smc", "Zelda.sfc"]
+                .iter()
+                .map(PathBuf::from)
+                .collect();
+        settings.filter = Some(vec!["mario".to_string()]);
+        settings.index = Some(2);
+        settings.fuzzy = Some(true);
+        settings.selector_command = Some("tail -n 1".to_string());
+        settings.pick_game().unwrap();
+
+        assert_eq!(Some(PathBuf::from("Mario 3.smc")), settings.select_game());
+    }
+
+    #[test]
+    fn select_game_fuzzy() {
+        let mut settings = super::Settings::new();
+        settings.games = [
+            "Super Mario Kart.smc",
+            "Mega Man Rockman Io.smc",
+            "Mario Kart (USA) [!].smc",
+            "Mario Kart.smc",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        settings.filter = Some(vec!["mario".to_string(), "kt".to_string()]);
+        settings.fuzzy = Some(true);
+
+        assert_eq!(3, settings.filtered_games().count());
+        assert_eq!(
+            Some(PathBuf::from("Mario Kart.smc")),
+            settings.select_game()
+        );
+        settings.index = Some(-1);
+        assert_eq!(
+            Some(PathBuf::from("Super Mario Kart.smc")),
+            settings.select_game()
+        );
+        settings.filter = Some(vec!["zelda".to_string()]);
+        assert_eq!(None, settings.select_game());
+    }
+
+    #[test]
+    fn select_game_index() {
+        let mut settings = super::Settings::new();
+        settings.games = ["zelda.smc", "mario.smc", "sonic.md", "mario2.smc"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        settings.filter = Some(vec!["mario".to_string()]);
+        let select = |settings: &mut super::Settings, index: isize| {
+            settings.index = Some(index);
+            settings.select_game()
+        };
+
+        assert_eq!(
+            Some(PathBuf::from("mario2.smc")),
+            select(&mut settings, 2)
+        );
+        assert_eq!(
+            Some(PathBuf::from("mario2.smc")),
+            select(&mut settings, -1)
+        );
+        assert_eq!(
+            Some(PathBuf::from("mario.smc")),
+            select(&mut settings, -2)
+        );
+        assert_eq!(None, select(&mut settings, 3));
+        assert_eq!(None, select(&mut settings, -3));
+        assert_eq!(Ok(-1), super::parse_index("-1"));
+        assert!(super::parse_index("0").is_err());
+        assert!(super::parse_index("last").is_err());
+    }
+
+    #[test]
+    fn scheduling_for() {
+        let mut settings = super::Settings::new();
+        settings.nice = Some("5".to_string());
+        settings.cpus = Some("0-1".to_string());
+        settings.core_options = Some(IndexMap::from([(
+            "pcsx2".to_string(),
+            super::rules::RuleOptions {
+                nice: Some("-5".to_string()),
+                ..Default::default()
+            },
+        )]));
+        let rule = super::rules::RuleOptions {
+            cpus: Some("2-3".to_string()),
+            ..Default::default()
+        };
+        let pcsx2 = PathBuf::from("/cores/pcsx2_libretro.so");
+
+        let scheduling = settings
+            .scheduling_for(&super::rules::RuleOptions::default(), &pcsx2)
+            .unwrap();
+        assert_eq!(Some(-5), scheduling.nice);
+        assert_eq!(Some(vec![0, 1]), scheduling.cpus);
+
+        let scheduling = settings.scheduling_for(&rule, &pcsx2).unwrap();
+        assert_eq!(Some(vec![2, 3]), scheduling.cpus);
+
+        let scheduling = settings
+            .scheduling_for(&rule, &PathBuf::from("/cores/snes9x_libretro.so"))
+            .unwrap();
+        assert_eq!(Some(5), scheduling.nice);
+
+        settings.nice = Some("fast".to_string());
+        assert!(settings
+            .scheduling_for(&rule, &PathBuf::from("snes9x"))
+            .is_err());
+    }
+
     #[test]
     fn update_from() -> Result<()> {
         let mut old = super::Settings::new();
@@ -1445,6 +7985,129 @@ mod tests {
             cores_rules: None,
             extension_rules: None,
             directory_rules: None,
+            doctor: None,
+            libretro_info_directory: None,
+            long: None,
+            queue: None,
+            json: None,
+            workdir: None,
+            env: None,
+            rule_options: None,
+            isolated: None,
+            retroarch_overrides: None,
+            keep_overrides: None,
+            highlander_scope: None,
+            list_instances: None,
+            quit: None,
+            notify: None,
+            events: None,
+            serve: None,
+            token_file: None,
+            print_env: None,
+            retroarch_stub: None,
+            fingerprint: None,
+            limit: None,
+            offset: None,
+            core_labels: None,
+            migrate_config: None,
+            rom_roots: None,
+            organize: None,
+            databases: None,
+            rename_preview: None,
+            rename: None,
+            yes: None,
+            format: None,
+            fzf: None,
+            fzf_snippet: None,
+            launch_from_fzf: None,
+            hotkey_listen: None,
+            hotkey_device: None,
+            hotkey_buttons: None,
+            pause_on_suspend: None,
+            preflight: None,
+            no_preflight: None,
+            profiles: None,
+            profile: None,
+            check_config: None,
+            policy: None,
+            ignore_policy: None,
+            lock: None,
+            locked: None,
+            unlock: None,
+            known_extensions: OnceLock::new(),
+            complete_games: None,
+            which_core: None,
+            relative: None,
+            resume_queue: None,
+            fail_fast: None,
+            exit_policy: None,
+            stdin_timeout: None,
+            keep_duplicates: None,
+            coreset: None,
+            coresets: None,
+            diff_config: None,
+            usage_counters: None,
+            usage_report: None,
+            title: None,
+            fallback_core: None,
+            menu_load: None,
+            presets: None,
+            preset: None,
+            history: None,
+            core_lock: None,
+            ignore_core_lock: None,
+            verify_cores: None,
+            tag: None,
+            note: None,
+            history_list: None,
+            stats: None,
+            export_list: None,
+            prune_cores: None,
+            remote: None,
+            remotes: None,
+            romsets: None,
+            stdin_rom: None,
+            startup_timeout: None,
+            retroarch_origin: None,
+            retroarch_directory: None,
+            which_retroarch: None,
+            suggestions: None,
+            timeout: None,
+            stdin_rules: None,
+            tts_command: None,
+            nice: None,
+            cpus: None,
+            core_options: None,
+            cache_days: None,
+            match_anchor: None,
+            interactive: None,
+            fuzzy: None,
+            selector_command: None,
+            matrix: None,
+            index: None,
+            exclude: None,
+            undo_config: None,
+            spawner: None,
+            load_state: None,
+            choose: None,
+            list_games: None,
+            core_env: None,
+            env_defaults: None,
+            count: None,
+            null: None,
+            escape: None,
+            games_from: None,
+            ai_service: None,
+            ai_service_mode: None,
+            ai_service_url: None,
+            recursive: None,
+            force: None,
+            sort: None,
+            unique: None,
+            debounce: None,
+            volume: None,
+            mute: None,
+            mixer_restore_command: None,
         };
 
         old.update_from(new);
@@ -1458,6 +8121,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn update_from_merges_env() {
+        let mut config = super::Settings::new_from_cmdline(Some(vec![
+            "enjoy".to_string(),
+            "--env".to_string(),
+            "A=config".to_string(),
+            "--env".to_string(),
+            "B=config".to_string(),
+        ]));
+        let cmdline = super::Settings::new_from_cmdline(Some(vec![
+            "enjoy".to_string(),
+            "--env".to_string(),
+            "B=cmdline".to_string(),
+        ]));
+
+        config.update_from(cmdline);
+
+        assert_eq!(
+            Some(IndexMap::from([
+                ("A".to_string(), "config".to_string()),
+                ("B".to_string(), "cmdline".to_string()),
+            ])),
+            config.env
+        );
+    }
+
     #[test]
     fn select_game_first() {
         let games: Vec<PathBuf> =
@@ -1489,6 +8178,129 @@ mod tests {
             cores_rules: None,
             extension_rules: None,
             directory_rules: None,
+            doctor: None,
+            libretro_info_directory: None,
+            long: None,
+            queue: None,
+            json: None,
+            workdir: None,
+            env: None,
+            rule_options: None,
+            isolated: None,
+            retroarch_overrides: None,
+            keep_overrides: None,
+            highlander_scope: None,
+            list_instances: None,
+            quit: None,
+            notify: None,
+            events: None,
+            serve: None,
+            token_file: None,
+            print_env: None,
+            retroarch_stub: None,
+            fingerprint: None,
+            limit: None,
+            offset: None,
+            core_labels: None,
+            migrate_config: None,
+            rom_roots: None,
+            organize: None,
+            databases: None,
+            rename_preview: None,
+            rename: None,
+            yes: None,
+            format: None,
+            fzf: None,
+            fzf_snippet: None,
+            launch_from_fzf: None,
+            hotkey_listen: None,
+            hotkey_device: None,
+            hotkey_buttons: None,
+            pause_on_suspend: None,
+            preflight: None,
+            no_preflight: None,
+            profiles: None,
+            profile: None,
+            check_config: None,
+            policy: None,
+            ignore_policy: None,
+            lock: None,
+            locked: None,
+            unlock: None,
+            known_extensions: OnceLock::new(),
+            complete_games: None,
+            which_core: None,
+            relative: None,
+            resume_queue: None,
+            fail_fast: None,
+            exit_policy: None,
+            stdin_timeout: None,
+            keep_duplicates: None,
+            coreset: None,
+            coresets: None,
+            diff_config: None,
+            usage_counters: None,
+            usage_report: None,
+            title: None,
+            fallback_core: None,
+            menu_load: None,
+            presets: None,
+            preset: None,
+            history: None,
+            core_lock: None,
+            ignore_core_lock: None,
+            verify_cores: None,
+            tag: None,
+            note: None,
+            history_list: None,
+            stats: None,
+            export_list: None,
+            prune_cores: None,
+            remote: None,
+            remotes: None,
+            romsets: None,
+            stdin_rom: None,
+            startup_timeout: None,
+            retroarch_origin: None,
+            retroarch_directory: None,
+            which_retroarch: None,
+            suggestions: None,
+            timeout: None,
+            stdin_rules: None,
+            tts_command: None,
+            nice: None,
+            cpus: None,
+            core_options: None,
+            cache_days: None,
+            match_anchor: None,
+            interactive: None,
+            fuzzy: None,
+            selector_command: None,
+            matrix: None,
+            index: None,
+            exclude: None,
+            undo_config: None,
+            spawner: None,
+            load_state: None,
+            choose: None,
+            list_games: None,
+            core_env: None,
+            env_defaults: None,
+            count: None,
+            null: None,
+            escape: None,
+            games_from: None,
+            ai_service: None,
+            ai_service_mode: None,
+            ai_service_url: None,
+            recursive: None,
+            force: None,
+            sort: None,
+            unique: None,
+            debounce: None,
+            volume: None,
+            mute: None,
+            mixer_restore_command: None,
         };
 
         assert_eq!(Some(PathBuf::from("zelda.smc")), settings.select_game());
@@ -1498,5 +8310,189 @@ mod tests {
 
         settings.filter = Some(vec!["gb".to_string()]);
         assert_eq!(None, settings.select_game());
+
+        settings.filter = Some(vec!["*o*".to_string()]);
+        settings.offset = Some(1);
+        assert_eq!(Some(PathBuf::from("sonic.md")), settings.select_game());
+
+        settings.filter = None;
+        settings.limit = Some(2);
+        assert_eq!(2, settings.filtered_games().count());
+
+        settings.filter = Some(vec!["*o*".to_string()]);
+        settings.offset = None;
+        settings.limit = None;
+        assert_eq!(vec![1, 2], settings.filtered_indices());
+        settings.offset = Some(1);
+        assert_eq!(vec![2], settings.filtered_indices());
+    }
+
+    // 50k games like from a huge stdin pipeline, where every 10th title matches "title 0*".
+    fn large_list_settings() -> super::Settings {
+        let mut settings = super::Settings::new();
+        settings.games = (0..50_000)
+            .map(|i| {
+                PathBuf::from(format!("/roms/Game Title {:05} (U).smc", i))
+            })
+            .collect();
+        settings.filter = Some(vec!["title 0*".to_string()]);
+
+        settings
+    }
+
+    // Benchmark, run with `cargo test --release -- --ignored --nocapture select_limit`.
+    //
+    // Compares the old way of evaluating the filter over the whole list before `offset` and
+    // `limit` are applied, with the lazy evaluation stopping after enough matches.
+    #[test]
+    #[ignore]
+    fn select_limit_benchmark() {
+        let mut settings = large_list_settings();
+        settings.offset = Some(10);
+        settings.limit = Some(5);
+        let patterns: Vec<wildmatch::WildMatch> = settings.filter_patterns();
+        let rounds: u32 = 20;
+
+        let started = std::time::Instant::now();
+        let mut before: Vec<&PathBuf> = vec![];
+        for _ in 0..rounds {
+            let all: Vec<&PathBuf> = settings
+                .games
+                .iter()
+                .filter(|game| settings.matches_filter(game, &patterns, &[]))
+                .collect();
+            before = all.into_iter().skip(10).take(5).collect();
+        }
+        let eager = started.elapsed() / rounds;
+
+        let started = std::time::Instant::now();
+        let mut after: Vec<&PathBuf> = vec![];
+        for _ in 0..rounds {
+            after = settings.filtered_games().collect();
+        }
+        let lazy = started.elapsed() / rounds;
+
+        println!(
+            "--offset 10 --limit 5 of {} games: before {:?}, after {:?}, {:.0}x faster",
+            settings.games.len(),
+            eager,
+            lazy,
+            eager.as_secs_f64() / lazy.as_secs_f64()
+        );
+        assert_eq!(before, after);
+        assert!(lazy < eager);
+    }
+
+    // Benchmark, run with `cargo test --release -- --ignored --nocapture filter_parallel`.
+    //
+    // Compares the old serial filter, which matches one game after another, with the parallel
+    // evaluation of `filtered_indices()`.  Both do the same work per game, so the speedup depends
+    // on the number of cores.  It is only checked with more than one.
+    #[test]
+    #[ignore]
+    fn filter_parallel_benchmark() {
+        let mut settings = large_list_settings();
+        settings.filter = Some(vec![
+            "game*".to_string(),
+            "title 0*".to_string(),
+            "*(u)".to_string(),
+        ]);
+        let patterns: Vec<wildmatch::WildMatch> = settings.filter_patterns();
+        let cores: usize =
+            std::thread::available_parallelism().map_or(1, |n| n.get());
+        let rounds: u32 = 20;
+
+        let started = std::time::Instant::now();
+        let mut before: Vec<usize> = vec![];
+        for _ in 0..rounds {
+            before = settings
+                .games
+                .iter()
+                .enumerate()
+                .filter(|(_, game)| {
+                    settings.matches_filter(game, &patterns, &[])
+                })
+                .map(|(index, _)| index)
+                .collect();
+        }
+        let serial = started.elapsed() / rounds;
+
+        let started = std::time::Instant::now();
+        let mut after: Vec<usize> = vec![];
+        for _ in 0..rounds {
+            after = settings.filtered_indices();
+        }
+        let parallel = started.elapsed() / rounds;
+
+        println!(
+            "3 patterns on {} games with {} cores: before {:?}, after {:?}, {:.1}x faster",
+            settings.games.len(),
+            cores,
+            serial,
+            parallel,
+            serial.as_secs_f64() / parallel.as_secs_f64()
+        );
+        assert_eq!(5_000, after.len());
+        assert_eq!(before, after);
+        if cores > 1 {
+            assert!(parallel < serial);
+        }
+    }
+
+    #[test]
+    fn apply_fzf_selection() {
+        let mut settings = super::Settings::new();
+        settings.games = vec![PathBuf::from("/roms/other.smc")];
+        settings.filter = Some(vec!["other".to_string()]);
+        settings.launch_from_fzf =
+            Some("/roms/Super Mario (U).smc\tSuper Mario (U)\tsnes\n".into());
+
+        settings.apply_fzf_selection();
+
+        assert_eq!(
+            vec![PathBuf::from("/roms/Super Mario (U).smc")],
+            settings.games
+        );
+        assert_eq!(None, settings.filter);
+        assert_eq!(None, settings.launch_from_fzf);
+    }
+
+    #[test]
+    fn dedup_games() {
+        let mut settings = super::Settings::new();
+        settings.games = vec![
+            PathBuf::from("/"),
+            PathBuf::from("/missing.smc"),
+            PathBuf::from("/tmp/../"),
+            PathBuf::from("/missing.smc"),
+        ];
+
+        settings.dedup_games();
+        assert_eq!(
+            vec![PathBuf::from("/"), PathBuf::from("/missing.smc")],
+            settings.games
+        );
+
+        settings.games.push(PathBuf::from("/"));
+        settings.keep_duplicates = Some(true);
+        settings.dedup_games();
+        assert_eq!(3, settings.games.len());
+
+        settings.games = vec![
+            PathBuf::from("/roms/snes/mario.smc"),
+            PathBuf::from("/backup/mario.smc"),
+            PathBuf::from("/roms/snes/Mario.smc"),
+            PathBuf::from("/roms/snes/mario.smc"),
+        ];
+        settings.keep_duplicates = None;
+        settings.unique = Some(true);
+        settings.dedup_games();
+        assert_eq!(
+            vec![
+                PathBuf::from("/roms/snes/mario.smc"),
+                PathBuf::from("/roms/snes/Mario.smc")
+            ],
+            settings.games
+        );
     }
 }
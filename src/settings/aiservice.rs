@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+use indexmap::map::IndexMap;
+
+/// What the AI service of `RetroArch` does with the translated screen, key `ai_service_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Shows the translation as an image over the game.
+    Image,
+    /// Reads the translation out loud.
+    Speech,
+    /// Reads the translation out loud with the narrator of the system.
+    Narrator,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "image" => Ok(Self::Image),
+            "speech" => Ok(Self::Speech),
+            "narrator" => Ok(Self::Narrator),
+            _ => Err(format!(
+                "Unknown ai_service_mode \"{}\", expected one of: image, \
+                 speech, narrator",
+                text
+            )),
+        }
+    }
+}
+
+impl Mode {
+    /// Value of the key `ai_service_mode` in `retroarch.cfg`.
+    #[must_use]
+    pub const fn value(self) -> u8 {
+        match self {
+            Self::Image => 0,
+            Self::Speech => 1,
+            Self::Narrator => 2,
+        }
+    }
+}
+
+/// Keys of `retroarch.cfg` to turn the AI service on or off for a single run.  The `mode` and the
+/// `url` of the translation server are only set when it is `enabled`.  The service itself is
+/// started in game with the hotkey `input_ai_service` of `RetroArch`.
+///
+/// ```ini
+/// [options]
+/// ai_service_url = http://localhost:4404/
+/// ai_service_mode = image
+///
+/// [~/roms/snes/japan/]
+/// ai_service = on
+/// ```
+#[must_use]
+pub fn overrides(
+    enabled: bool,
+    mode: Option<Mode>,
+    url: Option<&str>,
+) -> IndexMap<String, String> {
+    let mut keys: IndexMap<String, String> = IndexMap::new();
+
+    keys.insert("ai_service_enable".to_string(), enabled.to_string());
+    if enabled {
+        if let Some(mode) = mode {
+            keys.insert(
+                "ai_service_mode".to_string(),
+                mode.value().to_string(),
+            );
+        }
+        if let Some(url) = url {
+            keys.insert("ai_service_url".to_string(), url.to_string());
+        }
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+
+    use indexmap::map::IndexMap;
+
+    use super::Mode;
+
+    #[test]
+    fn overrides() {
+        assert_eq!(
+            IndexMap::from([
+                ("ai_service_enable".to_string(), "true".to_string()),
+                ("ai_service_mode".to_string(), "1".to_string()),
+                (
+                    "ai_service_url".to_string(),
+                    "http://localhost:4404/".to_string()
+                ),
+            ]),
+            super::overrides(
+                true,
+                "Speech".parse().ok(),
+                Some("http://localhost:4404/")
+            )
+        );
+        assert_eq!(
+            IndexMap::from([(
+                "ai_service_enable".to_string(),
+                "false".to_string()
+            )]),
+            super::overrides(false, Some(Mode::Image), Some("http://a/"))
+        );
+        assert!("ocr".parse::<Mode>().is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use std::str::FromStr;
+
+/// Where a pattern of option `--filter` must match the title of a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    /// The title starts with the pattern.
+    Start,
+    /// The title ends with the pattern.
+    End,
+    /// The whole title matches the pattern.
+    Exact,
+    /// The pattern is anywhere in the title.
+    Contains,
+}
+
+impl FromStr for Anchor {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "start" => Ok(Self::Start),
+            "end" => Ok(Self::End),
+            "exact" => Ok(Self::Exact),
+            "contains" => Ok(Self::Contains),
+            _ => Err(format!(
+                "Unknown match \"{}\", expected one of: start, end, exact, \
+                 contains",
+                text
+            )),
+        }
+    }
+}
+
+impl Anchor {
+    /// Wildcard pattern for the whole title, with stars added where the title may go on.
+    #[must_use]
+    pub fn wildcard(self, pattern: &str) -> String {
+        match self {
+            Self::Start => format!("{}*", pattern),
+            Self::End => format!("*{}", pattern),
+            Self::Exact => pattern.to_string(),
+            Self::Contains => format!("*{}*", pattern),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Anchor;
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Ok(Anchor::Start), "start".parse());
+        assert_eq!(Ok(Anchor::Exact), "EXACT".parse());
+        assert!("begin".parse::<Anchor>().is_err());
+    }
+
+    #[test]
+    fn wildcard() {
+        assert_eq!("mario*", Anchor::Start.wildcard("mario"));
+        assert_eq!("*mario", Anchor::End.wildcard("mario"));
+        assert_eq!("mario", Anchor::Exact.wildcard("mario"));
+        assert_eq!("*mario*", Anchor::Contains.wildcard("mario"));
+    }
+}
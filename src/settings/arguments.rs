@@ -2,6 +2,9 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
+/// Path of the user config, if not given with option `--config`.
+pub const DEFAULT_CONFIG: &str = "~/.config/enjoy/default.ini";
+
 /// Play any game ROM with associated emulator in `RetroArch`.
 ///
 /// `enjoy` is a launcher to run games from `RetroArch` without using the GUI.  It is a wrapper
@@ -29,7 +32,8 @@ pub struct Opt {
     /// If multiple files are specified, then the first entry is picked when starting emulator.
     /// Each line from stdin is added as a game entry too.  Globbing and wildcards are not
     /// supported and should be resolved by the shell.  Relative paths and the tilde are supported
-    /// and expanded.
+    /// and expanded.  A directory is replaced by the files in it with a known ROM extension, see
+    /// option `--recursive`.
     ///
     /// Example: "~/roms/snes/Super Mario World (U) [\!].smc"
     #[clap(parse(from_os_str))]
@@ -59,7 +63,7 @@ pub struct Opt {
         parse(from_os_str),
         value_name = "FILE",
         display_order = 1,
-        default_value = "~/.config/enjoy/default.ini"
+        default_value = DEFAULT_CONFIG
     )]
     pub config: PathBuf,
 
@@ -75,10 +79,76 @@ pub struct Opt {
     #[clap(short = 'o', long, display_order = 1)]
     pub config_path: bool,
 
+    /// Upgrade user settings to current version
+    ///
+    /// Rewrites the user config INI file to the current schema version and exit.  Older files are
+    /// still read and converted in memory on each start, this only makes the change permanent.
+    /// A numbered backup of the original file is saved first, see option `--undo-config`.
+    #[clap(long, display_order = 1)]
+    pub migrate_config: bool,
+
+    /// Undo last change to user settings
+    ///
+    /// Restores the user config INI file from the newest backup and exit.  A backup is saved in
+    /// the state directory of `enjoy` before each change it makes to the file, like with option
+    /// `--migrate-config` or the guided setup.  Each use steps back one more change, up to 20.
+    #[clap(long, display_order = 1)]
+    pub undo_config: bool,
+
+    /// Find rules which never apply
+    ///
+    /// Analyzes how the rules of the user config INI file interact and prints each finding
+    /// ranked by severity, then exit.  These are directory rules hidden by a broader pattern
+    /// before them, extension rules replaced by later ones or unused because of a core for all
+    /// games, rules with an unknown core alias and aliases no rule refers to.  A core given by
+    /// option `--core` or `--libretro` is taken into account.
+    #[clap(long, display_order = 1)]
+    pub check_config: bool,
+
+    /// Compare with another user settings file
+    ///
+    /// Resolves the games under the user config and under FILE, then prints the differences in
+    /// core, command and `retroarch.cfg` overrides and exit.  Nothing is run.  These are the
+    /// given games after filtering, or all games in the ROM roots from `[paths]` without any.
+    ///
+    /// Example: "~/.config/enjoy/new.ini"
+    #[clap(long, parse(from_os_str), value_name = "FILE", display_order = 1)]
+    pub diff_config: Option<PathBuf>,
+
+    /// Export the games with their resolved core
+    ///
+    /// Prints the path, title, system, resolved core, file size and hash of each game as "csv"
+    /// or "json" and exit, for spreadsheets and custom frontends.  Nothing is run.  These are the
+    /// given games after filtering, or all games in the ROM roots from `[paths]` without any.
+    /// Unknown values are empty.
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        possible_values = ["csv", "json"],
+        display_order = 1
+    )]
+    pub export_list: Option<String>,
+
+    /// Print all matching games
+    ///
+    /// Prints the fullpath of each game, one per line, and exit without launching any.  These are
+    /// the given games after filtering, or all games in the ROM roots from `[paths]` without any.
+    /// Useful as a filtering stage in shell pipelines.
+    #[clap(long, display_order = 1)]
+    pub list_games: bool,
+
+    /// Print number of matching games
+    ///
+    /// Prints how many games are left after filtering and exit without launching any, with an
+    /// error if there is none.  Counts the same games as option `--list-games`.
+    #[clap(long, display_order = 1)]
+    pub count: bool,
+
     /// Path or name of `RetroArch` command
     ///
     /// The executable name or path to the RetroArch commandline application to run.  If this is a
-    /// filename without directory part, then the systems `$PATH` is searched.
+    /// filename without directory part, then the systems `$PATH` is searched.  Without this
+    /// option the environment variable `RETROARCH` is used, then the user config.
     ///
     /// Example: "/usr/bin/retroarch" [default: retroarch]
     #[clap(
@@ -90,6 +160,11 @@ pub struct Opt {
     )]
     pub retroarch: Option<PathBuf>,
 
+    /// Replace `retroarch` by a stub, which records its commandline arguments, working directory
+    /// and environment to FILE.  For testing only.
+    #[clap(long, parse(from_os_str), value_name = "FILE", hide = true)]
+    pub retroarch_stub: Option<PathBuf>,
+
     /// Path to `RetroArch` base configuration
     ///
     /// The `retroarch.cfg` base configuration file of `RetroArch` itself.  Usually it is found in
@@ -131,9 +206,11 @@ pub struct Opt {
     ///
     /// The installation directory of libretro cores.  It is looked up whenever the `libretro` path
     /// is a relative filename.  At default this directory is extracted from `RetroArch` base
-    /// configuration file `retroarch.cfg`.
+    /// configuration file `retroarch.cfg`.  Multiple directories are searched in order and can be
+    /// separated by colons or given by repeating this option, like for cores of the user and
+    /// those installed by the distribution.
     ///
-    /// Example: "/home/user/.config/retroarch/cores"
+    /// Example: "/home/user/.config/retroarch/cores:/usr/lib/libretro"
     #[clap(
         short = 'D',
         long,
@@ -141,7 +218,17 @@ pub struct Opt {
         value_name = "DIR",
         display_order = 6
     )]
-    pub libretro_directory: Option<PathBuf>,
+    pub libretro_directory: Option<Vec<PathBuf>>,
+
+    /// Directory of libretro core info files
+    ///
+    /// The directory with `.info` files describing each core, such as the name of the emulated
+    /// system.  At default this directory is extracted from `RetroArch` base configuration file
+    /// `retroarch.cfg` as `libretro_info_path`.
+    ///
+    /// Example: "/usr/share/libretro/info"
+    #[clap(long, parse(from_os_str), value_name = "DIR", display_order = 6)]
+    pub libretro_info_directory: Option<PathBuf>,
 
     /// Force specific libretro core by user defined alias
     ///
@@ -153,20 +240,220 @@ pub struct Opt {
     #[clap(short = 'C', long, value_name = "ALIAS", display_order = 4)]
     pub core: Option<String>,
 
+    /// Activate profile from user config
+    ///
+    /// Apply the `retroarch.cfg` overrides of a section like `[profile:tv]` in the user config,
+    /// regardless of its condition in key `when`.  Profiles with a condition are activated
+    /// automatically, like `when = "hour>=22 or hour<6"` or `when = "DISPLAY=:1"`.  This option
+    /// can be specified multiple times.
+    ///
+    /// Example: "tv"
+    #[clap(long, value_name = "NAME", display_order = 4)]
+    pub profile: Option<Vec<String>>,
+
+    /// Apply core options from user config
+    ///
+    /// Set the core options of a section like `[preset:sharp-pixels]` in the user config for this
+    /// run.  The current core options are kept and the preset is applied on top.  Can also be set
+    /// per extension or directory rule with `preset =` in the user settings, which is applied
+    /// last.  This option can be specified multiple times.
+    ///
+    /// Example: "sharp-pixels"
+    #[clap(long, value_name = "NAME", display_order = 4)]
+    pub preset: Option<Vec<String>>,
+
+    /// Display title of the game
+    ///
+    /// Use this title instead of the filename in the output of `--which` with `--format`, in
+    /// events and notifications.  Useful for games with a cryptic filename and no match in a
+    /// title database.  Takes precedence over `title =` of an extension or directory rule in the
+    /// user settings.
+    ///
+    /// Example: "Super Mario World"
+    #[clap(long, value_name = "NAME", display_order = 4)]
+    pub title: Option<String>,
+
+    /// Tag the launch in the history
+    ///
+    /// Stored with the entry of the launched game in the history, to find sessions again later.
+    /// With `--history-list` or `--stats` only entries with this tag are included instead.  This
+    /// option can be specified multiple times.
+    ///
+    /// Example: "100%"
+    #[clap(long, value_name = "TAG", display_order = 4)]
+    pub tag: Option<Vec<String>>,
+
+    /// Note about the launch in the history
+    ///
+    /// Free text stored with the entry of the launched game in the history, shown by
+    /// `--history-list`.
+    ///
+    /// Example: "new personal best"
+    #[clap(long, value_name = "TEXT", display_order = 4)]
+    pub note: Option<String>,
+
+    /// Load a savestate at start
+    ///
+    /// Starts the game from the savestate in `slot`, or from the most recently written savestate
+    /// of the game without a slot.  Savestates often only work with the same build of the core.
+    /// A warning is printed, if the history shows that the core changed since the savestate was
+    /// created.  The slot has to be attached with an equal sign.
+    ///
+    /// Example: "--load-state=2"
+    #[clap(
+        long,
+        value_name = "SLOT",
+        require_equals = true,
+        min_values = 0,
+        display_order = 4
+    )]
+    pub load_state: Option<Option<u32>>,
+
     /// Apply simple wildcard to filter list of games
     ///
     /// Removes all games from the list, which do not match the `pattern`.  The wildcard
     /// functionality is limited and only the star `*` and questionmark `?` are supported.  The
     /// comparison is always case insensitive.  It will compare the base filename portion of the
     /// ROM path to the pattern, ignoring it's parent directory and filename extension.  At default
-    /// a star is added in front and end of pattern automatically when comparing.  It is useful if
-    /// more than one game entry is given to the program.  This option can be specified multiple
-    /// times.  All of them have to match.
+    /// a star is added in front and end of pattern automatically when comparing, see option
+    /// `--match`.  It is useful if more than one game entry is given to the program.  This option
+    /// can be specified multiple times.  All of them have to match.
     ///
     /// Example: "mario*[\!]"
     #[clap(short = 'f', long, value_name = "PATTERN", display_order = 2)]
     pub filter: Option<Vec<String>>,
 
+    /// Remove games matching wildcard
+    ///
+    /// Removes all games from the list, which match the `pattern`.  It is the opposite of option
+    /// `--filter` and compares the filename the same way, including options `--strict` and
+    /// `--match`, but never fuzzy.  This option can be specified multiple times.  Games matching
+    /// any of them are removed.
+    ///
+    /// Example: "(beta)"
+    #[clap(long, value_name = "PATTERN", display_order = 2)]
+    pub exclude: Option<Vec<String>>,
+
+    /// Use at most N games
+    ///
+    /// Limits the list of games after filtering to the first N entries.  Selection and filtering
+    /// stop as soon as enough games are found.  Useful with option --queue and huge lists from
+    /// stdin.
+    #[clap(long, value_name = "N", display_order = 2)]
+    pub limit: Option<usize>,
+
+    /// Skip first N games
+    ///
+    /// Skips the first N entries of the list of games after filtering.  Combined with option
+    /// --limit, this pages through a list of games.
+    #[clap(long, value_name = "N", display_order = 2)]
+    pub offset: Option<usize>,
+
+    /// Select the Nth matching game
+    ///
+    /// Launches the Nth game of the list after filtering instead of the first, starting at 1.
+    /// Negative numbers count from the end, so -1 is the last match.  With option `--fuzzy` the
+    /// games are ordered by their score.
+    #[clap(
+        long,
+        value_name = "N",
+        allow_hyphen_values = true,
+        parse(try_from_str = super::parse_index),
+        display_order = 2
+    )]
+    pub index: Option<isize>,
+
+    /// Working directory of `RetroArch`
+    ///
+    /// Start the `retroarch` process in this directory, instead of the current one.  Can also be
+    /// set per extension or directory rule with `workdir =` in the user settings, which takes
+    /// precedence.
+    ///
+    /// Example: "~/roms"
+    #[clap(long, parse(from_os_str), value_name = "DIR", display_order = 7)]
+    pub workdir: Option<PathBuf>,
+
+    /// Use cores and `RetroArch` of a core set
+    ///
+    /// Look up cores in the `libretro_directory` and start the `retroarch` of section
+    /// `[coreset:NAME]` in the user settings, such as for nightly cores installed next to the
+    /// stable ones.  Can also be set per extension or directory rule with `coreset =` in the user
+    /// settings, which takes precedence.
+    ///
+    /// Example: "nightly"
+    #[clap(long, value_name = "NAME", display_order = 7)]
+    pub coreset: Option<String>,
+
+    /// Niceness of RetroArch from -20 to 19
+    ///
+    /// Run `retroarch` with this CPU priority, where lower values are a higher priority.  Values
+    /// below 0 need the permission to raise the priority.  Can also be set per core in `[cores]`
+    /// and per extension or directory rule with `nice =` in the user settings, which take
+    /// precedence.
+    #[clap(
+        long,
+        value_name = "N",
+        allow_hyphen_values = true,
+        display_order = 7
+    )]
+    pub nice: Option<String>,
+
+    /// CPUs RetroArch may run on
+    ///
+    /// Restrict `retroarch` to these CPUs, like `taskset` does.  Can also be set per core in
+    /// `[cores]` and per extension or directory rule with `cpus =` in the user settings, which
+    /// take precedence.
+    ///
+    /// Example: "2-7" or "0,2,4"
+    #[clap(long, value_name = "LIST", display_order = 7)]
+    pub cpus: Option<String>,
+
+    /// Run `RetroArch` on another machine over SSH
+    ///
+    /// Builds the same command, but runs it with `ssh` on TARGET, such as the HTPC in the living
+    /// room.  Paths of games and cores are translated by the `map.DIR = REMOTE_DIR` keys of section
+    /// `[remote:HOST]` in the user settings, where `retroarch =` sets the program on that machine.
+    /// The overrides for `retroarch.cfg` are copied over for the run.  Login without password
+    /// prompt is expected, such as with keys.
+    ///
+    /// Example: "user@htpc"
+    #[clap(long, value_name = "TARGET", display_order = 7)]
+    pub remote: Option<String>,
+
+    /// Run `RetroArch` with a private config home
+    ///
+    /// Launches `retroarch` with `HOME` and `XDG_CONFIG_HOME` pointing to a private directory, so
+    /// experiments cannot touch the main `RetroArch` configuration.  A skeleton `retroarch.cfg`
+    /// with the current `libretro-directory` is created in there, if it does not exist.  The
+    /// directory has to be attached with an equal sign.
+    ///
+    /// Example: "--isolated=~/retroarch-test" [default: ~/.local/share/enjoy/isolated]
+    #[clap(
+        long,
+        value_parser,
+        value_name = "DIR",
+        require_equals = true,
+        min_values = 0,
+        display_order = 7
+    )]
+    pub isolated: Option<Option<PathBuf>>,
+
+    /// Set environment variable for `RetroArch`
+    ///
+    /// Adds an environment variable to the `retroarch` process in the form `KEY=VALUE`.  This
+    /// option can be specified multiple times.  In the user settings this is written as
+    /// `env.KEY = VALUE` and can be set per extension or directory rule too, which take
+    /// precedence.  Variables of the user settings with another `KEY` are kept.
+    ///
+    /// Example: "SDL_VIDEODRIVER=x11"
+    #[clap(
+        long,
+        value_name = "KEY=VALUE",
+        parse(try_from_str = super::rules::parse_env_assignment),
+        display_order = 7
+    )]
+    pub env: Option<Vec<(String, String)>>,
+
     /// Strict mode for filter
     ///
     /// Turns the option `--filter` to be more strict when comparing filenames.  It makes it case
@@ -175,12 +462,128 @@ pub struct Opt {
     #[clap(short = 's', long, display_order = 2)]
     pub strict: bool,
 
+    /// Where the filter matches the filename
+    ///
+    /// Anchors each pattern of option `--filter` at the "start" or "end" of the filename, or
+    /// requires the "exact" filename instead of matching any part of it with "contains".  So
+    /// "Mario" with "exact" does not select "Mario Paint" or "Super Mario".  Default is "exact" with
+    /// option `--strict` and "contains" otherwise.
+    #[clap(
+        long = "match",
+        value_name = "MODE",
+        possible_values = ["start", "end", "exact", "contains"],
+        display_order = 2
+    )]
+    pub match_anchor: Option<String>,
+
+    /// Order of the games before filter and selection
+    ///
+    /// Sorts all games from the commandline, stdin and list files, so the selected game does not
+    /// depend on the order they were given in.  With "name" alphabetical by filename ignoring
+    /// case, with "mtime" the most recently modified first and with "size" the largest first.
+    /// Default is "none", which keeps the given order.
+    ///
+    /// Example: "mtime"
+    #[clap(
+        long,
+        value_name = "ORDER",
+        possible_values = ["name", "mtime", "size", "none"],
+        display_order = 2
+    )]
+    pub sort: Option<String>,
+
     /// Print selected game ROM
     ///
     /// Writes the full filepath of the selected game to stdout.
     #[clap(short = 'w', long, display_order = 1)]
     pub which: bool,
 
+    /// Print the RetroArch executable to run
+    ///
+    /// Prints the executable and where it was taken from, then exit.  In order these are option
+    /// `--retroarch`, environment variable `RETROARCH`, key `retroarch` of the user config and a
+    /// search for `retroarch`, `org.libretro.RetroArch` and `retroarch.AppImage` in the
+    /// directories of `retroarch_directory` and `$PATH`.
+    #[clap(long, display_order = 1)]
+    pub which_retroarch: bool,
+
+    /// Print where the core was found
+    ///
+    /// Lists the core of the selected game in each directory of `libretro_directory` in search
+    /// order, then exit.  The first existing one is marked as `used`, later ones are `shadowed`
+    /// by it and the rest are `missing`.
+    #[clap(long, display_order = 1)]
+    pub which_core: bool,
+
+    /// Print paths relative to a directory
+    ///
+    /// Paths of `--which`, the `--format` templates and JSON output are printed relative to
+    /// `BASE`, where paths outside of it stay absolute.  Without a value it is the home directory
+    /// and paths start with `~/`.  The value `roots` stands for the ROM roots from `[paths]` in
+    /// the user config.  The value must be attached with an equal sign.
+    ///
+    /// Example: "--relative=roots"
+    #[clap(
+        long,
+        value_name = "BASE",
+        require_equals = true,
+        min_values = 0,
+        display_order = 1
+    )]
+    pub relative: Option<Option<String>>,
+
+    /// Turn the AI service of RetroArch on or off
+    ///
+    /// Enables the on-screen translation of RetroArch for this run only, without changing the
+    /// global `retroarch.cfg`.  Can also be set per extension or directory rule with `ai_service
+    /// =` in the user settings, which takes precedence.  The server and mode are set with
+    /// `ai_service_url` and `ai_service_mode` in the user config.
+    #[clap(
+        long,
+        value_name = "SWITCH",
+        possible_values = ["on", "off"],
+        display_order = 7
+    )]
+    pub ai_service: Option<String>,
+
+    /// Set the audio volume of RetroArch in decibel
+    ///
+    /// Sets `audio_volume` for this run only, without changing the global `retroarch.cfg`.  It
+    /// goes from -80 to 12, where 0 is unchanged and -20 is a lot quieter.  The mixer of the
+    /// system is not touched, see `mixer_restore_command` in the user config to run a program
+    /// after RetroArch exits.
+    ///
+    /// Example: "-20"
+    #[clap(
+        long,
+        value_name = "DB",
+        allow_hyphen_values = true,
+        parse(try_from_str = super::audio::parse_volume),
+        display_order = 7
+    )]
+    pub volume: Option<f32>,
+
+    /// Mute the audio of RetroArch
+    ///
+    /// Sets `audio_mute_enable` for this run only, so the game starts silent.  The sound can be
+    /// turned on in game with the mute hotkey of RetroArch.
+    #[clap(long, display_order = 7)]
+    pub mute: bool,
+
+    /// Escape special characters in printed paths
+    ///
+    /// Paths of games are printed unchanged by default, where a newline in a filename looks like
+    /// two games and invalid UTF-8 is lost.  With "shell" each path is quoted for a POSIX shell if
+    /// needed, using `$'...'` for control characters and invalid UTF-8.  With "c" these are
+    /// escaped like in C strings, such as `\n` and `\377`.  JSON output has its own escaping.
+    #[clap(
+        long,
+        value_name = "MODE",
+        possible_values = ["shell", "c", "none"],
+        display_order = 1
+    )]
+    pub escape: Option<String>,
+
     /// Print RetroArch commandline
     ///
     /// Writes full command with all arguments used to run RetroArch to stdout. Has higher priority
@@ -188,6 +591,22 @@ pub struct Opt {
     #[clap(short = 'W', long, display_order = 1)]
     pub which_command: bool,
 
+    /// Print fingerprint of command
+    ///
+    /// Writes a stable hash over the fully resolved command, its working directory, environment
+    /// and override config, followed by the game path.  Store it to detect accidental changes in
+    /// the launch behavior after editing rules.  Implies option --norun.
+    #[clap(long, display_order = 1)]
+    pub fingerprint: bool,
+
+    /// Print settings as shell exports
+    ///
+    /// Writes the resolved settings as `ENJOY_*` shell export lines to stdout, like the game,
+    /// core, retroarch binary and config paths.  Combine with option --norun to use it in wrapper
+    /// scripts, in example: eval "$(enjoy -x --print-env game.smc)"
+    #[clap(long, display_order = 1)]
+    pub print_env: bool,
+
     /// Print all core names
     ///
     /// Lists all core names on the left side of the user configuration under section "\[cores\]".
@@ -196,12 +615,212 @@ pub struct Opt {
     #[clap(short = 'n', long, display_order = 3)]
     pub list_cores: bool,
 
+    /// Print extensions supported by each core
+    ///
+    /// Prints a table of the extensions listed in the core info files of all core aliases of
+    /// section "\[cores\]", marking the extensions the extension rules use each core for, then
+    /// exit.  Shows extensions without a rule and rules for cores which do not support them.
+    #[clap(long, display_order = 3)]
+    pub matrix: bool,
+
+    /// Long output format
+    ///
+    /// Print additional details in lists.  With option `--list-cores` the cores are grouped and
+    /// labeled by the name of their emulated system (like "Nintendo - Super Nintendo Entertainment
+    /// System"), which is read from the core info files.
+    #[clap(long, display_order = 3)]
+    pub long: bool,
+
+    /// Template for output lines
+    ///
+    /// Formats each line of `--which` and `--list-cores` after a template.  Fields in curly braces
+    /// are replaced by their values: {path}, {name}, {title}, {ext}, {dir}, {core}, {libretro},
+    /// {label} and {system}.  Fields without a value in the current output are empty.  The escapes
+    /// "\t", "\n" and "\0" insert a tab, newline or null character, a literal brace is written
+    /// as "{{" or "}}".
+    ///
+    /// Example: '{path}\t{core}\t{title}'
+    #[clap(long, value_name = "TEMPLATE", display_order = 3)]
+    pub format: Option<String>,
+
+    /// Fuzzy matching for filter
+    ///
+    /// Matches the patterns of option `--filter` as letters in order anywhere in the filename,
+    /// such as "smw" for "Super Mario World (USA) [!]", and launches the best match instead of
+    /// the first.  Letters in a row and at the start of words count more, skipped letters less.
+    #[clap(long, display_order = 2)]
+    pub fuzzy: bool,
+
+    /// Choose the game from a list
+    ///
+    /// Opens a list of all games matching the filter in the terminal, instead of launching the
+    /// first one.  Typing narrows the list down, arrow keys move the selection, `Enter` launches
+    /// the selected game and `Escape` cancels.  With option `selector_command` in the user
+    /// settings, that program is used instead, even without this option.
+    #[clap(long, display_order = 2)]
+    pub interactive: bool,
+
+    /// Choose the game by number
+    ///
+    /// Prints a numbered list of all games matching the filter and reads the number of the game
+    /// to launch from the terminal, if there is more than one.  An empty answer cancels.  Simpler
+    /// than option `--interactive` and only used if stdout is a terminal, otherwise the first
+    /// game is launched as usual.
+    #[clap(long, display_order = 2)]
+    pub choose: bool,
+
+    /// List games for fzf
+    ///
+    /// Prints each game matching the filter as a line of tab separated path, title and core,
+    /// then exit.  Games are also read from stdin when the output goes into a pipe, so a list of
+    /// files can be piped through `enjoy` into `fzf`.  Use option `--fzf-snippet` for a ready
+    /// shell function.
+    #[clap(long, display_order = 3)]
+    pub fzf: bool,
+
+    /// Run game of a line from fzf
+    ///
+    /// Takes a line as printed by option `--fzf` and selected in `fzf`, and runs the game of it.
+    /// Only the path up to the first tab is used.
+    #[clap(long, value_name = "LINE", display_order = 3)]
+    pub launch_from_fzf: Option<String>,
+
+    /// Print shell function for fzf
+    ///
+    /// Prints an example shell function `enjoy-fzf` with a key binding, which lists the games in
+    /// the ROM roots from `[paths]` in `fzf` and runs the selected one, then exit.
+    #[clap(long, display_order = 3)]
+    pub fzf_snippet: bool,
+
+    /// List games for shell completion
+    ///
+    /// Prints the fullpath of each game in the ROM roots from `[paths]`, whose title or path
+    /// starts with `PREFIX`, then exit.  The title is the filename without ROM extension and is
+    /// compared case insensitive.  Completion in bash is enabled with:
+    ///
+    /// _enjoy() { local IFS=$'\n'; COMPREPLY=($(printf '%q\n' $(enjoy --complete-games
+    /// "$2"))); }; complete -F _enjoy enjoy
+    #[clap(long, value_name = "PREFIX", display_order = 3)]
+    pub complete_games: Option<String>,
+
+    /// Run all games one after another
+    ///
+    /// Instead of picking the first game, every game matching the filter is run back-to-back.
+    /// The exit code and duration of each `RetroArch` session is collected and a summary table is
+    /// printed at the end.  Useful for testing a batch of newly added ROMs in one sitting.
+    #[clap(long, display_order = 3)]
+    pub queue: bool,
+
+    /// Continue an interrupted queue
+    ///
+    /// The progress of `--queue` is saved after each game in the state directory
+    /// (`~/.local/state/enjoy/queue`).  If the queue was interrupted, like by a power loss or
+    /// Ctrl+C, this runs the games of that queue not finished yet, in the same order.  Games and
+    /// filter of the current commandline are ignored.
+    #[clap(long, display_order = 3)]
+    pub resume_queue: bool,
+
+    /// Stop the queue at the first failure
+    ///
+    /// A game of `--queue` exiting with a code other than `0` or failing to start stops the
+    /// queue.  The remaining games are listed as "not run" in the summary.
+    #[clap(long, display_order = 3)]
+    pub fail_fast: bool,
+
+    /// Run all games of the queue
+    ///
+    /// Continue `--queue` after a failed game, which is the default.  Overrides `fail_fast` from
+    /// the user config.
+    #[clap(long, display_order = 3, conflicts_with = "fail-fast")]
+    pub keep_going: bool,
+
+    /// When the queue exits with an error
+    ///
+    /// The exit code of `--queue` is nonzero if "any" game failed or was not run, or only if
+    /// "all" of them failed.  With "never", the default, the results are only reported in the
+    /// summary.
+    #[clap(
+        long,
+        value_name = "POLICY",
+        possible_values = ["never", "any", "all"],
+        display_order = 3
+    )]
+    pub exit_policy: Option<String>,
+
+    /// Link games into per-system folders
+    ///
+    /// Creates a tree of symlinks in DIR, with one folder for each system the games resolve to
+    /// by the rules (like "Nintendo - Super Nintendo Entertainment System").  The system name is
+    /// read from the core info files, or the name of the core is used without them.  Existing
+    /// files are never replaced.  Each created link is printed, then exit.  Other frontends which
+    /// expect a folder per system can use this view of a flat collection.
+    #[clap(long, parse(from_os_str), value_name = "DIR", display_order = 3)]
+    pub organize: Option<PathBuf>,
+
+    /// Show new filenames from the title database
+    ///
+    /// Looks up each game by its CRC-32 checksum in the database files and prints the current and
+    /// proposed filename, named after the title with the original extension.  The games are the
+    /// (filtered) games given, or all games in the ROM roots from `[paths]` without any.  Nothing
+    /// is renamed, then exit.
+    #[clap(long, display_order = 3)]
+    pub rename_preview: bool,
+
+    /// Rename games after the title database
+    ///
+    /// Like `--rename-preview`, but renames the files if `--yes` is given too.  Without it this is
+    /// a dry-run.  A file is never replaced: games whose new name is taken by another file, or
+    /// would be given to more than one game, are skipped.
+    #[clap(long, display_order = 3)]
+    pub rename: bool,
+
+    /// Suggest duplicate cores to remove
+    ///
+    /// Looks for multiple builds of the same core in each `libretro_directory`, such as versioned
+    /// names or leftover downloads like "snes9x_libretro (1).so".  Prints which build is kept and
+    /// which could be removed, then exit.  Kept is the build the rules use, or else the one
+    /// `RetroArch` looks for.  Removes the others if `--yes` is given too, without it this is a
+    /// dry-run.
+    #[clap(long, display_order = 3)]
+    pub prune_cores: bool,
+
+    /// Confirm changes to files
+    ///
+    /// Required by `--rename` and `--prune-cores` to actually rename or remove any file.
+    #[clap(long, display_order = 3)]
+    pub yes: bool,
+
+    /// Database file with game titles
+    ///
+    /// A DAT file in clrmamepro or Logiqx XML format (like from No-Intro or the `metadat` folder
+    /// of libretro-database) or a `RetroArch` `.rdb` database, used by `--rename-preview` and
+    /// `--rename`.  Can be given multiple times and adds to the files from section `[databases]`
+    /// in the user settings.
+    #[clap(long, parse(from_os_str), value_name = "FILE", display_order = 6)]
+    pub database: Option<Vec<PathBuf>>,
+
+    /// Output in JSON format
+    ///
+    /// Print machine readable JSON instead of plain text, where supported.  Currently this
+    /// affects the summary of option `--queue` and the report of a launched game.
+    #[clap(long, display_order = 3)]
+    pub json: bool,
+
     /// Force fullscreen mode
     ///
     /// Runs the emulator and `RetroArch` UI in fullscreen, regardless of any other setting.
     #[clap(short = 'F', long, display_order = 3)]
     pub fullscreen: bool,
 
+    /// Start in the menu of `RetroArch`
+    ///
+    /// Opens the menu of `RetroArch` instead of booting straight into the game, so settings can
+    /// be changed before it starts.  The game and its core are put into the playlist `enjoy` in
+    /// the `playlist_directory` of `retroarch.cfg`, which is replaced on each launch.  Not
+    /// allowed in lock mode.
+    #[clap(long, display_order = 3)]
+    pub menu_load: bool,
+
     /// There Can Only Be One!
     ///
     /// Prevents running another `retroarch` process, if one is already active.  In this case the
@@ -209,6 +828,19 @@ pub struct Opt {
     #[clap(short = '1', long, display_order = 3)]
     pub highlander: bool,
 
+    /// Processes considered by highlander
+    ///
+    /// Limit the search for a running `RetroArch` instance of option `--highlander` to processes
+    /// of the current "user" or login "session".  In any case only processes started from the
+    /// same executable as `--retroarch` are counted, if its path can be read.
+    #[clap(
+        long,
+        value_name = "SCOPE",
+        possible_values = ["any", "user", "session"],
+        display_order = 3
+    )]
+    pub highlander_scope: Option<String>,
+
     /// Ignore user settings
     ///
     /// The config INI file of this program will be ignored and not loaded up.  The entire
@@ -238,6 +870,289 @@ pub struct Opt {
     #[clap(short = 'z', long, display_order = 8)]
     pub nostdin: bool,
 
+    /// Scan directories given as games recursively
+    ///
+    /// A directory in the list of games is replaced by the files in it with a known ROM
+    /// extension.  With this option the files in all of its subdirectories are added too.
+    #[clap(short = 'r', long, display_order = 8)]
+    pub recursive: bool,
+
+    /// Read games from a list file
+    ///
+    /// Adds each line of the text `file` as a game entry, like the lines from stdin.  Empty lines
+    /// and lines starting with `#` are ignored.  Relative paths are relative to the folder of the
+    /// list file.  This option can be specified multiple times.
+    ///
+    /// Example: "~/playlists/favorites.txt"
+    #[clap(long, value_name = "FILE", parse(from_os_str), display_order = 8)]
+    pub games_from: Option<Vec<PathBuf>>,
+
+    /// Read games from stdin separated by NUL instead of lines
+    ///
+    /// Each entry from `stdin` ends with a NUL byte instead of a newline, as written by `find
+    /// -print0` or `fd --print0`.  Paths with newlines or bytes, which are not valid UTF-8, are
+    /// read as they are.
+    #[clap(short = '0', long, display_order = 8)]
+    pub null: bool,
+
+    /// Read the game itself from stdin
+    ///
+    /// The data piped into stdin is the content of the game, such as from a patcher or
+    /// downloader, instead of a list of games.  It is saved to a private temporary file, which is
+    /// removed on exit.  The core is found by the extension given with `--ext`.
+    #[clap(long, requires = "ext", display_order = 8)]
+    pub stdin_rom: bool,
+
+    /// Extension of the game read from stdin
+    ///
+    /// Required by `--stdin-rom` to find the core by the extension rules.
+    ///
+    /// Example: "sfc"
+    #[clap(
+        long,
+        value_name = "EXT",
+        requires = "stdin-rom",
+        display_order = 8
+    )]
+    pub ext: Option<String>,
+
+    /// Stop reading stdin after MS milliseconds without data
+    ///
+    /// Some GUI launchers start programs with a pipe as stdin, which is never closed.  Reading it
+    /// stops, if no new data arrived in this time.  Set to `0` to wait until stdin is closed, no
+    /// matter how long it takes.  Defaults to 2000.
+    #[clap(long, value_name = "MS", display_order = 8)]
+    pub stdin_timeout: Option<u64>,
+
+    /// Abort if starting takes longer than MS milliseconds
+    ///
+    /// Reading the configs and stdin and building the command must finish within this time,
+    /// otherwise the program exits with code 3 and names the slow phase.  Keeps the latency
+    /// predictable for kiosk setups, instead of stalling on huge or broken input.  Off by default
+    /// or with `0`.
+    #[clap(long, value_name = "MS", display_order = 8)]
+    pub startup_timeout: Option<u64>,
+
+    /// Ignore launches within MS milliseconds of the last one
+    ///
+    /// A double click in a file manager or a bouncing gamepad button can start the program twice,
+    /// before the single instance mode `--highlander` sees the first `RetroArch`.  Only the first
+    /// launch within this time proceeds, the others exit with a message.  Off by default or with
+    /// `0`.
+    ///
+    /// Example: "1500"
+    #[clap(long, value_name = "MS", display_order = 8)]
+    pub debounce: Option<u64>,
+
+    /// Terminate RetroArch after SECONDS
+    ///
+    /// Sends the terminate signal to RetroArch, if it still runs after this time, such as for
+    /// timed sessions or demo setups.  A game ended like this counts as failed.  Off by default or
+    /// with `0`.
+    #[clap(long, value_name = "SECONDS", display_order = 8)]
+    pub timeout: Option<u64>,
+
+    /// Keep games given more than once
+    ///
+    /// The same game from the commandline, stdin and config is only kept at its first position.
+    /// Paths are compared after resolving them, so `./mario.smc` and `~/roms/mario.smc` can be
+    /// the same game.  With this option all duplicates stay in the list.
+    #[clap(long, display_order = 8)]
+    pub keep_duplicates: bool,
+
+    /// Keep only one game of each filename
+    ///
+    /// Games with the same filename in different directories are collapsed too, such as from a
+    /// backup copy of the collection.  The first one in the list is kept.
+    #[clap(long, display_order = 8)]
+    pub unique: bool,
+
+    /// Show desktop notifications
+    ///
+    /// Send a notification when `RetroArch` exits abnormally after running for a while, or when
+    /// all games of option `--queue` are finished.  Useful when the emulator runs fullscreen on
+    /// another monitor or TV.  Requires a build with feature `notify`, otherwise this option is
+    /// ignored.
+    #[clap(long, display_order = 8)]
+    pub notify: bool,
+
+    /// Pause game while system suspends
+    ///
+    /// Listen to logind for suspend of the system while a game runs, and pause `RetroArch` right
+    /// before it.  After wake up the game is resumed, unless it was already paused.  The network
+    /// command interface of `RetroArch` is enabled for it.  Requires `gdbus` and a build with
+    /// feature `suspend`, otherwise this option is ignored.
+    #[clap(long, display_order = 8)]
+    pub pause_on_suspend: bool,
+
+    /// Keep temporary override files
+    ///
+    /// Do not delete the append-config composed from section `[retroarch]` of the user settings
+    /// after `RetroArch` exits.  The files are written to a directory like `/tmp/enjoy/<pid>`,
+    /// even with option `--norun`.  Useful to debug which settings were handed over.
+    #[clap(long, display_order = 8)]
+    pub keep_overrides: bool,
+
+    /// Skip checks before launch
+    ///
+    /// Do not run the checks of section `[preflight]` in the user config.  By default the save
+    /// directories from `retroarch.cfg` are checked to exist, be writable and have some free
+    /// space, before a game is launched.
+    #[clap(long, display_order = 8)]
+    pub no_preflight: bool,
+
+    /// Launch even with low battery or memory
+    ///
+    /// Section `[policy]` of the user config can refuse to launch with `on_low_resources = abort`,
+    /// if the battery is below `min_battery` or the free memory below `min_free_memory`.  This
+    /// option launches the game anyway, with a warning.
+    #[clap(long, display_order = 8)]
+    pub force: bool,
+
+    /// Use cores forbidden by policy
+    ///
+    /// Ignore the lists `blocked_cores` and `allowed_cores` of section `[policy]` in the user
+    /// config, as well as its checks of the battery and free memory.  Meant for administrators
+    /// of a shared setup, to run a core the policy forbids for everyone else.
+    #[clap(long, display_order = 8)]
+    pub ignore_policy: bool,
+
+    /// Launch even if the core changed
+    ///
+    /// With `core_lock = 1` in section `[options]` of the user config, a game is not launched
+    /// when the core file changed since its last run, such as after a distribution update.  This
+    /// accepts the changed core and records it as verified in the history.
+    #[clap(long, display_order = 8)]
+    pub ignore_core_lock: bool,
+
+    /// Only launch approved games
+    ///
+    /// Restrict the games to those in the approved `directories` or `playlist` of section
+    /// `[lock]` in `/etc/enjoy/lock.ini` or else the default user config.  Lock mode is also
+    /// enabled with key `enabled` in that section, to protect a family-room setup without relying
+    /// on this option.  While locked, options `--config` and `--noconfig` and content in the
+    /// arguments for `retroarch` are refused.
+    #[clap(long, display_order = 8)]
+    pub locked: bool,
+
+    /// Disable lock mode with password
+    ///
+    /// Ask for the password on the terminal and launch any game, if it matches the SHA-256 in
+    /// key `password_hash` of section `[lock]`.  The hash is created with `printf %s password |
+    /// sha256sum`.
+    #[clap(long, display_order = 8)]
+    pub unlock: bool,
+
+    /// Run HTTP server on localhost
+    ///
+    /// Listen on `127.0.0.1` for requests to start games, until the program is terminated.  The
+    /// port has to be attached with an equal sign.  `POST /launch` with a JSON body like
+    /// `{"game": "~/roms/mario.smc", "core": "snes"}` starts a game, `POST /quit` terminates the
+    /// running instances.  Each request needs the header `Authorization: Bearer <token>`, with
+    /// the token from option `--token-file`.
+    ///
+    /// Example: "--serve=8080" [default: 8787]
+    #[clap(
+        long,
+        value_parser,
+        value_name = "PORT",
+        require_equals = true,
+        min_values = 0,
+        display_order = 9
+    )]
+    pub serve: Option<Option<u16>>,
+
+    /// Secret for HTTP server
+    ///
+    /// Path to the file with the secret token required by option `--serve`.  If the file does not
+    /// exist, it is created with a random token readable by the current user only.
+    ///
+    /// [default: $XDG_RUNTIME_DIR/enjoy/token]
+    #[clap(long, parse(from_os_str), value_name = "FILE", display_order = 9)]
+    pub token_file: Option<PathBuf>,
+
+    /// Launch game with gamepad hotkey
+    ///
+    /// Wait for a button combination on the gamepad, until the program is terminated.  Each
+    /// time it is pressed while no `RetroArch` is running, the game is launched as if this
+    /// option was not given.  Set a default game with option `game` in the user config, to start
+    /// playing without a keyboard.
+    #[clap(long, display_order = 9)]
+    pub hotkey_listen: bool,
+
+    /// Joystick device for hotkey
+    ///
+    /// Path to the joystick device read by option `--hotkey-listen`.
+    ///
+    /// [default: /dev/input/js0]
+    #[clap(long, parse(from_os_str), value_name = "FILE", display_order = 9)]
+    pub hotkey_device: Option<PathBuf>,
+
+    /// Buttons for hotkey
+    ///
+    /// Button numbers to hold down at the same time for option `--hotkey-listen`, separated by
+    /// plus sign.  The numbers are as reported by the joystick device, check them with `jstest`.
+    ///
+    /// [default: 6+7]
+    #[clap(long, value_name = "BUTTONS", display_order = 9)]
+    pub hotkey_buttons: Option<String>,
+
+    /// List running instances
+    ///
+    /// Print the pid, running time, core and game of each `RetroArch` process launched by this
+    /// program, which is still running, then exit.  Use option `--json` for machine readable
+    /// output.
+    #[clap(long, display_order = 9)]
+    pub list_instances: bool,
+
+    /// Quit running instances
+    ///
+    /// Ask each `RetroArch` process launched by this program to quit, by sending the terminate
+    /// signal.  The pid of each terminated process is printed, then exit.
+    #[clap(long, display_order = 9)]
+    pub quit: bool,
+
+    /// Diagnose the setup
+    ///
+    /// Runs a suite of environment checks and prints a report with pass, warn or fail for each
+    /// of them, then exit.  It checks if `retroarch` is found and runnable, if its `retroarch.cfg`
+    /// is located, if the `libretro-directory` contains cores, if the user settings can be parsed
+    /// and if the save directories are writable.
+    #[clap(long, display_order = 9)]
+    pub doctor: bool,
+
+    /// Show how often options and rules were used
+    ///
+    /// Prints the local usage counters, most used first, followed by the rules which never
+    /// applied to a launched game, then exit.  Counting is off by default and enabled with
+    /// `usage_counters = 1` in section `[options]` of the user settings.  The counters are only
+    /// stored in the state directory of this program and never sent anywhere.
+    #[clap(long, display_order = 9)]
+    pub usage_report: bool,
+
+    /// Check if cores changed since their last run
+    ///
+    /// Compares each core in the history of launched games with the hash recorded at its last
+    /// launch, then exit.  Exits with an error if any core changed or is missing.  Useful for
+    /// speedruns and tool-assisted runs, which need reproducible versions of the emulators.
+    #[clap(long, display_order = 9)]
+    pub verify_cores: bool,
+
+    /// Show the history of launched games
+    ///
+    /// Prints the date, title, tags and note of each launch in the history, oldest first, then
+    /// exit.  Combine with `--tag` to show only launches with that tag.
+    #[clap(long, display_order = 9)]
+    pub history_list: bool,
+
+    /// Show how often each game was launched
+    ///
+    /// Prints the number of launches and the date of the last launch for each game in the
+    /// history, most launched first, then exit.  Combine with `--tag` to count only launches
+    /// with that tag.
+    #[clap(long, display_order = 9)]
+    pub stats: bool,
+
     /// Print version information
     ///
     /// Print the version number of this app and exit
@@ -0,0 +1,65 @@
+use indexmap::map::IndexMap;
+
+/// Lowest volume in decibel `RetroArch` accepts for key `audio_volume`.
+pub const MIN_VOLUME: f32 = -80.0;
+
+/// Highest volume in decibel `RetroArch` accepts for key `audio_volume`.
+pub const MAX_VOLUME: f32 = 12.0;
+
+/// Parse the volume of option `--volume` in decibel, like `-12` or `0`.
+pub fn parse_volume(text: &str) -> Result<f32, String> {
+    text.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|volume| (MIN_VOLUME..=MAX_VOLUME).contains(volume))
+        .ok_or_else(|| {
+            format!(
+                "Invalid volume \"{}\", expected decibel from {} to {}",
+                text, MIN_VOLUME, MAX_VOLUME
+            )
+        })
+}
+
+/// Keys of `retroarch.cfg` to set the `volume` in decibel or `mute` the audio for a single run.
+/// The mixer of the system is not touched.
+#[must_use]
+pub fn overrides(volume: Option<f32>, mute: bool) -> IndexMap<String, String> {
+    let mut keys: IndexMap<String, String> = IndexMap::new();
+
+    if let Some(volume) = volume {
+        keys.insert("audio_volume".to_string(), format!("{:.6}", volume));
+    }
+    if mute {
+        keys.insert("audio_mute_enable".to_string(), "true".to_string());
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+
+    use indexmap::map::IndexMap;
+
+    #[test]
+    fn parse_volume() {
+        assert_eq!(Ok(-12.5), super::parse_volume("-12.5"));
+        assert_eq!(Ok(12.0), super::parse_volume("12"));
+        assert!(super::parse_volume("13").is_err());
+        assert!(super::parse_volume("-81").is_err());
+        assert!(super::parse_volume("loud").is_err());
+        assert!(super::parse_volume("NaN").is_err());
+    }
+
+    #[test]
+    fn overrides() {
+        assert_eq!(
+            IndexMap::from([
+                ("audio_volume".to_string(), "-20.000000".to_string()),
+                ("audio_mute_enable".to_string(), "true".to_string()),
+            ]),
+            super::overrides(Some(-20.0), true)
+        );
+        assert!(super::overrides(None, false).is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::settings::file;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Number of backups kept for each user config, older ones are removed.
+pub const MAX_BACKUPS: u32 = 20;
+
+/// Extension of a backup with the content of the user config before the change.
+const CONTENT: &str = "ini";
+/// Extension of an empty backup, for a user config which did not exist before the change.
+const ABSENT: &str = "absent";
+
+/// Folder with the numbered backups of the user `config` in the state directory.  Each config
+/// gets its own folder, named after its absolute path with slashes replaced by `%`.  The path is
+/// not canonicalized, so the folder is the same before and after the config is created.
+#[must_use]
+pub fn backup_dir(config: &Path) -> PathBuf {
+    let config: PathBuf = env::current_dir()
+        .unwrap_or_default()
+        .join(file::tilde(config));
+
+    file::state_dir()
+        .join("config-backups")
+        .join(config.display().to_string().replace('/', "%"))
+}
+
+/// All backups in `dir`, ordered by their number from oldest to newest.
+fn list(dir: &Path) -> Vec<(u32, PathBuf)> {
+    let mut backups: Vec<(u32, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension().is_some_and(|extension| {
+                extension == CONTENT || extension == ABSENT
+            })
+        })
+        .filter_map(|path| {
+            let number: u32 = path.file_stem()?.to_str()?.parse().ok()?;
+            Some((number, path))
+        })
+        .collect();
+    backups.sort();
+
+    backups
+}
+
+/// Save the current state of `config` as the next numbered backup in `dir`, like `0007.ini`.  If
+/// the file does not exist yet, an empty `0007.absent` is saved instead.  Only the newest
+/// `MAX_BACKUPS` are kept.  Returns the path of the new backup.
+pub fn save(dir: &Path, config: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let backups: Vec<(u32, PathBuf)> = list(dir);
+    let number: u32 = backups.last().map_or(1, |(number, _)| number + 1);
+
+    let backup: PathBuf = if config.exists() {
+        let backup: PathBuf = dir.join(format!("{:04}.{}", number, CONTENT));
+        fs::copy(config, &backup)?;
+        backup
+    } else {
+        let backup: PathBuf = dir.join(format!("{:04}.{}", number, ABSENT));
+        fs::write(&backup, "")?;
+        backup
+    };
+
+    let excess: usize = (backups.len() + 1)
+        .saturating_sub(usize::try_from(MAX_BACKUPS).unwrap_or(usize::MAX));
+    for (_, old) in backups.iter().take(excess) {
+        fs::remove_file(old)?;
+    }
+
+    Ok(backup)
+}
+
+/// Replace the content of `config` with `content`, after saving a backup of it in `dir`.
+/// Returns the path of the backup.
+pub fn write(dir: &Path, config: &Path, content: &str) -> io::Result<PathBuf> {
+    let backup: PathBuf = save(dir, config)?;
+    if let Some(parent) = config.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(config, content)?;
+
+    Ok(backup)
+}
+
+/// Restore `config` from the newest backup in `dir` and remove that backup, so repeated calls
+/// step back further.  A config which did not exist before is removed.  Returns the path of the
+/// restored backup, or `None` if there is none.
+pub fn undo(dir: &Path, config: &Path) -> io::Result<Option<PathBuf>> {
+    let Some((_, backup)) = list(dir).pop() else {
+        return Ok(None);
+    };
+
+    if backup
+        .extension()
+        .is_some_and(|extension| extension == ABSENT)
+    {
+        if config.exists() {
+            fs::remove_file(config)?;
+        }
+    } else {
+        fs::copy(&backup, config)?;
+    }
+    fs::remove_file(&backup)?;
+
+    Ok(Some(backup))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Untested:
+    //  - backup_dir()
+
+    #[test]
+    fn write_and_undo() {
+        let root: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-backups-{}", std::process::id()));
+        let dir: PathBuf = root.join("backups");
+        let config: PathBuf = root.join("config/default.ini");
+
+        let first = super::write(&dir, &config, "version = 1\n").unwrap();
+        super::write(&dir, &config, "version = 2\n").unwrap();
+        let content = fs::read_to_string(&config).unwrap();
+        let restored = super::undo(&dir, &config).unwrap();
+        let restored_content = fs::read_to_string(&config).unwrap();
+        super::undo(&dir, &config).unwrap();
+        let exists = config.exists();
+        let empty = super::undo(&dir, &config).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(dir.join("0001.absent"), first);
+        assert_eq!("version = 2\n", content);
+        assert_eq!(Some(dir.join("0002.ini")), restored);
+        assert_eq!("version = 1\n", restored_content);
+        assert!(!exists);
+        assert_eq!(None, empty);
+    }
+
+    #[test]
+    fn save_keeps_newest() {
+        let root: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-backups-max-{}", std::process::id()));
+        let config: PathBuf = root.join("default.ini");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(&config, "").unwrap();
+
+        for _ in 0..super::MAX_BACKUPS + 2 {
+            super::save(&root.join("backups"), &config).unwrap();
+        }
+        let backups = super::list(&root.join("backups"));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(super::MAX_BACKUPS as usize, backups.len());
+        assert_eq!(3, backups[0].0);
+        assert_eq!(super::MAX_BACKUPS + 2, backups[backups.len() - 1].0);
+    }
+}
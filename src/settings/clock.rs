@@ -0,0 +1,44 @@
+use std::mem;
+
+/// Current local date and time from the C library, in the time zone of `TZ` or
+/// `/etc/localtime`.  `None` if it could not be converted.
+#[must_use]
+pub fn now() -> Option<libc::tm> {
+    // SAFETY: A null pointer makes `time()` only return the time, and `localtime_r()` writes
+    // into the plain data `tm`, which is only read after it returned success.
+    unsafe {
+        let time: libc::time_t = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            None
+        } else {
+            Some(tm)
+        }
+    }
+}
+
+/// Weekday of `tm` from 1 for Monday to 7 for Sunday, like `date +%u` prints it.
+#[must_use]
+pub fn weekday(tm: &libc::tm) -> i32 {
+    if tm.tm_wday == 0 {
+        7
+    } else {
+        tm.tm_wday
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    // Untested:
+    //  - now()
+
+    #[test]
+    fn weekday() {
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        tm.tm_wday = 0;
+        assert_eq!(7, super::weekday(&tm));
+        tm.tm_wday = 1;
+        assert_eq!(1, super::weekday(&tm));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::settings::arguments;
+
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::path::Path;
+use std::path::PathBuf;
+
+use indexmap::map::IndexMap;
+
+/// Section of the user config with the command presets.
+pub const SECTION: &str = "commands";
+
+/// Marks an argument as the name of a command preset, like `@tv`.
+pub const PREFIX: char = '@';
+
+/// Name of the preset, if `arg` is one like `@tv`.
+fn preset_name(arg: &OsStr) -> Option<&str> {
+    arg.to_str()?
+        .strip_prefix(PREFIX)
+        .filter(|name| !name.is_empty())
+}
+
+/// The arguments for `enjoy` itself, without the program name and the arguments for `retroarch`
+/// after `--`.
+fn own_arguments(args: &[OsString]) -> impl Iterator<Item = &OsString> {
+    args.iter().skip(1).take_while(|arg| *arg != "--")
+}
+
+/// Check if any of the commandline arguments `args` is the name of a command preset.
+#[must_use]
+pub fn has_presets(args: &[OsString]) -> bool {
+    own_arguments(args).any(|arg| preset_name(arg).is_some())
+}
+
+/// The user config given in the commandline arguments `args`, before they are parsed.  `None`
+/// with option `--noconfig`.
+#[must_use]
+pub fn config_path(args: &[OsString]) -> Option<PathBuf> {
+    let mut config: PathBuf = PathBuf::from(arguments::DEFAULT_CONFIG);
+    let mut arguments = own_arguments(args);
+
+    while let Some(arg) = arguments.next() {
+        let text: &str = arg.to_str().unwrap_or_default();
+        if text == "--noconfig" {
+            return None;
+        } else if text == "--config" || text == "-c" {
+            if let Some(value) = arguments.next() {
+                config = PathBuf::from(value);
+            }
+        } else if let Some(value) = text.strip_prefix("--config=") {
+            config = PathBuf::from(value);
+        } else if let Some(value) =
+            text.strip_prefix("-c").filter(|value| !value.is_empty())
+        {
+            config = PathBuf::from(value);
+        }
+    }
+
+    Some(config)
+}
+
+/// Replace each argument like `@tv` by the arguments of the command preset `tv` in `presets`,
+/// which are split like in a shell.  Presets are not expanded again inside of a preset.  An
+/// unknown preset is an error, unless a file of that name exists.  Arguments for `retroarch`
+/// after `--` are kept as they are.
+pub fn expand(
+    args: Vec<OsString>,
+    presets: &IndexMap<String, String>,
+) -> Result<Vec<OsString>, String> {
+    let mut expanded: Vec<OsString> = Vec::with_capacity(args.len());
+    let mut own: bool = true;
+
+    for (index, arg) in args.into_iter().enumerate() {
+        if arg == "--" {
+            own = false;
+        }
+        let name: Option<&str> = if own && index > 0 {
+            preset_name(&arg)
+        } else {
+            None
+        };
+        match name {
+            Some(name) if presets.contains_key(name) => {
+                let words: Vec<String> = shlex::split(&presets[name])
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid command preset in [{}]: {}",
+                            SECTION, name
+                        )
+                    })?;
+                expanded.extend(words.into_iter().map(OsString::from));
+            }
+            Some(name) if !Path::new(&arg).exists() => {
+                return Err(format!(
+                    "Unknown command preset {}{}, not found in section [{}]",
+                    PREFIX, name, SECTION
+                ));
+            }
+            _ => expanded.push(arg),
+        }
+    }
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+
+    use indexmap::map::IndexMap;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn config_path() {
+        assert_eq!(
+            Some(PathBuf::from(super::arguments::DEFAULT_CONFIG)),
+            super::config_path(&args(&["enjoy", "@tv", "game.smc"]))
+        );
+        assert_eq!(
+            Some(PathBuf::from("tv.ini")),
+            super::config_path(&args(&["enjoy", "-c", "tv.ini", "@tv"]))
+        );
+        assert_eq!(
+            Some(PathBuf::from("tv.ini")),
+            super::config_path(&args(&["enjoy", "--config=tv.ini", "@tv"]))
+        );
+        assert_eq!(
+            Some(PathBuf::from(super::arguments::DEFAULT_CONFIG)),
+            super::config_path(&args(&["enjoy", "@tv", "--", "-c", "ra.cfg"]))
+        );
+        assert_eq!(None, super::config_path(&args(&["enjoy", "--noconfig"])));
+    }
+
+    #[test]
+    fn expand() {
+        let presets: IndexMap<String, String> = IndexMap::from([(
+            "tv".to_string(),
+            "--fullscreen --profile 'living room' @tv".to_string(),
+        )]);
+
+        assert_eq!(
+            Ok(args(&[
+                "enjoy",
+                "--fullscreen",
+                "--profile",
+                "living room",
+                "@tv",
+                "game.smc",
+                "--",
+                "@tv",
+            ])),
+            super::expand(
+                args(&["enjoy", "@tv", "game.smc", "--", "@tv"]),
+                &presets
+            )
+        );
+        assert!(super::expand(args(&["enjoy", "@pc"]), &presets).is_err());
+        assert!(super::has_presets(&args(&["enjoy", "-f", "@tv"])));
+        assert!(!super::has_presets(&args(&["@tv", "--", "@tv", "@"])));
+    }
+}
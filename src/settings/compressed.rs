@@ -0,0 +1,124 @@
+#[cfg(feature = "archive")]
+use crate::settings::file;
+
+#[cfg(feature = "archive")]
+use std::fs;
+#[cfg(feature = "archive")]
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "archive")]
+use std::process::Command;
+
+/// Extension of Zstandard-compressed games, like `game.smc.zst` or `game.chd.zst`.
+pub const EXTENSION: &str = "zst";
+
+/// Folder in the cache directory of this program, which holds the decompressed games.
+pub const CACHE_DIR: &str = "zst";
+
+/// The game without its `.zst` extension, like `game.smc` for `game.smc.zst`.  `None` if it is
+/// not compressed.
+#[must_use]
+pub fn inner(game: &Path) -> Option<PathBuf> {
+    game.extension()
+        .filter(|extension| extension.eq_ignore_ascii_case(EXTENSION))
+        .map(|_| game.with_extension(""))
+}
+
+/// Check if a core reads compressed games itself, by the extensions from its core info file.
+#[must_use]
+pub fn is_supported(supported_extensions: &[String]) -> bool {
+    supported_extensions
+        .iter()
+        .any(|extension| extension.eq_ignore_ascii_case(EXTENSION))
+}
+
+/// Path of the decompressed game in the cache `dir`.  It is in a folder named after the hash of
+/// the compressed content, so it is reused as long as the content does not change.
+#[cfg(feature = "archive")]
+pub fn cached_path(dir: &Path, game: &Path) -> io::Result<PathBuf> {
+    let hash: u64 = file::fnv1a_file(game)?;
+    let name: PathBuf = inner(game)
+        .as_deref()
+        .and_then(Path::file_name)
+        .map_or_else(|| PathBuf::from("game"), PathBuf::from);
+
+    Ok(dir.join(format!("{:016x}", hash)).join(name))
+}
+
+/// Decompress the game into `target` with the program `zstd`, unless it exists already.  It is
+/// written under a temporary name first, so an interrupted run leaves no partial game behind.  The
+/// game is touched when written or reused, so the janitor keeps it in the cache for option
+/// `cache_days`, instead of going by the time of the compressed file `zstd` copies.
+#[cfg(feature = "archive")]
+pub fn decompress(game: &Path, target: &Path) -> io::Result<()> {
+    if target.is_file() {
+        return file::touch(target);
+    }
+    let dir: &Path = target.parent().unwrap_or_else(|| Path::new(""));
+    fs::create_dir_all(dir)?;
+    let partial: PathBuf =
+        dir.join(format!(".partial-{}", std::process::id()));
+
+    let status = Command::new("zstd")
+        .args(["--decompress", "--quiet", "--force", "-o"])
+        .arg(&partial)
+        .arg(game)
+        .status()?;
+    if !status.success() {
+        let _ = fs::remove_file(&partial);
+        return Err(io::Error::other(format!("zstd failed with {}", status)));
+    }
+
+    fs::rename(&partial, target)?;
+
+    file::touch(target)
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[cfg(feature = "archive")]
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    // Untested:
+    //  - decompress()
+
+    #[test]
+    fn inner() {
+        assert_eq!(
+            Some(PathBuf::from("/roms/game.smc")),
+            super::inner(Path::new("/roms/game.smc.zst"))
+        );
+        assert_eq!(
+            Some(PathBuf::from("game.cue")),
+            super::inner(Path::new("game.cue.ZST"))
+        );
+        assert_eq!(None, super::inner(Path::new("/roms/game.smc")));
+    }
+
+    #[test]
+    fn is_supported() {
+        assert!(super::is_supported(&["chd".to_string(), "ZST".to_string()]));
+        assert!(!super::is_supported(&["smc".to_string()]));
+    }
+
+    #[test]
+    #[cfg(feature = "archive")]
+    fn cached_path() {
+        let dir: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-compressed-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let game: PathBuf = dir.join("game.smc.zst");
+        fs::write(&game, "a").unwrap();
+        let first = super::cached_path(Path::new("/cache"), &game).unwrap();
+        fs::write(&game, "b").unwrap();
+        let second = super::cached_path(Path::new("/cache"), &game).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(PathBuf::from("/cache/af63dc4c8601ec8c/game.smc"), first);
+        assert_ne!(first, second);
+    }
+}
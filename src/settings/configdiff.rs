@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use indexmap::map::IndexMap;
+
+/// What a game resolves to under one user config, as far as compared by `--diff-config`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    pub core: String,
+    /// The program and all of its arguments.  The paths of the core and the append-config are
+    /// replaced by placeholders, as these are compared on their own.  The name of the
+    /// append-config changes with the overrides.
+    pub arguments: Vec<String>,
+}
+
+impl Resolved {
+    #[must_use]
+    pub fn new(core: &Path, program: String, arguments: Vec<String>) -> Self {
+        let mut all: Vec<String> = vec![program];
+        let mut previous: String = String::new();
+        for argument in arguments {
+            all.push(match previous.as_str() {
+                "--libretro" => "<core>".to_string(),
+                "--appendconfig" => "<overrides>".to_string(),
+                _ => argument.clone(),
+            });
+            previous = argument;
+        }
+
+        Self {
+            core: core.display().to_string(),
+            arguments: all,
+        }
+    }
+}
+
+/// Lines describing the differences of a game between two configs, empty if it resolves the same.
+/// A game which could not be resolved is shown with its error.
+#[must_use]
+pub fn compare(
+    old: &Result<Resolved, String>,
+    new: &Result<Resolved, String>,
+) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    match (old, new) {
+        (Ok(old), Ok(new)) => {
+            if old.core != new.core {
+                lines.push(format!("core: {} -> {}", old.core, new.core));
+            }
+            if old.arguments != new.arguments {
+                lines.push(format!("- {}", quote(&old.arguments)));
+                lines.push(format!("+ {}", quote(&new.arguments)));
+            }
+        }
+        (Err(old), Err(new)) if old == new => {}
+        (old, new) => {
+            for (sign, resolved) in [("-", old), ("+", new)] {
+                lines.push(match resolved {
+                    Ok(resolved) => {
+                        format!("{} {}", sign, quote(&resolved.arguments))
+                    }
+                    Err(error) => format!("{} error: {}", sign, error),
+                });
+            }
+        }
+    }
+
+    lines
+}
+
+/// Lines describing the differences of the `retroarch.cfg` overrides, one for each key which is
+/// added, removed or changed.
+#[must_use]
+pub fn compare_overrides(
+    old: &IndexMap<String, String>,
+    new: &IndexMap<String, String>,
+) -> Vec<String> {
+    let value = |entries: &IndexMap<String, String>, key: &str| {
+        entries
+            .get(key)
+            .map_or_else(|| "(unset)".to_string(), |v| format!("\"{}\"", v))
+    };
+
+    old.keys()
+        .chain(new.keys().filter(|key| !old.contains_key(*key)))
+        .filter(|key| old.get(*key) != new.get(*key))
+        .map(|key| {
+            format!("{}: {} -> {}", key, value(old, key), value(new, key))
+        })
+        .collect()
+}
+
+/// Join the arguments like they would be typed in a shell.
+fn quote(arguments: &[String]) -> String {
+    shlex::try_join(arguments.iter().map(String::as_str))
+        .unwrap_or_else(|_| format!("{:?}", arguments))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Resolved;
+    use indexmap::map::IndexMap;
+    use std::path::Path;
+
+    fn resolved(core: &str, arguments: &[&str]) -> Resolved {
+        Resolved::new(
+            Path::new(core),
+            "retroarch".to_string(),
+            arguments.iter().map(|arg| arg.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn resolved_placeholders() {
+        let resolved = resolved(
+            "/cores/snes9x_libretro.so",
+            &[
+                "--libretro",
+                "/cores/snes9x_libretro.so",
+                "--appendconfig",
+                "/run/enjoy/1/override-1.cfg",
+                "-f",
+            ],
+        );
+
+        assert_eq!(
+            vec![
+                "retroarch",
+                "--libretro",
+                "<core>",
+                "--appendconfig",
+                "<overrides>",
+                "-f"
+            ],
+            resolved.arguments
+        );
+    }
+
+    #[test]
+    fn compare() {
+        let snes9x = Ok(resolved("/cores/snes9x_libretro.so", &["mario.smc"]));
+        let bsnes = Ok(resolved("/cores/bsnes_libretro.so", &["mario.smc"]));
+        let error: Result<Resolved, String> = Err("No core".to_string());
+
+        assert!(super::compare(&snes9x, &snes9x).is_empty());
+        assert!(super::compare(&error, &error).is_empty());
+        assert_eq!(
+            vec![
+                "core: /cores/snes9x_libretro.so -> /cores/bsnes_libretro.so"
+                    .to_string()
+            ],
+            super::compare(&snes9x, &bsnes)
+        );
+        assert_eq!(
+            vec![
+                "- retroarch mario.smc".to_string(),
+                "+ error: No core".to_string()
+            ],
+            super::compare(&snes9x, &error)
+        );
+    }
+
+    #[test]
+    fn compare_overrides() {
+        let old: IndexMap<String, String> = IndexMap::from([
+            ("video_smooth".to_string(), "true".to_string()),
+            ("audio_volume".to_string(), "0".to_string()),
+        ]);
+        let new: IndexMap<String, String> = IndexMap::from([
+            ("audio_volume".to_string(), "-12".to_string()),
+            ("video_driver".to_string(), "vulkan".to_string()),
+        ]);
+
+        assert_eq!(
+            vec![
+                "video_smooth: \"true\" -> (unset)",
+                "audio_volume: \"0\" -> \"-12\"",
+                "video_driver: (unset) -> \"vulkan\"",
+            ],
+            super::compare_overrides(&old, &new)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use indexmap::map::IndexMap;
+
+/// Prefix of the sections in the user config with environment variables for a core, like
+/// `[env:mupen64plus_next]`.
+pub const SECTION_PREFIX: &str = "env:";
+
+/// Known workarounds for cores, set unless disabled with `env_defaults = 0`.  The threaded
+/// optimizations of the NVIDIA driver make these cores stutter or crash.
+pub const DEFAULTS: [(&str, &str, &str); 4] = [
+    ("dolphin", "__GL_THREADED_OPTIMIZATIONS", "0"),
+    ("flycast", "__GL_THREADED_OPTIMIZATIONS", "0"),
+    ("mupen64plus_next", "__GL_THREADED_OPTIMIZATIONS", "0"),
+    ("parallel_n64", "__GL_THREADED_OPTIMIZATIONS", "0"),
+];
+
+/// Environment variables for the core named `core`, like `snes9x`.  The `sections` of the user
+/// config are keyed by core name and replace the bundled `DEFAULTS`, which are only included if
+/// `defaults` is set.
+#[must_use]
+pub fn for_core(
+    core: &str,
+    sections: Option<&IndexMap<String, IndexMap<String, String>>>,
+    defaults: bool,
+) -> IndexMap<String, String> {
+    let mut env: IndexMap<String, String> = IndexMap::new();
+
+    if defaults {
+        env.extend(
+            DEFAULTS
+                .iter()
+                .filter(|(name, _, _)| *name == core)
+                .map(|(_, key, value)| (key.to_string(), value.to_string())),
+        );
+    }
+    if let Some(section) = sections.and_then(|sections| sections.get(core)) {
+        env.extend(section.clone());
+    }
+
+    env
+}
+
+#[cfg(test)]
+mod tests {
+
+    use indexmap::map::IndexMap;
+
+    #[test]
+    fn for_core() {
+        let sections: IndexMap<String, IndexMap<String, String>> =
+            IndexMap::from([(
+                "flycast".to_string(),
+                IndexMap::from([
+                    (
+                        "__GL_THREADED_OPTIMIZATIONS".to_string(),
+                        "1".to_string(),
+                    ),
+                    ("SDL_VIDEODRIVER".to_string(), "x11".to_string()),
+                ]),
+            )]);
+
+        assert_eq!(
+            IndexMap::from([
+                ("__GL_THREADED_OPTIMIZATIONS".to_string(), "1".to_string()),
+                ("SDL_VIDEODRIVER".to_string(), "x11".to_string()),
+            ]),
+            super::for_core("flycast", Some(&sections), true)
+        );
+        assert_eq!(
+            IndexMap::from([(
+                "__GL_THREADED_OPTIMIZATIONS".to_string(),
+                "0".to_string()
+            )]),
+            super::for_core("dolphin", Some(&sections), true)
+        );
+        assert!(super::for_core("dolphin", None, false).is_empty());
+        assert!(super::for_core("snes9x", Some(&sections), true).is_empty());
+    }
+}
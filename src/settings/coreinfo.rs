@@ -0,0 +1,160 @@
+use crate::settings::file;
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The interesting parts of a libretro core info file.  These files are distributed alongside
+/// the cores and describe them, like `snes9x_libretro.info` for `snes9x_libretro.so`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CoreInfo {
+    pub display_name: Option<String>,
+    pub systemname: Option<String>,
+    pub manufacturer: Option<String>,
+    pub database: Option<String>,
+    pub supported_extensions: Vec<String>,
+}
+
+impl CoreInfo {
+    /// Parse the content of an info file.  The format is a regular INI without sections, with
+    /// values enclosed by double quotes.  Unknown keys are ignored.
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let mut info: Self = Self::default();
+
+        for (key, value) in content
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(k, v)| (k.trim(), v.trim().trim_matches('"').to_string()))
+            .filter(|(_, v)| !v.is_empty())
+        {
+            match key {
+                "display_name" => info.display_name = Some(value),
+                "systemname" => info.systemname = Some(value),
+                "manufacturer" => info.manufacturer = Some(value),
+                "database" => info.database = Some(value),
+                "supported_extensions" => {
+                    info.supported_extensions =
+                        value.split('|').map(str::to_lowercase).collect();
+                }
+                _ => (),
+            }
+        }
+
+        info
+    }
+
+    /// Friendly name of the system the core emulates, like "Nintendo - Super Nintendo
+    /// Entertainment System".  The manufacturer is prepended, if not already part of the name.
+    #[must_use]
+    pub fn system(&self) -> Option<String> {
+        let name: &String = self.systemname.as_ref()?;
+
+        match &self.manufacturer {
+            Some(manufacturer) if !name.starts_with(manufacturer.as_str()) => {
+                Some(format!("{} - {}", manufacturer, name))
+            }
+            _ => Some(name.to_string()),
+        }
+    }
+}
+
+/// Name of a `libretro` core without directory and `_libretro.so` suffix, like `snes9x`.
+pub fn core_name(libretro: &Path) -> String {
+    libretro
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .trim_end_matches(".so")
+        .trim_end_matches("_libretro")
+        .to_string()
+}
+
+/// Build the path of the info file belonging to a `libretro` core.  Only the filename of the
+/// core is used, so it can be a fullpath or a name like `snes9x`.
+pub fn info_path(info_directory: &Path, libretro: &Path) -> PathBuf {
+    info_directory.join(format!("{}_libretro.info", core_name(libretro)))
+}
+
+/// Read and parse the info file of a `libretro` core from `info_directory`.  `None` if the file
+/// does not exist or cannot be read.
+pub fn load(info_directory: &Path, libretro: &Path) -> Option<CoreInfo> {
+    let path: PathBuf = info_path(&file::tilde(info_directory), libretro);
+
+    fs::read_to_string(path)
+        .ok()
+        .map(|content| CoreInfo::parse(&content))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+
+    use super::CoreInfo;
+
+    // Untested:
+    //  - load()
+
+    fn test_info_template() -> String {
+        String::from(
+            "
+            # Software Information
+            display_name = \"Nintendo - SNES / SFC (Snes9x - Current)\"
+            supported_extensions = \"smc|sfc|SWC|fig\"
+            manufacturer = \"Nintendo\"
+            systemname = \"Super Nintendo Entertainment System\"
+            database = \"Nintendo - Super Nintendo Entertainment System\"
+            license = \"\"
+            ",
+        )
+    }
+
+    #[test]
+    fn parse_info() {
+        let info = CoreInfo::parse(&test_info_template());
+
+        assert_eq!(
+            Some("Super Nintendo Entertainment System".to_string()),
+            info.systemname
+        );
+        assert_eq!(
+            vec!["smc", "sfc", "swc", "fig"],
+            info.supported_extensions
+        );
+    }
+
+    #[test]
+    fn system_with_manufacturer() {
+        let info = CoreInfo::parse(&test_info_template());
+
+        assert_eq!(
+            Some("Nintendo - Super Nintendo Entertainment System".to_string()),
+            info.system()
+        );
+    }
+
+    #[test]
+    fn system_missing() {
+        let info = CoreInfo::parse("display_name = \"Something\"");
+
+        assert_eq!(None, info.system());
+    }
+
+    #[test]
+    fn info_path_variants() {
+        let dir = PathBuf::from("/usr/share/libretro/info");
+        let expected =
+            PathBuf::from("/usr/share/libretro/info/snes9x_libretro.info");
+
+        assert_eq!(expected, super::info_path(&dir, &PathBuf::from("snes9x")));
+        assert_eq!(
+            expected,
+            super::info_path(
+                &dir,
+                &PathBuf::from("/home/user/cores/snes9x_libretro.so")
+            )
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::env;
+use std::path::PathBuf;
+
+use indexmap::map::IndexMap;
+
+/// Prefix of the sections in the user config, which define a core set, like `[coreset:nightly]`.
+pub const SECTION_PREFIX: &str = "coreset:";
+
+/// A named set of cores with its own `RetroArch`, for cores of different builds installed side by
+/// side, such as stable and nightly cores or 32 and 64-bit ones.
+///
+/// ```ini
+/// [coreset:nightly]
+/// libretro_directory = ~/Applications/RetroArch-Nightly.AppImage.home/.config/retroarch/cores
+/// retroarch = ~/Applications/RetroArch-Nightly.AppImage
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoreSet {
+    pub name: String,
+    /// Directories to search for cores in order.  Multiple directories are separated by colons
+    /// like in `$PATH`.
+    pub libretro_directory: Vec<PathBuf>,
+    pub retroarch: Option<PathBuf>,
+}
+
+impl CoreSet {
+    /// Create a core set from the keys of its section.  At least one of `libretro_directory` or
+    /// `retroarch` must be given, any other key is an error.
+    pub fn from_section(
+        name: &str,
+        section: &IndexMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut set = Self {
+            name: name.to_string(),
+            libretro_directory: vec![],
+            retroarch: None,
+        };
+
+        for (key, value) in section {
+            match key.as_str() {
+                "libretro_directory" => {
+                    set.libretro_directory = env::split_paths(value).collect();
+                }
+                "retroarch" => set.retroarch = Some(PathBuf::from(value)),
+                _ => {
+                    return Err(format!(
+                        "[{}{}] Unknown key \"{}\".",
+                        SECTION_PREFIX, name, key
+                    ))
+                }
+            }
+        }
+        if set.libretro_directory.is_empty() && set.retroarch.is_none() {
+            return Err(format!(
+                "[{}{}] Needs `libretro_directory` or `retroarch`.",
+                SECTION_PREFIX, name
+            ));
+        }
+
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use indexmap::map::IndexMap;
+    use std::path::PathBuf;
+
+    fn section(pairs: &[(&str, &str)]) -> IndexMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn from_section() {
+        let set = super::CoreSet::from_section(
+            "nightly",
+            &section(&[
+                ("libretro_directory", "/opt/nightly/cores:/usr/lib/libretro"),
+                ("retroarch", "/opt/nightly/retroarch"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!("nightly", set.name);
+        assert_eq!(
+            vec![
+                PathBuf::from("/opt/nightly/cores"),
+                PathBuf::from("/usr/lib/libretro")
+            ],
+            set.libretro_directory
+        );
+        assert_eq!(
+            Some(PathBuf::from("/opt/nightly/retroarch")),
+            set.retroarch
+        );
+    }
+
+    #[test]
+    fn from_section_invalid() {
+        assert!(super::CoreSet::from_section("empty", &section(&[])).is_err());
+        assert!(super::CoreSet::from_section(
+            "typo",
+            &section(&[("libretro_dir", "/cores")])
+        )
+        .is_err());
+    }
+}
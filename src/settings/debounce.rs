@@ -0,0 +1,98 @@
+use crate::settings::file;
+
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// File in the `file::runtime_dir()` with the time of the last launch in milliseconds.
+pub const STAMP_FILE: &str = "launch.stamp";
+
+/// Check if a launch at `now` follows the last recorded one in the `STAMP_FILE` of `dir` within
+/// `window`, like a double press of a gamepad button.  Otherwise `now` is recorded as the last
+/// launch.  The file is locked meanwhile, so of two invocations at the same moment the second one
+/// waits and then sees the first.  The window starts at the first launch and is not extended by
+/// the skipped ones.
+pub fn is_too_soon(
+    dir: &Path,
+    window: Duration,
+    now: SystemTime,
+) -> io::Result<bool> {
+    file::private_dir(dir)?;
+    let mut stamp: fs::File = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .open(dir.join(STAMP_FILE))?;
+
+    // SAFETY: The descriptor is open for the lifetime of `stamp`, and the lock is released when
+    // it is closed.
+    if unsafe { libc::flock(stamp.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut content: String = String::new();
+    stamp.read_to_string(&mut content)?;
+    let last: Option<SystemTime> = content
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|millis| UNIX_EPOCH + Duration::from_millis(millis));
+    // A last launch in the future, like after the clock was set back, is not counted.
+    if last.is_some_and(|last| {
+        now.duration_since(last)
+            .is_ok_and(|elapsed| elapsed < window)
+    }) {
+        return Ok(true);
+    }
+
+    let millis: u128 = now
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    stamp.set_len(0)?;
+    stamp.rewind()?;
+    stamp.write_all(millis.to_string().as_bytes())?;
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    #[test]
+    fn is_too_soon() {
+        let dir: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-debounce-{}", std::process::id()));
+        let window: Duration = Duration::from_millis(500);
+        let start: SystemTime = SystemTime::now();
+        let at = |millis: u64| start + Duration::from_millis(millis);
+
+        let launches: Vec<bool> = [0, 200, 600, 1000, 1200]
+            .iter()
+            .map(|millis| {
+                super::is_too_soon(&dir, window, at(*millis)).unwrap()
+            })
+            .collect();
+        // Back in time, such as after the clock was changed.
+        let earlier: bool = super::is_too_soon(&dir, window, at(0)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(vec![false, true, false, true, false], launches);
+        assert!(!earlier);
+    }
+}
@@ -0,0 +1,96 @@
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Environment variable with the `RetroArch` executable, if option `--retroarch` is not given.
+pub const ENV_VAR: &str = "RETROARCH";
+
+/// Names of the `RetroArch` executable in order of preference, if it is not set anywhere.  These
+/// are the native program, the command exported by its Flatpak and an AppImage.
+pub const PROGRAM_NAMES: [&str; 3] =
+    ["retroarch", "org.libretro.RetroArch", "retroarch.AppImage"];
+
+/// Where the `RetroArch` executable to run was taken from, highest priority first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Option,
+    Environment,
+    Config,
+    Search,
+    Default,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text: &str = match self {
+            Self::Option => "option --retroarch",
+            Self::Environment => "environment variable RETROARCH",
+            Self::Config => "user config",
+            Self::Search => "search",
+            Self::Default => "default, not found in search",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+/// Value of the environment variable `RETROARCH`, unless it is empty.
+#[must_use]
+pub fn from_env() -> Option<PathBuf> {
+    env::var_os(ENV_VAR)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Search each name of `PROGRAM_NAMES` in `directories` and then in the systems `$PATH`.  The
+/// first name found anywhere wins over the later names.
+#[must_use]
+pub fn search(directories: &[PathBuf]) -> Option<PathBuf> {
+    let path: Vec<PathBuf> = env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).collect())
+        .unwrap_or_default();
+
+    search_in(directories.iter().chain(path.iter()))
+}
+
+// Search each name in all directories.
+fn search_in<'a>(
+    directories: impl Iterator<Item = &'a PathBuf> + Clone,
+) -> Option<PathBuf> {
+    PROGRAM_NAMES.iter().find_map(|name| {
+        directories
+            .clone()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn search_in_order() {
+        let root: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-discovery-{}", std::process::id()));
+        let apps: PathBuf = root.join("apps");
+        let bin: PathBuf = root.join("bin");
+        fs::create_dir_all(&apps).unwrap();
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(apps.join("retroarch.AppImage"), "").unwrap();
+        fs::write(bin.join("org.libretro.RetroArch"), "").unwrap();
+
+        let dirs: Vec<PathBuf> = vec![apps.clone(), bin.clone()];
+        let flatpak = super::search_in(dirs.iter());
+        fs::write(bin.join("retroarch"), "").unwrap();
+        let native = super::search_in(dirs.iter());
+        let missing = super::search_in([root.join("none")].iter());
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(Some(bin.join("org.libretro.RetroArch")), flatpak);
+        assert_eq!(Some(bin.join("retroarch")), native);
+        assert_eq!(None, missing);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::settings::file;
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The outcome of a single diagnostic check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Pass => write!(f, "PASS"),
+            Self::Warn => write!(f, "WARN"),
+            Self::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// A single line in the diagnostic report.
+#[derive(Debug)]
+pub struct Check {
+    pub status: Status,
+    pub name: String,
+    pub message: String,
+}
+
+/// Collection of all checks in the order they were run.  Use `print()` to write the structured
+/// report to stdout.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { checks: vec![] }
+    }
+
+    pub fn add(&mut self, status: Status, name: &str, message: &str) {
+        self.checks.push(Check {
+            status,
+            name: name.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    pub fn pass(&mut self, name: &str, message: &str) {
+        self.add(Status::Pass, name, message);
+    }
+
+    pub fn warn(&mut self, name: &str, message: &str) {
+        self.add(Status::Warn, name, message);
+    }
+
+    pub fn fail(&mut self, name: &str, message: &str) {
+        self.add(Status::Fail, name, message);
+    }
+
+    /// Number of checks with the given `status`.
+    #[must_use]
+    pub fn count(&self, status: Status) -> usize {
+        self.checks.iter().filter(|c| c.status == status).count()
+    }
+
+    /// Print each check as `[STATUS] name: message` followed by a summary line.
+    pub fn print(&self) {
+        for check in &self.checks {
+            println!("[{}] {}: {}", check.status, check.name, check.message);
+        }
+        println!(
+            "{} passed, {} warnings, {} failed",
+            self.count(Status::Pass),
+            self.count(Status::Warn),
+            self.count(Status::Fail)
+        );
+    }
+}
+
+/// Check if the `retroarch` executable can be found and started with `--version`.  The first
+/// line of its output is reported on success.
+pub fn check_retroarch(report: &mut Report, retroarch: &Path) {
+    let name = "retroarch executable";
+
+    let fullpath: PathBuf = match file::which(retroarch) {
+        Some(path) => path,
+        None => {
+            report.fail(name, &format!("not found: {}", retroarch.display()));
+            return;
+        }
+    };
+
+    match Command::new(&fullpath).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            let text: String = format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let version: &str = text.lines().next().unwrap_or_default();
+            report.pass(
+                name,
+                &format!("{} ({})", fullpath.display(), version.trim()),
+            );
+        }
+        Ok(output) => report.fail(
+            name,
+            &format!(
+                "{} --version failed with {}",
+                fullpath.display(),
+                output.status
+            ),
+        ),
+        Err(e) => report.fail(
+            name,
+            &format!("could not run {}: {}", fullpath.display(), e),
+        ),
+    }
+}
+
+/// Check if the `libretro_directory` exists and contains at least one core file.
+pub fn check_libretro_directory(
+    report: &mut Report,
+    directory: Option<&Path>,
+) {
+    let name = "libretro directory";
+
+    let dir: PathBuf = match directory {
+        Some(dir) => file::tilde(dir),
+        None => {
+            report.fail(name, "not set in user config or retroarch.cfg");
+            return;
+        }
+    };
+
+    match fs::read_dir(&dir) {
+        Ok(entries) => {
+            let cores: usize = entries
+                .filter_map(std::result::Result::ok)
+                .filter(|e| {
+                    e.file_name().to_string_lossy().ends_with("_libretro.so")
+                })
+                .count();
+            if cores == 0 {
+                report.warn(
+                    name,
+                    &format!("{} contains no cores", dir.display()),
+                );
+            } else {
+                report.pass(
+                    name,
+                    &format!("{} ({} cores)", dir.display(), cores),
+                );
+            }
+        }
+        Err(e) => {
+            report.fail(name, &format!("{}: {}", dir.display(), e));
+        }
+    }
+}
+
+/// Check if a directory for save data from `retroarch.cfg` is writable.  `RetroArch` uses the
+/// value "default" to store the files next to the content, which cannot be verified here.
+pub fn check_writable_directory(
+    report: &mut Report,
+    name: &str,
+    directory: Option<&String>,
+) {
+    let value: &String = match directory {
+        Some(value) if !value.is_empty() && value != "default" => value,
+        _ => {
+            report.pass(name, "not set, files are stored next to content");
+            return;
+        }
+    };
+
+    let dir: PathBuf = file::tilde(Path::new(value));
+    if !dir.is_dir() {
+        report.fail(name, &format!("{} does not exist", dir.display()));
+    } else if file::is_writable_dir(&dir) {
+        report.pass(name, &format!("{} is writable", dir.display()));
+    } else {
+        report.fail(name, &format!("{} is not writable", dir.display()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Report;
+    use super::Status;
+
+    #[test]
+    fn report_count() {
+        let mut report = Report::new();
+        report.pass("a", "ok");
+        report.warn("b", "hmm");
+        report.fail("c", "no");
+        report.pass("d", "ok");
+
+        assert_eq!(2, report.count(Status::Pass));
+        assert_eq!(1, report.count(Status::Warn));
+        assert_eq!(1, report.count(Status::Fail));
+    }
+
+    #[test]
+    fn check_writable_directory_default() {
+        let mut report = Report::new();
+        super::check_writable_directory(
+            &mut report,
+            "savefile directory",
+            Some(&"default".to_string()),
+        );
+
+        assert_eq!(Status::Pass, report.checks[0].status);
+    }
+
+    #[test]
+    fn check_libretro_directory_missing() {
+        let mut report = Report::new();
+        super::check_libretro_directory(&mut report, None);
+
+        assert_eq!(Status::Fail, report.checks[0].status);
+    }
+}
@@ -0,0 +1,210 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::str::FromStr;
+
+/// How special characters in printed paths are written with option `--escape`, so other
+/// programs can read back every path exactly, even with newlines or invalid UTF-8 in it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Escape {
+    /// As they are, with invalid UTF-8 replaced by `�`.
+    #[default]
+    None,
+    /// Quoted for POSIX shells like `'Mario World.smc'`, only if needed.  Control characters and
+    /// invalid UTF-8 use the quoting of Bash like `$'Mario\nWorld.smc'`.
+    Shell,
+    /// Escaped like in strings of C without the quotes, like `Mario\nWorld.smc`.  Control
+    /// characters and invalid UTF-8 are octal bytes like `\377` and a backslash is `\\`.
+    C,
+}
+
+impl FromStr for Escape {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "shell" => Ok(Self::Shell),
+            "c" => Ok(Self::C),
+            _ => Err(format!(
+                "Unknown escape \"{}\", expected one of: shell, c, none",
+                text
+            )),
+        }
+    }
+}
+
+/// Part of a text, which is either a valid character or a single byte of invalid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Piece {
+    Char(char),
+    Byte(u8),
+}
+
+/// Split `bytes` into characters, keeping each byte of invalid UTF-8 on its own.
+fn pieces(mut bytes: &[u8]) -> Vec<Piece> {
+    let mut pieces: Vec<Piece> = Vec::with_capacity(bytes.len());
+
+    while !bytes.is_empty() {
+        let (valid, invalid): (&str, usize) = match std::str::from_utf8(bytes)
+        {
+            Ok(text) => (text, 0),
+            Err(e) => {
+                // Valid up to this point, as reported by the error.
+                let text = std::str::from_utf8(&bytes[..e.valid_up_to()])
+                    .unwrap_or_default();
+                (text, e.error_len().unwrap_or(bytes.len() - e.valid_up_to()))
+            }
+        };
+        pieces.extend(valid.chars().map(Piece::Char));
+        pieces.extend(
+            bytes[valid.len()..valid.len() + invalid]
+                .iter()
+                .map(|byte| Piece::Byte(*byte)),
+        );
+        bytes = &bytes[valid.len() + invalid..];
+    }
+
+    pieces
+}
+
+/// Check if `c` can stay unquoted in a shell, like in `roms/snes/mario.smc`.
+fn is_shell_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "/._-+,:@%=~".contains(c)
+}
+
+impl Escape {
+    /// Format `text` for output.
+    #[must_use]
+    pub fn apply(self, text: &OsStr) -> String {
+        match self {
+            Self::None => text.to_string_lossy().to_string(),
+            Self::Shell => shell(text.as_bytes()),
+            Self::C => c(text.as_bytes()),
+        }
+    }
+}
+
+/// Format `bytes` with the quoting of POSIX shells, see `Escape::Shell`.
+fn shell(bytes: &[u8]) -> String {
+    let pieces: Vec<Piece> = pieces(bytes);
+
+    if pieces.is_empty() {
+        "''".to_string()
+    } else if pieces
+        .iter()
+        .all(|piece| matches!(piece, Piece::Char(c) if is_shell_safe(*c)))
+    {
+        String::from_utf8_lossy(bytes).to_string()
+    } else if pieces
+        .iter()
+        .all(|piece| matches!(piece, Piece::Char(c) if !c.is_control()))
+    {
+        format!(
+            "'{}'",
+            String::from_utf8_lossy(bytes).replace('\'', "'\\''")
+        )
+    } else {
+        let mut escaped: String = "$'".to_string();
+        for piece in pieces {
+            match piece {
+                Piece::Char('\\') => escaped.push_str("\\\\"),
+                Piece::Char('\'') => escaped.push_str("\\'"),
+                Piece::Char('\n') => escaped.push_str("\\n"),
+                Piece::Char('\t') => escaped.push_str("\\t"),
+                Piece::Char('\r') => escaped.push_str("\\r"),
+                Piece::Char(c) if c.is_control() => {
+                    for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                        escaped.push_str(&format!("\\x{:02x}", byte));
+                    }
+                }
+                Piece::Char(c) => escaped.push(c),
+                Piece::Byte(byte) => {
+                    escaped.push_str(&format!("\\x{:02x}", byte));
+                }
+            }
+        }
+        escaped.push('\'');
+        escaped
+    }
+}
+
+/// Format `bytes` with the escapes of strings in C, see `Escape::C`.
+fn c(bytes: &[u8]) -> String {
+    let mut escaped: String = String::with_capacity(bytes.len());
+
+    for piece in pieces(bytes) {
+        match piece {
+            Piece::Char('\\') => escaped.push_str("\\\\"),
+            Piece::Char('\n') => escaped.push_str("\\n"),
+            Piece::Char('\t') => escaped.push_str("\\t"),
+            Piece::Char('\r') => escaped.push_str("\\r"),
+            Piece::Char(c) if c.is_control() => {
+                for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                    escaped.push_str(&format!("\\{:03o}", byte));
+                }
+            }
+            Piece::Char(c) => escaped.push(c),
+            Piece::Byte(byte) => escaped.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    use super::Escape;
+
+    fn apply(escape: Escape, bytes: &[u8]) -> String {
+        escape.apply(OsStr::from_bytes(bytes))
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Ok(Escape::Shell), "Shell".parse());
+        assert_eq!(Ok(Escape::C), "c".parse());
+        assert!("json".parse::<Escape>().is_err());
+    }
+
+    #[test]
+    fn shell() {
+        assert_eq!(
+            "~/roms/mario.smc",
+            apply(Escape::Shell, b"~/roms/mario.smc")
+        );
+        assert_eq!(
+            "'/roms/Mario'\\''s World (USA).smc'",
+            apply(Escape::Shell, b"/roms/Mario's World (USA).smc")
+        );
+        assert_eq!(
+            "'/roms/ゼルダ.sfc'",
+            apply(Escape::Shell, "/roms/ゼルダ.sfc".as_bytes())
+        );
+        assert_eq!(
+            "$'/roms/Mario\\nWorld\\'s\\xff.smc'",
+            apply(Escape::Shell, b"/roms/Mario\nWorld's\xff.smc")
+        );
+        assert_eq!("''", apply(Escape::Shell, b""));
+    }
+
+    #[test]
+    fn c() {
+        assert_eq!(
+            "/roms/Mario\\nWorld \\\\ \\377\\033.smc",
+            apply(Escape::C, b"/roms/Mario\nWorld \\ \xff\x1b.smc")
+        );
+        assert_eq!("ゼルダ.sfc", apply(Escape::C, "ゼルダ.sfc".as_bytes()));
+    }
+
+    #[test]
+    fn none() {
+        assert_eq!(
+            "Mario\n\u{fffd}.smc",
+            apply(Escape::None, b"Mario\n\xff.smc")
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::settings::coreinfo;
+use crate::settings::inoutput::json_string;
+
+use std::path::Path;
+use std::process::Command;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use indexmap::map::IndexMap;
+
+/// Something that happened to a game, which is published to all configured sinks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Launch {
+        title: String,
+        core: String,
+    },
+    Exit {
+        title: String,
+        core: String,
+        duration: Duration,
+        exit_code: Option<i32>,
+    },
+}
+
+impl Event {
+    /// Event for a game, which was just started.  The title is the filename of the game without
+    /// its ROM extension.
+    #[must_use]
+    pub fn launch(title: &str, libretro: &Path) -> Self {
+        Self::Launch {
+            title: title.to_string(),
+            core: coreinfo::core_name(libretro),
+        }
+    }
+
+    /// Event for a game, which just ended.
+    #[must_use]
+    pub fn exit(
+        title: &str,
+        libretro: &Path,
+        duration: Duration,
+        exit_code: Option<i32>,
+    ) -> Self {
+        Self::Exit {
+            title: title.to_string(),
+            core: coreinfo::core_name(libretro),
+            duration,
+            exit_code,
+        }
+    }
+
+    /// Format the event as a JSON object, with the duration in seconds.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        match self {
+            Self::Launch { title, core } => format!(
+                "{{\"event\":\"launch\",\"title\":{},\"core\":{}}}",
+                json_string(title),
+                json_string(core)
+            ),
+            Self::Exit {
+                title,
+                core,
+                duration,
+                exit_code,
+            } => format!(
+                "{{\"event\":\"exit\",\"title\":{},\"core\":{},\
+                 \"duration\":{:.3},\"exit_code\":{}}}",
+                json_string(title),
+                json_string(core),
+                duration.as_secs_f64(),
+                exit_code
+                    .map_or_else(|| "null".to_string(), |c| c.to_string())
+            ),
+        }
+    }
+}
+
+/// Longest time a sink may take to deliver an event, so an unreachable broker or web service
+/// cannot hold up the launch or exit of a game.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A destination for events, like a message broker or a web service.
+pub trait EventSink {
+    /// Deliver a single event.  The error is a message for the user.
+    fn publish(&self, event: &Event) -> Result<(), String>;
+}
+
+/// Publish events to an MQTT topic with the `mosquitto_pub` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttSink {
+    pub host: String,
+    pub port: Option<String>,
+    pub topic: String,
+}
+
+impl EventSink for MqttSink {
+    fn publish(&self, event: &Event) -> Result<(), String> {
+        let mut command: Command = Command::new("mosquitto_pub");
+        command.arg("-h").arg(&self.host);
+        if let Some(port) = &self.port {
+            command.arg("-p").arg(port);
+        }
+        command
+            .arg("-t")
+            .arg(&self.topic)
+            .arg("-m")
+            .arg(event.to_json());
+
+        run(&mut command, "mosquitto_pub", TIMEOUT)
+    }
+}
+
+/// Post events as JSON to a webhook URL with the `curl` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookSink {
+    pub url: String,
+}
+
+impl EventSink for WebhookSink {
+    fn publish(&self, event: &Event) -> Result<(), String> {
+        let mut command: Command = Command::new("curl");
+        command
+            .args(["--silent", "--fail", "--max-time"])
+            .arg(TIMEOUT.as_secs().to_string())
+            .args(["--header", "Content-Type: application/json"])
+            .arg("--data")
+            .arg(event.to_json())
+            .arg(&self.url);
+
+        run(&mut command, "curl", TIMEOUT)
+    }
+}
+
+// Run the command and wait for it to finish.  It is killed, if it takes longer than `timeout`.
+fn run(
+    command: &mut Command,
+    name: &str,
+    timeout: Duration,
+) -> Result<(), String> {
+    let mut child = command
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("could not run `{}`: {}", name, e))?;
+    let deadline: Instant = Instant::now() + timeout;
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => {
+                return Err(format!("`{}` failed with {}", name, status))
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "`{}` timed out after {} seconds",
+                    name,
+                    timeout.as_secs()
+                ));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(e) => {
+                return Err(format!("could not wait for `{}`: {}", name, e))
+            }
+        }
+    }
+}
+
+/// Create all sinks configured in the keys of section `[events]`.  An MQTT sink requires at
+/// least `mqtt_host` and `mqtt_topic`.
+///
+/// ```ini
+/// [events]
+/// mqtt_host = localhost
+/// mqtt_port = 1883
+/// mqtt_topic = enjoy/events
+/// webhook = http://localhost:8123/api/webhook/enjoy
+/// ```
+#[must_use]
+pub fn sinks(config: &IndexMap<String, String>) -> Vec<Box<dyn EventSink>> {
+    let mut sinks: Vec<Box<dyn EventSink>> = vec![];
+
+    if let (Some(host), Some(topic)) =
+        (config.get("mqtt_host"), config.get("mqtt_topic"))
+    {
+        sinks.push(Box::new(MqttSink {
+            host: host.to_string(),
+            port: config.get("mqtt_port").cloned(),
+            topic: topic.to_string(),
+        }));
+    }
+    if let Some(url) = config.get("webhook") {
+        sinks.push(Box::new(WebhookSink {
+            url: url.to_string(),
+        }));
+    }
+
+    sinks
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use indexmap::map::IndexMap;
+
+    use super::Event;
+
+    // Untested:
+    //  - MqttSink::publish()
+    //  - WebhookSink::publish()
+
+    #[test]
+    fn run_kills_after_timeout() {
+        let started = std::time::Instant::now();
+        let error = super::run(
+            std::process::Command::new("sleep").arg("10"),
+            "sleep",
+            Duration::from_millis(100),
+        );
+
+        assert!(error.unwrap_err().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(super::run(
+            &mut std::process::Command::new("true"),
+            "true",
+            Duration::from_secs(5)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn launch_json() {
+        let event = Event::launch(
+            "Super Mario World",
+            &PathBuf::from("/cores/snes9x_libretro.so"),
+        );
+
+        assert_eq!(
+            "{\"event\":\"launch\",\"title\":\"Super Mario World\",\"core\":\"snes9x\"}",
+            event.to_json()
+        );
+    }
+
+    #[test]
+    fn exit_json() {
+        let event = Event::exit(
+            "mario",
+            &PathBuf::from("snes9x"),
+            Duration::from_millis(1500),
+            None,
+        );
+
+        assert_eq!(
+            "{\"event\":\"exit\",\"title\":\"mario\",\"core\":\"snes9x\",\
+             \"duration\":1.500,\"exit_code\":null}",
+            event.to_json()
+        );
+    }
+
+    #[test]
+    fn sinks_from_config() {
+        let mut config: IndexMap<String, String> = IndexMap::new();
+        config.insert("mqtt_host".to_string(), "localhost".to_string());
+
+        assert!(super::sinks(&config).is_empty());
+
+        config.insert("mqtt_topic".to_string(), "enjoy".to_string());
+        config.insert("webhook".to_string(), "http://localhost".to_string());
+
+        assert_eq!(2, super::sinks(&config).len());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::settings::inoutput::json_string;
+
+use std::str::FromStr;
+
+use indexmap::map::IndexMap;
+
+/// Columns of the exported list in order.  The `core` is the resolved libretro core and `hash`
+/// the FNV-1a hash of the game file in hexadecimal.
+pub const COLUMNS: &[&str] =
+    &["path", "title", "system", "core", "size", "hash"];
+
+/// A game of the exported list with a value for each of the `COLUMNS`, empty if unknown.
+pub type Row = IndexMap<&'static str, String>;
+
+/// Output format of `--export-list`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "Unknown export format \"{}\", expected one of: csv, json",
+                text
+            )),
+        }
+    }
+}
+
+impl Format {
+    /// The whole exported list of `rows`.
+    #[must_use]
+    pub fn render(self, rows: &[Row]) -> String {
+        match self {
+            Self::Csv => to_csv(rows),
+            Self::Json => to_json(rows),
+        }
+    }
+}
+
+/// Rows as CSV with a header line, as described in RFC 4180.
+#[must_use]
+pub fn to_csv(rows: &[Row]) -> String {
+    let line = |values: Vec<&str>| {
+        let fields: Vec<String> = values.into_iter().map(csv_field).collect();
+        format!("{}\r\n", fields.join(","))
+    };
+
+    let mut csv: String = line(COLUMNS.to_vec());
+    for row in rows {
+        csv.push_str(&line(
+            COLUMNS
+                .iter()
+                .map(|column| row.get(column).map_or("", String::as_str))
+                .collect(),
+        ));
+    }
+
+    csv
+}
+
+// Quote a field, if it contains a comma, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Rows as a JSON array of objects.  The `size` is a number, all other values are strings.
+#[must_use]
+pub fn to_json(rows: &[Row]) -> String {
+    let objects: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let members: Vec<String> = COLUMNS
+                .iter()
+                .map(|column| {
+                    let value: &str =
+                        row.get(column).map_or("", String::as_str);
+                    let value: String = match *column {
+                        "size" if !value.is_empty() => value.to_string(),
+                        "size" => "null".to_string(),
+                        _ => json_string(value),
+                    };
+                    format!("{}: {}", json_string(column), value)
+                })
+                .collect();
+            format!("  {{{}}}", members.join(", "))
+        })
+        .collect();
+
+    if objects.is_empty() {
+        "[]\n".to_string()
+    } else {
+        format!("[\n{}\n]\n", objects.join(",\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Format;
+    use super::Row;
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row::from([
+                ("path", "/roms/Mario, \"World\".smc".to_string()),
+                ("title", "Mario".to_string()),
+                ("core", "/cores/snes9x_libretro.so".to_string()),
+                ("size", "524288".to_string()),
+            ]),
+            Row::from([("path", "/roms/unknown.bin".to_string())]),
+        ]
+    }
+
+    #[test]
+    fn format_from_str() {
+        assert_eq!(Ok(Format::Json), "JSON".parse());
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn to_csv() {
+        assert_eq!(
+            "path,title,system,core,size,hash\r\n\
+             \"/roms/Mario, \"\"World\"\".smc\",Mario,,\
+             /cores/snes9x_libretro.so,524288,\r\n\
+             /roms/unknown.bin,,,,,\r\n",
+            super::to_csv(&rows())
+        );
+    }
+
+    #[test]
+    fn to_json() {
+        let json = super::to_json(&rows());
+
+        assert!(json.starts_with(
+            "[\n  {\"path\": \"/roms/Mario, \\\"World\\\".smc\""
+        ));
+        assert!(json.contains("\"size\": 524288, \"hash\": \"\"}"));
+        assert!(json.contains("\"size\": null"));
+        assert_eq!("[]\n", super::to_json(&[]));
+    }
+}
@@ -1,16 +1,46 @@
+use crate::settings::program::Program;
+use crate::settings::provider::FsProvider;
+use crate::settings::provider::RealFs;
+
+use std::env;
 use std::error::Error;
+#[cfg(feature = "archive")]
+use std::ffi::CString;
+use std::fs;
+use std::io;
+#[cfg(feature = "archive")]
+use std::io::BufRead;
+#[cfg(feature = "archive")]
+use std::io::BufReader;
+#[cfg(feature = "archive")]
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use std::path::PathBuf;
 
-/// Opens a file with the associated default application.  It must be af file, not a folder.
-pub fn open_with_default(file: &Path) -> Result<(), Box<dyn Error>> {
+/// Opens a file with the `opener` program, which gets the full path as the last argument.  It
+/// must be a file, not a folder.
+pub fn open_with(opener: &Program, file: &Path) -> Result<(), Box<dyn Error>> {
     let fullpath: PathBuf = match to_fullpath(file) {
         Some(fullpath) => fullpath,
         None => return Err("Problem finding the config file.".into()),
     };
 
     if fullpath.is_file() {
-        open::that(fullpath)?;
+        let status = opener
+            .command()
+            .arg(&fullpath)
+            .status()
+            .map_err(|e| format!("Could not run opener: {}", e))?;
+        if !status.success() {
+            return Err(format!(
+                "Could not open {} with opener: {}",
+                fullpath.display(),
+                status
+            )
+            .into());
+        }
     } else {
         return Err(format!(
             "Path to config is not accessible or a file: {}",
@@ -25,11 +55,13 @@ pub fn open_with_default(file: &Path) -> Result<(), Box<dyn Error>> {
 /// Expands tilde and environmental variables in a `Path` and canonicalize to fullpath into a
 /// `PathBuf`.  `None` if not possible.
 pub fn to_fullpath(file: &Path) -> Option<PathBuf> {
+    to_fullpath_with(&RealFs, file)
+}
+
+/// Same as `to_fullpath()`, but the path is canonicalized by the given filesystem `fs`.
+pub fn to_fullpath_with(fs: &dyn FsProvider, file: &Path) -> Option<PathBuf> {
     match shellexpand::full(&file.display().to_string()) {
-        Ok(path) => match PathBuf::from(path.to_string()).canonicalize() {
-            Ok(fullpath) => Some(fullpath),
-            Err(_) => None,
-        },
+        Ok(path) => fs.canonicalize(Path::new(path.as_ref())),
         Err(_) => None,
     }
 }
@@ -64,6 +96,313 @@ pub fn endswith(endswith: &str, mut file: PathBuf) -> PathBuf {
     file
 }
 
+/// Search the systems `$PATH` for an executable, if `program` is a filename without directory
+/// part.  Otherwise the path itself is expanded and checked.  `None` if it does not exist.
+pub fn which(program: &Path) -> Option<PathBuf> {
+    if program.components().count() > 1 {
+        return to_fullpath(program).filter(|path| path.is_file());
+    }
+
+    env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Check if a directory is writable by creating and removing a small probe file in it.
+pub fn is_writable_dir(dir: &Path) -> bool {
+    let probe: PathBuf =
+        dir.join(format!(".enjoy-probe-{}", std::process::id()));
+
+    match fs::File::create(&probe) {
+        Ok(_) => fs::remove_file(&probe).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Directory for cached data of this program.  It is `$XDG_CACHE_HOME/enjoy` or
+/// `~/.cache/enjoy` as a fallback.  The directory is not created.
+pub fn cache_dir() -> PathBuf {
+    match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("enjoy"),
+        _ => tilde(Path::new("~/.cache/enjoy")),
+    }
+}
+
+/// Directory for persistent data of this program.  It is `$XDG_DATA_HOME/enjoy` or
+/// `~/.local/share/enjoy` as a fallback.  The directory is not created.
+pub fn data_dir() -> PathBuf {
+    match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("enjoy"),
+        _ => tilde(Path::new("~/.local/share/enjoy")),
+    }
+}
+
+/// Directory for state of this program, which should survive a restart but is no valuable data,
+/// like the progress of an interrupted queue.  It is `$XDG_STATE_HOME/enjoy` or
+/// `~/.local/state/enjoy` as a fallback.  The directory is not created.
+pub fn state_dir() -> PathBuf {
+    match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("enjoy"),
+        _ => tilde(Path::new("~/.local/state/enjoy")),
+    }
+}
+
+/// Directory for runtime files of this program, like instance records and temporary override
+/// files.  It is `$XDG_RUNTIME_DIR/enjoy`, or `enjoy-<uid>` in the systems temporary folder if the
+/// variable is not set.  The directory is not created, see `private_dir()`.
+pub fn runtime_dir() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("enjoy"),
+        _ => env::temp_dir()
+            .join(format!("enjoy-{}", current_uid().unwrap_or_default())),
+    }
+}
+
+/// User id this process runs as, read from the owner of `/proc/self`.
+pub fn current_uid() -> io::Result<u32> {
+    fs::metadata("/proc/self").map(|metadata| metadata.uid())
+}
+
+/// Create the directory `dir` with mode `0700`, if it does not exist yet.  An existing directory
+/// is only accepted, if it is owned by the current user and not accessible by anyone else.  So in
+/// a shared location like `/tmp` no other user can plant or replace files in it.
+pub fn private_dir(dir: &Path) -> io::Result<()> {
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => (),
+    }
+
+    let metadata: fs::Metadata = fs::symlink_metadata(dir)?;
+    if metadata.is_dir()
+        && metadata.uid() == current_uid()?
+        && metadata.mode() & 0o077 == 0
+    {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} is not a private directory of the current user",
+                dir.display()
+            ),
+        ))
+    }
+}
+
+/// Check if `dir` exists and passes the checks of `private_dir()`, without creating it.
+#[must_use]
+pub fn is_private_dir(dir: &Path) -> bool {
+    dir.is_dir() && private_dir(dir).is_ok()
+}
+
+/// Set the modification time of an existing `path` to now, like the `touch` command.
+#[cfg(feature = "archive")]
+pub fn touch(path: &Path) -> io::Result<()> {
+    let cpath: CString = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    // SAFETY: `cpath` is a valid nul terminated string and a null pointer for the times means
+    // the current time.
+    if unsafe { libc::utimes(cpath.as_ptr(), std::ptr::null()) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Stable 64 bit FNV-1a hash of some bytes.  Unlike the hasher of the standard library, the
+/// result is the same across runs and versions, so it can be used in filenames or stored on disk.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    fnv1a_update(0xcbf2_9ce4_8422_2325, bytes)
+}
+
+/// Continue the FNV-1a `hash` with more bytes.
+fn fnv1a_update(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Same as `fnv1a()` of the content of `path`, but read in chunks, so a large file is never
+/// loaded into memory as a whole.
+#[cfg(feature = "archive")]
+pub fn fnv1a_file(path: &Path) -> io::Result<u64> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut hash: u64 = fnv1a(b"");
+
+    loop {
+        let chunk: &[u8] = reader.fill_buf()?;
+        if chunk.is_empty() {
+            return Ok(hash);
+        }
+        hash = fnv1a_update(hash, chunk);
+        let length: usize = chunk.len();
+        reader.consume(length);
+    }
+}
+
+/// SHA-256 of some bytes in lowercase hex, as `sha256sum` prints it.
+#[must_use]
+pub fn sha256(bytes: &[u8]) -> String {
+    const K: [u32; 64] = [
+        0x428a_2f98,
+        0x7137_4491,
+        0xb5c0_fbcf,
+        0xe9b5_dba5,
+        0x3956_c25b,
+        0x59f1_11f1,
+        0x923f_82a4,
+        0xab1c_5ed5,
+        0xd807_aa98,
+        0x1283_5b01,
+        0x2431_85be,
+        0x550c_7dc3,
+        0x72be_5d74,
+        0x80de_b1fe,
+        0x9bdc_06a7,
+        0xc19b_f174,
+        0xe49b_69c1,
+        0xefbe_4786,
+        0x0fc1_9dc6,
+        0x240c_a1cc,
+        0x2de9_2c6f,
+        0x4a74_84aa,
+        0x5cb0_a9dc,
+        0x76f9_88da,
+        0x983e_5152,
+        0xa831_c66d,
+        0xb003_27c8,
+        0xbf59_7fc7,
+        0xc6e0_0bf3,
+        0xd5a7_9147,
+        0x06ca_6351,
+        0x1429_2967,
+        0x27b7_0a85,
+        0x2e1b_2138,
+        0x4d2c_6dfc,
+        0x5338_0d13,
+        0x650a_7354,
+        0x766a_0abb,
+        0x81c2_c92e,
+        0x9272_2c85,
+        0xa2bf_e8a1,
+        0xa81a_664b,
+        0xc24b_8b70,
+        0xc76c_51a3,
+        0xd192_e819,
+        0xd699_0624,
+        0xf40e_3585,
+        0x106a_a070,
+        0x19a4_c116,
+        0x1e37_6c08,
+        0x2748_774c,
+        0x34b0_bcb5,
+        0x391c_0cb3,
+        0x4ed8_aa4a,
+        0x5b9c_ca4f,
+        0x682e_6ff3,
+        0x748f_82ee,
+        0x78a5_636f,
+        0x84c8_7814,
+        0x8cc7_0208,
+        0x90be_fffa,
+        0xa450_6ceb,
+        0xbef9_a3f7,
+        0xc671_78f2,
+    ];
+    let mut hash: [u32; 8] = [
+        0x6a09_e667,
+        0xbb67_ae85,
+        0x3c6e_f372,
+        0xa54f_f53a,
+        0x510e_527f,
+        0x9b05_688c,
+        0x1f83_d9ab,
+        0x5be0_cd19,
+    ];
+
+    // Padded with a single bit, zeros and the length in bits to a multiple of 64 bytes.
+    let mut message: Vec<u8> = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(bytes.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w: [u32; 64] = [0; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7)
+                ^ w[i - 15].rotate_right(18)
+                ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17)
+                ^ w[i - 2].rotate_right(19)
+                ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = hash;
+        for i in 0..64 {
+            let s1 =
+                e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 =
+                a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (value, add) in hash.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    hash.iter().map(|value| format!("{:08x}", value)).collect()
+}
+
+/// Replace the longest directory of `mappings` which `path` is in by its mapped directory.  Other
+/// paths are kept as they are.
+#[must_use]
+pub fn map_prefix(path: &Path, mappings: &[(PathBuf, PathBuf)]) -> PathBuf {
+    mappings
+        .iter()
+        .filter_map(|(from, to)| {
+            path.strip_prefix(from).ok().map(|rest| {
+                let mapped: PathBuf = if rest.as_os_str().is_empty() {
+                    to.clone()
+                } else {
+                    to.join(rest)
+                };
+                (from.components().count(), mapped)
+            })
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map_or_else(|| path.to_path_buf(), |(_, path)| path)
+}
+
 /// Simply remove last character, if it is a slash.
 pub fn trim_last_slash(mut path: String) -> String {
     if path.ends_with('/') {
@@ -77,10 +416,36 @@ pub fn trim_last_slash(mut path: String) -> String {
 mod tests {
 
     use std::env;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
     use std::path::PathBuf;
 
+    use crate::settings::provider::fake::MemFs;
+
     // Untested:
-    //  - open_with_default()
+    //  - open_with()
+    //  - is_writable_dir()
+    //  - touch()
+
+    #[test]
+    fn private_dir_rejects_shared() {
+        let dir: PathBuf = env::temp_dir()
+            .join(format!("enjoy-test-private-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(super::private_dir(&dir).is_ok());
+        assert_eq!(
+            0o700,
+            fs::metadata(&dir).unwrap().permissions().mode() & 0o777
+        );
+        assert!(super::is_private_dir(&dir));
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(super::private_dir(&dir).is_err());
+        assert!(!super::is_private_dir(&dir));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 
     #[test]
     fn to_fullpath_empty() {
@@ -115,6 +480,26 @@ mod tests {
         assert_eq!(output, None);
     }
 
+    #[test]
+    fn to_fullpath_with_fake() {
+        let fs = MemFs::with_files(&["/roms/snes/mario.smc"]);
+
+        assert_eq!(
+            Some(PathBuf::from("/roms/snes/mario.smc")),
+            super::to_fullpath_with(
+                &fs,
+                &PathBuf::from("/roms/snes/mario.smc")
+            )
+        );
+        assert_eq!(
+            None,
+            super::to_fullpath_with(
+                &fs,
+                &PathBuf::from("/roms/snes/zelda.sfc")
+            )
+        );
+    }
+
     #[test]
     fn tilde_tilde_only() {
         let path: PathBuf = PathBuf::from("~");
@@ -164,4 +549,57 @@ mod tests {
 
         assert_eq!(output, PathBuf::from("snes9x_libretro_libretro.so"));
     }
+
+    #[test]
+    fn fnv1a_known_values() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, super::fnv1a(b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, super::fnv1a(b"a"));
+    }
+
+    #[test]
+    #[cfg(feature = "archive")]
+    fn fnv1a_file_same_as_bytes() {
+        let file: PathBuf = env::temp_dir()
+            .join(format!("enjoy-test-fnv1a-{}", std::process::id()));
+        let content: Vec<u8> = (0..20_000).map(|i| (i % 251) as u8).collect();
+        fs::write(&file, &content).unwrap();
+        let hash = super::fnv1a_file(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(super::fnv1a(&content), hash);
+    }
+
+    #[test]
+    fn sha256_known_values() {
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            super::sha256(b"")
+        );
+        assert_eq!(
+            "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b",
+            super::sha256(b"secret")
+        );
+        assert_eq!(
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            super::sha256(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )
+        );
+    }
+
+    #[test]
+    fn which_absolute() {
+        let path: PathBuf = PathBuf::from("/bin/sh");
+        let output = super::which(&path);
+
+        assert!(output.is_some());
+    }
+
+    #[test]
+    fn which_does_not_exist() {
+        let path: PathBuf = PathBuf::from("filedoesnotexist!(@)");
+        let output = super::which(&path);
+
+        assert_eq!(output, None);
+    }
 }
@@ -0,0 +1,62 @@
+/// Points for each character of the pattern found in the text.
+const MATCH: i64 = 1;
+/// Extra points for a character right after the previous match.
+const CONSECUTIVE: i64 = 5;
+/// Extra points for a character at the start of a word.
+const WORD_START: i64 = 8;
+/// Points taken for each character skipped in the text before a match.
+const GAP: i64 = 1;
+
+/// Score how well `pattern` matches `text` as a subsequence, ignoring case and whitespace in the
+/// pattern.  Characters in a row and at the start of words score higher and skipped characters
+/// lower, so `smw` and `mario world` both find `Super Mario World (USA) [!]`.  `None` if any
+/// character of the pattern is not found in order.
+#[must_use]
+pub fn score(pattern: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut position: usize = 0;
+    let mut previous: Option<usize> = None;
+    let mut score: i64 = 0;
+
+    for wanted in pattern
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+    {
+        let found: usize =
+            position + text[position..].iter().position(|c| *c == wanted)?;
+        score += MATCH - GAP * i64::try_from(found - position).unwrap_or(0);
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += CONSECUTIVE;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += WORD_START;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn score_subsequence() {
+        assert!(super::score("smw", "Super Mario World (USA)").is_some());
+        assert!(super::score("mario world", "Super Mario World").is_some());
+        assert_eq!(None, super::score("zelda", "Super Mario World"));
+        assert_eq!(None, super::score("wm", "Super Mario World"));
+        assert_eq!(Some(0), super::score("", "Super Mario World"));
+    }
+
+    #[test]
+    fn score_ranking() {
+        let score = |text: &str| super::score("mario", text).unwrap();
+
+        assert!(score("Mario Kart") > score("Super Mario Kart"));
+        assert!(score("Super Mario Kart") > score("Super Marine Kart Io"));
+        assert!(score("Super Mario Kart") > score("Mega Man Rockman Io"));
+    }
+}
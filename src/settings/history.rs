@@ -0,0 +1,333 @@
+use crate::settings::clock;
+use crate::settings::file;
+
+use indexmap::map::IndexMap;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// File with the history of launched games in the state directory of this program.
+#[must_use]
+pub fn history_file() -> PathBuf {
+    file::state_dir().join("history")
+}
+
+/// A single launch of a game.  Each entry is one line in the history file with the fields
+/// separated by tabs, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Seconds since Unix epoch.
+    pub time: u64,
+    /// Hash of the core file at launch time.
+    pub core_hash: u64,
+    pub core: PathBuf,
+    pub game: PathBuf,
+    pub title: String,
+    /// Tags to find the launch again, like `100%`.  They cannot contain commas.
+    pub tags: Vec<String>,
+    /// Free text about the session.
+    pub note: String,
+}
+
+impl Entry {
+    /// Read an entry from a line of the history file.  `None` for malformed lines.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+
+        Some(Self {
+            time: fields.next()?.parse().ok()?,
+            core_hash: u64::from_str_radix(fields.next()?, 16).ok()?,
+            core: PathBuf::from(fields.next()?),
+            game: PathBuf::from(fields.next()?),
+            title: fields.next().unwrap_or_default().to_string(),
+            tags: fields
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+            note: fields.next().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// Check if the entry has all of the `tags`.  Always true without any.
+    #[must_use]
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
+
+    /// Line of the history file, without the newline.  Tabs and newlines in the fields would
+    /// break the format and are replaced by spaces.
+    #[must_use]
+    pub fn to_line(&self) -> String {
+        let field = |text: &str| text.replace(['\t', '\n', '\r'], " ");
+
+        let tags: Vec<String> = self
+            .tags
+            .iter()
+            .map(|tag| field(tag).replace(',', " "))
+            .collect();
+
+        format!(
+            "{}\t{:016x}\t{}\t{}\t{}\t{}\t{}",
+            self.time,
+            self.core_hash,
+            field(&self.core.display().to_string()),
+            field(&self.game.display().to_string()),
+            field(&self.title),
+            tags.join(","),
+            field(&self.note)
+        )
+    }
+}
+
+/// Read all entries of a history file, oldest first.  A missing file has no entries.
+pub fn load(file: &Path) -> io::Result<Vec<Entry>> {
+    match fs::read_to_string(file) {
+        Ok(content) => Ok(content.lines().filter_map(Entry::parse).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e),
+    }
+}
+
+/// Add an entry to the end of the history file.
+pub fn append(file: &Path, entry: &Entry) -> io::Result<()> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    writeln!(
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file)?,
+        "{}",
+        entry.to_line()
+    )
+}
+
+/// Hash of the content of a core file, as recorded in the history.
+pub fn hash_file(file: &Path) -> io::Result<u64> {
+    Ok(file::fnv1a(&fs::read(file)?))
+}
+
+/// Hash of `core` at its last launch, which was verified by the user.
+#[must_use]
+pub fn last_hash(entries: &[Entry], core: &Path) -> Option<u64> {
+    entries
+        .iter()
+        .rev()
+        .find(|entry| entry.core == core)
+        .map(|entry| entry.core_hash)
+}
+
+/// Hash of `core` at the last launch of `game` with it up to `time`, like the session a savestate
+/// written at `time` was created in.
+#[must_use]
+pub fn hash_at(
+    entries: &[Entry],
+    core: &Path,
+    game: &Path,
+    time: u64,
+) -> Option<u64> {
+    entries
+        .iter()
+        .rev()
+        .find(|entry| {
+            entry.core == core && entry.game == game && entry.time <= time
+        })
+        .map(|entry| entry.core_hash)
+}
+
+/// Each core of the history once with its hash at the last launch, in order of first use.
+#[must_use]
+pub fn last_hashes(entries: &[Entry]) -> Vec<(&Path, u64)> {
+    let mut seen: HashSet<&Path> = HashSet::new();
+
+    entries
+        .iter()
+        .filter(|entry| seen.insert(&entry.core))
+        .filter_map(|entry| {
+            last_hash(entries, &entry.core)
+                .map(|hash| (entry.core.as_path(), hash))
+        })
+        .collect()
+}
+
+/// Number of launches and time of the last launch for each title, most launched first and by
+/// title for the same number.
+#[must_use]
+pub fn stats(entries: &[Entry]) -> Vec<(&str, usize, u64)> {
+    let mut titles: IndexMap<&str, (usize, u64)> = IndexMap::new();
+    for entry in entries {
+        let (count, last) = titles.entry(&entry.title).or_default();
+        *count += 1;
+        *last = (*last).max(entry.time);
+    }
+
+    let mut stats: Vec<(&str, usize, u64)> = titles
+        .into_iter()
+        .map(|(title, (count, last))| (title, count, last))
+        .collect();
+    stats.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    stats
+}
+
+/// Offset of the local time to UTC in seconds.  Zero if unknown.
+#[must_use]
+pub fn local_offset() -> i64 {
+    // The offset is a `long`, which is not 64 bit everywhere.
+    #[allow(clippy::useless_conversion)]
+    clock::now().map_or(0, |tm| i64::from(tm.tm_gmtoff))
+}
+
+/// Date and time like `2024-12-31 23:59` of seconds since Unix epoch, shifted by `offset`
+/// seconds.
+#[must_use]
+pub fn format_time(time: u64, offset: i64) -> String {
+    let time: i64 = i64::try_from(time).unwrap_or(0) + offset;
+    let (days, seconds) = (time.div_euclid(86400), time.rem_euclid(86400));
+
+    // Civil date from days since epoch, by Howard Hinnant.
+    let z: i64 = days + 719_468;
+    let era: i64 = z.div_euclid(146_097);
+    let day_of_era: i64 = z.rem_euclid(146_097);
+    let year_of_era: i64 = (day_of_era - day_of_era / 1460
+        + day_of_era / 36524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year: i64 =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp: i64 = (5 * day_of_year + 2) / 153;
+    let day: i64 = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month: i64 = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year: i64 = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Entry;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    // Untested:
+    //  - load()
+    //  - append()
+    //  - hash_file()
+    //  - local_offset()
+
+    fn entry(time: u64, core_hash: u64, core: &str) -> Entry {
+        Entry {
+            time,
+            core_hash,
+            core: PathBuf::from(core),
+            game: PathBuf::from("/roms/mario.smc"),
+            title: "Super\tMario".to_string(),
+            tags: vec!["100%".to_string(), "any,glitched".to_string()],
+            note: "new PB".to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_and_to_line() {
+        let line =
+            entry(1_700_000_000, 0xab, "/cores/snes9x_libretro.so").to_line();
+
+        assert_eq!(
+            "1700000000\t00000000000000ab\t/cores/snes9x_libretro.so\t\
+             /roms/mario.smc\tSuper Mario\t100%,any glitched\tnew PB",
+            line
+        );
+        let parsed = Entry::parse(&line).unwrap();
+        assert_eq!("Super Mario", parsed.title);
+        assert_eq!(vec!["100%", "any glitched"], parsed.tags);
+        assert_eq!("new PB", parsed.note);
+        assert!(parsed.has_tags(&["100%".to_string()]));
+        assert!(!parsed.has_tags(&["any".to_string()]));
+        assert_eq!(None, Entry::parse("broken"));
+
+        // Entries written before tags and notes.
+        let old = Entry::parse("1\t0\t/cores/a.so\t/roms/a.smc\ta").unwrap();
+        assert!(old.tags.is_empty());
+        assert!(old.has_tags(&[]));
+    }
+
+    #[test]
+    fn stats() {
+        let mut entries = vec![
+            entry(10, 1, "/cores/snes9x_libretro.so"),
+            entry(30, 1, "/cores/snes9x_libretro.so"),
+            entry(20, 1, "/cores/snes9x_libretro.so"),
+        ];
+        entries[1].title = "Zelda".to_string();
+
+        assert_eq!(
+            vec![("Super\tMario", 2, 20), ("Zelda", 1, 30)],
+            super::stats(&entries)
+        );
+    }
+
+    #[test]
+    fn hash_at() {
+        let core: &Path = Path::new("/cores/snes9x_libretro.so");
+        let game: &Path = Path::new("/roms/mario.smc");
+        let mut entries = vec![
+            entry(10, 1, "/cores/snes9x_libretro.so"),
+            entry(20, 2, "/cores/snes9x_libretro.so"),
+            entry(25, 3, "/cores/bsnes_libretro.so"),
+            entry(30, 4, "/cores/snes9x_libretro.so"),
+        ];
+        entries[3].game = PathBuf::from("/roms/zelda.smc");
+
+        assert_eq!(Some(1), super::hash_at(&entries, core, game, 15));
+        assert_eq!(Some(2), super::hash_at(&entries, core, game, 40));
+        assert_eq!(None, super::hash_at(&entries, core, game, 5));
+    }
+
+    #[test]
+    fn format_time() {
+        assert_eq!("1970-01-01 00:00", super::format_time(0, 0));
+        assert_eq!(
+            "2024-03-01 01:30",
+            super::format_time(1_709_249_400, 2 * 3600)
+        );
+    }
+
+    #[test]
+    fn last_hashes() {
+        let entries = vec![
+            entry(1, 1, "/cores/snes9x_libretro.so"),
+            entry(2, 5, "/cores/gambatte_libretro.so"),
+            entry(3, 2, "/cores/snes9x_libretro.so"),
+        ];
+
+        assert_eq!(
+            Some(2),
+            super::last_hash(&entries, Path::new("/cores/snes9x_libretro.so"))
+        );
+        assert_eq!(
+            vec![
+                (Path::new("/cores/snes9x_libretro.so"), 2),
+                (Path::new("/cores/gambatte_libretro.so"), 5)
+            ],
+            super::last_hashes(&entries)
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// Joystick device of the Linux joystick API, which is the first connected gamepad.
+pub const DEFAULT_DEVICE: &str = "/dev/input/js0";
+
+/// Buttons to hold down at the same time, if nothing else is configured.  On most gamepads these
+/// are Select (Back) and Start.
+pub const DEFAULT_BUTTONS: &str = "6+7";
+
+/// Time to wait before opening the device again, after it was unplugged or is missing.
+const RETRY: Duration = Duration::from_secs(2);
+
+const JS_EVENT_BUTTON: u8 = 0x01;
+const JS_EVENT_INIT: u8 = 0x80;
+
+/// A single event read from a joystick device, as defined in `linux/joystick.h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    pub value: i16,
+    pub kind: u8,
+    pub number: u8,
+}
+
+impl Event {
+    /// Decode the 8 bytes of a `struct js_event`.  The first 4 bytes are a timestamp, which is
+    /// not needed.
+    #[must_use]
+    pub fn from_bytes(bytes: [u8; 8]) -> Self {
+        Self {
+            value: i16::from_ne_bytes([bytes[4], bytes[5]]),
+            kind: bytes[6],
+            number: bytes[7],
+        }
+    }
+
+    /// Check if the event is about a button, including the initial state sent on open.
+    #[must_use]
+    pub fn is_button(&self) -> bool {
+        self.kind & !JS_EVENT_INIT == JS_EVENT_BUTTON
+    }
+
+    /// Check if the event is only the initial state of the device, sent right after opening it.
+    #[must_use]
+    pub fn is_init(&self) -> bool {
+        self.kind & JS_EVENT_INIT != 0
+    }
+}
+
+/// Combination of buttons, which triggers once all of them are held down at the same time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Combo {
+    buttons: Vec<u8>,
+    pressed: HashSet<u8>,
+}
+
+impl Combo {
+    /// Parse button numbers separated by `+`, like `6+7`.  The numbers are as reported by the
+    /// joystick device, `jstest` shows them.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let buttons: Vec<u8> = text
+            .split('+')
+            .map(|button| {
+                button.trim().parse::<u8>().map_err(|_| {
+                    format!("Invalid button \"{}\" in hotkey.", button.trim())
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            buttons,
+            pressed: HashSet::new(),
+        })
+    }
+
+    /// Forget all held buttons, like after opening the device again.
+    pub fn reset(&mut self) {
+        self.pressed.clear();
+    }
+
+    /// Track the state of the buttons.  Returns `true` only for the event completing the
+    /// combination, so holding it down triggers once.  The initial state of the device never
+    /// triggers, so a combination held while the device is opened is ignored.
+    pub fn update(&mut self, event: &Event) -> bool {
+        if !event.is_button() {
+            return false;
+        }
+        let was_complete: bool = self.is_complete();
+        if event.value == 0 {
+            self.pressed.remove(&event.number);
+        } else {
+            self.pressed.insert(event.number);
+        }
+
+        !event.is_init() && !was_complete && self.is_complete()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.buttons
+            .iter()
+            .all(|button| self.pressed.contains(button))
+    }
+}
+
+/// Read events from the joystick `device` forever and call `on_press` each time the `combo` is
+/// pressed.  The device is opened again after each call, so buttons pressed in the meantime,
+/// like while playing a game, are discarded.  A missing or unplugged device is waited for.
+pub fn listen<F: FnMut()>(device: &Path, mut combo: Combo, mut on_press: F) {
+    let mut reported: bool = false;
+
+    loop {
+        let mut file: File = match File::open(device) {
+            Ok(file) => file,
+            Err(e) => {
+                if !reported {
+                    eprintln!(
+                        "Waiting for joystick {}: {}",
+                        device.display(),
+                        e
+                    );
+                    reported = true;
+                }
+                thread::sleep(RETRY);
+                continue;
+            }
+        };
+        reported = false;
+        combo.reset();
+
+        if let Err(e) = read_until_combo(&mut file, &mut combo) {
+            eprintln!("Lost joystick {}: {}", device.display(), e);
+            thread::sleep(RETRY);
+            continue;
+        }
+        drop(file);
+        on_press();
+    }
+}
+
+/// Block until the `combo` is pressed on the opened device.
+fn read_until_combo(file: &mut File, combo: &mut Combo) -> io::Result<()> {
+    let mut bytes: [u8; 8] = [0; 8];
+
+    loop {
+        file.read_exact(&mut bytes)?;
+        if combo.update(&Event::from_bytes(bytes)) {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Combo;
+    use super::Event;
+
+    // Untested:
+    //  - listen()
+
+    fn button(number: u8, value: i16) -> Event {
+        Event {
+            value,
+            kind: 0x01,
+            number,
+        }
+    }
+
+    #[test]
+    fn event_from_bytes() {
+        let mut bytes: [u8; 8] = [0; 8];
+        bytes[4..6].copy_from_slice(&1i16.to_ne_bytes());
+        bytes[6] = 0x81;
+        bytes[7] = 7;
+        let event = Event::from_bytes(bytes);
+
+        assert_eq!(
+            Event {
+                value: 1,
+                kind: 0x81,
+                number: 7
+            },
+            event
+        );
+        assert!(event.is_button());
+        assert!(event.is_init());
+    }
+
+    #[test]
+    fn combo_parse() {
+        assert!(Combo::parse("6 + 7").is_ok());
+        assert!(Combo::parse("6+start").is_err());
+        assert!(Combo::parse("").is_err());
+    }
+
+    #[test]
+    fn combo_triggers_once() {
+        let mut combo = Combo::parse("6+7").unwrap();
+
+        assert!(!combo.update(&button(6, 1)));
+        assert!(combo.update(&button(7, 1)));
+        // Still held down, or another button pressed in addition.
+        assert!(!combo.update(&button(0, 1)));
+        assert!(!combo.update(&button(7, 0)));
+        assert!(combo.update(&button(7, 1)));
+    }
+
+    #[test]
+    fn combo_ignores_initial_state_and_axes() {
+        let mut combo = Combo::parse("6+7").unwrap();
+        let init = |number| Event {
+            value: 1,
+            kind: 0x81,
+            number,
+        };
+        let axis = Event {
+            value: 1,
+            kind: 0x02,
+            number: 7,
+        };
+
+        assert!(!combo.update(&init(6)));
+        assert!(!combo.update(&init(7)));
+        combo.reset();
+        assert!(!combo.update(&button(6, 1)));
+        assert!(!combo.update(&axis));
+    }
+}
@@ -0,0 +1,85 @@
+/// Remove the comments from the text of a user config, before it is parsed.  Comments start
+/// with `#` or `;`, either at the beginning of a line or after whitespace at its end, like in
+/// snippets copied from `retroarch.cfg`.  Both characters are kept inside double quotes and
+/// when not preceded by whitespace, such as in `"#ff0000"` or `C#`.  Comment lines become
+/// empty, so line numbers in error messages stay the same.
+#[must_use]
+pub fn strip_comments(text: &str) -> String {
+    text.split_inclusive('\n')
+        .map(|line| {
+            let content: &str = line.trim_end_matches(['\r', '\n']);
+            let ending: &str = &line[content.len()..];
+            format!("{}{}", strip_line_comment(content).trim_end(), ending)
+        })
+        .collect()
+}
+
+/// Remove one pair of double quotes around a value, which is otherwise taken as is.  Quotes keep
+/// whitespace at the start or end, which would be trimmed without them, such as in
+/// `retroarch = "~/Games/RetroArch "`.
+#[must_use]
+pub fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+// The line up to its comment.
+fn strip_line_comment(line: &str) -> &str {
+    let mut quoted: bool = false;
+    let mut after_space: bool = true;
+
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' | ';' if !quoted && after_space => return &line[..index],
+            _ => {}
+        }
+        after_space = c.is_whitespace();
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn strip_comments() {
+        let text = "# comment\n; comment\n[.smc .sfc] ; snes\ncore = snes # inline\n\
+                    color = \"#ff0000\" ; red\nlang = C#\n  # indented\r\nkey = a;b\n";
+
+        assert_eq!(
+            "\n\n[.smc .sfc]\ncore = snes\ncolor = \"#ff0000\"\nlang = C#\n\r\n\
+             key = a;b\n",
+            super::strip_comments(text)
+        );
+    }
+
+    #[test]
+    fn unquote() {
+        assert_eq!(
+            "~/Games/RetroArch ",
+            super::unquote("\"~/Games/RetroArch \"")
+        );
+        assert_eq!("\"inner\"", super::unquote("\"\"inner\"\""));
+        assert_eq!("", super::unquote("\"\""));
+        assert_eq!("\"", super::unquote("\""));
+        assert_eq!("snes9x", super::unquote("snes9x"));
+        assert_eq!(
+            "{ libretro = \"snes9x\" }",
+            super::unquote("{ libretro = \"snes9x\" }")
+        );
+    }
+
+    #[test]
+    fn strip_comments_quoted() {
+        assert_eq!(
+            "snes = { libretro = \"snes9x\", label = \"A ; B # C\" }",
+            super::strip_comments(
+                "snes = { libretro = \"snes9x\", label = \"A ; B # C\" } # x"
+            )
+        );
+    }
+}
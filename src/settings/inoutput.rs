@@ -1,42 +1,535 @@
+use crate::settings::escape::Escape;
 use crate::settings::file;
 
+use std::borrow::Cow;
 use std::error::Error;
+use std::ffi::OsStr;
 use std::io;
 use std::io::prelude::*;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use atty::Stream;
+#[cfg(feature = "serve")]
+use indexmap::map::IndexMap;
 
-/// Reads in each line from stdin, if anything is given.
-pub fn list_from_stdin() -> Result<Vec<String>, Box<dyn Error>> {
-    let mut list: Vec<String> = vec![];
+/// Reads in each line from stdin, if anything is given.  Usually this is only done when stdout is
+/// a terminal.  With `piped_stdout` it is also read when the output goes into a pipe, which is
+/// what list modes like `--fzf` are made for.  With a `timeout` reading stops, if no data arrives
+/// in time, see `read_stdin_timeout()`.  With `null` the entries are separated by NUL instead,
+/// see `split_list()`.
+pub fn list_from_stdin(
+    piped_stdout: bool,
+    timeout: Option<Duration>,
+    null: bool,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if !((piped_stdout || atty::is(Stream::Stdout))
+        && atty::isnt(Stream::Stdin))
+    {
+        return Ok(vec![]);
+    }
 
-    if atty::is(Stream::Stdout) && atty::isnt(Stream::Stdin) {
-        for line in io::stdin().lock().lines() {
-            list.push(line?);
+    let data: Vec<u8> = match timeout {
+        Some(timeout) => read_stdin_timeout(timeout)?,
+        None => {
+            let mut data: Vec<u8> = vec![];
+            io::stdin().lock().read_to_end(&mut data)?;
+            data
         }
+    };
+
+    split_list(&data, null)
+}
+
+/// Split the raw `data` read from stdin into paths, one per line.  With `null` each path ends
+/// with a NUL byte instead, like from `find -print0`.  These are taken byte for byte and may
+/// contain newlines or invalid UTF-8, while lines must be valid UTF-8.  Empty entries are
+/// skipped in NUL mode only.
+pub fn split_list(
+    data: &[u8],
+    null: bool,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if null {
+        Ok(data
+            .split(|byte| *byte == b'\0')
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| PathBuf::from(OsStr::from_bytes(entry)))
+            .collect())
+    } else {
+        Ok(std::str::from_utf8(data)?
+            .lines()
+            .map(PathBuf::from)
+            .collect())
+    }
+}
+
+/// Reads in each game from the list file `path`, one per line.  See `parse_list()`.
+pub fn list_from_file(path: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let path: PathBuf = file::tilde(path);
+    let text: String = std::fs::read_to_string(&path).map_err(|e| {
+        format!("Could not read game list {}: {}", path.display(), e)
+    })?;
+
+    Ok(parse_list(&text, path.parent().unwrap_or(Path::new(""))))
+}
+
+/// Games of a list file with the content `text`, one per line.  Empty lines and comments
+/// starting with `#` are skipped.  Relative paths are relative to `dir` of the list file.
+#[must_use]
+pub fn parse_list(text: &str, dir: &Path) -> Vec<PathBuf> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| dir.join(file::tilde(Path::new(line))))
+        .collect()
+}
+
+/// Save the content of a game from `reader` as `stdin.EXT` in `dir`, which must be private to the
+/// current user.  A leading dot of `extension` is ignored.  No data is an error.
+pub fn write_stdin_rom(
+    dir: &Path,
+    extension: &str,
+    reader: &mut dyn Read,
+) -> io::Result<PathBuf> {
+    let path: PathBuf =
+        dir.join(format!("stdin.{}", extension.trim_start_matches('.')));
+    let mut file = std::fs::File::create(&path)?;
+    if io::copy(reader, &mut file)? == 0 {
+        drop(file);
+        std::fs::remove_file(&path)?;
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "no game data",
+        ));
     }
 
-    Ok(list)
+    Ok(path)
 }
 
-/// Prints out a non empty path.
+/// Read all data from stdin, until it is closed or no data arrived for `timeout`.  Some GUI
+/// launchers start programs with a pipe as stdin, which is never written to nor closed.  Reading
+/// it would hang forever.
+fn read_stdin_timeout(timeout: Duration) -> io::Result<Vec<u8>> {
+    let millis: i32 = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+    let mut data: Vec<u8> = vec![];
+    // At least as big as the buffer of stdin itself, so reads bypass it.  Otherwise data could
+    // wait in that buffer, while `poll()` reports nothing to read.
+    let mut buffer: Vec<u8> = vec![0; 64 * 1024];
+    let mut stdin = io::stdin().lock();
+
+    loop {
+        let mut fd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `fd` is a single valid `pollfd`, matching the count of 1.
+        let ready: i32 = unsafe { libc::poll(&mut fd, 1, millis) };
+        if ready < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(error);
+        }
+        if ready == 0 {
+            break;
+        }
+
+        let read: usize = stdin.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        data.extend_from_slice(&buffer[..read]);
+    }
+
+    Ok(data)
+}
+
+/// How special characters in printed paths are escaped.  It is set once from option `--escape`.
+static ESCAPE: OnceLock<Escape> = OnceLock::new();
+
+/// Base directories, which printed paths are made relative to.  It is set once from option
+/// `--relative`, so all output formats rebase their paths the same way.
+static RELATIVE: OnceLock<Relative> = OnceLock::new();
+
+/// How paths are printed relative to a base directory.  Paths outside of it stay absolute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Relative {
+    /// Paths in the home directory start with `~/`.
+    Home(PathBuf),
+    /// Paths in one of the directories are relative to it, like `snes/mario.smc`.
+    Bases(Vec<PathBuf>),
+}
+
+impl Relative {
+    /// Create from the value of option `--relative`.  `~` is the home directory and `roots` are
+    /// the ROM roots from `[paths]`, any other value is a directory.
+    #[must_use]
+    pub fn parse(base: &str, roots: &[PathBuf]) -> Self {
+        let fullpath = |dir: &Path| {
+            file::to_fullpath(dir).unwrap_or_else(|| file::tilde(dir))
+        };
+
+        match base {
+            "~" | "" => Self::Home(fullpath(Path::new("~"))),
+            "roots" => {
+                Self::Bases(roots.iter().map(|root| fullpath(root)).collect())
+            }
+            dir => Self::Bases(vec![fullpath(Path::new(dir))]),
+        }
+    }
+
+    /// `path` relative to the base.  The longest matching base directory is used.
+    #[must_use]
+    pub fn rebase(&self, path: &Path) -> PathBuf {
+        match self {
+            Self::Home(home) => match path.strip_prefix(home) {
+                Ok(rest) if rest.as_os_str().is_empty() => PathBuf::from("~"),
+                Ok(rest) => Path::new("~").join(rest),
+                Err(_) => path.to_path_buf(),
+            },
+            Self::Bases(bases) => bases
+                .iter()
+                .filter_map(|base| path.strip_prefix(base).ok())
+                .min_by_key(|rest| rest.components().count())
+                .map_or_else(
+                    || path.to_path_buf(),
+                    |rest| match rest.as_os_str().is_empty() {
+                        true => PathBuf::from("."),
+                        false => rest.to_path_buf(),
+                    },
+                ),
+        }
+    }
+}
+
+/// Make all printed paths relative to `relative` from now on.  Only the first call has an effect.
+pub fn set_relative(relative: Relative) {
+    RELATIVE.set(relative).ok();
+}
+
+/// Make all printed paths escaped with `escape` from now on.  Only the first call has an effect.
+pub fn set_escape(escape: Escape) {
+    ESCAPE.set(escape).ok();
+}
+
+/// `path` relative to the base of option `--relative` if set.
+fn relative_path(path: &Path) -> Cow<'_, Path> {
+    match RELATIVE.get() {
+        Some(relative) => Cow::Owned(relative.rebase(path)),
+        None => Cow::Borrowed(path),
+    }
+}
+
+/// Format a path for output, relative to the base of option `--relative` and escaped as with
+/// option `--escape` if set.
+#[must_use]
+pub fn display_path(path: &Path) -> String {
+    escaped(relative_path(path).as_os_str())
+}
+
+/// Format `text` for output, escaped as with option `--escape` if set.
+fn escaped(text: &OsStr) -> String {
+    ESCAPE.get().copied().unwrap_or_default().apply(text)
+}
+
+/// Format a path for output like `display_path()` as a string inside JSON output.  It is not
+/// escaped with option `--escape`, as JSON has its own escaping.
+#[must_use]
+pub fn json_path(path: &Path) -> String {
+    json_string(&relative_path(path).display().to_string())
+}
+
+/// Prints out a non empty path, relative to the base of option `--relative` if set.
 pub fn print_path(path: &Option<PathBuf>) {
-    let string_path: String = file::to_str(path.as_ref());
+    let string_path: String = match path {
+        Some(path) => display_path(path),
+        None => String::new(),
+    };
 
     if !string_path.is_empty() {
         println!("{}", string_path);
     }
 }
 
-/// Prints the fullpath of an existing file.
+/// Prints the fullpath of an existing file, escaped as with option `--escape` if set.
 pub fn print_fullpath(path: &Option<PathBuf>) {
     match path {
         Some(file) => {
             if let Some(fullpath) = file::to_fullpath(file) {
-                println!("{}", fullpath.display());
+                println!("{}", escaped(fullpath.as_os_str()));
             }
         }
         None => (),
     }
 }
+
+/// Escape a text to be used as a string inside JSON output, including the surrounding double
+/// quotes.
+pub fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                escaped.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+
+    escaped
+}
+
+/// Format a variable assignment for POSIX shells like `export NAME='value'`.  The value is
+/// always enclosed in single quotes, so it can be sourced or used with `eval` safely.
+pub fn shell_export(name: &str, value: &str) -> String {
+    format!("export {}='{}'", name, value.replace('\'', "'\\''"))
+}
+
+/// Parse a flat JSON object, where every value is a string, like `{"game": "mario.smc"}`.
+/// Anything else, including nested values, numbers or duplicate keys, is rejected with a message.
+#[cfg(feature = "serve")]
+pub fn parse_json_object(
+    text: &str,
+) -> Result<IndexMap<String, String>, String> {
+    let mut chars = text.trim().chars().peekable();
+    let mut object: IndexMap<String, String> = IndexMap::new();
+
+    if chars.next() != Some('{') {
+        return Err("expected a JSON object".into());
+    }
+    skip_whitespace(&mut chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+    } else {
+        loop {
+            skip_whitespace(&mut chars);
+            let key: String = parse_json_string(&mut chars)?;
+            skip_whitespace(&mut chars);
+            if chars.next() != Some(':') {
+                return Err(format!("expected ':' after key \"{}\"", key));
+            }
+            skip_whitespace(&mut chars);
+            let value: String = parse_json_string(&mut chars)?;
+            if object.insert(key.clone(), value).is_some() {
+                return Err(format!("duplicate key \"{}\"", key));
+            }
+            skip_whitespace(&mut chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return Err("expected ',' or '}'".into()),
+            }
+        }
+    }
+
+    if chars.next().is_some() {
+        return Err("unexpected content after JSON object".into());
+    }
+
+    Ok(object)
+}
+
+#[cfg(feature = "serve")]
+fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+/// Parse a JSON string including its surrounding double quotes.
+#[cfg(feature = "serve")]
+fn parse_json_string(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+) -> Result<String, String> {
+    if chars.next() != Some('"') {
+        return Err("expected a string".into());
+    }
+
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(text),
+            Some('\\') => match chars.next() {
+                Some('"') => text.push('"'),
+                Some('\\') => text.push('\\'),
+                Some('/') => text.push('/'),
+                Some('n') => text.push('\n'),
+                Some('r') => text.push('\r'),
+                Some('t') => text.push('\t'),
+                Some('u') => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    let c: char = u32::from_str_radix(&code, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| {
+                            format!("invalid escape \\u{}", code)
+                        })?;
+                    text.push(c);
+                }
+                _ => return Err("invalid escape sequence".into()),
+            },
+            Some(c) if c.is_control() => {
+                return Err("control character in string".into())
+            }
+            Some(c) => text.push(c),
+            None => return Err("unterminated string".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    use super::Relative;
+
+    // Untested:
+    //  - list_from_stdin()
+    //  - list_from_file()
+    //  - print_path()
+    //  - print_fullpath()
+    //  - display_path()
+
+    #[test]
+    fn parse_list() {
+        assert_eq!(
+            vec![
+                PathBuf::from("/roms/snes/Mario.smc"),
+                PathBuf::from("/mnt/Zelda.sfc"),
+            ],
+            super::parse_list(
+                "# Favorites\n\n  Mario.smc\n/mnt/Zelda.sfc\n",
+                Path::new("/roms/snes")
+            )
+        );
+    }
+
+    #[test]
+    fn split_list() {
+        assert_eq!(
+            vec![PathBuf::from("Mario.smc"), PathBuf::from("Zelda.smc")],
+            super::split_list(b"Mario.smc\nZelda.smc\n", false).unwrap()
+        );
+        assert_eq!(
+            vec![
+                PathBuf::from("Mario\nWorld.smc"),
+                PathBuf::from(OsStr::from_bytes(b"Zelda\xff.smc")),
+            ],
+            super::split_list(b"Mario\nWorld.smc\0Zelda\xff.smc\0\0", true)
+                .unwrap()
+        );
+        assert!(super::split_list(b"Zelda\xff.smc\n", false).is_err());
+    }
+
+    #[test]
+    fn relative_rebase() {
+        let home = Relative::Home(PathBuf::from("/home/user"));
+        let roots = Relative::Bases(vec![
+            PathBuf::from("/home/user/roms"),
+            PathBuf::from("/home/user/roms/snes"),
+        ]);
+
+        assert_eq!(
+            Path::new("~/roms/mario.smc"),
+            home.rebase(Path::new("/home/user/roms/mario.smc"))
+        );
+        assert_eq!(Path::new("~"), home.rebase(Path::new("/home/user")));
+        assert_eq!(
+            Path::new("/home/username/a.gb"),
+            home.rebase(Path::new("/home/username/a.gb"))
+        );
+        assert_eq!(
+            Path::new("mario.smc"),
+            roots.rebase(Path::new("/home/user/roms/snes/mario.smc"))
+        );
+        assert_eq!(
+            Path::new("gb/tetris.gb"),
+            roots.rebase(Path::new("/home/user/roms/gb/tetris.gb"))
+        );
+        assert_eq!(
+            Path::new("/mnt/zelda.sfc"),
+            roots.rebase(Path::new("/mnt/zelda.sfc"))
+        );
+    }
+
+    #[test]
+    fn write_stdin_rom() {
+        let dir: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-stdin-rom-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let written = super::write_stdin_rom(&dir, ".sfc", &mut &b"SNES"[..]);
+        let empty = super::write_stdin_rom(&dir, "gb", &mut &b""[..]);
+        let content = std::fs::read(dir.join("stdin.sfc"));
+        let leftover: bool = dir.join("stdin.gb").exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(dir.join("stdin.sfc"), written.unwrap());
+        assert_eq!(b"SNES".to_vec(), content.unwrap());
+        assert!(empty.is_err());
+        assert!(!leftover);
+    }
+
+    #[test]
+    fn json_string_plain() {
+        assert_eq!("\"mario.smc\"", super::json_string("mario.smc"));
+    }
+
+    #[test]
+    fn shell_export() {
+        assert_eq!(
+            "export ENJOY_GAME='/roms/Mario'\\''s Land.gb'",
+            super::shell_export("ENJOY_GAME", "/roms/Mario's Land.gb")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serve")]
+    fn parse_json_object() {
+        let object = super::parse_json_object(
+            "{\"game\": \"a \\\"b\\\".smc\", \"core\":\"snes\"}",
+        )
+        .unwrap();
+
+        assert_eq!(Some(&"a \"b\".smc".to_string()), object.get("game"));
+        assert_eq!(Some(&"snes".to_string()), object.get("core"));
+        assert!(super::parse_json_object("{}").unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "serve")]
+    fn parse_json_object_invalid() {
+        assert!(super::parse_json_object("").is_err());
+        assert!(super::parse_json_object("{\"game\": 1}").is_err());
+        assert!(
+            super::parse_json_object("{\"a\": \"1\", \"a\": \"2\"}").is_err()
+        );
+        assert!(super::parse_json_object("{\"a\": \"1\"} x").is_err());
+        assert!(super::parse_json_object("{\"a\": \"1\"").is_err());
+    }
+
+    #[test]
+    fn json_string_escape() {
+        assert_eq!(
+            "\"a \\\"b\\\" \\\\ \\t\\u0001\"",
+            super::json_string("a \"b\" \\ \t\u{1}")
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use crate::settings::coreinfo;
+use crate::settings::file;
+use crate::settings::inoutput::json_path;
+use crate::settings::inoutput::json_string;
+use crate::settings::process;
+use crate::settings::queue::format_duration;
+
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// Runtime record of a `RetroArch` process launched by this program.  Each record is stored as
+/// `<pid>.instance` file with `key=value` lines in the `file::runtime_dir()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Instance {
+    pub pid: u32,
+    pub game: PathBuf,
+    pub libretro: PathBuf,
+    /// Seconds since Unix epoch.
+    pub started: u64,
+    /// Executable and start time of the process, to recognize it after its pid was reused.
+    pub exe: Option<PathBuf>,
+    pub start_time: Option<u64>,
+    /// Pid of this program which launched the process.  Its temporary files are in use as long as
+    /// the process runs.
+    pub owner: Option<u32>,
+}
+
+impl Instance {
+    /// Create a record for a process started right now.
+    #[must_use]
+    pub fn new(pid: u32, game: &Path, libretro: &Path) -> Self {
+        Self {
+            pid,
+            game: game.to_path_buf(),
+            libretro: libretro.to_path_buf(),
+            started: now(),
+            exe: process::exe(pid),
+            start_time: process::start_time(pid),
+            owner: Some(std::process::id()),
+        }
+    }
+
+    /// Check if the recorded process is still running.  The process with the same pid must be
+    /// started from the same executable at the same time, otherwise the pid was reused by an
+    /// unrelated process after the recorded one ended.
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        self.exe.is_some()
+            && self.start_time.is_some()
+            && process::exe(self.pid) == self.exe
+            && process::start_time(self.pid) == self.start_time
+    }
+
+    /// Parse the content of a record file.  `None` if the pid is missing.
+    #[must_use]
+    pub fn parse(content: &str) -> Option<Self> {
+        let mut instance: Self = Self::default();
+
+        for (key, value) in content.lines().filter_map(|l| l.split_once('=')) {
+            match key {
+                "pid" => instance.pid = value.parse().ok()?,
+                "game" => instance.game = PathBuf::from(value),
+                "libretro" => instance.libretro = PathBuf::from(value),
+                "started" => instance.started = value.parse().unwrap_or(0),
+                "exe" => instance.exe = Some(PathBuf::from(value)),
+                "start_time" => instance.start_time = value.parse().ok(),
+                "owner" => instance.owner = value.parse().ok(),
+                _ => (),
+            }
+        }
+
+        if instance.pid == 0 {
+            None
+        } else {
+            Some(instance)
+        }
+    }
+
+    /// Content of the record file.
+    #[must_use]
+    pub fn to_record(&self) -> String {
+        let mut record: String = format!(
+            "pid={}\ngame={}\nlibretro={}\nstarted={}\n",
+            self.pid,
+            self.game.display(),
+            self.libretro.display(),
+            self.started
+        );
+        if let Some(exe) = &self.exe {
+            record.push_str(&format!("exe={}\n", exe.display()));
+        }
+        if let Some(start_time) = self.start_time {
+            record.push_str(&format!("start_time={}\n", start_time));
+        }
+        if let Some(owner) = self.owner {
+            record.push_str(&format!("owner={}\n", owner));
+        }
+
+        record
+    }
+
+    /// Name of the core without the `_libretro.so` suffix, like `snes9x`.
+    #[must_use]
+    pub fn core(&self) -> String {
+        coreinfo::core_name(&self.libretro)
+    }
+
+    /// Time since the process was started.
+    #[must_use]
+    pub fn uptime(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.started))
+    }
+
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"pid\":{},\"game\":{},\"core\":{},\"libretro\":{},\
+             \"started\":{}}}",
+            self.pid,
+            json_path(&self.game),
+            json_string(&self.core()),
+            json_path(&self.libretro),
+            self.started
+        )
+    }
+}
+
+/// Seconds since Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn record_path(dir: &Path, pid: u32) -> PathBuf {
+    dir.join(format!("{}.instance", pid))
+}
+
+/// Write the record of a launched instance.  The directory must be private to the current user,
+/// see `file::private_dir()`.
+pub fn register(dir: &Path, instance: &Instance) -> io::Result<()> {
+    let path: PathBuf = record_path(dir, instance.pid);
+
+    file::private_dir(dir)?;
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => (),
+    }
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?
+        .write_all(instance.to_record().as_bytes())
+}
+
+/// Remove the record of an instance.  A missing record is not an error.
+pub fn unregister(dir: &Path, pid: u32) {
+    let _ = fs::remove_file(record_path(dir, pid));
+}
+
+/// All recorded instances, which are still running, sorted by start time.  Records of processes
+/// that are gone, like after a crash of this program, are removed on the fly.  Nothing is listed,
+/// if the directory is not private to the current user, as its records cannot be trusted.
+pub fn list(dir: &Path) -> Vec<Instance> {
+    if !file::is_private_dir(dir) {
+        return vec![];
+    }
+    let mut instances: Vec<Instance> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(std::result::Result::ok)
+                .map(|e| e.path())
+                .filter(|path| {
+                    path.extension().unwrap_or_default() == "instance"
+                })
+                .filter_map(|path| {
+                    fs::read_to_string(path)
+                        .ok()
+                        .and_then(|content| Instance::parse(&content))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    instances.retain(|instance| {
+        let running: bool = instance.is_running();
+        if !running {
+            unregister(dir, instance.pid);
+        }
+        running
+    });
+    instances.sort_by_key(|instance| instance.started);
+
+    instances
+}
+
+/// Ask a running instance to quit, by sending the terminate signal.  The process is checked right
+/// before, so no other process with a reused pid gets the signal.
+pub fn terminate(instance: &Instance) -> io::Result<bool> {
+    if !instance.is_running() {
+        return Ok(false);
+    }
+    let pid: libc::pid_t = libc::pid_t::try_from(instance.pid)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    // SAFETY: Sending a signal has no memory effects, and the pid is a single positive process.
+    if unsafe { libc::kill(pid, libc::SIGTERM) } == 0 {
+        Ok(true)
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Format all instances as a plain text table.
+#[must_use]
+pub fn to_table(instances: &[Instance]) -> String {
+    let mut table: String =
+        format!("{:>7}  {:>8}  {:<12}  {}", "PID", "TIME", "CORE", "GAME");
+
+    for instance in instances {
+        table.push_str(&format!(
+            "\n{:>7}  {:>8}  {:<12}  {}",
+            instance.pid,
+            format_duration(instance.uptime()),
+            instance.core(),
+            instance.game.display()
+        ));
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+
+    use super::Instance;
+
+    // Untested:
+    //  - register()
+    //  - unregister()
+    //  - list()
+    //  - terminate()
+
+    fn test_instance_template() -> Instance {
+        Instance {
+            pid: 4321,
+            game: PathBuf::from("/roms/snes/mario.smc"),
+            libretro: PathBuf::from("/cores/snes9x_libretro.so"),
+            started: 1_700_000_000,
+            exe: Some(PathBuf::from("/usr/bin/retroarch")),
+            start_time: Some(987_654),
+            owner: Some(1234),
+        }
+    }
+
+    #[test]
+    fn record_roundtrip() {
+        let instance = test_instance_template();
+
+        assert_eq!(
+            Some(instance.clone()),
+            Instance::parse(&instance.to_record())
+        );
+    }
+
+    #[test]
+    fn parse_without_pid() {
+        assert_eq!(None, Instance::parse("game=/roms/snes/mario.smc\n"));
+    }
+
+    #[test]
+    fn is_running_checks_identity() {
+        let pid: u32 = std::process::id();
+        let mut instance =
+            Instance::new(pid, &PathBuf::new(), &PathBuf::new());
+
+        assert!(instance.is_running());
+
+        instance.start_time = instance.start_time.map(|time| time + 1);
+        assert!(!instance.is_running());
+
+        instance = Instance::new(pid, &PathBuf::new(), &PathBuf::new());
+        instance.exe = Some(PathBuf::from("/usr/bin/retroarch"));
+        assert!(!instance.is_running());
+
+        instance.exe = None;
+        assert!(!instance.is_running());
+    }
+
+    #[test]
+    fn core_name() {
+        assert_eq!("snes9x", test_instance_template().core());
+    }
+
+    #[test]
+    fn to_json() {
+        assert_eq!(
+            "{\"pid\":4321,\"game\":\"/roms/snes/mario.smc\",\"core\":\"snes9x\",\
+             \"libretro\":\"/cores/snes9x_libretro.so\",\"started\":1700000000}",
+            test_instance_template().to_json()
+        );
+    }
+}
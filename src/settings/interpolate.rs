@@ -0,0 +1,130 @@
+/// Replace variables like `${HOME}` in the text of a user config with their value from `lookup`,
+/// usually the environment.  So a shared config can refer to locations which differ on each
+/// machine.
+///
+/// - `${NAME}` is the value of the variable, which must be set.
+/// - `${NAME:-fallback}` is the `fallback`, if the variable is unset or empty.
+/// - `$${` is a literal `${`.
+///
+/// Any other `$` is kept as it is.  Comment lines are not changed, so they can mention unset
+/// variables.  Errors tell the line number.
+pub fn expand(
+    text: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut expanded: String = String::with_capacity(text.len());
+
+    for (number, line) in text.split_inclusive('\n').enumerate() {
+        let trimmed: &str = line.trim_start();
+        if trimmed.starts_with('#') || trimmed.starts_with(';') {
+            expanded.push_str(line);
+            continue;
+        }
+        expand_line(line, &lookup, &mut expanded)
+            .map_err(|e| format!("Line {}: {}", number + 1, e))?;
+    }
+
+    Ok(expanded)
+}
+
+fn expand_line(
+    line: &str,
+    lookup: &impl Fn(&str) -> Option<String>,
+    expanded: &mut String,
+) -> Result<(), String> {
+    let mut rest: &str = line;
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after: &str = &rest[start..];
+        if let Some(escaped) = after.strip_prefix("$${") {
+            expanded.push_str("${");
+            rest = escaped;
+        } else if let Some(variable) = after.strip_prefix("${") {
+            let end: usize = variable.find('}').ok_or(
+                "Unclosed ${ in config, write $${ for a literal one.",
+            )?;
+            let (name, fallback) = match variable[..end].split_once(":-") {
+                Some((name, fallback)) => (name, Some(fallback)),
+                None => (&variable[..end], None),
+            };
+            if !is_name(name) {
+                return Err(format!("Invalid variable name \"{}\".", name));
+            }
+            let value: Option<String> =
+                lookup(name).filter(|value| !value.is_empty());
+            match (value, fallback) {
+                (Some(value), _) => expanded.push_str(&value),
+                (None, Some(fallback)) => expanded.push_str(fallback),
+                (None, None) => {
+                    return Err(format!(
+                        "Variable ${{{0}}} is not set, give a fallback like \
+                         ${{{0}:-default}}.",
+                        name
+                    ))
+                }
+            }
+            rest = &variable[end + 1..];
+        } else {
+            expanded.push('$');
+            rest = &after[1..];
+        }
+    }
+    expanded.push_str(rest);
+
+    Ok(())
+}
+
+// Names of environment variables as the shell allows them.
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "ROMS" => Some("/mnt/roms".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expand() {
+        assert_eq!(
+            Ok("[/mnt/roms/snes]\nlibretro = ~/cores:/usr/lib\n".to_string()),
+            super::expand(
+                "[${ROMS}/snes]\nlibretro = ${CORES:-~/cores}:/usr/lib\n",
+                lookup
+            )
+        );
+        assert_eq!(
+            Ok("a = ${ROMS} $HOME $\n".to_string()),
+            super::expand("a = $${ROMS} $HOME $\n", lookup)
+        );
+        assert_eq!(
+            Ok("a = fallback".to_string()),
+            super::expand("a = ${EMPTY:-fallback}", lookup)
+        );
+        assert_eq!(
+            Ok("# ${UNSET}\n".to_string()),
+            super::expand("# ${UNSET}\n", lookup)
+        );
+    }
+
+    #[test]
+    fn expand_errors() {
+        let error =
+            super::expand("a = 1\nb = ${UNSET}\n", lookup).unwrap_err();
+        assert!(error.starts_with("Line 2: Variable ${UNSET} is not set"));
+        assert!(super::expand("a = ${ROMS", lookup).is_err());
+        assert!(super::expand("a = ${1X}", lookup).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::settings::file;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Default private home directory for isolation mode, if none is given explicitly.
+pub fn default_dir() -> PathBuf {
+    file::data_dir().join("isolated")
+}
+
+/// Path of the private `retroarch.cfg` inside the isolated home directory.  This is where
+/// `RetroArch` looks for it, when `XDG_CONFIG_HOME` points to `dir/.config`.
+pub fn config_path(dir: &Path) -> PathBuf {
+    dir.join(".config").join("retroarch").join("retroarch.cfg")
+}
+
+/// Environment variables for the `retroarch` process to use `dir` as its home directory.
+pub fn env_vars(dir: &Path) -> Vec<(String, PathBuf)> {
+    vec![
+        ("HOME".to_string(), dir.to_path_buf()),
+        ("XDG_CONFIG_HOME".to_string(), dir.join(".config")),
+    ]
+}
+
+/// Build the content of a skeleton `retroarch.cfg`.  It only points to the already installed
+/// cores and their info files, so they are found without downloading them again.
+pub fn skeleton_config(
+    libretro_directory: Option<&Path>,
+    libretro_info_directory: Option<&Path>,
+) -> String {
+    let mut content = String::from(
+        "# Private RetroArch configuration created by enjoy for isolation mode.\n",
+    );
+
+    if let Some(dir) = libretro_directory {
+        content.push_str(&format!(
+            "libretro_directory = \"{}\"\n",
+            file::tilde(dir).display()
+        ));
+    }
+    if let Some(dir) = libretro_info_directory {
+        content.push_str(&format!(
+            "libretro_info_path = \"{}\"\n",
+            file::tilde(dir).display()
+        ));
+    }
+
+    content
+}
+
+/// Create the isolated home directory with a skeleton `retroarch.cfg`, if it does not exist
+/// yet.  An existing config is never overwritten, so changes made in `RetroArch` persist between
+/// isolated sessions.  Returns the path to the `retroarch.cfg`.
+pub fn prepare(
+    dir: &Path,
+    libretro_directory: Option<&Path>,
+    libretro_info_directory: Option<&Path>,
+) -> io::Result<PathBuf> {
+    let config: PathBuf = config_path(dir);
+
+    if !config.exists() {
+        if let Some(parent) = config.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(
+            &config,
+            skeleton_config(libretro_directory, libretro_info_directory),
+        )?;
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+
+    // Untested:
+    //  - default_dir()
+    //  - prepare()
+
+    #[test]
+    fn config_path() {
+        assert_eq!(
+            PathBuf::from("/tmp/iso/.config/retroarch/retroarch.cfg"),
+            super::config_path(&PathBuf::from("/tmp/iso"))
+        );
+    }
+
+    #[test]
+    fn skeleton_config() {
+        let content = super::skeleton_config(
+            Some(&PathBuf::from("/usr/lib/libretro")),
+            None,
+        );
+
+        assert!(content.contains("libretro_directory = \"/usr/lib/libretro\""));
+        assert!(!content.contains("libretro_info_path"));
+    }
+}
@@ -0,0 +1,276 @@
+use crate::settings::compressed;
+use crate::settings::file;
+use crate::settings::overrides;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// Days a decompressed game stays in the cache after its last launch, if option `cache_days` is
+/// not set.
+pub const DEFAULT_CACHE_DAYS: u64 = 30;
+
+/// Check if a process with `pid` exists.
+#[must_use]
+pub fn is_alive(pid: u32) -> bool {
+    Path::new(&format!("/proc/{}", pid)).exists()
+}
+
+/// Check if `path` was modified longer than `max_age` before `now`.  Always `false` without a
+/// `max_age`, or if the time cannot be read.
+fn is_older(path: &Path, max_age: Option<Duration>, now: SystemTime) -> bool {
+    max_age.is_some_and(|max_age| {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > max_age)
+    })
+}
+
+/// Remove the temporary directories `<pid>` in the runtime `dir`, which were left behind by
+/// processes of this program that ended without cleaning up, like after a crash.  A directory is
+/// kept while its process runs, or while a `RetroArch` launched by it is in `owners` of the
+/// instance registry, as it may still read the append-config.  Directories kept on purpose with
+/// `--keep-overrides` are removed after `max_age` only.
+pub fn clean_runtime(
+    dir: &Path,
+    owners: &HashSet<u32>,
+    max_age: Option<Duration>,
+    now: SystemTime,
+) -> usize {
+    if !file::is_private_dir(dir) {
+        return 0;
+    }
+
+    clean_runtime_with(
+        dir,
+        |pid| is_alive(pid) || owners.contains(&pid),
+        max_age,
+        now,
+    )
+}
+
+fn clean_runtime_with(
+    dir: &Path,
+    in_use: impl Fn(u32) -> bool,
+    max_age: Option<Duration>,
+    now: SystemTime,
+) -> usize {
+    let is_kept = |path: &Path| {
+        let marker: PathBuf = path.join(overrides::KEEP_MARKER);
+        marker.exists()
+            && (max_age.is_none() || !is_older(&marker, max_age, now))
+    };
+    let orphans: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(std::result::Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .and_then(|name| name.parse::<u32>().ok())
+                        .is_some_and(|pid| {
+                            pid != process::id() && !in_use(pid)
+                        })
+                })
+                .filter(|path| !is_kept(path))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    orphans
+        .iter()
+        .filter(|path| fs::remove_dir_all(path).is_ok())
+        .count()
+}
+
+/// Remove files from the cache `dir` of decompressed games: partial files of interrupted runs and
+/// games not launched for longer than `max_age`.  `None` keeps all complete games.  Games in
+/// `in_use` by a running `RetroArch` of the instance registry are always kept.  Folders which are
+/// empty afterwards are removed too.
+pub fn clean_cache(
+    dir: &Path,
+    in_use: &HashSet<PathBuf>,
+    max_age: Option<Duration>,
+    now: SystemTime,
+) -> usize {
+    let is_expired = |path: &Path| {
+        if in_use.contains(path) {
+            return false;
+        }
+        let name: String = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if let Some(pid) = name.strip_prefix(".partial-") {
+            return pid
+                .parse::<u32>()
+                .is_ok_and(|pid| pid != process::id() && !is_alive(pid));
+        }
+        is_older(path, max_age, now)
+    };
+    let mut removed: usize = 0;
+
+    for folder in fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+    {
+        removed += fs::read_dir(&folder)
+            .into_iter()
+            .flatten()
+            .filter_map(std::result::Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && is_expired(path))
+            .filter(|path| fs::remove_file(path).is_ok())
+            .count();
+        // Fails as long as the folder is not empty.
+        let _ = fs::remove_dir(&folder);
+    }
+
+    removed
+}
+
+/// Remove the leftovers of earlier runs from the runtime directory and the cache, keeping files
+/// for `days` or forever with `0`.  The temporary files of `owners` and the cached `games` of the
+/// running instances are kept.  Returns the number of removed files and directories.  Errors are
+/// ignored, anything not removed now is tried again on the next start.
+pub fn clean(
+    owners: &HashSet<u32>,
+    games: &HashSet<PathBuf>,
+    days: u64,
+) -> usize {
+    let max_age: Option<Duration> = (days > 0)
+        .then(|| Duration::from_secs(days.saturating_mul(24 * 60 * 60)));
+
+    clean_runtime(&file::runtime_dir(), owners, max_age, SystemTime::now())
+        + clean_cache(
+            &file::cache_dir().join(compressed::CACHE_DIR),
+            games,
+            max_age,
+            SystemTime::now(),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    use crate::settings::overrides;
+
+    // Untested:
+    //  - clean_runtime()
+    //  - clean()
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!(
+            "enjoy-test-janitor-{}-{}",
+            name,
+            process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn is_alive() {
+        assert!(super::is_alive(process::id()));
+        assert!(!super::is_alive(u32::MAX));
+    }
+
+    #[test]
+    fn clean_runtime_with() {
+        let dir: PathBuf = test_dir("runtime");
+        let own: String = process::id().to_string();
+        for name in ["1", "2", "4", "token", own.as_str()] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        fs::write(dir.join("3"), "").unwrap();
+        fs::write(dir.join("4").join(overrides::KEEP_MARKER), "").unwrap();
+
+        let day = Duration::from_secs(24 * 60 * 60);
+        let removed = super::clean_runtime_with(
+            &dir,
+            |pid| pid == 2,
+            Some(day),
+            SystemTime::now(),
+        );
+        let kept: bool = dir.join("4").exists();
+        let expired = super::clean_runtime_with(
+            &dir,
+            |_| false,
+            Some(day),
+            SystemTime::now() + day * 2,
+        );
+        let mut left: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(1, removed);
+        assert!(kept);
+        assert_eq!(2, expired);
+        let mut expected: Vec<String> = vec!["3".into(), "token".into(), own];
+        expected.sort();
+        assert_eq!(expected, left);
+    }
+
+    #[test]
+    fn clean_cache() {
+        let dir: PathBuf = test_dir("cache");
+        fs::create_dir_all(dir.join("aaaa")).unwrap();
+        fs::create_dir_all(dir.join("bbbb")).unwrap();
+        fs::write(dir.join("aaaa/game.smc"), "").unwrap();
+        fs::write(dir.join("bbbb/game.sfc"), "").unwrap();
+        fs::write(dir.join(format!("bbbb/.partial-{}", u32::MAX)), "")
+            .unwrap();
+        fs::write(dir.join(format!("bbbb/.partial-{}", process::id())), "")
+            .unwrap();
+        fs::create_dir_all(dir.join("cccc")).unwrap();
+        fs::write(dir.join("cccc/game.gb"), "").unwrap();
+        let running: HashSet<PathBuf> =
+            HashSet::from([dir.join("cccc/game.gb")]);
+
+        let day = Duration::from_secs(24 * 60 * 60);
+        let kept = super::clean_cache(&dir, &running, None, SystemTime::now());
+        let fresh =
+            super::clean_cache(&dir, &running, Some(day), SystemTime::now());
+        let old = super::clean_cache(
+            &dir,
+            &running,
+            Some(day),
+            SystemTime::now() + day * 2,
+        );
+        let aaaa: bool = dir.join("aaaa").exists();
+        let cccc: bool = dir.join("cccc/game.gb").exists();
+        let own: bool = dir
+            .join(format!("bbbb/.partial-{}", process::id()))
+            .exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(1, kept);
+        assert_eq!(0, fresh);
+        assert_eq!(2, old);
+        assert!(!aaaa);
+        assert!(own);
+        assert!(cccc);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::settings::inoutput::json_string;
+
+use std::path::Path;
+use std::process::ExitStatus;
+use std::process::Output;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// How a launched `RetroArch` ended.  Frontends which launch games through `Settings::launch()`
+/// get this instead of the raw output of the process.
+#[derive(Debug, Clone)]
+pub struct LaunchReport {
+    pub status: ExitStatus,
+    /// Time from starting `RetroArch` until it exited.
+    pub duration: Duration,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// `RetroArch` was terminated, because it ran longer than option `timeout`.
+    pub timed_out: bool,
+}
+
+impl LaunchReport {
+    #[must_use]
+    pub fn new(output: Output, duration: Duration, timed_out: bool) -> Self {
+        Self {
+            status: output.status,
+            duration,
+            stdout: output.stdout,
+            stderr: output.stderr,
+            timed_out,
+        }
+    }
+
+    /// Check if `RetroArch` exited on its own with exit code 0.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.status.success() && !self.timed_out
+    }
+
+    /// The report of launching `game` as a JSON object.  The duration is in seconds.
+    #[must_use]
+    pub fn to_json(&self, game: &Path) -> String {
+        let exit_code: String = self
+            .status
+            .code()
+            .map_or_else(|| "null".to_string(), |c| c.to_string());
+        let text = |bytes: &[u8]| json_string(&String::from_utf8_lossy(bytes));
+
+        format!(
+            "{{\"game\":{},\"success\":{},\"exit_code\":{},\"duration\":{:.3},\
+             \"timed_out\":{},\"stdout\":{},\"stderr\":{}}}",
+            json_string(&game.display().to_string()),
+            self.is_success(),
+            exit_code,
+            self.duration.as_secs_f64(),
+            self.timed_out,
+            text(&self.stdout),
+            text(&self.stderr),
+        )
+    }
+}
+
+/// Terminates a process, if it still runs after its time limit.
+#[derive(Debug)]
+pub struct Deadline {
+    cancel: mpsc::Sender<()>,
+    expired: Arc<AtomicBool>,
+}
+
+impl Deadline {
+    /// Send the terminate signal to process `pid`, unless `finish()` is called within `limit`.
+    #[must_use]
+    pub fn start(pid: u32, limit: Duration) -> Self {
+        let (cancel, cancelled) = mpsc::channel::<()>();
+        let expired: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let flag: Arc<AtomicBool> = Arc::clone(&expired);
+        thread::spawn(move || {
+            if cancelled.recv_timeout(limit)
+                == Err(mpsc::RecvTimeoutError::Timeout)
+            {
+                flag.store(true, Ordering::SeqCst);
+                if let Ok(pid) = libc::pid_t::try_from(pid) {
+                    unsafe { libc::kill(pid, libc::SIGTERM) };
+                }
+            }
+        });
+
+        Self { cancel, expired }
+    }
+
+    /// Stop waiting after the process exited.  Returns `true`, if it was terminated before.
+    #[must_use]
+    pub fn finish(self) -> bool {
+        let _ = self.cancel.send(());
+        self.expired.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::os::unix::process::ExitStatusExt;
+    use std::path::Path;
+    use std::process::Command;
+    use std::process::ExitStatus;
+    use std::process::Output;
+    use std::time::Duration;
+
+    #[test]
+    fn to_json() {
+        let output = Output {
+            status: ExitStatus::from_raw(15),
+            stdout: b"".to_vec(),
+            stderr: b"[INFO] \"bye\"\n".to_vec(),
+        };
+        let report = super::LaunchReport::new(
+            output,
+            Duration::from_millis(1500),
+            true,
+        );
+
+        assert!(!report.is_success());
+        assert_eq!(
+            "{\"game\":\"/roms/mario.smc\",\"success\":false,\"exit_code\":null,\
+             \"duration\":1.500,\"timed_out\":true,\"stdout\":\"\",\
+             \"stderr\":\"[INFO] \\\"bye\\\"\\n\"}",
+            report.to_json(Path::new("/roms/mario.smc"))
+        );
+    }
+
+    #[test]
+    fn deadline_terminates() {
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let deadline =
+            super::Deadline::start(child.id(), Duration::from_millis(50));
+        let status = child.wait().unwrap();
+
+        assert!(deadline.finish());
+        assert!(!status.success());
+    }
+
+    #[test]
+    fn deadline_finished_in_time() {
+        let mut child = Command::new("true").spawn().unwrap();
+        let deadline =
+            super::Deadline::start(child.id(), Duration::from_secs(5));
+        let status = child.wait().unwrap();
+
+        assert!(!deadline.finish());
+        assert!(status.success());
+    }
+}
@@ -0,0 +1,295 @@
+use std::fmt;
+
+use wildmatch::WildMatch;
+
+/// How likely a finding is a mistake in the user config.  Findings are listed from the highest
+/// severity down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    High,
+    Medium,
+    Low,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::High => write!(f, "HIGH"),
+            Self::Medium => write!(f, "MEDIUM"),
+            Self::Low => write!(f, "LOW"),
+        }
+    }
+}
+
+/// A single problem found in the user config, with the section it is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub section: String,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] [{}] {}", self.severity, self.section, self.message)
+    }
+}
+
+/// A directory or extension rule as written in the user config, like `[.smc .sfc]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rule {
+    pub section: String,
+    /// Value of key `core`, an alias from `[cores]`.
+    pub core: Option<String>,
+    /// Rule sets key `libretro` directly.
+    pub libretro: bool,
+}
+
+impl Rule {
+    /// Check if the rule selects a core at all.  Rules could also set only options like
+    /// `workdir`.
+    fn selects_core(&self) -> bool {
+        self.libretro || self.core.is_some()
+    }
+}
+
+/// Everything of the user config needed to analyze how rules interact.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Model {
+    /// Aliases defined in `[cores]`.
+    pub aliases: Vec<String>,
+    /// Aliases with extensions declared in their inline table in `[cores]`.
+    pub aliases_with_extensions: Vec<String>,
+    /// Directory rules in the order of the config.
+    pub directory_rules: Vec<Rule>,
+    /// Extension rules in the order of the config.
+    pub extension_rules: Vec<Rule>,
+    /// Core alias or `libretro` set for every game, like with option `--core`.
+    pub global_core: Option<String>,
+}
+
+/// Run all checks and return the findings, highest severity first.
+#[must_use]
+pub fn lint(model: &Model) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+
+    findings.extend(unknown_aliases(model));
+    findings.extend(shadowed_directories(&model.directory_rules));
+    findings.extend(duplicate_extensions(&model.extension_rules));
+    findings.extend(global_core_wins(model));
+    findings.extend(unused_aliases(model));
+    findings.sort_by_key(|finding| finding.severity);
+
+    findings
+}
+
+/// Rules referring to an alias, which is not defined in `[cores]`.  These are dropped silently
+/// when the config is read.
+fn unknown_aliases(model: &Model) -> Vec<Finding> {
+    model
+        .directory_rules
+        .iter()
+        .chain(&model.extension_rules)
+        .filter_map(|rule| {
+            let core: &String = rule.core.as_ref()?;
+            (!rule.libretro && !model.aliases.contains(core)).then(|| Finding {
+                severity: Severity::High,
+                section: rule.section.clone(),
+                message: format!(
+                    "core \"{}\" is not defined in [cores], the rule is ignored",
+                    core
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Directory rules, which can never match, because an earlier pattern matches all of their
+/// folders and the first matching rule wins.
+fn shadowed_directories(rules: &[Rule]) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+
+    for (index, rule) in rules.iter().enumerate() {
+        let pattern: String = normalize(&rule.section);
+        if let Some(earlier) = rules[..index].iter().find(|earlier| {
+            WildMatch::new(&normalize(&earlier.section)).matches(&pattern)
+        }) {
+            findings.push(Finding {
+                severity: Severity::High,
+                section: rule.section.clone(),
+                message: format!(
+                    "can never match, [{}] comes first and matches all of its folders",
+                    earlier.section
+                ),
+            });
+        }
+    }
+
+    findings
+}
+
+/// Extensions declared in more than one section, where the later one wins.
+fn duplicate_extensions(rules: &[Rule]) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+
+    for (index, rule) in rules.iter().enumerate() {
+        for extension in extensions(&rule.section) {
+            if let Some(later) = rules[index + 1..]
+                .iter()
+                .find(|later| extensions(&later.section).contains(&extension))
+            {
+                findings.push(Finding {
+                    severity: Severity::Medium,
+                    section: rule.section.clone(),
+                    message: format!(
+                        "extension .{} is replaced by later [{}]",
+                        extension, later.section
+                    ),
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+/// A core set for every game makes all rules selecting a core unreachable.
+fn global_core_wins(model: &Model) -> Vec<Finding> {
+    let core: &String = match &model.global_core {
+        Some(core) => core,
+        None => return vec![],
+    };
+    let sections: Vec<String> = model
+        .directory_rules
+        .iter()
+        .chain(&model.extension_rules)
+        .filter(|rule| rule.selects_core())
+        .map(|rule| format!("[{}]", rule.section))
+        .collect();
+    if sections.is_empty() {
+        return vec![];
+    }
+
+    vec![Finding {
+        severity: Severity::Medium,
+        section: "options".to_string(),
+        message: format!(
+            "\"{}\" is used for every game, so the core of {} rules is never used: {}",
+            core,
+            sections.len(),
+            sections.join(" ")
+        ),
+    }]
+}
+
+/// Aliases in `[cores]`, which no rule refers to.  They can still be used with option `--core`.
+fn unused_aliases(model: &Model) -> Vec<Finding> {
+    model
+        .aliases
+        .iter()
+        .filter(|alias| {
+            model.global_core.as_ref() != Some(*alias)
+                && !model.aliases_with_extensions.contains(alias)
+                && !model
+                    .directory_rules
+                    .iter()
+                    .chain(&model.extension_rules)
+                    .any(|rule| rule.core.as_ref() == Some(*alias))
+        })
+        .map(|alias| Finding {
+            severity: Severity::Low,
+            section: "cores".to_string(),
+            message: format!(
+                "alias \"{}\" is not used by any rule, only with --core",
+                alias
+            ),
+        })
+        .collect()
+}
+
+// Directory pattern with tilde expanded and without trailing slash, as used for matching.
+fn normalize(section: &str) -> String {
+    let expanded: String = shellexpand::tilde(section).to_string();
+
+    expanded.trim_end_matches('/').to_string()
+}
+
+// Extensions of a section like `.smc .sfc`, without the dots.
+fn extensions(section: &str) -> Vec<&str> {
+    section
+        .split_whitespace()
+        .map(|extension| extension.trim_start_matches('.'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Model;
+    use super::Rule;
+    use super::Severity;
+
+    // Untested:
+    //  - Display of Finding
+
+    fn rule(section: &str, core: &str) -> Rule {
+        Rule {
+            section: section.to_string(),
+            core: Some(core.to_string()),
+            libretro: false,
+        }
+    }
+
+    fn model() -> Model {
+        Model {
+            aliases: vec!["snes".into(), "gb".into(), "psx".into()],
+            aliases_with_extensions: vec![],
+            directory_rules: vec![
+                rule("~/roms/*", "snes"),
+                rule("~/roms/psx/", "psx"),
+                rule("/mnt/roms/", "snez"),
+            ],
+            extension_rules: vec![
+                rule(".smc .sfc", "snes"),
+                rule(".gb", "gb"),
+                rule(".sfc", "snes"),
+            ],
+            global_core: None,
+        }
+    }
+
+    #[test]
+    fn lint_ranked() {
+        let findings = super::lint(&model());
+        let sections: Vec<(Severity, &str)> = findings
+            .iter()
+            .map(|f| (f.severity, f.section.as_str()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (Severity::High, "/mnt/roms/"),
+                (Severity::High, "~/roms/psx/"),
+                (Severity::Medium, ".smc .sfc"),
+            ],
+            sections
+        );
+    }
+
+    #[test]
+    fn lint_global_core() {
+        let mut model = model();
+        model.global_core = Some("gb".to_string());
+        model.aliases_with_extensions = vec!["psx".to_string()];
+        model.directory_rules.clear();
+        model.aliases.push("n64".to_string());
+
+        let findings = super::lint(&model);
+
+        assert_eq!(3, findings.len());
+        assert_eq!(Severity::Medium, findings[1].severity);
+        assert!(findings[1].message.contains("of 3 rules"));
+        assert_eq!(Severity::Low, findings[2].severity);
+        assert!(findings[2].message.contains("\"n64\""));
+    }
+}
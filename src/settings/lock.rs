@@ -0,0 +1,325 @@
+use crate::settings::file;
+use crate::settings::passthrough;
+
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::path::PathBuf;
+
+use indexmap::map::IndexMap;
+
+/// System wide file with section `[lock]`, which takes precedence over the default user config.
+/// Players usually cannot write to it.
+pub const SYSTEM_CONFIG: &str = "/etc/enjoy/lock.ini";
+
+/// Flags of `retroarch`, which would start a game not checked by the lock.
+const CONTENT_FLAGS: &[&str] =
+    &["--subsystem", "--menu", "--load-menu-on-error"];
+
+/// Lock mode from section `[lock]` of the user config.  While locked, only games in one of the
+/// approved directories or listed in the playlist can be launched.  Meant for family-room
+/// cabinets, where only approved titles should start.  The lock itself is protected either by a
+/// password or by making the file with the lock read-only for the players.  It is read from
+/// `SYSTEM_CONFIG` or else the default user config, never from a config given with `--config`.
+///
+/// ```ini
+/// [lock]
+/// enabled = 1
+/// directories = ~/roms/kids "~/roms/party games"
+/// playlist = ~/.config/enjoy/approved.m3u
+/// password_hash = 5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lock {
+    /// Lock without option `--locked`.
+    pub enabled: bool,
+    /// Games in these directories and their subdirectories are approved.
+    pub directories: Vec<PathBuf>,
+    /// A file with one approved game per line.  Empty lines and lines starting with `#` are
+    /// ignored, like in a `.m3u` playlist.  Relative paths are relative to the playlist.
+    pub playlist: Option<PathBuf>,
+    /// SHA-256 of the password for option `--unlock`, as printed by `sha256sum`.
+    pub password_hash: Option<String>,
+}
+
+impl Lock {
+    /// Read the lock from the keys of section `[lock]`.  Directories are separated by spaces and
+    /// can be quoted.
+    pub fn from_section(
+        section: &IndexMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut lock: Self = Self::default();
+
+        for (key, value) in section {
+            match key.as_str() {
+                "enabled" => {
+                    lock.enabled = matches!(
+                        value.trim().to_lowercase().as_str(),
+                        "1" | "true" | "yes" | "on"
+                    );
+                }
+                "directories" => {
+                    lock.directories = shlex::split(value)
+                        .ok_or_else(|| {
+                            format!("Invalid directories in [lock]: {}", value)
+                        })?
+                        .iter()
+                        .map(|dir| file::tilde(Path::new(dir)))
+                        .collect();
+                }
+                "playlist" => {
+                    lock.playlist = Some(file::tilde(Path::new(value.trim())));
+                }
+                "password_hash" => {
+                    lock.password_hash = Some(value.trim().to_lowercase());
+                }
+                _ => return Err(format!("Unknown key in [lock]: {}", key)),
+            }
+        }
+
+        Ok(lock)
+    }
+
+    /// Check if `game` is approved.  The game and all approved paths are compared as fullpaths,
+    /// so symbolic links cannot be used to get around the lock.
+    pub fn check(&self, game: &Path) -> Result<(), String> {
+        let game: PathBuf = file::to_fullpath(game).ok_or_else(|| {
+            format!("Locked, game not found: {}", game.display())
+        })?;
+        let approved: Vec<PathBuf> = match &self.playlist {
+            Some(playlist) => {
+                let content: String =
+                    fs::read_to_string(playlist).map_err(|e| {
+                        format!(
+                            "Locked, could not read playlist {}: {}",
+                            playlist.display(),
+                            e
+                        )
+                    })?;
+                parse_playlist(
+                    &content,
+                    playlist.parent().unwrap_or_else(|| Path::new("")),
+                )
+            }
+            None => vec![],
+        };
+
+        let in_directory: bool = self
+            .directories
+            .iter()
+            .filter_map(|dir| file::to_fullpath(dir))
+            .any(|dir| game.starts_with(dir));
+        let in_playlist: bool = approved
+            .iter()
+            .filter_map(|path| file::to_fullpath(path))
+            .any(|path| path == game);
+
+        if in_directory || in_playlist {
+            Ok(())
+        } else {
+            Err(format!("Locked, game is not approved: {}", game.display()))
+        }
+    }
+
+    /// Check the password from `ask` against `password_hash`.  Without a hash the lock can only
+    /// be disabled in the user config, so `ask` is not called.
+    pub fn unlock<F>(&self, ask: F) -> Result<(), String>
+    where
+        F: FnOnce() -> io::Result<String>,
+    {
+        let expected: &String = self.password_hash.as_ref().ok_or(
+            "No password_hash in [lock], disable the lock in the user config instead.",
+        )?;
+        let password: String =
+            ask().map_err(|e| format!("Could not read password: {}", e))?;
+
+        if file::sha256(password.as_bytes()) == *expected {
+            Ok(())
+        } else {
+            Err("Wrong password.".into())
+        }
+    }
+}
+
+/// Check that the `arguments` for `retroarch` do not load any content themselves, which would
+/// get around the check of the game.
+pub fn check_arguments(arguments: &[String]) -> Result<(), String> {
+    if let Some(content) = passthrough::content_in(arguments).first() {
+        return Err(format!(
+            "Locked, content in the RetroArch arguments is not allowed: {}",
+            content
+        ));
+    }
+    if let Some(flag) = passthrough::flags_in(arguments)
+        .iter()
+        .find(|flag| CONTENT_FLAGS.contains(&flag.as_str()))
+    {
+        return Err(format!(
+            "Locked, RetroArch argument is not allowed: {}",
+            flag
+        ));
+    }
+
+    Ok(())
+}
+
+/// Paths in a playlist with one game per line.  Relative paths are joined to `base`.
+#[must_use]
+pub fn parse_playlist(content: &str, base: &Path) -> Vec<PathBuf> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base.join(file::tilde(Path::new(line))))
+        .collect()
+}
+
+/// Turn the echo of typed characters on the terminal `fd` on or off.
+fn set_echo(fd: i32, on: bool) -> io::Result<()> {
+    // SAFETY: `termios` is plain data and only changed after `tcgetattr()` filled it in.
+    unsafe {
+        let mut termios: libc::termios = mem::zeroed();
+        if libc::tcgetattr(fd, &mut termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if on {
+            termios.c_lflag |= libc::ECHO;
+        } else {
+            termios.c_lflag &= !libc::ECHO;
+        }
+        if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask for a password on the terminal without echo.  The terminal is used directly, as stdin
+/// could be a list of games.
+pub fn read_password(prompt: &str) -> io::Result<String> {
+    let tty = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")?;
+
+    write!(&tty, "{}", prompt)?;
+    set_echo(tty.as_raw_fd(), false)?;
+    let mut password: String = String::new();
+    let read = io::BufReader::new(&tty).read_line(&mut password);
+    set_echo(tty.as_raw_fd(), true)?;
+    writeln!(&tty)?;
+    read?;
+
+    Ok(password.trim_end_matches(['\n', '\r']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    use indexmap::map::IndexMap;
+
+    use crate::settings::file;
+
+    use super::Lock;
+
+    // Untested:
+    //  - set_echo()
+    //  - read_password()
+
+    fn strings(arguments: &[&str]) -> Vec<String> {
+        arguments.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn check_arguments() {
+        assert!(
+            super::check_arguments(&strings(&["-v", "-L", "a.so"])).is_ok()
+        );
+        assert!(
+            super::check_arguments(&strings(&["-v", "other.smc"])).is_err()
+        );
+        assert!(
+            super::check_arguments(&strings(&["--", "-other.smc"])).is_err()
+        );
+        assert!(
+            super::check_arguments(&strings(&["--subsystem", "sgb"])).is_err()
+        );
+        assert!(super::check_arguments(&strings(&["--menu"])).is_err());
+    }
+
+    #[test]
+    fn from_section() {
+        let section: IndexMap<String, String> = IndexMap::from([
+            ("enabled".to_string(), "yes".to_string()),
+            (
+                "directories".to_string(),
+                "/roms/kids \"/roms/party games\"".to_string(),
+            ),
+        ]);
+        let lock = Lock::from_section(&section).unwrap();
+
+        assert!(lock.enabled);
+        assert_eq!(
+            vec![
+                PathBuf::from("/roms/kids"),
+                PathBuf::from("/roms/party games")
+            ],
+            lock.directories
+        );
+
+        let section: IndexMap<String, String> =
+            IndexMap::from([("password".to_string(), "secret".to_string())]);
+        assert!(Lock::from_section(&section).is_err());
+    }
+
+    #[test]
+    fn parse_playlist() {
+        let content = "#EXTM3U\n\nmario.smc\n/roms/gb/tetris.gb\n";
+
+        assert_eq!(
+            vec![
+                PathBuf::from("/lists/mario.smc"),
+                PathBuf::from("/roms/gb/tetris.gb")
+            ],
+            super::parse_playlist(content, Path::new("/lists"))
+        );
+    }
+
+    #[test]
+    fn check_directories() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let mut lock = Lock {
+            directories: vec![PathBuf::from(dir).join("src")],
+            ..Lock::default()
+        };
+        let game = PathBuf::from(dir).join("src/main.rs");
+
+        assert!(lock.check(&game).is_ok());
+        assert!(lock.check(&PathBuf::from(dir).join("Cargo.toml")).is_err());
+
+        lock.directories.clear();
+        assert!(lock.check(&game).is_err());
+    }
+
+    #[test]
+    fn unlock() {
+        let lock = Lock {
+            password_hash: Some(file::sha256(b"secret")),
+            ..Lock::default()
+        };
+
+        assert!(lock.unlock(|| Ok("secret".to_string())).is_ok());
+        assert!(lock.unlock(|| Ok("guess".to_string())).is_err());
+        assert!(Lock::default()
+            .unlock(|| panic!("asked without password_hash"))
+            .is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::settings::coreinfo;
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use indexmap::map::IndexMap;
+
+/// Explanation of the markers printed below the table.
+pub const LEGEND: &str = "* supported and used by the extension rule\n\
+                          + supported, but the rule uses another core or there is none\n\
+                          ! used by the rule, but not supported by the core";
+
+/// A core alias of `[cores]` with the extensions of its core info file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Column {
+    pub alias: String,
+    pub libretro: PathBuf,
+    /// Lowercase and without the dot.  Empty without a core info file.
+    pub extensions: Vec<String>,
+}
+
+/// Marker of a cell for an extension and a core.
+fn marker(supported: bool, used: bool) -> char {
+    match (supported, used) {
+        (true, true) => '*',
+        (true, false) => '+',
+        (false, true) => '!',
+        (false, false) => '.',
+    }
+}
+
+/// Table of all extensions supported by the `columns` or used in the extension `rules`, one row
+/// each, against the core aliases.  The last column is the core the rule of the extension uses,
+/// or `-` without a rule.
+#[must_use]
+pub fn table(columns: &[Column], rules: &IndexMap<String, PathBuf>) -> String {
+    let rules: IndexMap<String, String> = rules
+        .iter()
+        .map(|(extension, libretro)| {
+            (extension.to_lowercase(), coreinfo::core_name(libretro))
+        })
+        .collect();
+    let extensions: BTreeSet<&str> = columns
+        .iter()
+        .flat_map(|column| column.extensions.iter().map(String::as_str))
+        .chain(rules.keys().map(String::as_str))
+        .collect();
+    let width: usize = extensions
+        .iter()
+        .map(|extension| extension.chars().count() + 1)
+        .max()
+        .unwrap_or(0)
+        .max("EXT".len());
+
+    let mut table: String = format!("{:<width$}", "EXT", width = width);
+    for column in columns {
+        table.push_str(&format!("  {}", column.alias));
+    }
+    table.push_str("  RULE");
+
+    for extension in extensions {
+        let rule: Option<&String> = rules.get(extension);
+        table.push_str(&format!(
+            "\n{:<width$}",
+            format!(".{}", extension),
+            width = width
+        ));
+        for column in columns {
+            let supported: bool =
+                column.extensions.iter().any(|e| e == extension);
+            let used: bool = rule.is_some_and(|core| {
+                *core == coreinfo::core_name(&column.libretro)
+            });
+            table.push_str(&format!(
+                "  {:<alias$}",
+                marker(supported, used),
+                alias = column.alias.chars().count()
+            ));
+        }
+        table.push_str(&format!("  {}", rule.map_or("-", String::as_str)));
+    }
+
+    table
+}
+
+/// Column of a core alias, with the extensions read from the core info file in `info_directory`.
+#[must_use]
+pub fn column(
+    alias: &str,
+    libretro: &Path,
+    info_directory: Option<&Path>,
+) -> Column {
+    Column {
+        alias: alias.to_string(),
+        libretro: libretro.to_path_buf(),
+        extensions: info_directory
+            .and_then(|dir| coreinfo::load(dir, libretro))
+            .map(|info| info.supported_extensions)
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+
+    use indexmap::map::IndexMap;
+
+    use super::Column;
+
+    // Untested:
+    //  - column()
+
+    #[test]
+    fn table() {
+        let columns: Vec<Column> = vec![
+            Column {
+                alias: "snes".to_string(),
+                libretro: PathBuf::from("/cores/snes9x_libretro.so"),
+                extensions: vec!["smc".to_string(), "sfc".to_string()],
+            },
+            Column {
+                alias: "bsnes".to_string(),
+                libretro: PathBuf::from("bsnes"),
+                extensions: vec!["sfc".to_string(), "bs".to_string()],
+            },
+        ];
+        let rules: IndexMap<String, PathBuf> = IndexMap::from([
+            ("SMC".to_string(), PathBuf::from("snes9x")),
+            (
+                "sfc".to_string(),
+                PathBuf::from("/cores/snes9x_libretro.so"),
+            ),
+            ("gb".to_string(), PathBuf::from("bsnes")),
+        ]);
+
+        assert_eq!(
+            "EXT   snes  bsnes  RULE\n\
+             .bs   .     +      -\n\
+             .gb   .     !      bsnes\n\
+             .sfc  *     +      snes9x\n\
+             .smc  *     .      snes9x",
+            super::table(&columns, &rules)
+        );
+    }
+}
@@ -0,0 +1,225 @@
+/// Version of the config schema this program reads.  Files without `version` key in section
+/// `[options]` are treated as version 1.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// A documented transformation from schema version `from` to the next.  It is applied to every
+/// line of the file individually and returns the replacement, if the line needs to change.
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(section: &str, line: &str) -> Option<String>,
+}
+
+/// All known migrations in ascending order of their version.
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "rename keys with dashes in [options] to use underscores, \
+                  like `retroarch-config` to `retroarch_config`",
+    apply: underscore_option_keys,
+}];
+
+/// Result of bringing a config up to date.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Migrated {
+    pub text: String,
+    pub from: u32,
+    pub applied: Vec<&'static str>,
+}
+
+impl Migrated {
+    /// Check if the original text was already at the current version.
+    #[must_use]
+    pub const fn is_current(&self) -> bool {
+        self.from == CURRENT_VERSION
+    }
+}
+
+/// Apply all migrations needed to bring the INI `text` to `CURRENT_VERSION`.  Comments and
+/// formatting of unchanged lines are kept, so the result can be written back to the file.  Error
+/// if the version is invalid or newer than supported.
+pub fn migrate(text: &str) -> Result<Migrated, String> {
+    let from: u32 = version(text)?;
+    if from > CURRENT_VERSION {
+        return Err(format!(
+            "Config version {} is newer than supported version {}.",
+            from, CURRENT_VERSION
+        ));
+    }
+
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let mut applied: Vec<&'static str> = vec![];
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from) {
+        let mut section: String = String::new();
+        for line in &mut lines {
+            if let Some(name) = section_name(line) {
+                section = name.to_string();
+            } else if let Some(new_line) = (migration.apply)(&section, line) {
+                *line = new_line;
+            }
+        }
+        applied.push(migration.description);
+    }
+    if from < CURRENT_VERSION {
+        set_version(&mut lines, CURRENT_VERSION);
+    }
+
+    let mut text: String = lines.join("\n");
+    text.push('\n');
+
+    Ok(Migrated {
+        text,
+        from,
+        applied,
+    })
+}
+
+/// Read the schema version from key `version` in section `[options]`.  Defaults to 1.
+fn version(text: &str) -> Result<u32, String> {
+    let mut section: &str = "";
+    for line in text.lines() {
+        if let Some(name) = section_name(line) {
+            section = name;
+        } else if section == "options" {
+            if let Some((key, value)) = split_key(line) {
+                if key.trim() == "version" {
+                    let value: &str = value[1..].trim();
+                    return value.parse().map_err(|_| {
+                        format!("Invalid config version: {}", value)
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(1)
+}
+
+/// Replace the `version` in section `[options]` or add it, creating the section if needed.
+fn set_version(lines: &mut Vec<String>, version: u32) {
+    let entry: String = format!("version = {}", version);
+    let mut section: &str = "";
+    let mut header: Option<usize> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(name) = section_name(line) {
+            section = name;
+            if section == "options" && header.is_none() {
+                header = Some(index);
+            }
+        } else if section == "options"
+            && split_key(line).is_some_and(|(key, _)| key.trim() == "version")
+        {
+            lines[index] = entry;
+            return;
+        }
+    }
+
+    match header {
+        Some(index) => lines.insert(index + 1, entry),
+        None => {
+            lines
+                .splice(0..0, ["[options]".to_string(), entry, String::new()]);
+        }
+    }
+}
+
+/// Name of the section, if the line is a header like `[options]`.
+fn section_name(line: &str) -> Option<&str> {
+    line.trim().strip_prefix('[')?.strip_suffix(']')
+}
+
+/// Split a key/value line at the first delimiter `=` or `:`.  The second part starts with the
+/// delimiter.  `None` for empty lines, comments and lines without delimiter.
+fn split_key(line: &str) -> Option<(&str, &str)> {
+    let trimmed: &str = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with(['#', ';']) {
+        return None;
+    }
+
+    line.find(['=', ':']).map(|index| line.split_at(index))
+}
+
+/// Migration from version 1: keys in `[options]` used the same names as the commandline options,
+/// but only the spelling with underscores was ever read.
+fn underscore_option_keys(section: &str, line: &str) -> Option<String> {
+    if section != "options" {
+        return None;
+    }
+
+    split_key(line)
+        .filter(|(key, _)| key.contains('-'))
+        .map(|(key, value)| format!("{}{}", key.replace('-', "_"), value))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::CURRENT_VERSION;
+
+    #[test]
+    fn migrate_version_1() {
+        let migrated = super::migrate(
+            "# comment-with-dash = 1\n\
+             [options]\n\
+             retroarch-config = /a-b.cfg\n\
+             libretro_directory : ~/cores\n\
+             \n\
+             [cores]\n\
+             snes-hd = snes9x\n",
+        )
+        .unwrap();
+
+        assert_eq!(1, migrated.from);
+        assert_eq!(1, migrated.applied.len());
+        assert_eq!(
+            format!(
+                "# comment-with-dash = 1\n\
+                 [options]\n\
+                 version = {}\n\
+                 retroarch_config = /a-b.cfg\n\
+                 libretro_directory : ~/cores\n\
+                 \n\
+                 [cores]\n\
+                 snes-hd = snes9x\n",
+                CURRENT_VERSION
+            ),
+            migrated.text
+        );
+    }
+
+    #[test]
+    fn migrate_without_options_section() {
+        let migrated = super::migrate("[cores]\nsnes = snes9x\n").unwrap();
+
+        assert_eq!(
+            format!(
+                "[options]\nversion = {}\n\n[cores]\nsnes = snes9x\n",
+                CURRENT_VERSION
+            ),
+            migrated.text
+        );
+    }
+
+    #[test]
+    fn migrate_current() {
+        let text: String = format!(
+            "[options]\nversion = {}\nretroarch-config = x\n",
+            CURRENT_VERSION
+        );
+        let migrated = super::migrate(&text).unwrap();
+
+        assert!(migrated.is_current());
+        assert!(migrated.applied.is_empty());
+        assert_eq!(text, migrated.text);
+    }
+
+    #[test]
+    fn migrate_invalid_version() {
+        assert!(super::migrate("[options]\nversion = two\n").is_err());
+        assert!(super::migrate(&format!(
+            "[options]\nversion = {}\n",
+            CURRENT_VERSION + 1
+        ))
+        .is_err());
+    }
+}
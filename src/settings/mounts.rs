@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Filesystems which can never be written to, regardless of the mount options.
+const READ_ONLY_TYPES: &[&str] = &["iso9660", "udf", "squashfs", "erofs"];
+
+/// A single mounted filesystem, as listed in `/proc/mounts`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub point: PathBuf,
+    pub fstype: String,
+    pub options: Vec<String>,
+}
+
+impl Mount {
+    /// Check if files on this mount cannot be written to.
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        self.options.iter().any(|option| option == "ro")
+            || READ_ONLY_TYPES.contains(&self.fstype.as_str())
+    }
+}
+
+/// Parse the content of `/proc/mounts`.  Spaces and other special characters in the mountpoint
+/// are encoded as octal escapes like `\040`, which are decoded.
+#[must_use]
+pub fn parse(content: &str) -> Vec<Mount> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let point: PathBuf = PathBuf::from(unescape(fields.next()?));
+            let fstype: String = fields.next()?.to_string();
+            let options: Vec<String> =
+                fields.next()?.split(',').map(ToString::to_string).collect();
+
+            Some(Mount {
+                point,
+                fstype,
+                options,
+            })
+        })
+        .collect()
+}
+
+/// The mount containing `path`, which is the one with the longest matching mountpoint.  Later
+/// mounts on the same point hide earlier ones.
+#[must_use]
+pub fn find<'a>(mounts: &'a [Mount], path: &Path) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|mount| path.starts_with(&mount.point))
+        .max_by_key(|mount| mount.point.components().count())
+}
+
+/// The mount containing `path` on this system.  `path` should be absolute.
+#[must_use]
+pub fn mount_of(path: &Path) -> Option<Mount> {
+    let content: String = fs::read_to_string("/proc/mounts").ok()?;
+
+    find(&parse(&content), path).cloned()
+}
+
+fn unescape(text: &str) -> String {
+    let mut result: String = String::new();
+    let mut rest: &str = text;
+
+    while let Some(index) = rest.find('\\') {
+        result.push_str(&rest[..index]);
+        let code: &str = rest.get(index + 1..index + 4).unwrap_or_default();
+        match u8::from_str_radix(code, 8) {
+            Ok(byte) if code.len() == 3 => {
+                result.push(char::from(byte));
+                rest = &rest[index + 4..];
+            }
+            _ => {
+                result.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    // Untested:
+    //  - mount_of()
+
+    const MOUNTS: &str = "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+/dev/sr0 /run/media/user/GAMES iso9660 ro,nosuid,nodev 0 0
+nas:/roms /mnt/nas\\040roms nfs4 ro,relatime 0 0
+/dev/loop3 /mnt/nas\\040roms/psx squashfs rw 0 0
+/dev/sdb1 /mnt/usb vfat rw,noexec 0 0
+";
+
+    #[test]
+    fn parse_unescapes_mountpoint() {
+        let mounts = super::parse(MOUNTS);
+
+        assert_eq!(5, mounts.len());
+        assert_eq!(PathBuf::from("/mnt/nas roms"), mounts[2].point);
+        assert_eq!(vec!["ro", "relatime"], mounts[2].options);
+    }
+
+    #[test]
+    fn find_read_only() {
+        let mounts = super::parse(MOUNTS);
+        let read_only = |path: &str| {
+            super::find(&mounts, Path::new(path))
+                .unwrap()
+                .is_read_only()
+        };
+
+        assert!(!read_only("/home/user/roms/mario.smc"));
+        assert!(read_only("/run/media/user/GAMES/zelda.sfc"));
+        assert!(read_only("/mnt/nas roms/snes/mario.smc"));
+        assert!(read_only("/mnt/nas roms/psx/ff7.chd"));
+        assert!(!read_only("/mnt/usb/sonic.md"));
+        assert!(!read_only("/mnt/usbstick/sonic.md"));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::settings::queue;
+use crate::settings::queue::format_duration;
+
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use notify_rust::Notification;
+
+/// Minimum running time of a game, before an abnormal exit is reported.  Shorter runs usually
+/// happen while the user is still in front of the screen.
+pub const LONG_RUN: Duration = Duration::from_secs(30);
+
+/// Show a desktop notification with a title and a text body.
+pub fn send(summary: &str, body: &str) -> Result<(), String> {
+    Notification::new()
+        .appname("enjoy")
+        .summary(summary)
+        .body(body)
+        .show()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Title and body of the notification after a single game with the display `title` exited.
+/// `None` if the game exited successfully or was not running for at least `LONG_RUN`.
+#[must_use]
+pub fn run_message(
+    title: &str,
+    status: &ExitStatus,
+    duration: Duration,
+) -> Option<(String, String)> {
+    if status.success() || duration < LONG_RUN {
+        return None;
+    }
+
+    Some((
+        "RetroArch exited abnormally".to_string(),
+        format!("{} ({}) after {}", title, status, format_duration(duration)),
+    ))
+}
+
+/// Title and body of the notification after all games of a queue were run.
+#[must_use]
+pub fn queue_message(summary: &queue::Summary) -> (String, String) {
+    ("Queue finished".to_string(), summary.totals())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+    use std::time::Duration;
+
+    use crate::settings::queue::Summary;
+
+    // Untested:
+    //  - send()
+
+    #[test]
+    fn run_message_abnormal() {
+        let status = ExitStatus::from_raw(256);
+
+        let (_, body) =
+            super::run_message("Mario", &status, Duration::from_secs(600))
+                .unwrap();
+
+        assert_eq!("Mario (exit status: 1) after 0:10:00", body);
+    }
+
+    #[test]
+    fn run_message_short_or_success() {
+        assert_eq!(
+            None,
+            super::run_message(
+                "Mario",
+                &ExitStatus::from_raw(256),
+                Duration::from_secs(5)
+            )
+        );
+        assert_eq!(
+            None,
+            super::run_message(
+                "Mario",
+                &ExitStatus::from_raw(0),
+                Duration::from_secs(600)
+            )
+        );
+    }
+
+    #[test]
+    fn queue_message() {
+        let (_, body) = super::queue_message(&Summary::new());
+
+        assert_eq!("0 games, 0 succeeded, 0 failed, total time 0:00:00", body);
+    }
+}
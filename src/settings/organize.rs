@@ -0,0 +1,101 @@
+use crate::settings::coreinfo;
+
+use std::fs;
+use std::io;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Outcome of linking a single game into the organized tree.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Linked {
+    /// A new symlink was created.
+    Created,
+    /// The symlink already existed and points to the same game.
+    Unchanged,
+    /// Another file with the same name is in the way, it is left untouched.
+    Conflict,
+}
+
+/// Name of the folder for a game.  It is the system name from the core info file, like
+/// "Nintendo - Super Nintendo Entertainment System", or the name of the core itself if there is
+/// no info.  Slashes are replaced, so the name is always a single folder.
+#[must_use]
+pub fn group_name(system: Option<String>, libretro: &Path) -> String {
+    let name: String = system
+        .filter(|system| !system.trim().is_empty())
+        .unwrap_or_else(|| coreinfo::core_name(libretro));
+
+    name.replace('/', "-")
+}
+
+/// Create a symlink to `game` inside folder `group` of `dir`, named like the game itself.  The
+/// folders are created as needed.  Returns the path of the link and what happened.
+pub fn link(
+    dir: &Path,
+    group: &str,
+    game: &Path,
+) -> io::Result<(PathBuf, Linked)> {
+    let file_name = game.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "game has no filename")
+    })?;
+    let folder: PathBuf = dir.join(group);
+    let link: PathBuf = folder.join(file_name);
+
+    if fs::symlink_metadata(&link).is_ok() {
+        let linked: Linked =
+            if fs::read_link(&link).is_ok_and(|target| target == game) {
+                Linked::Unchanged
+            } else {
+                Linked::Conflict
+            };
+        return Ok((link, linked));
+    }
+
+    fs::create_dir_all(&folder)?;
+    symlink(game, &link)?;
+
+    Ok((link, Linked::Created))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+
+    // Untested:
+    //  - link()
+
+    #[test]
+    fn group_name_system() {
+        assert_eq!(
+            "Sega - Mega-CD - Sega CD",
+            super::group_name(
+                Some("Sega - Mega-CD / Sega CD".to_string()),
+                &PathBuf::from("genesis_plus_gx_libretro.so")
+            )
+        );
+        assert_eq!(
+            "A-B",
+            super::group_name(Some("A/B".to_string()), &PathBuf::from("x"))
+        );
+    }
+
+    #[test]
+    fn group_name_fallback() {
+        assert_eq!(
+            "snes9x",
+            super::group_name(
+                None,
+                &PathBuf::from("/cores/snes9x_libretro.so")
+            )
+        );
+        assert_eq!(
+            "snes9x",
+            super::group_name(
+                Some(" ".to_string()),
+                &PathBuf::from("snes9x_libretro.so")
+            )
+        );
+    }
+}
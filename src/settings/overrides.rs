@@ -0,0 +1,194 @@
+use crate::settings::file;
+
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+
+use indexmap::map::IndexMap;
+
+/// Collection of `retroarch.cfg` keys, which are applied on top of the regular configuration for
+/// a single run.  All sources are composed into one file and handed over to `RetroArch` with
+/// `--appendconfig`.  Later values of the same key replace earlier ones.
+///
+/// ```ini
+/// [retroarch]
+/// video_driver = vulkan
+/// video_shader_enable = true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Overrides {
+    pub entries: IndexMap<String, String>,
+}
+
+impl Overrides {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            entries: IndexMap::new(),
+        }
+    }
+
+    /// Add a single key, replacing any previous value.
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.entries.insert(key.to_string(), value.to_string());
+    }
+
+    /// Add all keys from another source, replacing previous values.
+    pub fn extend(&mut self, entries: &IndexMap<String, String>) {
+        for (key, value) in entries {
+            self.set(key, value);
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Content of the append-config in `retroarch.cfg` format, one `key = "value"` per line.
+    #[must_use]
+    pub fn to_cfg(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(key, value)| {
+                format!("{} = \"{}\"\n", key, value.replace('"', ""))
+            })
+            .collect()
+    }
+
+    /// Filename derived from the content.  Identical overrides always get the same name, which
+    /// keeps the fingerprint of a command stable.
+    #[must_use]
+    pub fn file_name(&self) -> String {
+        self.file_name_with("override")
+    }
+
+    /// Same as `file_name()`, but starting with another `prefix`, for other files in the same
+    /// format.
+    #[must_use]
+    pub fn file_name_with(&self, prefix: &str) -> String {
+        format!(
+            "{}-{:016x}.cfg",
+            prefix,
+            file::fnv1a(self.to_cfg().as_bytes())
+        )
+    }
+
+    /// Write the append-config into `dir`, which should come from `create_temp_dir()`.  The file
+    /// is always written from scratch with mode `0600`, an existing file is replaced.
+    pub fn write(&self, dir: &Path) -> io::Result<PathBuf> {
+        self.write_with(dir, "override")
+    }
+
+    /// Same as `write()`, but the filename starts with `prefix`, see `file_name_with()`.
+    pub fn write_with(&self, dir: &Path, prefix: &str) -> io::Result<PathBuf> {
+        let path: PathBuf = dir.join(self.file_name_with(prefix));
+
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => (),
+        }
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)?
+            .write_all(self.to_cfg().as_bytes())?;
+
+        Ok(path)
+    }
+}
+
+/// File in the temporary directory of a process, which marks it as kept with `--keep-overrides`.
+pub const KEEP_MARKER: &str = ".keep";
+
+/// Directory for temporary files of the current process, like `$XDG_RUNTIME_DIR/enjoy/1234`.
+/// The directory is not created, see `create_temp_dir()`.
+pub fn temp_dir() -> PathBuf {
+    file::runtime_dir().join(process::id().to_string())
+}
+
+/// Create the directory for temporary files of the current process.  It and the runtime
+/// directory it lives in must be private to the current user.
+pub fn create_temp_dir() -> io::Result<PathBuf> {
+    let dir: PathBuf = temp_dir();
+
+    file::private_dir(&file::runtime_dir())?;
+    file::private_dir(&dir)?;
+
+    Ok(dir)
+}
+
+/// Removes the temporary directory of the current process when dropped, unless the files should
+/// be kept.  So the files are also removed, if the program stops early because of an error.
+#[derive(Debug)]
+pub struct TempDirGuard {
+    pub keep: bool,
+}
+
+impl Drop for TempDirGuard {
+    fn drop(&mut self) {
+        if !self.keep {
+            if let Err(e) = cleanup() {
+                eprintln!("Could not remove temporary override files: {}", e);
+            }
+        } else if temp_dir().is_dir() {
+            let _ = fs::write(temp_dir().join(KEEP_MARKER), "");
+        }
+    }
+}
+
+/// Remove the temporary directory of the current process with all its content.  Does nothing if
+/// it does not exist.
+pub fn cleanup() -> io::Result<()> {
+    let dir: PathBuf = temp_dir();
+
+    if dir.is_dir() {
+        fs::remove_dir_all(dir)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Overrides;
+
+    // Untested:
+    //  - write()
+    //  - create_temp_dir()
+    //  - cleanup()
+
+    #[test]
+    fn to_cfg_replaces_keys() {
+        let mut overrides = Overrides::new();
+        overrides.set("video_driver", "gl");
+        overrides.set("video_vsync", "true");
+        overrides.set("video_driver", "vulkan");
+
+        assert_eq!(
+            "video_driver = \"vulkan\"\nvideo_vsync = \"true\"\n",
+            overrides.to_cfg()
+        );
+    }
+
+    #[test]
+    fn file_name_deterministic() {
+        let mut first = Overrides::new();
+        first.set("video_driver", "vulkan");
+        let mut second = Overrides::new();
+        second.set("video_driver", "vulkan");
+        let mut third = Overrides::new();
+        third.set("video_driver", "gl");
+
+        assert_eq!(first.file_name(), second.file_name());
+        assert_ne!(first.file_name(), third.file_name());
+        assert!(first.file_name().starts_with("override-"));
+        assert!(first.file_name().ends_with(".cfg"));
+    }
+}
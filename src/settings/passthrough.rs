@@ -0,0 +1,372 @@
+use crate::settings::retroarch::Version;
+
+/// A commandline flag of `retroarch`, with its short and long form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flag {
+    pub short: Option<char>,
+    pub long: &'static str,
+    /// The flag needs a value, like `--libretro core.so`.
+    pub takes_value: bool,
+    /// The first version of `RetroArch` with the flag.  `None` if every version has it.  Old
+    /// distribution packages would abort with an unknown option, if it is used anyway.
+    pub since: Option<Version>,
+}
+
+impl Flag {
+    const fn since(self, major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            since: Some(Version(major, minor, patch)),
+            ..self
+        }
+    }
+
+    /// Check if the flag is available in given `version`.  An unknown version is assumed to
+    /// support everything, because it cannot be decided.
+    #[must_use]
+    pub fn is_supported(&self, version: Option<Version>) -> bool {
+        match (self.since, version) {
+            (Some(since), Some(version)) => version >= since,
+            _ => true,
+        }
+    }
+}
+
+const fn flag(
+    short: Option<char>,
+    long: &'static str,
+    takes_value: bool,
+) -> Flag {
+    Flag {
+        short,
+        long,
+        takes_value,
+        since: None,
+    }
+}
+
+/// All flags `retroarch --help` lists in current versions, with the version they were added in.
+pub const FLAGS: &[Flag] = &[
+    flag(Some('h'), "--help", false),
+    flag(None, "--version", false),
+    flag(None, "--features", false),
+    flag(None, "--menu", false),
+    flag(Some('L'), "--libretro", true),
+    flag(Some('c'), "--config", true),
+    flag(None, "--appendconfig", true),
+    flag(Some('f'), "--fullscreen", false),
+    flag(Some('v'), "--verbose", false),
+    flag(None, "--log-file", true).since(1, 7, 6),
+    flag(Some('s'), "--save", true),
+    flag(Some('S'), "--savestate", true),
+    flag(Some('e'), "--entryslot", true).since(1, 15, 0),
+    flag(None, "--subsystem", true).since(1, 7, 5),
+    flag(Some('N'), "--nodevice", true),
+    flag(Some('A'), "--dualanalog", true),
+    flag(Some('d'), "--device", true),
+    flag(Some('P'), "--bsvplay", true),
+    flag(Some('R'), "--bsvrecord", true),
+    flag(None, "--sram-mode", true).since(1, 7, 0),
+    flag(Some('H'), "--host", false),
+    flag(Some('C'), "--connect", true),
+    flag(None, "--port", true),
+    flag(None, "--stateless", false),
+    flag(None, "--check-frames", true),
+    flag(None, "--nick", true),
+    flag(None, "--spectate", false),
+    flag(None, "--command", true),
+    flag(Some('r'), "--record", true),
+    flag(None, "--recordconfig", true),
+    flag(None, "--size", true),
+    flag(None, "--set-shader", true),
+    flag(None, "--ups", true),
+    flag(None, "--bps", true),
+    flag(None, "--ips", true),
+    flag(None, "--no-patch", false),
+    flag(Some('D'), "--detach", false),
+    flag(None, "--max-frames", true),
+    flag(None, "--max-frames-ss", false).since(1, 7, 8),
+    flag(None, "--max-frames-ss-path", true).since(1, 7, 8),
+    flag(None, "--eof-exit", false).since(1, 9, 1),
+    flag(None, "--accessibility", false).since(1, 8, 2),
+    flag(None, "--load-menu-on-error", false).since(1, 10, 0),
+];
+
+/// Lookup a flag by its short form like `-L` or long form like `--libretro`.
+#[must_use]
+pub fn find(name: &str) -> Option<&'static Flag> {
+    FLAGS.iter().find(|flag| {
+        flag.long == name
+            || flag.short.map(|short| format!("-{}", short)).as_deref()
+                == Some(name)
+    })
+}
+
+/// Names of all flags with their long form in `arguments`, in order.  Values of flags are
+/// skipped.  Unknown flags are listed as written, without a value attached with an equal sign.
+#[must_use]
+pub fn flags_in(arguments: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    let mut iter = arguments.iter();
+
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            continue;
+        }
+        if arg == "--" {
+            break;
+        }
+        let (name, attached): (Vec<String>, bool) = split_flag(arg);
+        let last: Option<&Flag> = name.last().and_then(|n| find(n));
+        names
+            .extend(name.iter().map(|n| {
+                find(n).map_or_else(|| n.clone(), |f| f.long.into())
+            }));
+        if last.is_some_and(|flag| flag.takes_value) && !attached {
+            iter.next();
+        }
+    }
+
+    names
+}
+
+/// Content files given in `arguments`, which are the arguments that are neither a flag nor the
+/// value of a flag.  Everything after `--` is content too.
+#[must_use]
+pub fn content_in(arguments: &[String]) -> Vec<String> {
+    let mut content: Vec<String> = vec![];
+    let mut iter = arguments.iter();
+
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            content.extend(iter.by_ref().cloned());
+            break;
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            content.push(arg.clone());
+            continue;
+        }
+        let (name, attached): (Vec<String>, bool) = split_flag(arg);
+        if name
+            .last()
+            .and_then(|n| find(n))
+            .is_some_and(|flag| flag.takes_value)
+            && !attached
+        {
+            iter.next();
+        }
+    }
+
+    content
+}
+
+/// Check if the `version` of `retroarch` is needed to check the `arguments`, because a flag is
+/// unknown or not available in all versions.  Probing the version starts `retroarch`, so this
+/// avoids it for the common flags.
+#[must_use]
+pub fn needs_version(arguments: &[String]) -> bool {
+    flags_in(arguments)
+        .iter()
+        .any(|name| find(name).map_or(true, |flag| flag.since.is_some()))
+}
+
+/// Check the `arguments` given to `retroarch` by the user.  `set_by_enjoy` are the arguments this
+/// program already adds to the commandline.  Returns a warning for each unknown flag, with the
+/// closest flag available in `version` as a suggestion, and for each flag with a value also set
+/// by this program, because `retroarch` only uses one of them.  Flags too new for `version` are
+/// not warned about here, they are removed by `retroarch::filter_unsupported()`.
+#[must_use]
+pub fn check(
+    arguments: &[String],
+    set_by_enjoy: &[String],
+    version: Option<Version>,
+) -> Vec<String> {
+    let enjoy: Vec<String> = flags_in(set_by_enjoy);
+    let mut warnings: Vec<String> = vec![];
+
+    for name in flags_in(arguments) {
+        match find(&name) {
+            Some(flag) if !flag.is_supported(version) => (),
+            Some(flag) if flag.takes_value && enjoy.contains(&name) => {
+                warnings.push(format!(
+                    "{} is already set by enjoy, only one of them is used",
+                    name
+                ));
+            }
+            Some(_) => (),
+            None => warnings.push(match suggest(&name, version) {
+                Some(known) => {
+                    format!("unknown flag {}, did you mean {}?", name, known)
+                }
+                None => format!("unknown flag {}", name),
+            }),
+        }
+    }
+
+    warnings
+}
+
+// Split an argument into the names of its flags and if a value is attached.  Long flags can have
+// their value attached with an equal sign like `--entryslot=2`.  Short flags can be combined like
+// `-fv`, where the first flag taking a value takes the rest as its value, like `-Lcore.so`.
+fn split_flag(arg: &str) -> (Vec<String>, bool) {
+    if arg.starts_with("--") {
+        return match arg.split_once('=') {
+            Some((name, _)) => (vec![name.to_string()], true),
+            None => (vec![arg.to_string()], false),
+        };
+    }
+
+    let mut names: Vec<String> = vec![];
+    let shorts: &str = &arg[1..];
+    for (index, short) in shorts.char_indices() {
+        let name: String = format!("-{}", short);
+        let takes_value: bool = find(&name).is_some_and(|f| f.takes_value);
+        names.push(name);
+        if takes_value {
+            return (names, index + short.len_utf8() < shorts.len());
+        }
+    }
+
+    (names, false)
+}
+
+// The long flag available in `version` closest to an unknown `name`, if it looks like a typo of
+// it.
+fn suggest(name: &str, version: Option<Version>) -> Option<&'static str> {
+    let name: String = format!("--{}", name.trim_start_matches('-'));
+
+    FLAGS
+        .iter()
+        .filter(|flag| flag.is_supported(version))
+        .map(|flag| (distance(&name, flag.long), flag.long))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, long)| long)
+}
+
+// Number of single character edits to turn `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous: usize = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current: usize = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(current)
+            };
+            previous = current;
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::settings::retroarch::Version;
+
+    fn strings(arguments: &[&str]) -> Vec<String> {
+        arguments.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn flags_in_skips_values() {
+        let arguments =
+            strings(&["-fv", "-L", "core.so", "--entryslot=2", "-Lcore.so"]);
+
+        assert_eq!(
+            vec![
+                "--fullscreen",
+                "--verbose",
+                "--libretro",
+                "--entryslot",
+                "--libretro"
+            ],
+            super::flags_in(&arguments)
+        );
+    }
+
+    #[test]
+    fn content_in() {
+        let arguments = strings(&[
+            "-fv",
+            "-L",
+            "core.so",
+            "game.smc",
+            "--entryslot=2",
+            "--",
+            "-other.smc",
+        ]);
+
+        assert_eq!(
+            vec!["game.smc", "-other.smc"],
+            super::content_in(&arguments)
+        );
+    }
+
+    #[test]
+    fn check_unknown_and_conflicts() {
+        let arguments =
+            strings(&["--fulscreen", "-L", "other.so", "-f", "--xyz"]);
+        let enjoy = strings(&["mario.smc", "--libretro", "snes.so", "-f"]);
+
+        assert_eq!(
+            vec![
+                "unknown flag --fulscreen, did you mean --fullscreen?",
+                "--libretro is already set by enjoy, only one of them is used",
+                "unknown flag --xyz",
+            ],
+            super::check(&arguments, &enjoy, None)
+        );
+    }
+
+    #[test]
+    fn check_by_version() {
+        let arguments = strings(&["--entryslot", "2", "--eofexit"]);
+        let enjoy = strings(&["mario.smc", "--entryslot", "1"]);
+
+        assert_eq!(
+            vec!["unknown flag --eofexit"],
+            super::check(&arguments, &enjoy, Some(Version(1, 9, 0)))
+        );
+        assert_eq!(
+            vec![
+                "--entryslot is already set by enjoy, only one of them is used",
+                "unknown flag --eofexit, did you mean --eof-exit?",
+            ],
+            super::check(&arguments, &enjoy, Some(Version(1, 15, 0)))
+        );
+    }
+
+    #[test]
+    fn flag_is_supported() {
+        let entryslot = super::find("-e").unwrap();
+
+        assert!(!entryslot.is_supported(Some(Version(1, 14, 0))));
+        assert!(entryslot.is_supported(Some(Version(1, 15, 0))));
+        assert!(entryslot.is_supported(None));
+        assert!(super::find("--verbose")
+            .unwrap()
+            .is_supported(Some(Version(1, 0, 0))));
+    }
+
+    #[test]
+    fn needs_version() {
+        assert!(!super::needs_version(&strings(&["-v", "-L", "core.so"])));
+        assert!(super::needs_version(&strings(&["--entryslot=2"])));
+        assert!(super::needs_version(&strings(&["--xyz"])));
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(0, super::distance("--menu", "--menu"));
+        assert_eq!(1, super::distance("--verbos", "--verbose"));
+        assert_eq!(2, super::distance("--cofnig", "--config"));
+    }
+}
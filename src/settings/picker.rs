@@ -0,0 +1,152 @@
+use crate::settings::program::Program;
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::process::Stdio;
+
+/// Terminal the picker is drawn on and reads the keys from.  Stdin may be the list of games.
+pub const TTY: &str = "/dev/tty";
+
+/// Let the user choose one of `items` by number, from a numbered list written to `output`.  The
+/// question is repeated until a number of the list is given.  An empty answer or the end of
+/// `input` cancels with `None`.
+pub fn choose(
+    items: &[String],
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<Option<usize>> {
+    let width: usize = items.len().to_string().len();
+    for (number, item) in items.iter().enumerate() {
+        writeln!(output, "{:>width$}) {}", number + 1, item, width = width)?;
+    }
+
+    loop {
+        write!(output, "Choose a game [1-{}]: ", items.len())?;
+        output.flush()?;
+        let mut answer: String = String::new();
+        if input.read_line(&mut answer)? == 0 || answer.trim().is_empty() {
+            return Ok(None);
+        }
+        match answer.trim().parse::<usize>() {
+            Ok(number) if (1..=items.len()).contains(&number) => {
+                return Ok(Some(number - 1));
+            }
+            _ => writeln!(output, "No game with number: {}", answer.trim())?,
+        }
+    }
+}
+
+/// Same as `choose()` on the terminal, as stdin may be the list of games.
+pub fn choose_on_tty(items: &[String]) -> io::Result<Option<usize>> {
+    let mut tty: File = OpenOptions::new().read(true).write(true).open(TTY)?;
+    let mut input = BufReader::new(tty.try_clone()?);
+
+    choose(items, &mut input, &mut tty)
+}
+
+/// Let the external `selector_command` choose one of `items`, like `fzf` or `rofi -dmenu`.  It
+/// gets one entry per line on stdin and must print the chosen line.  Exiting with an error or
+/// without output cancels with `None`.  The command is split like in a shell, without running
+/// one.
+pub fn select_with(
+    selector_command: &str,
+    items: &[String],
+) -> Result<Option<usize>, String> {
+    let mut child = Program::parse("selector_command", selector_command)?
+        .command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not run selector_command: {}", e))?;
+
+    // A selector may exit before reading all entries, so errors on writing are ignored.
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(items.join("\n").as_bytes());
+        let _ = stdin.write_all(b"\n");
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Could not run selector_command: {}", e))?;
+    let chosen = String::from_utf8_lossy(&output.stdout);
+    let chosen: &str = chosen.trim_end_matches(['\r', '\n']);
+
+    if !output.status.success() || chosen.is_empty() {
+        return Ok(None);
+    }
+    match items.iter().position(|item| item == chosen) {
+        Some(index) => Ok(Some(index)),
+        None => Err(format!(
+            "selector_command printed an unknown game: {}",
+            chosen
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+
+    // Untested:
+    //  - choose_on_tty()
+
+    fn test_items() -> Vec<String> {
+        [
+            "Super Mario World.smc",
+            "Mario Paint.smc",
+            "Zelda.smc",
+            "Super Metroid.smc",
+        ]
+        .iter()
+        .map(|item| item.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn select_with() {
+        let items = test_items();
+
+        assert_eq!(Ok(Some(3)), super::select_with("tail -n 1", &items));
+        assert_eq!(Ok(None), super::select_with("false", &items));
+        assert_eq!(Ok(None), super::select_with("grep Sonic", &items));
+        assert!(super::select_with("echo 'Sonic.md'", &items).is_err());
+        assert!(super::select_with("", &items).is_err());
+    }
+
+    #[test]
+    fn choose() {
+        let items = test_items();
+        let mut output: Vec<u8> = vec![];
+
+        let chosen = super::choose(
+            &items,
+            &mut Cursor::new("0\nzelda\n3\n"),
+            &mut output,
+        );
+
+        assert_eq!(Some(2), chosen.unwrap());
+        assert_eq!(
+            "1) Super Mario World.smc\n\
+             2) Mario Paint.smc\n\
+             3) Zelda.smc\n\
+             4) Super Metroid.smc\n\
+             Choose a game [1-4]: No game with number: 0\n\
+             Choose a game [1-4]: No game with number: zelda\n\
+             Choose a game [1-4]: ",
+            String::from_utf8_lossy(&output)
+        );
+        assert_eq!(
+            None,
+            super::choose(&items, &mut Cursor::new("\n"), &mut vec![])
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            super::choose(&items, &mut Cursor::new(""), &mut vec![]).unwrap()
+        );
+    }
+}
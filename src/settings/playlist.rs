@@ -0,0 +1,90 @@
+use crate::settings::file;
+use crate::settings::inoutput::json_string;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Name of the playlist in `RetroArch`, which holds the game for `--menu-load`.  It is replaced
+/// on each launch.
+pub const FILE_NAME: &str = "enjoy.lpl";
+
+/// Directory of the playlists, if key `playlist_directory` of `retroarch.cfg` is not set.  Like
+/// `RetroArch` it is next to its config, or in its default config directory without one.
+#[must_use]
+pub fn default_directory(retroarch_config: Option<&Path>) -> PathBuf {
+    match retroarch_config.and_then(Path::parent) {
+        Some(dir) => dir.join("playlists"),
+        None => file::tilde(Path::new("~/.config/retroarch/playlists")),
+    }
+}
+
+/// Content of a playlist in the JSON format of `RetroArch` with a single game, which is started
+/// with the core `libretro` when selected in the menu.
+#[must_use]
+pub fn to_json(
+    game: &Path,
+    label: &str,
+    libretro: &Path,
+    core_name: &str,
+) -> String {
+    let text = |path: &Path| json_string(&path.display().to_string());
+
+    format!(
+        "{{\n  \"version\": \"1.5\",\n  \"default_core_path\": \"\",\n  \
+         \"default_core_name\": \"\",\n  \"items\": [\n    {{\n      \
+         \"path\": {},\n      \"label\": {},\n      \"core_path\": {},\n      \
+         \"core_name\": {},\n      \"crc32\": \"DETECT\",\n      \
+         \"db_name\": {}\n    }}\n  ]\n}}\n",
+        text(game),
+        json_string(label),
+        text(libretro),
+        json_string(core_name),
+        json_string(FILE_NAME)
+    )
+}
+
+/// Write the playlist into `dir`, which is created if needed.
+pub fn write(dir: &Path, content: &str) -> io::Result<PathBuf> {
+    let path: PathBuf = dir.join(FILE_NAME);
+    fs::create_dir_all(dir)?;
+    fs::write(&path, content)?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    // Untested:
+    //  - write()
+
+    #[test]
+    fn default_directory() {
+        assert_eq!(
+            PathBuf::from("/home/user/.config/retroarch/playlists"),
+            super::default_directory(Some(Path::new(
+                "/home/user/.config/retroarch/retroarch.cfg"
+            )))
+        );
+    }
+
+    #[test]
+    fn to_json() {
+        let json = super::to_json(
+            Path::new("/roms/Mario \"World\".smc"),
+            "Mario",
+            Path::new("/cores/snes9x_libretro.so"),
+            "Snes9x",
+        );
+
+        assert!(json.contains("\"path\": \"/roms/Mario \\\"World\\\".smc\""));
+        assert!(json.contains("\"core_path\": \"/cores/snes9x_libretro.so\""));
+        assert!(json.contains("\"core_name\": \"Snes9x\""));
+        assert!(json.contains("\"db_name\": \"enjoy.lpl\""));
+    }
+}
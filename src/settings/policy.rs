@@ -0,0 +1,347 @@
+use crate::settings::coreinfo;
+use crate::settings::resources;
+use crate::settings::resources::Battery;
+
+use std::path::Path;
+use std::str::FromStr;
+
+use indexmap::map::IndexMap;
+use wildmatch::WildMatch;
+
+/// What happens when the battery or free memory is below the limits of the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LowResources {
+    /// Print a warning and launch anyway.
+    #[default]
+    Warn,
+    /// Refuse to launch, unless forced.
+    Abort,
+}
+
+impl FromStr for LowResources {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "warn" => Ok(Self::Warn),
+            "abort" => Ok(Self::Abort),
+            _ => Err(format!(
+                "Unknown on_low_resources \"{}\", expected one of: warn, abort",
+                text
+            )),
+        }
+    }
+}
+
+/// Which cores may be used, from section `[policy]` of the user config.  Useful for shared or
+/// kid setups to forbid certain systems.  Cores are named by their alias in `[cores]`, their
+/// core name like `snes9x` or their filename, each can contain wildcards `*` and `?`.
+///
+/// On laptops and handhelds it also checks the battery and free memory before a launch, so heavy
+/// cores do not run out of either mid-session.  A key `min_free_memory.CORE` sets the memory for
+/// matching cores, where the highest of all matches is required.
+///
+/// ```ini
+/// [policy]
+/// blocked_cores = psx mupen64plus_next
+/// allowed_cores = snes gb*
+/// min_battery = 20
+/// min_free_memory = 512M
+/// min_free_memory.dolphin = 4G
+/// on_low_resources = abort
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Cores which can never be used.
+    pub blocked_cores: Vec<String>,
+    /// If set, only these cores can be used.
+    pub allowed_cores: Option<Vec<String>>,
+    /// Charge of the battery in percent, below which running on battery is too low.
+    pub min_battery: Option<u8>,
+    /// Bytes of free memory required for the cores matching each pattern.  The key `*` is used
+    /// for `min_free_memory` without a core.
+    pub min_free_memory: IndexMap<String, u64>,
+    pub on_low_resources: LowResources,
+}
+
+impl Policy {
+    /// Read the policy from the keys of section `[policy]`.  Lists are separated by spaces or
+    /// commas.
+    pub fn from_section(
+        section: &IndexMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut policy: Self = Self::default();
+
+        for (key, value) in section {
+            let list: Vec<String> = value
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|name| !name.is_empty())
+                .map(ToString::to_string)
+                .collect();
+            match key.as_str() {
+                "blocked_cores" => policy.blocked_cores = list,
+                "allowed_cores" => policy.allowed_cores = Some(list),
+                "min_battery" => {
+                    policy.min_battery =
+                        Some(value.trim().parse().map_err(|_| {
+                            format!(
+                                "Invalid min_battery in [policy]: {}",
+                                value
+                            )
+                        })?);
+                }
+                "min_free_memory" => {
+                    policy.min_free_memory.insert(
+                        "*".to_string(),
+                        resources::parse_size(value)?,
+                    );
+                }
+                "on_low_resources" => {
+                    policy.on_low_resources = value.trim().parse()?;
+                }
+                _ => match key.strip_prefix("min_free_memory.") {
+                    Some(core) if !core.is_empty() => {
+                        policy.min_free_memory.insert(
+                            core.to_string(),
+                            resources::parse_size(value)?,
+                        );
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Unknown key in [policy]: {}",
+                            key
+                        ))
+                    }
+                },
+            }
+        }
+
+        Ok(policy)
+    }
+
+    /// Check if the core `libretro` may be used.  `aliases` are the names in `[cores]` pointing
+    /// to the same core.  A blocked core is never allowed, even if it is in the allowed list.
+    pub fn check(
+        &self,
+        libretro: &Path,
+        aliases: &[String],
+    ) -> Result<(), String> {
+        let core: String = coreinfo::core_name(libretro);
+        let names: Vec<String> = core_names(libretro, aliases);
+        let listed = |patterns: &[String]| {
+            patterns.iter().any(|pattern| matches(pattern, &names))
+        };
+
+        if listed(&self.blocked_cores) {
+            Err(format!(
+                "Policy violation: core \"{}\" is blocked in [policy].",
+                core
+            ))
+        } else if !self.allowed_cores.as_deref().map_or(true, listed) {
+            Err(format!(
+                "Policy violation: core \"{}\" is not allowed in [policy].",
+                core
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check if the core `libretro` can run with the `battery` and `available` memory in bytes,
+    /// which are `None` if unknown.  The battery only counts while discharging.  `aliases` are
+    /// the names in `[cores]` pointing to the same core.  Returns a message for each resource
+    /// which is too low.
+    #[must_use]
+    pub fn low_resources(
+        &self,
+        libretro: &Path,
+        aliases: &[String],
+        battery: Option<Battery>,
+        available: Option<u64>,
+    ) -> Vec<String> {
+        let mut messages: Vec<String> = vec![];
+
+        if let (Some(min), Some(battery)) = (self.min_battery, battery) {
+            if battery.discharging && battery.capacity < min {
+                messages.push(format!(
+                    "Battery is at {}%, below min_battery of {}% in [policy].",
+                    battery.capacity, min
+                ));
+            }
+        }
+
+        let names: Vec<String> = core_names(libretro, aliases);
+        let required: Option<u64> = self
+            .min_free_memory
+            .iter()
+            .filter(|(pattern, _)| matches(pattern, &names))
+            .map(|(_, bytes)| *bytes)
+            .max();
+        if let (Some(required), Some(available)) = (required, available) {
+            if available < required {
+                messages.push(format!(
+                    "Free memory is {}, below min_free_memory of {} for core \"{}\" \
+                     in [policy].",
+                    resources::format_size(available),
+                    resources::format_size(required),
+                    coreinfo::core_name(libretro)
+                ));
+            }
+        }
+
+        messages
+    }
+}
+
+/// All names of the core `libretro` in the policy: its core name like `snes9x`, its filename and
+/// the `aliases` in `[cores]`.
+fn core_names(libretro: &Path, aliases: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = vec![
+        coreinfo::core_name(libretro),
+        libretro
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+    ];
+    names.extend(aliases.iter().cloned());
+
+    names
+}
+
+/// Check if the wildcard `pattern` matches any of the `names`.
+fn matches(pattern: &str, names: &[String]) -> bool {
+    let pattern = WildMatch::new(pattern);
+    names.iter().any(|name| pattern.matches(name))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use indexmap::map::IndexMap;
+
+    use crate::settings::resources::Battery;
+
+    use super::LowResources;
+    use super::Policy;
+
+    #[test]
+    fn from_section() {
+        let section: IndexMap<String, String> = IndexMap::from([
+            ("blocked_cores".to_string(), "psx, n64".to_string()),
+            ("allowed_cores".to_string(), "snes gb*".to_string()),
+        ]);
+        let policy = Policy::from_section(&section).unwrap();
+
+        assert_eq!(vec!["psx", "n64"], policy.blocked_cores);
+        assert_eq!(
+            Some(vec!["snes".into(), "gb*".into()]),
+            policy.allowed_cores
+        );
+
+        let section: IndexMap<String, String> =
+            IndexMap::from([("blocked".to_string(), "psx".to_string())]);
+        assert!(Policy::from_section(&section).is_err());
+    }
+
+    #[test]
+    fn from_section_resources() {
+        let section: IndexMap<String, String> = IndexMap::from([
+            ("min_battery".to_string(), "20".to_string()),
+            ("min_free_memory".to_string(), "512M".to_string()),
+            ("min_free_memory.n64".to_string(), "2G".to_string()),
+            ("on_low_resources".to_string(), "abort".to_string()),
+        ]);
+        let policy = Policy::from_section(&section).unwrap();
+
+        assert_eq!(Some(20), policy.min_battery);
+        assert_eq!(
+            IndexMap::from([
+                ("*".to_string(), 512 << 20),
+                ("n64".to_string(), 2 << 30)
+            ]),
+            policy.min_free_memory
+        );
+        assert_eq!(LowResources::Abort, policy.on_low_resources);
+
+        for (key, value) in [
+            ("min_battery", "low"),
+            ("min_free_memory.", "1G"),
+            ("on_low_resources", "ignore"),
+        ] {
+            let section: IndexMap<String, String> =
+                IndexMap::from([(key.to_string(), value.to_string())]);
+            assert!(Policy::from_section(&section).is_err());
+        }
+    }
+
+    #[test]
+    fn low_resources() {
+        let n64 = Path::new("/cores/mupen64plus_next_libretro.so");
+        let policy = Policy {
+            min_battery: Some(20),
+            min_free_memory: IndexMap::from([
+                ("*".to_string(), 512 << 20),
+                ("n64".to_string(), 2 << 30),
+            ]),
+            ..Default::default()
+        };
+        let battery = |capacity: u8, discharging: bool| {
+            Some(Battery {
+                capacity,
+                discharging,
+            })
+        };
+
+        assert!(policy
+            .low_resources(
+                n64,
+                &["n64".to_string()],
+                battery(50, true),
+                Some(4 << 30)
+            )
+            .is_empty());
+        assert!(policy
+            .low_resources(n64, &[], battery(10, false), Some(1 << 30))
+            .is_empty());
+        assert_eq!(
+            2,
+            policy
+                .low_resources(
+                    n64,
+                    &["n64".to_string()],
+                    battery(10, true),
+                    Some(1 << 30)
+                )
+                .len()
+        );
+        assert!(policy.low_resources(n64, &[], None, None).is_empty());
+    }
+
+    #[test]
+    fn check_blocked_and_allowed() {
+        let snes9x = Path::new("/cores/snes9x_libretro.so");
+        let gambatte = Path::new("/cores/gambatte_libretro.so");
+        let mut policy = Policy {
+            blocked_cores: vec!["psx".to_string()],
+            ..Default::default()
+        };
+
+        assert!(policy.check(snes9x, &["snes".to_string()]).is_ok());
+        assert!(policy
+            .check(
+                Path::new("/cores/swanstation_libretro.so"),
+                &["psx".to_string()]
+            )
+            .is_err());
+
+        policy.allowed_cores = Some(vec!["snes*".to_string()]);
+        assert!(policy.check(snes9x, &[]).is_ok());
+        assert!(policy.check(gambatte, &["gb".to_string()]).is_err());
+
+        policy.blocked_cores = vec!["snes9x_libretro.so".to_string()];
+        assert!(policy.check(snes9x, &[]).is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use crate::settings::doctor;
+use crate::settings::file;
+
+use std::ffi::CString;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::str::FromStr;
+
+use indexmap::map::IndexMap;
+
+/// How a check before launching a game is handled, if it does not pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// The check is not run.
+    Off,
+    /// A warning is printed to stderr, but the game is launched anyway.
+    Warn,
+    /// The game is not launched.
+    Fail,
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim().to_lowercase().as_str() {
+            "off" | "0" | "false" | "no" => Ok(Self::Off),
+            "warn" => Ok(Self::Warn),
+            "fail" | "1" | "true" | "yes" => Ok(Self::Fail),
+            _ => Err(format!(
+                "Invalid check level \"{}\", use off, warn or fail.",
+                text
+            )),
+        }
+    }
+}
+
+/// Checks run right before a game is launched, from section `[preflight]` of the user config.
+/// `RetroArch` itself fails silently or only on the first save, if it cannot write its files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checks {
+    /// Save directories must exist and be writable.
+    pub save_directories: Level,
+    /// Save directories must have at least `min_free_mb` of free space.
+    pub free_space: Level,
+    pub min_free_mb: u64,
+}
+
+impl Default for Checks {
+    fn default() -> Self {
+        Self {
+            save_directories: Level::Warn,
+            free_space: Level::Warn,
+            min_free_mb: 64,
+        }
+    }
+}
+
+impl Checks {
+    /// Read the checks from the keys of section `[preflight]`.  Missing keys keep the default.
+    pub fn from_section(
+        section: &IndexMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut checks: Self = Self::default();
+
+        for (key, value) in section {
+            match key.as_str() {
+                "save_directories" => {
+                    checks.save_directories = value.parse()?
+                }
+                "free_space" => checks.free_space = value.parse()?,
+                "min_free_mb" => {
+                    checks.min_free_mb =
+                        value.trim().parse().map_err(|_| {
+                            format!("Invalid number in min_free_mb: {}", value)
+                        })?;
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown check in [preflight]: {}",
+                        key
+                    ))
+                }
+            }
+        }
+
+        Ok(checks)
+    }
+
+    /// Run all enabled checks on the directories, each given with a descriptive name.  Warnings
+    /// are printed to stderr.  Returns the messages of all failed checks as a single error.
+    pub fn run(&self, directories: &[(&str, &Path)]) -> Result<(), String> {
+        let mut report = doctor::Report::new();
+
+        for (name, dir) in directories {
+            let fullpath = dir.display();
+            if self.save_directories != Level::Off {
+                let status: doctor::Status =
+                    to_status(self.save_directories, false);
+                if !dir.is_dir() {
+                    report.add(
+                        status,
+                        name,
+                        &format!("{} does not exist", fullpath),
+                    );
+                    continue;
+                } else if !file::is_writable_dir(dir) {
+                    report.add(
+                        status,
+                        name,
+                        &format!("{} is not writable", fullpath),
+                    );
+                    continue;
+                }
+            }
+            if self.free_space != Level::Off {
+                if let Some(free) = free_space(dir) {
+                    let free_mb: u64 = free / 1024 / 1024;
+                    report.add(
+                        to_status(
+                            self.free_space,
+                            free_mb >= self.min_free_mb,
+                        ),
+                        name,
+                        &format!(
+                            "{} has only {} MB free space, less than {} MB",
+                            fullpath, free_mb, self.min_free_mb
+                        ),
+                    );
+                }
+            }
+        }
+
+        let mut failed: Vec<String> = vec![];
+        for check in &report.checks {
+            match check.status {
+                doctor::Status::Pass => (),
+                doctor::Status::Warn => {
+                    eprintln!("Warning: {}: {}", check.name, check.message);
+                }
+                doctor::Status::Fail => {
+                    failed.push(format!("{}: {}", check.name, check.message));
+                }
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(failed.join("; "))
+        }
+    }
+}
+
+fn to_status(level: Level, passed: bool) -> doctor::Status {
+    match level {
+        _ if passed => doctor::Status::Pass,
+        Level::Off => doctor::Status::Pass,
+        Level::Warn => doctor::Status::Warn,
+        Level::Fail => doctor::Status::Fail,
+    }
+}
+
+/// Free space in bytes of the filesystem containing `dir`, available to unprivileged users like
+/// `df` reports it.  Read with `statvfs()` directly, so no process is started on each launch.
+/// `None` if it could not be determined.
+#[must_use]
+pub fn free_space(dir: &Path) -> Option<u64> {
+    let path: CString = CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat: MaybeUninit<libc::statvfs> = MaybeUninit::uninit();
+
+    // SAFETY: `path` is a valid nul terminated string and `stat` is only read after `statvfs()`
+    // returned success, which means it was filled in.
+    let stat: libc::statvfs = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+
+    // The field types differ between platforms, on some they are 32 bit.
+    #[allow(clippy::useless_conversion)]
+    u64::from(stat.f_bavail).checked_mul(u64::from(stat.f_frsize))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use indexmap::map::IndexMap;
+
+    use super::Checks;
+    use super::Level;
+
+    #[test]
+    fn level_from_str() {
+        assert_eq!(Ok(Level::Off), "off".parse());
+        assert_eq!(Ok(Level::Warn), " Warn".parse());
+        assert_eq!(Ok(Level::Fail), "1".parse());
+        assert!("sometimes".parse::<Level>().is_err());
+    }
+
+    #[test]
+    fn checks_from_section() {
+        let section: IndexMap<String, String> = IndexMap::from([
+            ("save_directories".to_string(), "fail".to_string()),
+            ("min_free_mb".to_string(), "512".to_string()),
+        ]);
+        let checks = Checks::from_section(&section).unwrap();
+
+        assert_eq!(Level::Fail, checks.save_directories);
+        assert_eq!(Level::Warn, checks.free_space);
+        assert_eq!(512, checks.min_free_mb);
+
+        let section: IndexMap<String, String> =
+            IndexMap::from([("free".to_string(), "warn".to_string())]);
+        assert!(Checks::from_section(&section).is_err());
+    }
+
+    #[test]
+    fn free_space() {
+        assert!(super::free_space(&std::env::temp_dir()).is_some());
+        assert_eq!(
+            None,
+            super::free_space(Path::new("/enjoy/does/not/exist"))
+        );
+    }
+
+    #[test]
+    fn run_missing_directory() {
+        let missing = Path::new("/enjoy/does/not/exist");
+        let mut checks = Checks::default();
+
+        assert!(checks.run(&[("savefile directory", missing)]).is_ok());
+
+        checks.save_directories = Level::Fail;
+        let error = checks.run(&[("savefile directory", missing)]);
+        assert_eq!(
+            Err("savefile directory: /enjoy/does/not/exist does not exist"
+                .to_string()),
+            error
+        );
+
+        checks.save_directories = Level::Off;
+        assert!(checks.run(&[("savefile directory", missing)]).is_ok());
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use indexmap::map::IndexMap;
+
+/// Prefix of the sections in the user config, which define a preset, like `[preset:sharp]`.
+pub const SECTION_PREFIX: &str = "preset:";
+
+/// Prefix of the filename of the generated core options in the temporary directory.
+pub const FILE_PREFIX: &str = "core-options";
+
+/// A named bundle of core options, which can be reused for several systems.  The keys are the
+/// core options as in `retroarch-core-options.cfg`.
+///
+/// ```ini
+/// [preset:sharp-pixels]
+/// snes9x_overscan = disabled
+/// genesis_plus_gx_blargg_ntsc_filter = disabled
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preset {
+    pub name: String,
+    pub options: IndexMap<String, String>,
+}
+
+impl Preset {
+    #[must_use]
+    pub fn from_section(
+        name: &str,
+        section: &IndexMap<String, String>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            options: section.clone(),
+        }
+    }
+}
+
+/// Core options file of `RetroArch`, if key `core_options_path` of `retroarch.cfg` is not set.
+/// It is next to `retroarch.cfg`.
+#[must_use]
+pub fn default_core_options(retroarch_config: &Path) -> PathBuf {
+    retroarch_config.with_file_name("retroarch-core-options.cfg")
+}
+
+/// Read the options of a core options file with one `key = "value"` per line.  Comments and
+/// malformed lines are ignored.
+#[must_use]
+pub fn parse_core_options(content: &str) -> IndexMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.trim().to_string(),
+                value.trim().trim_matches('"').to_string(),
+            )
+        })
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    #[test]
+    fn default_core_options() {
+        assert_eq!(
+            PathBuf::from(
+                "/home/user/.config/retroarch/retroarch-core-options.cfg"
+            ),
+            super::default_core_options(Path::new(
+                "/home/user/.config/retroarch/retroarch.cfg"
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_core_options() {
+        let options = super::parse_core_options(
+            "# comment\nsnes9x_overscan = \"enabled\"\n\nbroken\n\
+             mgba_solar_sensor_level = \"0\"\n",
+        );
+
+        assert_eq!(2, options.len());
+        assert_eq!(
+            Some(&"enabled".to_string()),
+            options.get("snes9x_overscan")
+        );
+        assert_eq!(
+            Some(&"0".to_string()),
+            options.get("mgba_solar_sensor_level")
+        );
+    }
+}
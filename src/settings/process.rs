@@ -0,0 +1,264 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Which processes are taken into account when looking for a running instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Processes of all users.
+    Any,
+    /// Processes of the current user only.
+    User,
+    /// Processes of the current login session only.
+    Session,
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "any" => Ok(Self::Any),
+            "user" => Ok(Self::User),
+            "session" => Ok(Self::Session),
+            _ => Err(format!(
+                "Unknown scope \"{}\", expected one of: any, user, session",
+                text
+            )),
+        }
+    }
+}
+
+/// The parts of a running process under `/proc` needed for matching.  Fields are `None`, if
+/// they could not be read, which usually happens for processes of other users.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub exe: Option<PathBuf>,
+    pub uid: Option<u32>,
+    pub session: Option<u32>,
+}
+
+impl ProcessInfo {
+    /// Read the information of a single process from `/proc/<pid>`.
+    #[must_use]
+    pub fn read(pid: u32) -> Option<Self> {
+        let dir: PathBuf = PathBuf::from(format!("/proc/{}", pid));
+        let name: String = fs::read_to_string(dir.join("comm")).ok()?;
+
+        Some(Self {
+            pid,
+            name: name.trim_end().to_string(),
+            exe: exe(pid),
+            uid: fs::read_to_string(dir.join("status"))
+                .ok()
+                .and_then(|status| parse_uid(&status)),
+            session: fs::read_to_string(dir.join("sessionid"))
+                .ok()
+                .and_then(|id| id.trim().parse().ok()),
+        })
+    }
+
+    /// Check if this process is an instance of `name`.  The kernel truncates process names to 15
+    /// characters, so does the comparison.  If `executable` is given and the path of the process
+    /// could be read, then both must point to the same file.  Finally the process must be in the
+    /// `scope` of the process `current`.
+    #[must_use]
+    pub fn matches(
+        &self,
+        name: &str,
+        executable: Option<&Path>,
+        scope: Scope,
+        current: &Self,
+    ) -> bool {
+        let short_name: String = name.chars().take(15).collect();
+        if name.is_empty() || self.name != short_name {
+            return false;
+        }
+
+        if let (Some(expected), Some(exe)) = (executable, &self.exe) {
+            if expected != exe {
+                return false;
+            }
+        }
+
+        match scope {
+            Scope::Any => true,
+            Scope::User => self.uid.is_some() && self.uid == current.uid,
+            Scope::Session => {
+                self.uid.is_some()
+                    && self.uid == current.uid
+                    && self.session == current.session
+            }
+        }
+    }
+}
+
+/// Extract the real user id from the content of `/proc/<pid>/status`.
+fn parse_uid(status: &str) -> Option<u32> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))
+        .and_then(|ids| ids.split_whitespace().next())
+        .and_then(|id| id.parse().ok())
+}
+
+/// Path of the executable a process was started from.  `None` if it cannot be read, which
+/// usually happens for processes of other users.
+pub fn exe(pid: u32) -> Option<PathBuf> {
+    fs::read_link(format!("/proc/{}/exe", pid)).ok().map(|exe| {
+        PathBuf::from(exe.to_string_lossy().trim_end_matches(" (deleted)"))
+    })
+}
+
+/// Time a process started after system boot, in clock ticks.  Together with the pid it
+/// identifies a process, even after its pid was reused.
+pub fn start_time(pid: u32) -> Option<u64> {
+    fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| parse_start_time(&stat))
+}
+
+/// Extract the start time from the content of `/proc/<pid>/stat`.  The name of the process in
+/// parentheses can contain spaces, so the fields are counted after its closing parenthesis.
+fn parse_start_time(stat: &str) -> Option<u64> {
+    stat.rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()
+}
+
+/// All process ids currently listed in `/proc`.
+fn pids() -> Vec<u32> {
+    fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .filter_map(std::result::Result::ok)
+                .filter_map(|e| e.file_name().to_string_lossy().parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Find all running processes named `name`, excluding the current process.  See
+/// `ProcessInfo::matches()` for the meaning of `executable` and `scope`.
+pub fn find(name: &str, executable: Option<&Path>, scope: Scope) -> Vec<u32> {
+    let current: ProcessInfo =
+        ProcessInfo::read(std::process::id()).unwrap_or_default();
+
+    pids()
+        .into_iter()
+        .filter(|pid| *pid != current.pid)
+        .filter_map(ProcessInfo::read)
+        .filter(|p| p.matches(name, executable, scope, &current))
+        .map(|p| p.pid)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+
+    use super::ProcessInfo;
+    use super::Scope;
+
+    // Untested:
+    //  - ProcessInfo::read()
+    //  - find()
+
+    fn test_process_template() -> ProcessInfo {
+        ProcessInfo {
+            pid: 100,
+            name: "retroarch".to_string(),
+            exe: Some(PathBuf::from("/usr/bin/retroarch")),
+            uid: Some(1000),
+            session: Some(2),
+        }
+    }
+
+    #[test]
+    fn start_time() {
+        assert!(super::start_time(std::process::id()).is_some());
+        assert_eq!(None, super::start_time(0));
+    }
+
+    #[test]
+    fn parse_start_time() {
+        let stat =
+            "4321 (retro arch) S 1 4321 4321 0 -1 4194560 1 0 0 0 2 1 0 0 \
+                    20 0 1 0 987654 12345678 100";
+
+        assert_eq!(Some(987654), super::parse_start_time(stat));
+        assert_eq!(None, super::parse_start_time("4321 (retroarch) S 1"));
+    }
+
+    #[test]
+    fn scope_from_str() {
+        assert_eq!(Ok(Scope::User), "User".parse::<Scope>());
+        assert!("nobody".parse::<Scope>().is_err());
+    }
+
+    #[test]
+    fn parse_uid() {
+        let status = "Name:\tretroarch\nUid:\t1000\t1000\t1000\t1000\n";
+
+        assert_eq!(Some(1000), super::parse_uid(status));
+        assert_eq!(None, super::parse_uid("Name:\tretroarch\n"));
+    }
+
+    #[test]
+    fn matches_executable() {
+        let process = test_process_template();
+        let current = test_process_template();
+        let exe = PathBuf::from("/usr/bin/retroarch");
+        let wrapper = PathBuf::from("/opt/ppsspp/retroarch");
+
+        assert!(process.matches(
+            "retroarch",
+            Some(&exe),
+            Scope::Any,
+            &current
+        ));
+        assert!(!process.matches(
+            "retroarch",
+            Some(&wrapper),
+            Scope::Any,
+            &current
+        ));
+        assert!(!process.matches("ppsspp", None, Scope::Any, &current));
+    }
+
+    #[test]
+    fn matches_scope() {
+        let process = test_process_template();
+        let other_session = ProcessInfo {
+            session: Some(3),
+            ..test_process_template()
+        };
+        let other_user = ProcessInfo {
+            uid: Some(1001),
+            ..test_process_template()
+        };
+
+        assert!(process.matches("retroarch", None, Scope::Session, &process));
+        assert!(process.matches(
+            "retroarch",
+            None,
+            Scope::User,
+            &other_session
+        ));
+        assert!(!process.matches(
+            "retroarch",
+            None,
+            Scope::Session,
+            &other_session
+        ));
+        assert!(!process.matches("retroarch", None, Scope::User, &other_user));
+        assert!(process.matches("retroarch", None, Scope::Any, &other_user));
+    }
+}
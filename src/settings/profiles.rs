@@ -0,0 +1,258 @@
+use crate::settings::clock;
+
+use std::cell::OnceCell;
+use std::env;
+
+use indexmap::map::IndexMap;
+
+/// Prefix of the sections in the user config, which define a profile, like `[profile:tv]`.
+pub const SECTION_PREFIX: &str = "profile:";
+
+/// Names of the values in conditions, which are taken from the local time instead of the
+/// environment.  `weekday` is 1 for Monday up to 7 for Sunday.
+const TIME_KEYS: &[&str] = &["hour", "minute", "weekday"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A single comparison like `hour>=22` or `DISPLAY=:1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Test {
+    key: String,
+    op: Op,
+    value: String,
+}
+
+impl Test {
+    fn parse(text: &str) -> Result<Self, String> {
+        // Longer operators first, so `>=` is not taken as `>`.
+        for (symbol, op) in [
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("==", Op::Eq),
+            ("=", Op::Eq),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ] {
+            if let Some((key, value)) = text.split_once(symbol) {
+                let key: &str = key.trim();
+                let ordered: bool = !matches!(op, Op::Eq | Op::Ne);
+                if key.is_empty() || (ordered && value.trim().is_empty()) {
+                    break;
+                }
+                return Ok(Self {
+                    key: key.to_string(),
+                    op,
+                    value: value.trim().to_string(),
+                });
+            }
+        }
+
+        Err(format!("Invalid condition \"{}\".", text.trim()))
+    }
+
+    /// Compare as numbers, if both sides are numbers.  Text can only be compared for equality.
+    /// A missing value never matches, except for `!=`.
+    fn matches(&self, actual: Option<&str>) -> bool {
+        let actual: &str = match actual {
+            Some(actual) => actual,
+            None => return self.op == Op::Ne,
+        };
+        let ordering = match (actual.parse::<i64>(), self.value.parse::<i64>())
+        {
+            (Ok(actual), Ok(expected)) => actual.cmp(&expected),
+            _ => match self.op {
+                Op::Eq => return actual == self.value,
+                Op::Ne => return actual != self.value,
+                _ => return false,
+            },
+        };
+
+        match self.op {
+            Op::Eq => ordering.is_eq(),
+            Op::Ne => ordering.is_ne(),
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+            Op::Gt => ordering.is_gt(),
+            Op::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+/// Condition of a profile, like `hour>=22 or hour<6`.  Each test compares an environment
+/// variable or one of `hour`, `minute` and `weekday` of the local time with a value.  Tests are
+/// combined with `and` and `or`, where `and` binds stronger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    any: Vec<Vec<Test>>,
+}
+
+impl Condition {
+    /// Parse the text of a `when` key.  Surrounding quotes are removed.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text: &str = text.trim().trim_matches('"').trim();
+        let words: Vec<&str> = text.split_whitespace().collect();
+        for word in [words.first(), words.last()].into_iter().flatten() {
+            if matches!(*word, "and" | "or") {
+                return Err(format!("Incomplete condition \"{}\".", text));
+            }
+        }
+        let any: Vec<Vec<Test>> = text
+            .split(" or ")
+            .map(|all| all.split(" and ").map(Test::parse).collect())
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { any })
+    }
+
+    /// Check the condition with values from `lookup`.
+    pub fn matches<F>(&self, lookup: F) -> bool
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        self.any.iter().any(|all| {
+            all.iter()
+                .all(|test| test.matches(lookup(&test.key).as_deref()))
+        })
+    }
+}
+
+/// A named set of `retroarch.cfg` overrides, which is only applied when its condition is met.
+///
+/// ```ini
+/// [profile:night]
+/// when = "hour>=22 or hour<6"
+/// audio_volume = -12
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub when: Option<Condition>,
+    pub overrides: IndexMap<String, String>,
+}
+
+impl Profile {
+    /// Create a profile from the keys of its section.  All keys except `when` are overrides.
+    pub fn from_section(
+        name: &str,
+        section: &IndexMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut overrides: IndexMap<String, String> = section.clone();
+        let when: Option<Condition> = overrides
+            .shift_remove("when")
+            .map(|when| Condition::parse(&when))
+            .transpose()
+            .map_err(|e| format!("[{}{}] {}", SECTION_PREFIX, name, e))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            when,
+            overrides,
+        })
+    }
+}
+
+/// Values for conditions from the current environment and local time.  The time is only looked
+/// up once and only if a condition needs it.
+#[derive(Default)]
+pub struct Environment {
+    time: OnceCell<Vec<String>>,
+}
+
+impl Environment {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Value for a key of a condition.  `None` if it is not set.
+    pub fn get(&self, key: &str) -> Option<String> {
+        match TIME_KEYS.iter().position(|time_key| *time_key == key) {
+            Some(index) => {
+                self.time.get_or_init(local_time).get(index).cloned()
+            }
+            None => env::var(key).ok(),
+        }
+    }
+}
+
+// Current hour, minute and weekday in local time, without leading zeros.
+fn local_time() -> Vec<String> {
+    clock::now()
+        .map(|tm| {
+            [tm.tm_hour, tm.tm_min, clock::weekday(&tm)]
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use indexmap::map::IndexMap;
+
+    use super::Condition;
+    use super::Profile;
+
+    // Untested:
+    //  - Environment::get()
+
+    fn lookup(key: &str) -> Option<String> {
+        match key {
+            "hour" => Some("23".to_string()),
+            "DISPLAY" => Some(":1".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn condition_matches() {
+        let matches =
+            |text: &str| Condition::parse(text).unwrap().matches(lookup);
+
+        assert!(matches("\"DISPLAY=:1\""));
+        assert!(matches("hour>=22"));
+        assert!(!matches("hour<6"));
+        assert!(matches("hour<6 or hour>=22"));
+        assert!(!matches("hour>=22 and DISPLAY=:0"));
+        assert!(matches("hour>=22 and DISPLAY != :0"));
+        assert!(matches("MISSING != 1"));
+        assert!(!matches("MISSING = 1"));
+        assert!(!matches("DISPLAY > 1"));
+    }
+
+    #[test]
+    fn condition_invalid() {
+        assert!(Condition::parse("hour").is_err());
+        assert!(Condition::parse(">=22").is_err());
+        assert!(Condition::parse("hour>=").is_err());
+        assert!(Condition::parse("TERM=").is_ok());
+        assert!(Condition::parse("hour>=22 or").is_err());
+    }
+
+    #[test]
+    fn profile_from_section() {
+        let section: IndexMap<String, String> = IndexMap::from([
+            ("when".to_string(), "hour>=22".to_string()),
+            ("audio_volume".to_string(), "-12".to_string()),
+        ]);
+        let profile = Profile::from_section("night", &section).unwrap();
+
+        assert_eq!("night", profile.name);
+        assert!(profile.when.is_some());
+        assert_eq!(
+            IndexMap::from([("audio_volume".to_string(), "-12".to_string())]),
+            profile.overrides
+        );
+    }
+}
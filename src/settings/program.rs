@@ -0,0 +1,56 @@
+use std::process::Command;
+
+/// Program to open files like the user config with, if option `opener` is not set.
+pub const DEFAULT_OPENER: &str = "xdg-open";
+
+/// Program with arguments from an option of the user settings, like `tts_command`.  The text is
+/// split into words like in a shell, but the program is always run directly.  So quotes group
+/// words, but pipes, variables or redirections have no special meaning.  This is the only way
+/// commands from the user settings are run, and no shell is ever invoked otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    words: Vec<String>,
+}
+
+impl Program {
+    /// Split the `text` of the option `name`.  An empty text or unbalanced quotes are errors.
+    pub fn parse(name: &str, text: &str) -> Result<Self, String> {
+        shlex::split(text)
+            .filter(|words| !words.is_empty())
+            .map(|words| Self { words })
+            .ok_or_else(|| format!("Invalid {}: {}", name, text))
+    }
+
+    /// New command running the program with its arguments.  More arguments can be added.
+    #[must_use]
+    pub fn command(&self) -> Command {
+        let mut command: Command = Command::new(&self.words[0]);
+        command.args(&self.words[1..]);
+
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::ffi::OsStr;
+
+    use super::Program;
+
+    #[test]
+    fn parse() {
+        let program =
+            Program::parse("opener", "xdg-open 'a b' $HOME | cat").unwrap();
+        let command = program.command();
+        let args: Vec<&OsStr> = command.get_args().collect();
+
+        assert_eq!("xdg-open", command.get_program());
+        assert_eq!(vec!["a b", "$HOME", "|", "cat"], args);
+        assert_eq!(
+            Err("Invalid opener: ".to_string()),
+            Program::parse("opener", "")
+        );
+        assert!(Program::parse("opener", "xdg-open \"").is_err());
+    }
+}
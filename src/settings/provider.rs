@@ -0,0 +1,150 @@
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
+use std::process::Command;
+use std::process::Output;
+
+/// Access to the filesystem, as far as resolving the paths of games and cores needs it.  The
+/// regular implementation is `RealFs`, others can replace it in tests or sandboxed environments.
+pub trait FsProvider {
+    /// Absolute path with all symlinks and relative components resolved.  `None` if the path
+    /// does not exist.
+    fn canonicalize(&self, path: &Path) -> Option<PathBuf>;
+}
+
+/// The filesystem of the operating system.
+pub struct RealFs;
+
+impl FsProvider for RealFs {
+    fn canonicalize(&self, path: &Path) -> Option<PathBuf> {
+        path.canonicalize().ok()
+    }
+}
+
+/// Starting external programs, like `retroarch` itself.  The regular implementation is
+/// `RealProcess`.
+pub trait ProcessRunner {
+    /// Start the fully configured `command` without waiting for it.
+    fn spawn(&self, command: &mut Command) -> io::Result<Box<dyn Running>>;
+}
+
+/// A started program, which can be waited for.
+pub trait Running {
+    /// Process id of the program.
+    fn id(&self) -> u32;
+
+    /// Wait until the program exits and collect its output.
+    fn wait(self: Box<Self>) -> io::Result<Output>;
+}
+
+/// Programs run as child processes of the operating system.
+pub struct RealProcess;
+
+impl ProcessRunner for RealProcess {
+    fn spawn(&self, command: &mut Command) -> io::Result<Box<dyn Running>> {
+        Ok(Box::new(command.spawn()?))
+    }
+}
+
+impl Running for Child {
+    fn id(&self) -> u32 {
+        Self::id(self)
+    }
+
+    fn wait(self: Box<Self>) -> io::Result<Output> {
+        self.wait_with_output()
+    }
+}
+
+/// Stand-ins for tests, which never touch the real filesystem or spawn anything.
+#[cfg(test)]
+pub mod fake {
+
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::process::Command;
+    use std::process::ExitStatus;
+    use std::process::Output;
+
+    use super::FsProvider;
+    use super::ProcessRunner;
+    use super::Running;
+
+    /// Filesystem with a fixed set of files, given as absolute paths.
+    #[derive(Default)]
+    pub struct MemFs {
+        pub files: HashSet<PathBuf>,
+    }
+
+    impl MemFs {
+        pub fn with_files(files: &[&str]) -> Self {
+            Self {
+                files: files.iter().map(PathBuf::from).collect(),
+            }
+        }
+    }
+
+    impl FsProvider for MemFs {
+        fn canonicalize(&self, path: &Path) -> Option<PathBuf> {
+            let dir_exists = |dir: &Path| {
+                self.files.iter().any(|file| file.starts_with(dir))
+            };
+            Some(path.to_path_buf())
+                .filter(|path| path.has_root())
+                .filter(|path| self.files.contains(path) || dir_exists(path))
+        }
+    }
+
+    /// Runner recording the programs and arguments of each command, which exit with `code`.  If
+    /// `missing` is set, then the program cannot be started at all.
+    #[derive(Default)]
+    pub struct FakeRunner {
+        pub code: i32,
+        pub missing: bool,
+        pub spawned: RefCell<Vec<Vec<String>>>,
+    }
+
+    struct FakeChild {
+        code: i32,
+    }
+
+    impl ProcessRunner for FakeRunner {
+        fn spawn(
+            &self,
+            command: &mut Command,
+        ) -> io::Result<Box<dyn Running>> {
+            let mut cmdline: Vec<String> =
+                vec![command.get_program().to_string_lossy().to_string()];
+            cmdline.extend(
+                command
+                    .get_args()
+                    .map(|arg| arg.to_string_lossy().to_string()),
+            );
+            self.spawned.borrow_mut().push(cmdline);
+            if self.missing {
+                return Err(io::ErrorKind::NotFound.into());
+            }
+
+            Ok(Box::new(FakeChild { code: self.code }))
+        }
+    }
+
+    impl Running for FakeChild {
+        fn id(&self) -> u32 {
+            u32::MAX
+        }
+
+        fn wait(self: Box<Self>) -> io::Result<Output> {
+            Ok(Output {
+                status: ExitStatus::from_raw(self.code << 8),
+                stdout: vec![],
+                stderr: vec![],
+            })
+        }
+    }
+}
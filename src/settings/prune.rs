@@ -0,0 +1,184 @@
+use crate::settings::file;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use indexmap::map::IndexMap;
+
+/// Extensions of libretro cores on the supported platforms.
+const CORE_EXTENSIONS: &[&str] = &["so", "dll", "dylib"];
+
+/// Name of the core a file is a build of, like `snes9x` for `snes9x_libretro.so`, a versioned
+/// `snes9x_libretro-1.62.so` or a leftover download like `snes9x_libretro (1).so`.  `None` for
+/// files which are no core.
+#[must_use]
+pub fn core_base(file: &Path) -> Option<String> {
+    let name: &str = file.file_name()?.to_str()?;
+
+    match name.find("_libretro") {
+        Some(0) | None => None,
+        Some(index) => Some(name[..index].to_string()),
+    }
+}
+
+// Check if the file has the name `RetroArch` looks for, like `snes9x_libretro.so`.
+fn is_canonical(file: &Path, base: &str) -> bool {
+    file.file_stem().is_some_and(|stem| {
+        stem.to_string_lossy() == format!("{}_libretro", base)
+    }) && file.extension().is_some_and(|extension| {
+        CORE_EXTENSIONS.contains(&extension.to_string_lossy().as_ref())
+    })
+}
+
+/// Several builds of the same core in a directory.  One of them is kept, all others are
+/// suggested for removal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub base: String,
+    pub keep: PathBuf,
+    pub remove: Vec<PathBuf>,
+    /// The kept core is used by the rules of the user config.
+    pub referenced: bool,
+}
+
+/// Groups of the `files` in a directory with more than one build of the same core, by name of
+/// the core.  Kept is the build used by the rules in `referenced`, or else the one with the name
+/// `RetroArch` looks for, or else the first by name.
+#[must_use]
+pub fn plan(files: &[PathBuf], referenced: &HashSet<PathBuf>) -> Vec<Group> {
+    let mut builds: IndexMap<String, Vec<PathBuf>> = IndexMap::new();
+    let mut files: Vec<&PathBuf> = files.iter().collect();
+    files.sort();
+    for file in files {
+        if let Some(base) = core_base(file) {
+            builds.entry(base).or_default().push(file.clone());
+        }
+    }
+
+    builds
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(base, mut files)| {
+            let index: usize = files
+                .iter()
+                .position(|file| referenced.contains(file))
+                .or_else(|| {
+                    files.iter().position(|file| is_canonical(file, &base))
+                })
+                .unwrap_or(0);
+            let keep: PathBuf = files.remove(index);
+
+            Group {
+                referenced: referenced.contains(&keep),
+                base,
+                keep,
+                remove: files,
+            }
+        })
+        .collect()
+}
+
+/// All files directly in `dir`, which are builds of a core.  A missing directory has none.
+pub fn core_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    Ok(entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && core_base(path).is_some())
+        .collect())
+}
+
+/// Check if two files have the same content.
+#[must_use]
+pub fn is_identical(a: &Path, b: &Path) -> bool {
+    match (fs::read(a), fs::read(b)) {
+        (Ok(a), Ok(b)) => {
+            a.len() == b.len() && file::fnv1a(&a) == file::fnv1a(&b)
+        }
+        _ => false,
+    }
+}
+
+/// Size in mebibytes with one decimal, like `2.1 MiB`.
+#[must_use]
+pub fn format_size(bytes: u64) -> String {
+    let mib: f64 = bytes as f64 / 1024.0 / 1024.0;
+
+    format!("{:.1} MiB", mib)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::HashSet;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    // Untested:
+    //  - core_files()
+    //  - is_identical()
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names
+            .iter()
+            .map(|name| PathBuf::from("/cores").join(name))
+            .collect()
+    }
+
+    #[test]
+    fn core_base() {
+        for name in [
+            "snes9x_libretro.so",
+            "snes9x_libretro-1.62.so",
+            "snes9x_libretro (1).so",
+            "snes9x_libretro.so.bak",
+        ] {
+            assert_eq!(
+                Some("snes9x".to_string()),
+                super::core_base(Path::new(name))
+            );
+        }
+        assert_eq!(None, super::core_base(Path::new("snes9x.info")));
+        assert_eq!(None, super::core_base(Path::new("_libretro.so")));
+    }
+
+    #[test]
+    fn plan() {
+        let files = paths(&[
+            "snes9x_libretro.so.bak",
+            "snes9x_libretro.so",
+            "mgba_libretro.so",
+            "bsnes_libretro (1).so",
+            "bsnes_libretro-v115.so",
+        ]);
+        let referenced: HashSet<PathBuf> =
+            HashSet::from([PathBuf::from("/cores/bsnes_libretro-v115.so")]);
+        let groups = super::plan(&files, &referenced);
+
+        assert_eq!(2, groups.len());
+        assert_eq!("bsnes", groups[0].base);
+        assert!(groups[0].referenced);
+        assert_eq!(
+            PathBuf::from("/cores/bsnes_libretro-v115.so"),
+            groups[0].keep
+        );
+        assert_eq!(paths(&["bsnes_libretro (1).so"]), groups[0].remove);
+        assert_eq!("snes9x", groups[1].base);
+        assert!(!groups[1].referenced);
+        assert_eq!(PathBuf::from("/cores/snes9x_libretro.so"), groups[1].keep);
+        assert_eq!(paths(&["snes9x_libretro.so.bak"]), groups[1].remove);
+    }
+
+    #[test]
+    fn format_size() {
+        assert_eq!("0.0 MiB", super::format_size(0));
+        assert_eq!("2.5 MiB", super::format_size(5 * 512 * 1024));
+    }
+}
@@ -0,0 +1,367 @@
+use crate::settings::file;
+use crate::settings::inoutput::json_path;
+use crate::settings::inoutput::json_string;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Result of a single game in a queue run.  `exit_code` is `None` if the game could not be
+/// started at all or was not run, in which case `error` may explain why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueEntry {
+    pub game: PathBuf,
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+    pub error: Option<String>,
+}
+
+impl QueueEntry {
+    /// A game counts as successful, if `RetroArch` exited with code `0`.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// Short status text for the summary table.
+    #[must_use]
+    pub fn status(&self) -> String {
+        match (self.exit_code, &self.error) {
+            (Some(0), _) => "ok".to_string(),
+            (Some(code), _) => format!("exit {}", code),
+            (None, Some(_)) => "error".to_string(),
+            (None, None) => "not run".to_string(),
+        }
+    }
+}
+
+/// When a queue counts as failed, so its exit code is nonzero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitPolicy {
+    /// Never, the results are only reported in the summary.
+    Never,
+    /// If any game failed or was not run.
+    Any,
+    /// Only if no game succeeded.
+    All,
+}
+
+impl FromStr for ExitPolicy {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "never" => Ok(Self::Never),
+            "any" => Ok(Self::Any),
+            "all" => Ok(Self::All),
+            _ => Err(format!(
+                "Unknown exit policy \"{}\", expected one of: never, any, all",
+                text
+            )),
+        }
+    }
+}
+
+impl ExitPolicy {
+    /// Check if the queue of `summary` failed by this policy.  An empty queue never fails.
+    #[must_use]
+    pub fn is_failure(self, summary: &Summary) -> bool {
+        match self {
+            _ if summary.entries.is_empty() => false,
+            Self::Never => false,
+            Self::Any => summary.failed() > 0,
+            Self::All => summary.succeeded() == 0,
+        }
+    }
+}
+
+/// Collected results of all games run back-to-back.
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub entries: Vec<QueueEntry>,
+}
+
+impl Summary {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    #[must_use]
+    pub fn succeeded(&self) -> usize {
+        self.entries.iter().filter(|e| e.is_success()).count()
+    }
+
+    #[must_use]
+    pub fn failed(&self) -> usize {
+        self.entries.len() - self.succeeded()
+    }
+
+    #[must_use]
+    pub fn total_duration(&self) -> Duration {
+        self.entries.iter().map(|e| e.duration).sum()
+    }
+
+    /// Format the summary as a plain text table with one row per game and a total line.
+    #[must_use]
+    pub fn to_table(&self) -> String {
+        let mut table: String = format!(
+            "{:>3}  {:<8}  {:>8}  {}\n",
+            "#", "STATUS", "TIME", "GAME"
+        );
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            table.push_str(&format!(
+                "{:>3}  {:<8}  {:>8}  {}",
+                index + 1,
+                entry.status(),
+                format_duration(entry.duration),
+                entry.game.display()
+            ));
+            if let Some(error) = &entry.error {
+                table.push_str(&format!(" ({})", error));
+            }
+            table.push('\n');
+        }
+        table.push_str(&self.totals());
+
+        table
+    }
+
+    /// One line with the number of games, their results and the total time.
+    #[must_use]
+    pub fn totals(&self) -> String {
+        format!(
+            "{} games, {} succeeded, {} failed, total time {}",
+            self.entries.len(),
+            self.succeeded(),
+            self.failed(),
+            format_duration(self.total_duration())
+        )
+    }
+
+    /// Format the summary as a JSON object, with durations in seconds.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let games: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let exit_code: String = entry
+                    .exit_code
+                    .map_or_else(|| "null".to_string(), |c| c.to_string());
+                let error: String = entry
+                    .error
+                    .as_ref()
+                    .map_or_else(|| "null".to_string(), |e| json_string(e));
+
+                format!(
+                    "{{\"game\":{},\"status\":{},\"exit_code\":{},\
+                     \"duration\":{:.3},\"error\":{}}}",
+                    json_path(&entry.game),
+                    json_string(&entry.status()),
+                    exit_code,
+                    entry.duration.as_secs_f64(),
+                    error,
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"games\":[{}],\"succeeded\":{},\"failed\":{},\
+             \"duration\":{:.3}}}",
+            games.join(","),
+            self.succeeded(),
+            self.failed(),
+            self.total_duration().as_secs_f64()
+        )
+    }
+}
+
+/// File with the progress of the running queue, in the state directory.
+#[must_use]
+pub fn progress_file() -> PathBuf {
+    file::state_dir().join("queue")
+}
+
+/// Progress of a queue, saved after each game so an interrupted queue can be resumed.  The file
+/// lists all games of the queue as `game<TAB>path` lines, followed by a `done<TAB>path` line
+/// appended for each finished game.  Lines are only appended while the queue runs, so a power
+/// loss keeps everything finished before.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Progress {
+    pub games: Vec<PathBuf>,
+    pub done: HashSet<PathBuf>,
+}
+
+impl Progress {
+    /// Read the progress from the content of a progress file.  Unknown lines are ignored.
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let mut progress: Self = Self::default();
+
+        for line in content.lines() {
+            match line.split_once('\t') {
+                Some(("game", path)) => progress.games.push(path.into()),
+                Some(("done", path)) => {
+                    progress.done.insert(path.into());
+                }
+                _ => {}
+            }
+        }
+
+        progress
+    }
+
+    /// Read the progress file.  `None` if there is none, which means the last queue finished.
+    pub fn load(file: &Path) -> io::Result<Option<Self>> {
+        match fs::read_to_string(file) {
+            Ok(content) => Ok(Some(Self::parse(&content))),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Games of the queue not finished yet, in order.
+    #[must_use]
+    pub fn remaining(&self) -> Vec<PathBuf> {
+        self.games
+            .iter()
+            .filter(|game| !self.done.contains(*game))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Start a new progress file for the `games` of a queue, replacing an older one.
+pub fn start_progress(file: &Path, games: &[PathBuf]) -> io::Result<()> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let content: String = games
+        .iter()
+        .map(|game| format!("game\t{}\n", game.display()))
+        .collect();
+
+    fs::write(file, content)
+}
+
+/// Record a finished game in the progress file.
+pub fn mark_done(file: &Path, game: &Path) -> io::Result<()> {
+    let mut progress = fs::OpenOptions::new().append(true).open(file)?;
+
+    writeln!(progress, "done\t{}", game.display())
+}
+
+/// Format a duration as `h:mm:ss`.
+#[must_use]
+pub fn format_duration(duration: Duration) -> String {
+    let seconds: u64 = duration.as_secs();
+
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use super::ExitPolicy;
+    use super::Progress;
+    use super::QueueEntry;
+    use super::Summary;
+
+    // Untested:
+    //  - progress_file()
+    //  - Progress::load()
+    //  - start_progress()
+    //  - mark_done()
+
+    #[test]
+    fn progress_remaining() {
+        let content =
+            "game\t/roms/a.smc\ngame\t/roms/b.smc\ngame\t/roms/c.smc\n\
+                       done\t/roms/a.smc\ndone\t/roms/c.smc\n";
+        let progress = Progress::parse(content);
+
+        assert_eq!(3, progress.games.len());
+        assert_eq!(vec![PathBuf::from("/roms/b.smc")], progress.remaining());
+        assert!(Progress::parse("").remaining().is_empty());
+    }
+
+    fn test_summary_template() -> Summary {
+        let mut summary = Summary::new();
+
+        summary.entries.push(QueueEntry {
+            game: PathBuf::from("/roms/mario.smc"),
+            exit_code: Some(0),
+            duration: Duration::from_secs(754),
+            error: None,
+        });
+        summary.entries.push(QueueEntry {
+            game: PathBuf::from("/roms/zelda.smc"),
+            exit_code: None,
+            duration: Duration::ZERO,
+            error: Some("game file not found".to_string()),
+        });
+
+        summary
+    }
+
+    #[test]
+    fn format_duration() {
+        assert_eq!(
+            "0:12:34",
+            super::format_duration(Duration::from_secs(754))
+        );
+        assert_eq!(
+            "2:00:05",
+            super::format_duration(Duration::from_secs(7205))
+        );
+    }
+
+    #[test]
+    fn exit_policy() {
+        let summary = test_summary_template();
+
+        assert!(!ExitPolicy::Never.is_failure(&summary));
+        assert!(ExitPolicy::Any.is_failure(&summary));
+        assert!(!ExitPolicy::All.is_failure(&summary));
+        assert!(!ExitPolicy::Any.is_failure(&Summary::new()));
+        assert_eq!(Ok(ExitPolicy::All), "ALL".parse());
+        assert!("some".parse::<ExitPolicy>().is_err());
+    }
+
+    #[test]
+    fn summary_counts() {
+        let summary = test_summary_template();
+
+        assert_eq!(1, summary.succeeded());
+        assert_eq!(1, summary.failed());
+        assert_eq!("error", summary.entries[1].status());
+    }
+
+    #[test]
+    fn summary_json() {
+        let summary = test_summary_template();
+
+        assert_eq!(
+            "{\"games\":[\
+             {\"game\":\"/roms/mario.smc\",\"status\":\"ok\",\"exit_code\":0,\"duration\":754.000,\"error\":null},\
+             {\"game\":\"/roms/zelda.smc\",\"status\":\"error\",\"exit_code\":null,\"duration\":0.000,\"error\":\"game file not found\"}\
+             ],\"succeeded\":1,\"failed\":1,\"duration\":754.000}",
+            summary.to_json()
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use crate::settings::file;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use indexmap::map::IndexMap;
+
+/// Prefix of the sections in the user config, which describe a remote machine, like
+/// `[remote:htpc]`.
+pub const SECTION_PREFIX: &str = "remote:";
+
+/// Prefix of the keys in a remote section, which map a local directory to the one on the remote
+/// machine.
+const MAP_PREFIX: &str = "map.";
+
+/// Program to run commands on the remote machine.
+pub const SSH: &str = "ssh";
+
+/// A machine `RetroArch` is run on over SSH with `--remote`.  The local paths of games and cores
+/// are translated to those on the remote machine.
+///
+/// ```ini
+/// [remote:htpc]
+/// retroarch = /usr/bin/retroarch
+/// map./home/user/roms = /media/roms
+/// map./home/user/.config/retroarch/cores = /home/htpc/.config/retroarch/cores
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    pub host: String,
+    /// `RetroArch` on the remote machine, instead of the local one.
+    pub retroarch: Option<PathBuf>,
+    /// Local directories and the same directory on the remote machine.
+    pub mappings: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Remote {
+    /// A remote without a section, where all paths are the same as locally.
+    #[must_use]
+    pub fn new(host: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            retroarch: None,
+            mappings: vec![],
+        }
+    }
+
+    /// Create a remote from the keys of its section.  Any other key than `retroarch` and
+    /// `map.DIR` is an error.
+    pub fn from_section(
+        host: &str,
+        section: &IndexMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut remote: Self = Self::new(host);
+
+        for (key, value) in section {
+            if key == "retroarch" {
+                remote.retroarch = Some(PathBuf::from(value));
+            } else if let Some(local) = key.strip_prefix(MAP_PREFIX) {
+                remote
+                    .mappings
+                    .push((PathBuf::from(local), PathBuf::from(value)));
+            } else {
+                return Err(format!(
+                    "[{}{}] Unknown key \"{}\".",
+                    SECTION_PREFIX, host, key
+                ));
+            }
+        }
+
+        Ok(remote)
+    }
+
+    /// Path on the remote machine.  The longest mapped directory `path` is in is replaced, other
+    /// paths are kept as they are.
+    #[must_use]
+    pub fn map_path(&self, path: &Path) -> PathBuf {
+        file::map_prefix(path, &self.mappings)
+    }
+
+    /// Shell command run on the remote machine, like `ssh` expects it.  Arguments which are
+    /// absolute paths are mapped.  The local `appendconfig` file is not available there, so its
+    /// content is written to a temporary file on the remote machine for the run.
+    #[must_use]
+    pub fn script(
+        &self,
+        program: &str,
+        arguments: &[String],
+        env: &[(String, String)],
+        workdir: Option<&Path>,
+        appendconfig: Option<(&str, &str)>,
+    ) -> String {
+        let map = |argument: &str| {
+            if argument.starts_with('/') {
+                self.map_path(Path::new(argument)).display().to_string()
+            } else {
+                argument.to_string()
+            }
+        };
+
+        let mut command: Vec<String> = vec![];
+        if !env.is_empty() {
+            command.push("env".to_string());
+            command.extend(env.iter().map(|(key, value)| {
+                quote(&format!("{}={}", key, map(value)))
+            }));
+        }
+        command.push(quote(&self.retroarch.as_ref().map_or_else(
+            || map(program),
+            |retroarch| retroarch.display().to_string(),
+        )));
+        for argument in arguments {
+            command.push(match appendconfig {
+                Some((local, _)) if argument == local => {
+                    "\"$cfg\"".to_string()
+                }
+                _ => quote(&map(argument)),
+            });
+        }
+
+        let mut script: String = String::new();
+        if let Some(dir) = workdir {
+            script.push_str(&format!(
+                "cd {} && ",
+                quote(&self.map_path(dir).display().to_string())
+            ));
+        }
+        match appendconfig {
+            Some((_, content)) => script.push_str(&format!(
+                "cfg=$(mktemp) && printf %s {} > \"$cfg\" && {{ {}; status=$?; \
+                 rm -f \"$cfg\"; exit $status; }}",
+                quote(content),
+                command.join(" ")
+            )),
+            None => script.push_str(&format!("exec {}", command.join(" "))),
+        }
+
+        script
+    }
+}
+
+/// Host of an SSH target like `user@host`.
+#[must_use]
+pub fn host_of(target: &str) -> &str {
+    target.rsplit_once('@').map_or(target, |(_, host)| host)
+}
+
+// Quote for the POSIX shell on the remote machine.
+fn quote(text: &str) -> String {
+    shlex::try_quote(text).map_or_else(
+        |_| format!("'{}'", text.replace('\0', "").replace('\'', "'\\''")),
+        |quoted| quoted.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Remote;
+    use indexmap::map::IndexMap;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    fn remote() -> Remote {
+        Remote::from_section(
+            "htpc",
+            &IndexMap::from([
+                ("map./home/user".to_string(), "/home/htpc".to_string()),
+                ("map./home/user/roms".to_string(), "/media/roms".to_string()),
+            ]),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn from_section_invalid() {
+        assert!(Remote::from_section(
+            "htpc",
+            &IndexMap::from([("host".to_string(), "x".to_string())])
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn map_path() {
+        let remote = remote();
+
+        assert_eq!(
+            PathBuf::from("/media/roms/snes/mario.smc"),
+            remote.map_path(Path::new("/home/user/roms/snes/mario.smc"))
+        );
+        assert_eq!(
+            PathBuf::from("/home/htpc/cores/snes9x_libretro.so"),
+            remote.map_path(Path::new("/home/user/cores/snes9x_libretro.so"))
+        );
+        assert_eq!(
+            PathBuf::from("/usr/lib/libretro"),
+            remote.map_path(Path::new("/usr/lib/libretro"))
+        );
+    }
+
+    #[test]
+    fn script() {
+        let remote = remote();
+        let arguments: Vec<String> = [
+            "/home/user/roms/Mario World.smc",
+            "--appendconfig",
+            "/run/enjoy/1/override-1.cfg",
+            "-f",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+
+        assert_eq!(
+            "exec retroarch '/media/roms/Mario World.smc' --appendconfig \
+             /run/enjoy/1/override-1.cfg -f",
+            remote.script("retroarch", &arguments, &[], None, None)
+        );
+        assert_eq!(
+            "cd /home/htpc && cfg=$(mktemp) && printf %s 'video_smooth = \"true\"\n' \
+             > \"$cfg\" && { env 'A=1' retroarch '/media/roms/Mario World.smc' \
+             --appendconfig \"$cfg\" -f; status=$?; rm -f \"$cfg\"; exit $status; }",
+            remote.script(
+                "retroarch",
+                &arguments,
+                &[("A".to_string(), "1".to_string())],
+                Some(Path::new("/home/user")),
+                Some((
+                    "/run/enjoy/1/override-1.cfg",
+                    "video_smooth = \"true\"\n"
+                ))
+            )
+        );
+    }
+
+    #[test]
+    fn host_of() {
+        assert_eq!("htpc", super::host_of("user@htpc"));
+        assert_eq!("htpc", super::host_of("htpc"));
+    }
+}
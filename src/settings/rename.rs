@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// What would happen to a single game file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The file would be renamed to the target.
+    Rename,
+    /// The file is already named like its title.
+    Unchanged,
+    /// The title of the file is not in any database.
+    Unknown,
+    /// Another file already has the target name, or another game would get the same name.
+    Collision,
+}
+
+/// Proposed new name for a game file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Proposal {
+    pub game: PathBuf,
+    pub target: Option<PathBuf>,
+    pub status: Status,
+}
+
+/// Filename for a title from the database, keeping the extension of the original file.  The
+/// `extension` includes the leading dot, like `.sfc` or `.smc.gz`.  Slashes and control
+/// characters are replaced, so the result is always a single filename.
+#[must_use]
+pub fn file_name(title: &str, extension: &str) -> String {
+    let title: String = title
+        .trim()
+        .chars()
+        .map(|c| if c == '/' || c.is_control() { '-' } else { c })
+        .collect();
+
+    format!("{title}{extension}")
+}
+
+/// Propose the renames for a list of games with their new filename, or `None` if there is no
+/// title for it.  A rename collides if the target is taken by another file on disk or by the
+/// target of another game in the list, in which case none of these games are renamed.
+#[must_use]
+pub fn plan(games: Vec<(PathBuf, Option<String>)>) -> Vec<Proposal> {
+    let mut proposals: Vec<Proposal> = games
+        .into_iter()
+        .map(|(game, name)| {
+            let target: Option<PathBuf> =
+                name.map(|name| game.with_file_name(name));
+            let status: Status = match &target {
+                None => Status::Unknown,
+                Some(target) if *target == game => Status::Unchanged,
+                Some(target) if fs::symlink_metadata(target).is_ok() => {
+                    Status::Collision
+                }
+                Some(_) => Status::Rename,
+            };
+            Proposal {
+                game,
+                target,
+                status,
+            }
+        })
+        .collect();
+
+    let mut targets: HashMap<PathBuf, usize> = HashMap::new();
+    for proposal in &proposals {
+        if let Some(target) = &proposal.target {
+            *targets.entry(target.clone()).or_default() += 1;
+        }
+    }
+    for proposal in &mut proposals {
+        if proposal.status == Status::Rename
+            && proposal
+                .target
+                .as_ref()
+                .is_some_and(|target| targets[target] > 1)
+        {
+            proposal.status = Status::Collision;
+        }
+    }
+
+    proposals
+}
+
+/// Rename a game to its target.  The target is linked first and the original removed after, so
+/// an existing file is never replaced, even if it was created after the plan.
+pub fn apply(game: &Path, target: &Path) -> io::Result<()> {
+    fs::hard_link(game, target)?;
+    fs::remove_file(game)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Status;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Untested:
+    //  - apply()
+
+    #[test]
+    fn file_name() {
+        assert_eq!(
+            "Super Mario World (USA).sfc",
+            super::file_name(" Super Mario World (USA) ", ".sfc")
+        );
+        assert_eq!("AC-DC.smc.gz", super::file_name("AC/DC", ".smc.gz"));
+    }
+
+    #[test]
+    fn plan() {
+        let dir: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-rename-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Taken.sfc"), "").unwrap();
+
+        let proposals = super::plan(vec![
+            (dir.join("smw.sfc"), Some("Super Mario World.sfc".into())),
+            (dir.join("Same.sfc"), Some("Same.sfc".into())),
+            (dir.join("unknown.sfc"), None),
+            (dir.join("taken.sfc"), Some("Taken.sfc".into())),
+            (dir.join("a.sfc"), Some("Twice.sfc".into())),
+            (dir.join("b.sfc"), Some("Twice.sfc".into())),
+        ]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let status: Vec<&Status> =
+            proposals.iter().map(|proposal| &proposal.status).collect();
+        assert_eq!(
+            vec![
+                &Status::Rename,
+                &Status::Unchanged,
+                &Status::Unknown,
+                &Status::Collision,
+                &Status::Collision,
+                &Status::Collision,
+            ],
+            status
+        );
+        assert_eq!(
+            Some(dir.join("Super Mario World.sfc")),
+            proposals[0].target
+        );
+    }
+}
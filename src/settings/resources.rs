@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::Path;
+
+/// Folder of the batteries and power adapters in the sysfs of Linux.
+pub const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// File with the memory statistics of Linux.
+pub const MEMINFO: &str = "/proc/meminfo";
+
+/// Charge of the battery of a laptop or handheld.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Battery {
+    /// Charge in percent.
+    pub capacity: u8,
+    /// Running on battery, instead of being charged or full.
+    pub discharging: bool,
+}
+
+/// The battery in the power supplies of `dir`, see `POWER_SUPPLY_DIR`.  With more than one, the
+/// lowest charge is used.  `None` on machines without a battery.
+#[must_use]
+pub fn battery(dir: &Path) -> Option<Battery> {
+    let read = |path: &Path, name: &str| {
+        fs::read_to_string(path.join(name))
+            .map(|text| text.trim().to_string())
+            .ok()
+    };
+
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| read(path, "type").as_deref() == Some("Battery"))
+        .filter_map(|path| {
+            Some(Battery {
+                capacity: read(&path, "capacity")?.parse().ok()?,
+                discharging: read(&path, "status").as_deref()
+                    == Some("Discharging"),
+            })
+        })
+        .min_by_key(|battery| battery.capacity)
+}
+
+/// Memory in bytes available for new programs without swapping, from the `content` of
+/// `MEMINFO`.
+#[must_use]
+pub fn available_memory(content: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|value| value.trim().strip_suffix("kB"))
+        .and_then(|kilobytes| kilobytes.trim().parse::<u64>().ok())
+        .map(|kilobytes| kilobytes * 1024)
+}
+
+/// Parse a size in bytes like `512M` or `4G`.  The suffixes `K`, `M`, `G` and `T` are powers of
+/// 1024, like in systemd.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text: &str = text.trim();
+    let (number, factor): (&str, u64) = match text
+        .char_indices()
+        .last()
+        .map(|(i, c)| (i, c.to_ascii_uppercase()))
+    {
+        Some((i, 'K')) => (&text[..i], 1 << 10),
+        Some((i, 'M')) => (&text[..i], 1 << 20),
+        Some((i, 'G')) => (&text[..i], 1 << 30),
+        Some((i, 'T')) => (&text[..i], 1 << 40),
+        _ => (text, 1),
+    };
+
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(factor))
+        .ok_or_else(|| format!("Invalid size \"{}\", expected like 4G", text))
+}
+
+/// Format `bytes` for messages, like `1.5 GiB`.
+#[must_use]
+pub fn format_size(bytes: u64) -> String {
+    #[allow(clippy::cast_precision_loss)]
+    let gibibytes: f64 = bytes as f64 / f64::from(1 << 30);
+
+    format!("{:.1} GiB", gibibytes)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::Battery;
+
+    #[test]
+    fn battery() {
+        let dir: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-power-{}", std::process::id()));
+        for (name, kind, capacity, status) in [
+            ("AC", "Mains", "", ""),
+            ("BAT0", "Battery", "64\n", "Discharging\n"),
+            ("BAT1", "Battery", "15\n", "Discharging\n"),
+        ] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+            fs::write(dir.join(name).join("type"), kind).unwrap();
+            fs::write(dir.join(name).join("capacity"), capacity).unwrap();
+            fs::write(dir.join(name).join("status"), status).unwrap();
+        }
+
+        let battery = super::battery(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            Some(Battery {
+                capacity: 15,
+                discharging: true
+            }),
+            battery
+        );
+        assert_eq!(None, super::battery(&dir));
+    }
+
+    #[test]
+    fn available_memory() {
+        assert_eq!(
+            Some(2048 * 1024),
+            super::available_memory(
+                "MemTotal:       16000000 kB\nMemAvailable:       2048 kB\n"
+            )
+        );
+        assert_eq!(None, super::available_memory("MemTotal: 1 kB\n"));
+    }
+
+    #[test]
+    fn parse_size() {
+        assert_eq!(Ok(4 << 30), super::parse_size("4G"));
+        assert_eq!(Ok(512 << 20), super::parse_size("512m"));
+        assert_eq!(Ok(1000), super::parse_size("1000"));
+        assert!(super::parse_size("lots").is_err());
+        assert!(super::parse_size("G").is_err());
+    }
+}
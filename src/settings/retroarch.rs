@@ -1,30 +1,188 @@
 use crate::settings::file;
+use crate::settings::passthrough;
+use crate::settings::process;
+use crate::settings::provider::FsProvider;
 
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
 
 use configparser::ini;
 use indexmap::map::IndexMap;
 
-/// Check if a process is running.  If `print_pid` is `true`, then print the pid of found process
-/// to stdout.
-pub fn is_running(process_name: &str, print_pid: bool) -> bool {
-    let mut cmdline: Command = Command::new(String::from("pidof"));
+/// Keys of `retroarch.cfg` for directories of save data, each with the key to store them next to
+/// the content instead.
+pub const SAVE_DIRECTORY_KEYS: &[(&str, &str)] = &[
+    ("savefile_directory", "savefiles_in_content_dir"),
+    ("savestate_directory", "savestates_in_content_dir"),
+];
+
+/// Check if a process is running.  If `executable` is given, then the process must be started
+/// from that file, so wrappers or other programs with the same name are not mistaken for it.  The
+/// search can be limited to processes of the current user or session with `scope`.  If
+/// `print_pid` is `true`, then print the pid of found process to stdout.
+pub fn is_running(
+    process_name: &str,
+    executable: Option<&Path>,
+    scope: process::Scope,
+    print_pid: bool,
+) -> bool {
+    let executable: Option<PathBuf> = executable.and_then(file::which);
+
+    match process::find(process_name, executable.as_deref(), scope).first() {
+        Some(pid) => {
+            if print_pid {
+                println!("{}", pid);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Version number of `RetroArch` as `major.minor.patch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub u32, pub u32, pub u32);
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
 
-    // return one PID only
-    cmdline.arg("--single-shot");
-    if !print_pid {
-        // quiet mode, only set the exit code
-        cmdline.arg("-q");
+impl Version {
+    /// Parse a version like `1.15.0` or `v1.9`.  A missing patch number defaults to `0`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut numbers = text.trim_start_matches('v').split('.');
+        let major: u32 = numbers.next()?.parse().ok()?;
+        let minor: u32 = numbers.next()?.parse().ok()?;
+        let patch: u32 = numbers
+            .next()
+            .map(|n| {
+                n.chars()
+                    .take_while(char::is_ascii_digit)
+                    .collect::<String>()
+            })
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0);
+
+        Some(Self(major, minor, patch))
     }
 
-    cmdline
-        .arg(process_name)
-        .status()
-        .expect("Could not execute `pidof` command.")
-        .success()
+    /// Find the first word looking like a version number in the output of `retroarch --version`.
+    /// Depending on the release the line looks like "Version: 1.15.0 (Git 1a2b3c)" or
+    /// "RetroArch: Frontend for libretro -- v1.9.0 -- 1a2b3c".
+    pub fn from_output(output: &str) -> Option<Self> {
+        output
+            .split(|c: char| c.is_whitespace() || c == ':')
+            .filter(|word| word.contains('.'))
+            .find_map(Self::parse)
+    }
+}
+
+/// Get the version of `retroarch` executable.  It is probed only once per run for each
+/// executable and in addition cached to disk, keyed by the fullpath and modification time of the
+/// executable.  So the (slow) start of `retroarch --version` happens only after the program was
+/// updated.  `None` if the version could not be determined.
+pub fn version(retroarch: &Path) -> Option<Version> {
+    static VERSIONS: OnceLock<Mutex<HashMap<PathBuf, Option<Version>>>> =
+        OnceLock::new();
+
+    let fullpath: PathBuf = file::which(retroarch)?;
+    let mut versions = VERSIONS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    *versions
+        .entry(fullpath.clone())
+        .or_insert_with(|| probe_version(&fullpath))
+}
+
+// Read the version of the executable at `fullpath` from the disk cache, or run it and update the
+// cache.  The cache has one line per executable: "path<TAB>mtime<TAB>version"
+fn probe_version(fullpath: &Path) -> Option<Version> {
+    let modified: u64 = fs::metadata(fullpath)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path: String = fullpath.display().to_string();
+    let key: String = format!("{}\t{}", path, modified);
+    let cache: PathBuf = file::cache_dir().join("retroarch-version");
+    let content: String = fs::read_to_string(&cache).unwrap_or_default();
+
+    if let Some(cached) = cached_version(&content, &key) {
+        return Some(cached);
+    }
+
+    let output = Command::new(fullpath).arg("--version").output().ok()?;
+    let version: Version = Version::from_output(&format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    ))?;
+
+    // Replace the line of an older version of the same executable.
+    let mut lines: Vec<&str> = content
+        .lines()
+        .filter(|line| line.split('\t').next() != Some(path.as_str()))
+        .collect();
+    let line: String = format!("{}\t{}", key, version);
+    lines.push(&line);
+    if fs::create_dir_all(file::cache_dir()).is_ok() {
+        fs::write(&cache, format!("{}\n", lines.join("\n"))).ok();
+    }
+
+    Some(version)
+}
+
+// Lookup the version for `key`, which is "path<TAB>mtime", in the content of the disk cache.
+fn cached_version(content: &str, key: &str) -> Option<Version> {
+    content
+        .lines()
+        .filter_map(|line| line.rsplit_once('\t'))
+        .find(|(cached_key, _)| *cached_key == key)
+        .and_then(|(_, cached_version)| Version::parse(cached_version.trim()))
+}
+
+/// Split `arguments` into those supported by `version` and those removed, because their flag was
+/// added in a later version.  The long form of a flag can have its value attached with an equal
+/// sign like `--entryslot=3`, else the value following it is removed together with the flag.
+pub fn filter_unsupported(
+    arguments: &[String],
+    version: Option<Version>,
+) -> (Vec<String>, Vec<String>) {
+    let mut supported: Vec<String> = vec![];
+    let mut removed: Vec<String> = vec![];
+    let mut iter = arguments.iter();
+
+    while let Some(arg) = iter.next() {
+        let name: &str = arg.split('=').next().unwrap_or_default();
+        match passthrough::find(name) {
+            Some(flag) if !flag.is_supported(version) => {
+                removed.push(arg.to_string());
+                if !arg.contains('=') {
+                    if let Some(value) = iter.next() {
+                        removed.push(value.to_string());
+                    }
+                }
+            }
+            _ => supported.push(arg.to_string()),
+        }
+    }
+
+    (supported, removed)
 }
 
 /// Searches the default locations for the file `retroarch.cfg`, which is the main
@@ -65,23 +223,98 @@ pub fn search_default_config() -> Option<PathBuf> {
 /// Parses a `RetroArch` configuration file and returns a `IndexMap` from it.  The format is like
 /// a regular INI file without sections.  The set `lookup_keys` contains all key names to look
 /// for in the file and extract only those key and value pairs as strings.  The surrounding
-/// double quotes are removed from the value.
+/// double quotes are removed from the value.  The files in `appendconfigs` are applied on top in
+/// order and `#include` lines are followed, so the result is the effective config `RetroArch`
+/// sees.
 pub fn parse_retroarch_config(
     path: &Option<PathBuf>,
+    appendconfigs: &[PathBuf],
     lookup_keys: &HashSet<String>,
 ) -> Result<IndexMap<String, String>, Box<dyn Error>> {
+    let path: &PathBuf = path.as_ref().expect("No configuration file.");
+    let mut content: String = read_with_includes(path)
+        .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+
+    // Like `RetroArch`, missing files to append are skipped.
+    for file in appendconfigs {
+        if let Ok(append) = read_with_includes(&file::tilde(file)) {
+            content.push('\n');
+            content.push_str(&append);
+        }
+    }
+
     let mut ini = ini::Ini::new_cs();
+    let map = ini.read(content)?;
+
+    Ok(extract_default_inikeys(&map, lookup_keys))
+}
+
+/// Read a `retroarch.cfg` like file and replace each line `#include "file"` by the content of that
+/// file, like `RetroArch` does.  Relative paths are relative to the including file.  Keys after an
+/// include override those in it.  Missing includes are skipped and nesting deeper than
+/// `MAX_INCLUDE_DEPTH` is ignored, so include loops end.
+pub fn read_with_includes(path: &Path) -> io::Result<String> {
+    read_with_includes_depth(path, 0)
+}
+
+/// Nested `#include` files are only followed up to this depth.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+fn read_with_includes_depth(path: &Path, depth: usize) -> io::Result<String> {
+    let content: String = fs::read_to_string(path)?;
+    let mut result: String = String::with_capacity(content.len());
+
+    for line in content.lines() {
+        let include: Option<&str> = line
+            .trim()
+            .strip_prefix("#include")
+            .map(|name| name.trim().trim_matches('"'))
+            .filter(|name| !name.is_empty());
+        match include {
+            Some(name) if depth < MAX_INCLUDE_DEPTH => {
+                let included: PathBuf = path
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .join(file::tilde(Path::new(name)));
+                if let Ok(text) =
+                    read_with_includes_depth(&included, depth + 1)
+                {
+                    result.push_str(&text);
+                    result.push('\n');
+                }
+            }
+            _ => {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+    }
+
+    Ok(result)
+}
 
-    match ini.load(
-        &path
-            .as_ref()
-            .expect("No configuration file.")
-            .display()
-            .to_string(),
-    ) {
-        Ok(ini) => Ok(extract_default_inikeys(&ini, lookup_keys)),
-        Err(e) => Err(e.into()),
+/// Files given to `retroarch` with option `--appendconfig` in `arguments`, in order.  Multiple
+/// files in one argument are separated by a pipe, like `a.cfg|b.cfg`.
+#[must_use]
+pub fn appendconfigs(arguments: &[String]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = vec![];
+    let mut iter = arguments.iter();
+
+    while let Some(arg) = iter.next() {
+        let value: Option<&String> = match arg.strip_prefix("--appendconfig") {
+            Some("") => iter.next(),
+            Some(attached) if attached.starts_with('=') => {
+                files.extend(attached[1..].split('|').map(PathBuf::from));
+                continue;
+            }
+            _ => None,
+        };
+        if let Some(value) = value {
+            files.extend(value.split('|').map(PathBuf::from));
+        }
     }
+
+    files
 }
 
 // Searches all `lookup_keys` in `default` section of an INI structure and returns a regular
@@ -105,45 +338,273 @@ fn extract_default_inikeys(
     found_keys
 }
 
-/// Combine the `libretro-directory` and `libretro` core file to a fullpath.  Add a string to
-/// the end of the filename, if it does not end like that.  This includes the file extension
-/// and end of the filename part.  In example the common "_libretro.so" could be added.
+/// Combine each of the `libretro-directories` with the `libretro` core file, in order.  Add a
+/// string to the end of the filename, if it does not end like that.  This includes the file
+/// extension and end of the filename part.  In example the common "_libretro.so" could be added.
+/// A `libretro` with a fullpath or without any directory is the only candidate.
+pub fn libretro_candidates(
+    directories: &[PathBuf],
+    libretro: &Path,
+    endswith: &str,
+) -> Vec<PathBuf> {
+    let libretro: PathBuf = file::tilde(libretro);
+    let directories: Vec<PathBuf> =
+        if directories.is_empty() || libretro.has_root() {
+            vec![PathBuf::new()]
+        } else {
+            directories.iter().map(|dir| file::tilde(dir)).collect()
+        };
+
+    directories
+        .into_iter()
+        .map(|dir| file::endswith(endswith, dir.join(&libretro)))
+        .collect()
+}
+
+/// Fullpath of the first existing candidate of `libretro_candidates()`.  Cores installed by the
+/// distribution and those of the user can so live in different directories.  The core is looked
+/// up in the filesystem `fs`.
 pub fn libretro_fullpath(
-    directory: Option<PathBuf>,
+    fs: &dyn FsProvider,
+    directories: &[PathBuf],
     libretro: Option<PathBuf>,
     endswith: &str,
 ) -> Option<PathBuf> {
-    let mut fullpath: PathBuf = PathBuf::new();
-
-    if let Some(dir) = directory {
-        fullpath = file::tilde(&dir);
-    };
-    fullpath = fullpath.join(file::tilde(&libretro.unwrap_or_default()));
-    fullpath = file::endswith(endswith, fullpath);
-
-    file::to_fullpath(&fullpath)
+    libretro_candidates(directories, &libretro.unwrap_or_default(), endswith)
+        .iter()
+        .find_map(|candidate| file::to_fullpath_with(fs, candidate))
 }
 
 #[cfg(test)]
 mod tests {
 
     use std::collections::HashSet;
+    use std::env;
+    use std::fs;
 
     use configparser::ini;
     use indexmap::map::IndexMap;
 
+    use std::path::PathBuf;
+
+    use crate::settings::process::Scope;
+    use crate::settings::provider::fake::MemFs;
+
+    use super::Version;
+
     // Untested:
     //  - search_default_config()
-    //  - libretro_fullpath()
+    //  - version()
+
+    #[test]
+    fn libretro_fullpath() {
+        let fs = MemFs::with_files(&["/cores/snes9x_libretro.so"]);
+
+        assert_eq!(
+            Some(PathBuf::from("/cores/snes9x_libretro.so")),
+            super::libretro_fullpath(
+                &fs,
+                &[PathBuf::from("/cores")],
+                Some(PathBuf::from("snes9x")),
+                "_libretro.so"
+            )
+        );
+        assert_eq!(
+            Some(PathBuf::from("/cores/snes9x_libretro.so")),
+            super::libretro_fullpath(
+                &fs,
+                &[PathBuf::from("/elsewhere")],
+                Some(PathBuf::from("/cores/snes9x_libretro.so")),
+                "_libretro.so"
+            )
+        );
+        assert_eq!(
+            None,
+            super::libretro_fullpath(
+                &fs,
+                &[PathBuf::from("/cores")],
+                Some(PathBuf::from("mesen")),
+                "_libretro.so"
+            )
+        );
+    }
+
+    #[test]
+    fn libretro_fullpath_multiple_directories() {
+        let fs = MemFs::with_files(&[
+            "/home/user/cores/snes9x_libretro.so",
+            "/usr/lib/libretro/snes9x_libretro.so",
+            "/usr/lib/libretro/mesen_libretro.so",
+        ]);
+        let dirs = [
+            PathBuf::from("/home/user/cores"),
+            PathBuf::from("/usr/lib/libretro"),
+        ];
+
+        assert_eq!(
+            Some(PathBuf::from("/home/user/cores/snes9x_libretro.so")),
+            super::libretro_fullpath(
+                &fs,
+                &dirs,
+                Some(PathBuf::from("snes9x")),
+                "_libretro.so"
+            )
+        );
+        assert_eq!(
+            Some(PathBuf::from("/usr/lib/libretro/mesen_libretro.so")),
+            super::libretro_fullpath(
+                &fs,
+                &dirs,
+                Some(PathBuf::from("mesen")),
+                "_libretro.so"
+            )
+        );
+        assert_eq!(
+            vec![PathBuf::from("/cores/gambatte_libretro.so")],
+            super::libretro_candidates(
+                &dirs,
+                &PathBuf::from("/cores/gambatte"),
+                "_libretro.so"
+            )
+        );
+    }
+
+    #[test]
+    fn version_from_output_current() {
+        let output =
+            "Version: 1.15.0 (Git 1ab2c3d)\nCompiler: GCC (12.2.0) 64-bit";
+
+        assert_eq!(Some(Version(1, 15, 0)), Version::from_output(output));
+    }
+
+    #[test]
+    fn version_from_output_old() {
+        let output =
+            "RetroArch: Frontend for libretro -- v1.9.0 -- 1ab2c3d --";
+
+        assert_eq!(Some(Version(1, 9, 0)), Version::from_output(output));
+    }
+
+    #[test]
+    fn cached_version_by_path() {
+        let content =
+            "/usr/bin/retroarch\t100\t1.15.0\n/opt/ra/retroarch\t200\t1.7.5\n";
+
+        assert_eq!(
+            Some(Version(1, 7, 5)),
+            super::cached_version(content, "/opt/ra/retroarch\t200")
+        );
+        assert_eq!(
+            Some(Version(1, 15, 0)),
+            super::cached_version(content, "/usr/bin/retroarch\t100")
+        );
+        assert_eq!(
+            None,
+            super::cached_version(content, "/usr/bin/retroarch\t101")
+        );
+    }
+
+    #[test]
+    fn version_from_output_garbage() {
+        assert_eq!(None, Version::from_output("command not found"));
+    }
+
+    #[test]
+    fn filter_unsupported_old_version() {
+        let arguments: Vec<String> = ["--verbose", "--entryslot", "3", "-f"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+
+        let (supported, removed) =
+            super::filter_unsupported(&arguments, Some(Version(1, 9, 0)));
+
+        assert_eq!(vec!["--verbose", "-f"], supported);
+        assert_eq!(vec!["--entryslot", "3"], removed);
+    }
+
+    #[test]
+    fn filter_unsupported_unknown_version() {
+        let arguments: Vec<String> =
+            ["--entryslot=3"].iter().map(|a| a.to_string()).collect();
+
+        let (supported, removed) = super::filter_unsupported(&arguments, None);
+
+        assert_eq!(vec!["--entryslot=3"], supported);
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn appendconfigs() {
+        let arguments: Vec<String> = [
+            "--verbose",
+            "--appendconfig",
+            "a.cfg|b.cfg",
+            "--appendconfig=c.cfg",
+            "--appendconfigs",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+
+        assert_eq!(
+            vec![
+                PathBuf::from("a.cfg"),
+                PathBuf::from("b.cfg"),
+                PathBuf::from("c.cfg")
+            ],
+            super::appendconfigs(&arguments)
+        );
+    }
+
+    #[test]
+    fn parse_retroarch_config_includes() {
+        let dir =
+            env::temp_dir().join(format!("enjoy-ra-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(
+            dir.join("retroarch.cfg"),
+            "#include \"sub/paths.cfg\"\nlibretro_directory = \"/main\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("sub/paths.cfg"),
+            "savefile_directory = \"/saves\"\nlibretro_directory = \"/sub\"\n\
+             libretro_info_path = \"/info\"\n#include \"../retroarch.cfg\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("append.cfg"),
+            "libretro_info_path = \"/append\"\n",
+        )
+        .unwrap();
+        let keys: HashSet<String> = HashSet::from([
+            "libretro_directory".into(),
+            "libretro_info_path".into(),
+            "savefile_directory".into(),
+        ]);
+
+        let map = super::parse_retroarch_config(
+            &Some(dir.join("retroarch.cfg")),
+            &[dir.join("append.cfg"), dir.join("missing.cfg")],
+            &keys,
+        )
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!("/saves", map["savefile_directory"]);
+        assert_eq!("/main", map["libretro_directory"]);
+        assert_eq!("/append", map["libretro_info_path"]);
+    }
 
     #[test]
     fn is_running_cargo() {
-        assert_eq!(true, super::is_running("cargo", false));
+        assert_eq!(true, super::is_running("cargo", None, Scope::Any, false));
     }
 
     #[test]
     fn is_running_empty() {
-        assert_eq!(false, super::is_running("", false));
+        assert_eq!(false, super::is_running("", None, Scope::Any, false));
     }
 
     #[test]
@@ -0,0 +1,66 @@
+use std::path::Path;
+
+use indexmap::map::IndexMap;
+
+/// Name of a game as listed in `[romsets]`, which is its filename without extension in
+/// lowercase, like `mslug` for `mslug.zip`.
+#[must_use]
+pub fn romname(game: &Path) -> Option<String> {
+    game.file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .filter(|name| !name.is_empty())
+}
+
+/// Read a compatibility list with one `romname core` pair per line, such as exported from a
+/// spreadsheet.  The two are separated by whitespace, a comma, semicolon or equals sign.  Empty
+/// lines and comments starting with `#` are ignored, just as lines without a core.
+///
+/// ```text
+/// # romname, core
+/// mslug, fbneo
+/// sf2 = mame
+/// ```
+#[must_use]
+pub fn parse_list(content: &str) -> IndexMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line
+                .split(|c: char| {
+                    c.is_whitespace() || matches!(c, ',' | ';' | '=')
+                })
+                .filter(|field| !field.is_empty());
+            Some((fields.next()?.to_lowercase(), fields.next()?.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    #[test]
+    fn romname() {
+        assert_eq!(
+            Some("mslug".to_string()),
+            super::romname(Path::new("/roms/arcade/MSlug.zip"))
+        );
+        assert_eq!(None, super::romname(Path::new("/")));
+    }
+
+    #[test]
+    fn parse_list() {
+        let list = super::parse_list(
+            "# romname, core\nmslug, fbneo\n\nSF2 = mame\nkof98;fbneo\n\
+             broken\n",
+        );
+
+        assert_eq!(3, list.len());
+        assert_eq!(Some(&"fbneo".to_string()), list.get("mslug"));
+        assert_eq!(Some(&"mame".to_string()), list.get("sf2"));
+        assert_eq!(Some(&"fbneo".to_string()), list.get("kof98"));
+    }
+}
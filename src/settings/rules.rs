@@ -0,0 +1,355 @@
+use std::path::PathBuf;
+
+use configparser::ini;
+use indexmap::map::IndexMap;
+
+/// Additional settings of an extension or directory rule, besides the `core` or `libretro` it
+/// resolves to.  They are applied on top of the global options, when the rule matches a game.
+///
+/// ```ini
+/// [.n64 .z64]
+/// core = n64
+/// workdir = ~/roms/n64
+/// coreset = nightly
+/// title = Super Mario 64
+/// preset = sharp-pixels
+/// ai_service = on
+/// env.MESA_LOADER_DRIVER_OVERRIDE = zink
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleOptions {
+    pub workdir: Option<PathBuf>,
+    /// Name of a `[coreset:NAME]` section.
+    pub coreset: Option<String>,
+    /// Display title of the games, instead of their filename.
+    pub title: Option<String>,
+    /// Name of a `[preset:NAME]` section.
+    pub preset: Option<String>,
+    /// Niceness of `RetroArch`, checked when the command is built.
+    pub nice: Option<String>,
+    /// CPUs `RetroArch` may run on like `0-3,6`, checked when the command is built.
+    pub cpus: Option<String>,
+    /// Turn the AI service of `RetroArch` on or off, see `aiservice::overrides()`.
+    pub ai_service: Option<bool>,
+    pub env: IndexMap<String, String>,
+}
+
+impl RuleOptions {
+    /// Read all supported keys of a single section.  Unknown keys are ignored.
+    #[must_use]
+    pub fn from_section(ini: &ini::Ini, section: &str) -> Self {
+        let mut options: Self = Self::default();
+
+        if let Some(keys) = ini.get_map_ref().get(section) {
+            for (key, value) in keys
+                .iter()
+                .filter_map(|(k, v)| v.as_ref().map(|v| (k, v.to_string())))
+            {
+                if key == "workdir" {
+                    options.workdir = Some(PathBuf::from(value));
+                } else if key == "coreset" {
+                    options.coreset = Some(value);
+                } else if key == "title" {
+                    options.title = Some(value);
+                } else if key == "preset" {
+                    options.preset = Some(value);
+                } else if key == "nice" {
+                    options.nice = Some(value);
+                } else if key == "cpus" {
+                    options.cpus = Some(value);
+                } else if key == "ai_service" {
+                    options.ai_service = Some(matches!(
+                        value.trim().to_lowercase().as_str(),
+                        "1" | "true" | "yes" | "on"
+                    ));
+                } else if let Some(name) = env_key(key) {
+                    options.env.insert(name.to_string(), value);
+                }
+            }
+        }
+
+        options
+    }
+
+    /// Check if no option is set at all.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.workdir.is_none()
+            && self.coreset.is_none()
+            && self.title.is_none()
+            && self.preset.is_none()
+            && self.nice.is_none()
+            && self.cpus.is_none()
+            && self.ai_service.is_none()
+            && self.env.is_empty()
+    }
+
+    /// Combine with `other` options.  Values set in `other` take precedence, environment
+    /// variables are added or replaced individually.
+    pub fn merge(&mut self, other: &Self) {
+        if other.workdir.is_some() {
+            self.workdir = other.workdir.clone();
+        }
+        if other.coreset.is_some() {
+            self.coreset = other.coreset.clone();
+        }
+        if other.title.is_some() {
+            self.title = other.title.clone();
+        }
+        if other.preset.is_some() {
+            self.preset = other.preset.clone();
+        }
+        if other.nice.is_some() {
+            self.nice = other.nice.clone();
+        }
+        if other.cpus.is_some() {
+            self.cpus = other.cpus.clone();
+        }
+        if other.ai_service.is_some() {
+            self.ai_service = other.ai_service;
+        }
+        for (key, value) in &other.env {
+            self.env.insert(key.to_string(), value.to_string());
+        }
+    }
+}
+
+/// An alias in section `[cores]`.  Besides the plain form `snes = snes9x`, an inline table can
+/// declare a display name and the extensions handled by the core, which replaces a separate
+/// extension rule for simple cases.  Extensions are lowercase and without leading dot.  The
+/// keys `nice` and `cpus` apply to all games run with the core.
+///
+/// ```ini
+/// [cores]
+/// snes = { libretro = "snes9x", label = "Super Nintendo", extensions = "smc sfc" }
+/// ps2 = { libretro = "pcsx2", nice = -5, cpus = "2-7" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoreEntry {
+    pub libretro: PathBuf,
+    pub label: Option<String>,
+    pub extensions: Vec<String>,
+    pub nice: Option<String>,
+    pub cpus: Option<String>,
+}
+
+impl CoreEntry {
+    /// Parse the value of an alias.  `None` if it is empty, the inline table is malformed or
+    /// misses the `libretro` key.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        let value: &str = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        if !value.starts_with('{') {
+            return Some(Self {
+                libretro: PathBuf::from(value),
+                ..Self::default()
+            });
+        }
+
+        let table: IndexMap<String, String> = parse_inline_table(value)?;
+        Some(Self {
+            libretro: PathBuf::from(table.get("libretro")?),
+            label: table.get("label").cloned(),
+            extensions: table
+                .get("extensions")
+                .map(|e| {
+                    e.split_whitespace()
+                        .map(|e| e.trim_start_matches('.').to_lowercase())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            nice: table.get("nice").cloned(),
+            cpus: table.get("cpus").cloned(),
+        })
+    }
+}
+
+/// Parse an inline table like `{ key = "value", other = value }`.  Values can be enclosed in
+/// double quotes to include commas.  `None` if the braces are missing or an entry has no `=`.
+#[must_use]
+pub fn parse_inline_table(text: &str) -> Option<IndexMap<String, String>> {
+    let inner: &str = text.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let mut entries: Vec<String> = vec![];
+    let mut current = String::new();
+    let mut quoted: bool = false;
+
+    for c in inner.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ',' if !quoted => entries.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    entries.push(current);
+
+    let mut table: IndexMap<String, String> = IndexMap::new();
+    for entry in entries.iter().filter(|e| !e.trim().is_empty()) {
+        let (key, value) = entry.split_once('=')?;
+        let value: &str = value.trim();
+        let value: &str = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        table.insert(key.trim().to_string(), value.to_string());
+    }
+
+    Some(table)
+}
+
+/// Extract the name of an environment variable from a key like `env.SDL_VIDEODRIVER`.  `None`
+/// if the key does not start with `env.` or the name is empty.
+#[must_use]
+pub fn env_key(key: &str) -> Option<&str> {
+    key.strip_prefix("env.").filter(|name| !name.is_empty())
+}
+
+/// Parse a `KEY=VALUE` assignment of an environment variable from the commandline.
+pub fn parse_env_assignment(
+    assignment: &str,
+) -> Result<(String, String), String> {
+    assignment
+        .split_once('=')
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got \"{}\"", assignment))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+
+    use configparser::ini;
+
+    use super::CoreEntry;
+    use super::RuleOptions;
+
+    fn test_ini_template() -> ini::Ini {
+        let mut ini: ini::Ini = ini::Ini::new_cs();
+
+        ini.read(String::from(
+            "
+            [.n64 .z64]
+            core = n64
+            workdir = ~/roms/n64
+            coreset = nightly
+            title = Super Mario 64
+            preset = sharp-pixels
+            nice = -5
+            ai_service = on
+            env.MESA_LOADER_DRIVER_OVERRIDE = zink
+            env. = ignored
+            ",
+        ))
+        .unwrap();
+
+        ini
+    }
+
+    #[test]
+    fn from_section() {
+        let ini = test_ini_template();
+        let options = RuleOptions::from_section(&ini, ".n64 .z64");
+
+        assert_eq!(Some(PathBuf::from("~/roms/n64")), options.workdir);
+        assert_eq!(Some("nightly".to_string()), options.coreset);
+        assert_eq!(Some("Super Mario 64".to_string()), options.title);
+        assert_eq!(Some("sharp-pixels".to_string()), options.preset);
+        assert_eq!(Some("-5".to_string()), options.nice);
+        assert_eq!(Some(true), options.ai_service);
+        assert_eq!(
+            Some(&"zink".to_string()),
+            options.env.get("MESA_LOADER_DRIVER_OVERRIDE")
+        );
+        assert_eq!(1, options.env.len());
+    }
+
+    #[test]
+    fn from_section_missing() {
+        let ini = test_ini_template();
+        let options = RuleOptions::from_section(&ini, ".gb");
+
+        assert!(options.is_empty());
+    }
+
+    #[test]
+    fn merge_override() {
+        let mut global = RuleOptions {
+            workdir: Some(PathBuf::from("/tmp")),
+            ..Default::default()
+        };
+        global.env.insert("A".to_string(), "1".to_string());
+        global.env.insert("B".to_string(), "2".to_string());
+
+        let mut rule = RuleOptions::default();
+        rule.env.insert("B".to_string(), "3".to_string());
+
+        global.merge(&rule);
+
+        assert_eq!(Some(PathBuf::from("/tmp")), global.workdir);
+        assert_eq!(Some(&"1".to_string()), global.env.get("A"));
+        assert_eq!(Some(&"3".to_string()), global.env.get("B"));
+    }
+
+    #[test]
+    fn core_entry_plain() {
+        assert_eq!(
+            Some(CoreEntry {
+                libretro: PathBuf::from("snes9x"),
+                ..Default::default()
+            }),
+            CoreEntry::parse("snes9x")
+        );
+        assert_eq!(None, CoreEntry::parse(" "));
+    }
+
+    #[test]
+    fn core_entry_table() {
+        let entry = CoreEntry::parse(
+            "{ libretro = \"snes9x\", label = \"Super Nintendo, SFC\", extensions = \".SMC sfc\" }",
+        )
+        .unwrap();
+
+        assert_eq!(PathBuf::from("snes9x"), entry.libretro);
+        assert_eq!(Some("Super Nintendo, SFC".to_string()), entry.label);
+        assert_eq!(vec!["smc", "sfc"], entry.extensions);
+        assert_eq!(None, entry.nice);
+
+        let entry = CoreEntry::parse(
+            "{ libretro = pcsx2, nice = -5, cpus = \"2-7\" }",
+        )
+        .unwrap();
+        assert_eq!(Some("-5".to_string()), entry.nice);
+        assert_eq!(Some("2-7".to_string()), entry.cpus);
+    }
+
+    #[test]
+    fn core_entry_table_invalid() {
+        assert_eq!(None, CoreEntry::parse("{ label = \"Super Nintendo\" }"));
+        assert_eq!(None, CoreEntry::parse("{ libretro = \"snes9x }"));
+        assert_eq!(None, CoreEntry::parse("{ libretro }"));
+    }
+
+    #[test]
+    fn parse_env_assignment() {
+        assert_eq!(
+            Ok(("SDL_VIDEODRIVER".to_string(), "x11".to_string())),
+            super::parse_env_assignment("SDL_VIDEODRIVER=x11")
+        );
+        assert_eq!(
+            Ok(("EMPTY".to_string(), "".to_string())),
+            super::parse_env_assignment("EMPTY=")
+        );
+        assert!(super::parse_env_assignment("=value").is_err());
+        assert!(super::parse_env_assignment("NOVALUE").is_err());
+    }
+}
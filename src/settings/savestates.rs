@@ -0,0 +1,157 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+/// Savestate slot to load at start with `--load-state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    /// The slot of the most recently written savestate of the game.
+    Newest,
+    Number(u32),
+}
+
+/// A savestate file of a game, like `Mario.state2` for slot 2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State {
+    pub path: PathBuf,
+    pub slot: u32,
+    /// Seconds since Unix epoch of the last write.
+    pub modified: u64,
+}
+
+/// Slot of a savestate file name of the game with file stem `stem`.  Slot 0 has no number, like
+/// `Mario.state`.  `None` for other files, like the automatic state `Mario.state.auto`.
+#[must_use]
+pub fn slot_of(name: &str, stem: &str) -> Option<u32> {
+    let number: &str = name.strip_prefix(stem)?.strip_prefix(".state")?;
+
+    if number.is_empty() {
+        Some(0)
+    } else if number.chars().all(|c| c.is_ascii_digit()) {
+        number.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// All savestates of `game` in `dir` and its subdirectories one level down, where `RetroArch`
+/// puts them when sorting savestates by core or by content directory.  Newest first.
+#[must_use]
+pub fn find(dir: &Path, game: &Path) -> Vec<State> {
+    let stem: String = game
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let mut dirs: Vec<PathBuf> = vec![dir.to_path_buf()];
+    dirs.extend(
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir()),
+    );
+
+    let mut states: Vec<State> = dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let slot: u32 =
+                slot_of(&entry.file_name().to_string_lossy(), &stem)?;
+            let modified: u64 = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()?
+                .duration_since(UNIX_EPOCH)
+                .ok()?
+                .as_secs();
+            Some(State {
+                path: entry.path(),
+                slot,
+                modified,
+            })
+        })
+        .collect();
+    states
+        .sort_by(|a, b| b.modified.cmp(&a.modified).then(a.slot.cmp(&b.slot)));
+
+    states
+}
+
+/// The savestate to load for `slot` from `states`, ordered newest first.
+#[must_use]
+pub fn select(states: &[State], slot: Slot) -> Option<&State> {
+    match slot {
+        Slot::Newest => states.first(),
+        Slot::Number(number) => {
+            states.iter().find(|state| state.slot == number)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    use super::Slot;
+    use super::State;
+
+    #[test]
+    fn slot_of() {
+        assert_eq!(Some(0), super::slot_of("Mario.state", "Mario"));
+        assert_eq!(Some(12), super::slot_of("Mario.state12", "Mario"));
+        assert_eq!(None, super::slot_of("Mario.state.auto", "Mario"));
+        assert_eq!(None, super::slot_of("Mario.srm", "Mario"));
+        assert_eq!(None, super::slot_of("Mario 2.state", "Mario"));
+    }
+
+    #[test]
+    fn find_and_select() {
+        let dir: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-savestates-{}", std::process::id()));
+        fs::create_dir_all(dir.join("Snes9x")).unwrap();
+        for file in [
+            "Mario.state",
+            "Snes9x/Mario.state3",
+            "Mario.state.auto",
+            "Zelda.state1",
+        ] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+
+        let states: Vec<State> =
+            super::find(&dir, Path::new("/roms/Mario.smc"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut slots: Vec<u32> =
+            states.iter().map(|state| state.slot).collect();
+        slots.sort_unstable();
+        assert_eq!(vec![0, 3], slots);
+        assert_eq!(
+            Some(&dir.join("Snes9x/Mario.state3")),
+            super::select(&states, Slot::Number(3)).map(|state| &state.path)
+        );
+        assert_eq!(None, super::select(&states, Slot::Number(1)));
+        assert!(super::select(&states, Slot::Newest).is_some());
+    }
+
+    #[test]
+    fn select_newest() {
+        let state = |slot: u32, modified: u64| State {
+            path: PathBuf::from(format!("Mario.state{}", slot)),
+            slot,
+            modified,
+        };
+        let states: Vec<State> = vec![state(2, 300), state(0, 200)];
+
+        assert_eq!(Some(&states[0]), super::select(&states, Slot::Newest));
+        assert_eq!(None, super::select(&[], Slot::Newest));
+    }
+}
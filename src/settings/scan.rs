@@ -0,0 +1,285 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rayon::prelude::*;
+use wildmatch::WildMatch;
+
+/// File with patterns of files and folders to skip when scanning for games, in the folder they
+/// apply to and with the syntax of `.gitignore`.
+pub const IGNORE_FILE: &str = ".enjoyignore";
+
+/// Folders created by operating systems and NAS devices, which never contain games.
+pub const SYSTEM_DIRS: [&str; 5] = [
+    "lost+found",
+    "$RECYCLE.BIN",
+    "System Volume Information",
+    "@eaDir",
+    "#recycle",
+];
+
+/// A single line of an ignore file.
+#[derive(Debug)]
+struct Pattern {
+    glob: WildMatch,
+    /// Starts with `!` and includes a path again, which an earlier pattern ignored.
+    negated: bool,
+    /// Ends with `/` and only matches folders.
+    dir_only: bool,
+    /// Contains a `/` before the end, so it matches the path relative to the folder of the
+    /// ignore file instead of the name at any depth.
+    anchored: bool,
+}
+
+/// The patterns of an ignore file and the folder it is in.
+#[derive(Debug)]
+pub struct IgnoreFile {
+    dir: PathBuf,
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreFile {
+    /// Parse the content of an ignore file in `dir`.  Empty lines and lines starting with `#`
+    /// are skipped.  Only the wildcards `*` and `?` are supported.
+    #[must_use]
+    pub fn parse(dir: &Path, content: &str) -> Self {
+        let patterns: Vec<Pattern> = content
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(line) => (true, line),
+                    None => (false, line),
+                };
+                let (dir_only, line) = match line.strip_suffix('/') {
+                    Some(line) => (true, line),
+                    None => (false, line),
+                };
+                let anchored: bool = line.contains('/');
+
+                Pattern {
+                    glob: WildMatch::new(line.trim_start_matches('/')),
+                    negated,
+                    dir_only,
+                    anchored,
+                }
+            })
+            .collect();
+
+        Self {
+            dir: dir.to_path_buf(),
+            patterns,
+        }
+    }
+
+    /// Read the ignore file of `dir`, if there is one.
+    #[must_use]
+    pub fn load(dir: &Path) -> Option<Self> {
+        fs::read_to_string(dir.join(IGNORE_FILE))
+            .ok()
+            .map(|content| Self::parse(dir, &content))
+    }
+
+    /// Check if the last pattern matching `path` ignores it or includes it again.  `None` if no
+    /// pattern matches.
+    fn decide(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative: String = path
+            .strip_prefix(&self.dir)
+            .ok()?
+            .to_string_lossy()
+            .to_string();
+        let name: String = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        self.patterns
+            .iter()
+            .rev()
+            .filter(|pattern| is_dir || !pattern.dir_only)
+            .find(|pattern| {
+                if pattern.anchored {
+                    pattern.glob.matches(&relative)
+                } else {
+                    pattern.glob.matches(&name)
+                }
+            })
+            .map(|pattern| !pattern.negated)
+    }
+}
+
+/// Check if `path` is ignored by the ignore files of its folder and the folders above.  The
+/// ignore files are ordered from the top folder down, so a deeper one can include a path again.
+#[must_use]
+pub fn is_ignored(
+    ignores: &[Arc<IgnoreFile>],
+    path: &Path,
+    is_dir: bool,
+) -> bool {
+    ignores
+        .iter()
+        .rev()
+        .find_map(|ignore| ignore.decide(path, is_dir))
+        .unwrap_or(false)
+}
+
+/// All files in `dir` and its subdirectories, sorted by path.  Hidden files and folders, system
+/// folders like `lost+found` and everything matched by an `.enjoyignore` file are skipped.
+/// Symbolic links to folders are not followed, so a link back to a parent cannot loop.  The
+/// subdirectories are read in parallel, which speeds up slow network shares a lot.
+pub fn files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = walk(dir, &[], true);
+    files.par_sort_unstable();
+
+    files
+}
+
+/// Same as `files()`, but only the files directly in `dir` without its subdirectories.
+#[must_use]
+pub fn top_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = walk(dir, &[], false);
+    files.sort_unstable();
+
+    files
+}
+
+fn walk(
+    dir: &Path,
+    parents: &[Arc<IgnoreFile>],
+    recursive: bool,
+) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    // The ignore files are shared with the threads reading the subdirectories.
+    let mut ignores: Vec<Arc<IgnoreFile>> = parents.to_vec();
+    ignores.extend(IgnoreFile::load(dir).map(Arc::new));
+    let mut files: Vec<PathBuf> = vec![];
+    let mut folders: Vec<PathBuf> = vec![];
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let path: PathBuf = entry.path();
+        let is_dir: bool = entry.file_type().is_ok_and(|kind| kind.is_dir());
+        if name.starts_with('.')
+            || (is_dir && SYSTEM_DIRS.contains(&name.as_ref()))
+            || is_ignored(&ignores, &path, is_dir)
+        {
+            continue;
+        }
+        if is_dir {
+            if recursive {
+                folders.push(path);
+            }
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+
+    files.par_extend(
+        folders
+            .par_iter()
+            .flat_map_iter(|folder| walk(folder, &ignores, true)),
+    );
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use super::IgnoreFile;
+
+    #[test]
+    fn files_sorted() {
+        let src = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src");
+        let files = super::files(&src);
+
+        assert!(files.contains(&src.join("main.rs")));
+        assert!(files.contains(&src.join("settings/file.rs")));
+        assert!(files.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(super::files(&src.join("main.rs")).is_empty());
+    }
+
+    #[test]
+    fn top_files() {
+        let src = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src");
+        let files = super::top_files(&src);
+
+        assert!(files.contains(&src.join("main.rs")));
+        assert!(!files.contains(&src.join("settings/file.rs")));
+        assert!(!files.contains(&src.join("settings")));
+    }
+
+    #[test]
+    fn is_ignored() {
+        let ignores: Vec<Arc<IgnoreFile>> = vec![
+            Arc::new(IgnoreFile::parse(
+                Path::new("/roms"),
+                "# comment\n*.txt\nhacks/\n/bios\nsnes/beta*\n",
+            )),
+            Arc::new(IgnoreFile::parse(
+                Path::new("/roms/gb"),
+                "!readme.txt\n",
+            )),
+        ];
+        let ignored = |path: &str, is_dir: bool| {
+            super::is_ignored(&ignores, Path::new(path), is_dir)
+        };
+
+        assert!(ignored("/roms/snes/notes.txt", false));
+        assert!(!ignored("/roms/gb/readme.txt", false));
+        assert!(ignored("/roms/snes/hacks", true));
+        assert!(!ignored("/roms/snes/hacks", false));
+        assert!(ignored("/roms/bios", true));
+        assert!(!ignored("/roms/snes/bios", true));
+        assert!(ignored("/roms/snes/beta1.smc", false));
+        assert!(!ignored("/roms/gb/beta1.gb", false));
+        assert!(!ignored("/roms/snes/mario.smc", false));
+        assert!(!ignored("/other/notes", false));
+    }
+
+    #[test]
+    fn files_with_ignore_file() {
+        let root: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-scan-{}", std::process::id()));
+        for dir in ["snes/hacks", "gb", "lost+found", ".hidden"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "snes/mario.smc",
+            "snes/notes.txt",
+            "snes/hacks/mario2.smc",
+            "gb/tetris.gb",
+            "gb/readme.txt",
+            "lost+found/game.gb",
+            ".hidden/game.gb",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(root.join(super::IGNORE_FILE), "*.txt\nhacks/\n").unwrap();
+        fs::write(root.join("gb").join(super::IGNORE_FILE), "!*.txt\n")
+            .unwrap();
+
+        let files = super::files(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            vec![
+                root.join("gb/readme.txt"),
+                root.join("gb/tetris.gb"),
+                root.join("snes/mario.smc"),
+            ],
+            files
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::io;
+use std::mem;
+
+/// CPU priority and the CPUs `RetroArch` may run on.  Demanding cores like those of the PS2 or
+/// GameCube can get a higher priority or dedicated CPUs, without affecting all other games.
+///
+/// ```ini
+/// [/home/user/roms/ps2/]
+/// nice = -5
+/// cpus = 2-7
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scheduling {
+    /// Niceness from -20 (highest priority) to 19 (lowest).  Values below 0 need the permission to
+    /// raise the priority, such as `CAP_SYS_NICE`.
+    pub nice: Option<i32>,
+    /// Numbers of the CPUs, starting at 0.
+    pub cpus: Option<Vec<usize>>,
+}
+
+impl Scheduling {
+    /// Parse the text values of the keys `nice` and `cpus`.
+    pub fn parse(
+        nice: Option<&str>,
+        cpus: Option<&str>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            nice: nice.map(parse_nice).transpose()?,
+            cpus: cpus.map(parse_cpus).transpose()?,
+        })
+    }
+
+    /// Check if the defaults of the operating system are kept.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nice.is_none() && self.cpus.is_none()
+    }
+
+    /// Set the priority and CPUs of the running process `pid`.
+    pub fn apply(&self, pid: u32) -> io::Result<()> {
+        let pid: libc::pid_t = libc::pid_t::try_from(pid)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        if let Some(nice) = self.nice {
+            // The type of `which` differs between the C libraries.
+            #[allow(clippy::useless_conversion)]
+            let result: i32 = unsafe {
+                libc::setpriority(
+                    libc::PRIO_PROCESS.try_into().unwrap_or_default(),
+                    pid.unsigned_abs(),
+                    nice,
+                )
+            };
+            if result != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        if let Some(cpus) = &self.cpus {
+            let result: i32 = unsafe {
+                let mut set: libc::cpu_set_t = mem::zeroed();
+                for cpu in cpus {
+                    libc::CPU_SET(*cpu, &mut set);
+                }
+                libc::sched_setaffinity(
+                    pid,
+                    mem::size_of::<libc::cpu_set_t>(),
+                    &set,
+                )
+            };
+            if result != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse a niceness from -20 to 19.
+pub fn parse_nice(text: &str) -> Result<i32, String> {
+    text.trim()
+        .parse::<i32>()
+        .ok()
+        .filter(|nice| (-20..=19).contains(nice))
+        .ok_or_else(|| {
+            format!("Invalid nice \"{}\", expected -20 to 19.", text)
+        })
+}
+
+/// Parse a list of CPUs like `taskset` does, such as `0-3,6`.
+pub fn parse_cpus(text: &str) -> Result<Vec<usize>, String> {
+    let error =
+        || format!("Invalid cpus \"{}\", expected a list like 0-3,6.", text);
+    let max: usize = mem::size_of::<libc::cpu_set_t>() * 8;
+    let mut cpus: Vec<usize> = vec![];
+
+    for part in text.split(',').map(str::trim) {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let first: usize = first.trim().parse().map_err(|_| error())?;
+        let last: usize = last.trim().parse().map_err(|_| error())?;
+        if first > last || last >= max {
+            return Err(error());
+        }
+        cpus.extend(first..=last);
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+
+    Ok(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Scheduling;
+
+    // Untested:
+    //  - Scheduling::apply()
+
+    #[test]
+    fn parse_nice() {
+        assert_eq!(Ok(-5), super::parse_nice("-5"));
+        assert_eq!(Ok(19), super::parse_nice(" 19 "));
+        assert!(super::parse_nice("20").is_err());
+        assert!(super::parse_nice("high").is_err());
+    }
+
+    #[test]
+    fn parse_cpus() {
+        assert_eq!(Ok(vec![0, 1, 2, 3, 6]), super::parse_cpus("0-3,6"));
+        assert_eq!(Ok(vec![2]), super::parse_cpus("2, 2"));
+        assert!(super::parse_cpus("3-1").is_err());
+        assert!(super::parse_cpus("").is_err());
+        assert!(super::parse_cpus("99999").is_err());
+    }
+
+    #[test]
+    fn parse() {
+        let scheduling = Scheduling::parse(Some("-5"), Some("2-3")).unwrap();
+
+        assert_eq!(Some(-5), scheduling.nice);
+        assert_eq!(Some(vec![2, 3]), scheduling.cpus);
+        assert!(!scheduling.is_empty());
+        assert!(Scheduling::parse(None, None).unwrap().is_empty());
+        assert!(Scheduling::parse(Some("x"), None).is_err());
+    }
+}
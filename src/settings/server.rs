@@ -0,0 +1,479 @@
+use crate::settings::file;
+use crate::settings::inoutput::json_string;
+
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+#[cfg(not(feature = "tokio"))]
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+#[cfg(not(feature = "tokio"))]
+use std::thread;
+use std::time::Duration;
+
+use indexmap::map::IndexMap;
+
+/// Port of the HTTP server, if none is given.
+pub const DEFAULT_PORT: u16 = 8787;
+
+/// Upper limit for the request line and all headers together.
+const MAX_HEADER_BYTES: usize = 8192;
+
+/// Upper limit for the body of a request.
+const MAX_BODY_BYTES: usize = 4096;
+
+/// Work to continue in the background after a response is sent, like a running game.
+pub type Job = Box<dyn FnOnce() + Send>;
+
+/// What a handler returns for a request: the response and optional background work.
+pub type Reply = (Response, Option<Job>);
+
+/// A parsed HTTP request.  Header names are lowercase.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: IndexMap<String, String>,
+    pub body: String,
+}
+
+/// An HTTP response with a JSON body.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    #[must_use]
+    pub const fn json(status: u16, body: String) -> Self {
+        Self { status, body }
+    }
+
+    /// Response with a body like `{"error":"message"}`.
+    #[must_use]
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, format!("{{\"error\":{}}}", json_string(message)))
+    }
+
+    const fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            411 => "Length Required",
+            413 => "Payload Too Large",
+            422 => "Unprocessable Entity",
+            _ => "Internal Server Error",
+        }
+    }
+
+    /// Complete response including status line and headers.  The connection is always closed.
+    #[must_use]
+    pub fn to_http(&self) -> String {
+        format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            self.reason(),
+            self.body.len(),
+            self.body
+        )
+    }
+}
+
+/// Read and validate a request.  On any violation of the expected format or limits the error
+/// response to send back is returned instead.
+pub fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, Response> {
+    let mut request: Request = Request::default();
+    let mut header_bytes: usize = 0;
+
+    loop {
+        let mut line = String::new();
+        let read: usize = reader
+            .by_ref()
+            .take((MAX_HEADER_BYTES - header_bytes + 1) as u64)
+            .read_line(&mut line)
+            .map_err(|_| Response::error(400, "could not read request"))?;
+        header_bytes += read;
+        if read == 0 || header_bytes > MAX_HEADER_BYTES {
+            return Err(Response::error(
+                400,
+                "incomplete or too large header",
+            ));
+        }
+
+        let line: &str = line.trim_end_matches(['\r', '\n']);
+        if request.method.is_empty() {
+            let parts: Vec<&str> = line.split(' ').collect();
+            match parts.as_slice() {
+                [method, path, version] if version.starts_with("HTTP/1.") => {
+                    request.method = (*method).to_string();
+                    request.path = (*path).to_string();
+                }
+                _ => return Err(Response::error(400, "invalid request line")),
+            }
+        } else if line.is_empty() {
+            break;
+        } else {
+            match line.split_once(':') {
+                Some((name, value)) => {
+                    request.headers.insert(
+                        name.trim().to_lowercase(),
+                        value.trim().to_string(),
+                    );
+                }
+                None => return Err(Response::error(400, "invalid header")),
+            }
+        }
+    }
+
+    let length: usize = match request.headers.get("content-length") {
+        Some(value) => value
+            .parse()
+            .map_err(|_| Response::error(400, "invalid content length"))?,
+        // A body without length, like with chunked transfer encoding, is not supported.
+        None if request.headers.contains_key("transfer-encoding") => {
+            return Err(Response::error(411, "content length required"))
+        }
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(Response::error(413, "body too large"));
+    }
+
+    let mut body: Vec<u8> = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|_| Response::error(400, "incomplete body"))?;
+    request.body = String::from_utf8(body)
+        .map_err(|_| Response::error(400, "body is not valid UTF-8"))?;
+
+    Ok(request)
+}
+
+/// Check the header `Authorization: Bearer <token>` of a request.
+#[must_use]
+pub fn is_authorized(request: &Request, token: &str) -> bool {
+    request
+        .headers
+        .get("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| {
+            constant_time_eq(given.as_bytes(), token.as_bytes())
+        })
+}
+
+/// Compare two secrets without leaking the position of the first difference through timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Read the secret token from `path`.  If the file does not exist, a new random token is
+/// generated and saved, readable by the current user only.  An existing file must be a regular
+/// file owned by the current user with mode `0600`, otherwise someone else could have planted a
+/// token known to them.
+pub fn load_token(path: &Path) -> io::Result<String> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => return read_token(path, &metadata),
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        Err(_) => (),
+    }
+
+    let mut random: [u8; 16] = [0; 16];
+    fs::File::open("/dev/urandom")?.read_exact(&mut random)?;
+    let token: String = random.iter().map(|b| format!("{:02x}", b)).collect();
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(format!("{}\n", token).as_bytes())?;
+
+    Ok(token)
+}
+
+// Read the token of an existing file, after checking its owner and mode.  The opened file must be
+// the same as the checked one, so it cannot be swapped in between.
+fn read_token(path: &Path, metadata: &fs::Metadata) -> io::Result<String> {
+    if !metadata.is_file()
+        || metadata.uid() != file::current_uid()?
+        || metadata.mode() & 0o777 != 0o600
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "token file must be a regular file owned by the current user with mode 0600",
+        ));
+    }
+
+    let mut opened: fs::File = fs::File::open(path)?;
+    let current: fs::Metadata = opened.metadata()?;
+    if (current.dev(), current.ino()) != (metadata.dev(), metadata.ino()) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "token file was replaced while reading it",
+        ));
+    }
+    let mut token: String = String::new();
+    opened.read_to_string(&mut token)?;
+    let token: String = token.trim().to_string();
+
+    if token.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "token file is empty",
+        ));
+    }
+
+    Ok(token)
+}
+
+/// Set while a game launched by the server is checked and running.
+static LAUNCHING: AtomicBool = AtomicBool::new(false);
+
+/// Exclusive right of a request to launch a game.  The check for a running `RetroArch` and the
+/// launch happen under the claim, so concurrent requests cannot both launch.  Released when
+/// dropped.
+#[derive(Debug)]
+pub struct LaunchClaim(());
+
+impl LaunchClaim {
+    /// Take the claim.  `None` if another request holds it already.
+    #[must_use]
+    pub fn acquire() -> Option<Self> {
+        LAUNCHING
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| Self(()))
+    }
+}
+
+impl Drop for LaunchClaim {
+    fn drop(&mut self) {
+        LAUNCHING.store(false, Ordering::Release);
+    }
+}
+
+/// Listen on localhost at `port` and answer each authorized request with `handler`.  Requests
+/// are handled one after another, background jobs run in their own thread.  Problems with a
+/// single connection are reported to stderr.
+#[cfg(not(feature = "tokio"))]
+pub fn serve<F>(port: u16, token: &str, mut handler: F) -> io::Result<()>
+where
+    F: FnMut(&Request) -> Reply,
+{
+    let listener: TcpListener = TcpListener::bind(("127.0.0.1", port))?;
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        match stream.and_then(|stream| respond(&stream, token, &mut handler)) {
+            Ok(Some(job)) => {
+                thread::spawn(job);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Connection error: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Same as `serve()`, but on the async runtime.  Connections are answered concurrently and all
+/// background jobs are tracked by a `Supervisor`.  On Ctrl+C no new connections are accepted and
+/// the running jobs are waited for.
+#[cfg(feature = "tokio")]
+pub async fn serve_async<F>(
+    port: u16,
+    token: String,
+    handler: F,
+) -> io::Result<()>
+where
+    F: Fn(&Request) -> Reply + Send + Sync + 'static,
+{
+    use crate::settings::supervisor::Supervisor;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+
+    let handler = Arc::new(handler);
+    let token: Arc<str> = Arc::from(token);
+    let (jobs, mut queued) = mpsc::unbounded_channel::<Job>();
+    let mut supervisor = Supervisor::new();
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let stream: TcpStream = match accepted.and_then(|(s, _)| s.into_std()) {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        eprintln!("Connection error: {}", e);
+                        continue;
+                    }
+                };
+                let (handler, token, jobs) = (handler.clone(), token.clone(), jobs.clone());
+                supervisor.spawn_blocking("connection", move || {
+                    let result = stream
+                        .set_nonblocking(false)
+                        .and_then(|()| respond(&stream, &token, &mut |r| handler(r)));
+                    match result {
+                        // The receiver only goes away on shutdown, then the job is dropped.
+                        Ok(Some(job)) => drop(jobs.send(job)),
+                        Ok(None) => {}
+                        Err(e) => eprintln!("Connection error: {}", e),
+                    }
+                });
+            }
+            Some(job) = queued.recv() => supervisor.spawn_blocking("job", job),
+            Some(()) = supervisor.join_next() => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    eprintln!("Shutting down, waiting for {} tasks.", supervisor.len());
+    supervisor.shutdown().await;
+
+    Ok(())
+}
+
+/// Answer a single connection.  Returns the background job of the handler, if any.
+fn respond<F>(
+    stream: &TcpStream,
+    token: &str,
+    handler: &mut F,
+) -> io::Result<Option<Job>>
+where
+    F: FnMut(&Request) -> Reply,
+{
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream);
+
+    let (response, job): Reply = match read_request(&mut reader) {
+        Ok(request) if is_authorized(&request, token) => handler(&request),
+        Ok(_) => (Response::error(401, "missing or wrong token"), None),
+        Err(response) => (response, None),
+    };
+
+    let mut stream: &TcpStream = stream;
+    stream.write_all(response.to_http().as_bytes())?;
+
+    Ok(job)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::BufReader;
+
+    use super::Response;
+
+    // Untested:
+    //  - serve()
+    //  - serve_async()
+
+    #[test]
+    fn load_token_checks_mode() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir()
+            .join(format!("enjoy-test-token-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let token = super::load_token(&path).unwrap();
+        assert_eq!(32, token.len());
+        assert_eq!(
+            0o600,
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777
+        );
+        assert_eq!(token, super::load_token(&path).unwrap());
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(super::load_token(&path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn launch_claim_exclusive() {
+        let claim = super::LaunchClaim::acquire();
+
+        assert!(claim.is_some());
+        assert!(super::LaunchClaim::acquire().is_none());
+        drop(claim);
+        assert!(super::LaunchClaim::acquire().is_some());
+    }
+
+    fn read(text: &str) -> Result<super::Request, Response> {
+        super::read_request(&mut BufReader::new(text.as_bytes()))
+    }
+
+    #[test]
+    fn read_request_post() {
+        let request = read(
+            "POST /launch HTTP/1.1\r\nAuthorization: Bearer abc\r\n\
+             Content-Length: 17\r\n\r\n{\"game\":\"a.smc\"}\n",
+        )
+        .unwrap();
+
+        assert_eq!("POST", request.method);
+        assert_eq!("/launch", request.path);
+        assert_eq!("{\"game\":\"a.smc\"}\n", request.body);
+        assert!(super::is_authorized(&request, "abc"));
+        assert!(!super::is_authorized(&request, "abcd"));
+    }
+
+    #[test]
+    fn read_request_invalid() {
+        assert_eq!(400, read("GARBAGE\r\n\r\n").unwrap_err().status);
+        assert_eq!(
+            411,
+            read("POST /quit HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n")
+                .unwrap_err()
+                .status
+        );
+        assert!(read("POST /quit HTTP/1.1\r\n\r\n").is_ok());
+        assert_eq!(
+            413,
+            read("POST /quit HTTP/1.1\r\nContent-Length: 99999\r\n\r\n")
+                .unwrap_err()
+                .status
+        );
+        assert_eq!(
+            400,
+            read(&format!(
+                "GET / HTTP/1.1\r\nX: {}\r\n\r\n",
+                "a".repeat(9000)
+            ))
+            .unwrap_err()
+            .status
+        );
+    }
+
+    #[test]
+    fn response_to_http() {
+        let response = Response::error(404, "not found");
+
+        assert_eq!(
+            "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\n\
+             Content-Length: 21\r\nConnection: close\r\n\r\n{\"error\":\"not found\"}",
+            response.to_http()
+        );
+    }
+}
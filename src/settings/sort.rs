@@ -0,0 +1,205 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// Order of the games before filtering and selection with option `--sort`, so the first game does
+/// not depend on the order of stdin or the commandline.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sort {
+    /// Keep the games in the order they were given.
+    #[default]
+    None,
+    /// Alphabetical by filename, ignoring case and the parent directory.
+    Name,
+    /// Most recently modified first.
+    Mtime,
+    /// Largest file first.
+    Size,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "name" => Ok(Self::Name),
+            "mtime" => Ok(Self::Mtime),
+            "size" => Ok(Self::Size),
+            _ => Err(format!(
+                "Unknown sort \"{}\", expected one of: name, mtime, size, none",
+                text
+            )),
+        }
+    }
+}
+
+/// Filename of `game` in lowercase for sorting by name.
+fn name_key(game: &Path) -> String {
+    game.file_name()
+        .unwrap_or(game.as_os_str())
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+impl Sort {
+    /// Sort `games` in place.  The sort is stable and ties are ordered by the full path, so the
+    /// result is always the same.  Games which cannot be read for `mtime` or `size` go last.
+    pub fn apply(self, games: &mut [PathBuf]) {
+        match self {
+            Self::None => {}
+            Self::Name => {
+                games.sort_by_cached_key(|game| (name_key(game), game.clone()))
+            }
+            Self::Mtime => sort_by_metadata(games, |metadata| {
+                metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)
+            }),
+            Self::Size => sort_by_metadata(games, fs::Metadata::len),
+        }
+    }
+}
+
+/// Sort `games` descending by a `value` of their metadata, with missing files last.
+fn sort_by_metadata<T: Ord>(
+    games: &mut [PathBuf],
+    value: impl Fn(&fs::Metadata) -> T,
+) {
+    games.sort_by_cached_key(|game| {
+        let value: Option<T> =
+            fs::metadata(game).ok().map(|metadata| value(&metadata));
+        (Descending(value), game.clone())
+    });
+}
+
+/// Reverses the order of a value, with `None` after everything else.
+#[derive(PartialEq, Eq)]
+struct Descending<T>(Option<T>);
+
+impl<T: Ord> Ord for Descending<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => b.cmp(a),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+impl<T: Ord> PartialOrd for Descending<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::ffi::CString;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    use super::Sort;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    /// Set the access and modification time of `path` to `seconds` since the epoch.
+    fn set_mtime(path: &Path, seconds: i64) {
+        let cpath = CString::new(path.as_os_str().as_bytes()).unwrap();
+        let time = libc::timeval {
+            tv_sec: seconds,
+            tv_usec: 0,
+        };
+        // SAFETY: `cpath` is a valid nul terminated string and the times are an array of two.
+        assert_eq!(0, unsafe {
+            libc::utimes(cpath.as_ptr(), [time, time].as_ptr())
+        });
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Ok(Sort::Mtime), "MTime".parse());
+        assert_eq!(Ok(Sort::None), "none".parse());
+        assert!("date".parse::<Sort>().is_err());
+    }
+
+    #[test]
+    fn name() {
+        let mut games = paths(&[
+            "/roms/snes/zelda.smc",
+            "/roms/gb/Tetris.gb",
+            "/roms/nes/mario.nes",
+            "/roms/gb/mario.nes",
+        ]);
+        Sort::Name.apply(&mut games);
+
+        assert_eq!(
+            paths(&[
+                "/roms/gb/mario.nes",
+                "/roms/nes/mario.nes",
+                "/roms/gb/Tetris.gb",
+                "/roms/snes/zelda.smc",
+            ]),
+            games
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        let dir: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-sort-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, size, mtime) in [
+            ("old.smc", 30, 1000),
+            ("new.smc", 10, 3000),
+            ("mid.smc", 20, 2000),
+        ] {
+            fs::File::create(dir.join(name))
+                .unwrap()
+                .set_len(size)
+                .unwrap();
+            set_mtime(&dir.join(name), mtime);
+        }
+        let given = vec![
+            dir.join("missing.smc"),
+            dir.join("mid.smc"),
+            dir.join("old.smc"),
+            dir.join("new.smc"),
+        ];
+
+        let mut by_mtime = given.clone();
+        Sort::Mtime.apply(&mut by_mtime);
+        let mut by_size = given.clone();
+        Sort::Size.apply(&mut by_size);
+        let mut unsorted = given.clone();
+        Sort::None.apply(&mut unsorted);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            vec![
+                dir.join("new.smc"),
+                dir.join("mid.smc"),
+                dir.join("old.smc"),
+                dir.join("missing.smc"),
+            ],
+            by_mtime
+        );
+        assert_eq!(
+            vec![
+                dir.join("old.smc"),
+                dir.join("mid.smc"),
+                dir.join("new.smc"),
+                dir.join("missing.smc"),
+            ],
+            by_size
+        );
+        assert_eq!(given, unsorted);
+    }
+}
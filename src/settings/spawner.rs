@@ -0,0 +1,172 @@
+use crate::settings::file;
+
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+/// Program to start `RetroArch` in a transient systemd scope.
+pub const SYSTEMD_RUN: &str = "systemd-run";
+
+/// How the `RetroArch` process is started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Kind {
+    /// As a direct child of `enjoy`.
+    #[default]
+    Direct,
+    /// In its own transient systemd scope with `systemd-run --scope`.  It replaces itself with
+    /// `RetroArch`, so the started process is still the one of `RetroArch`.
+    SystemdRun,
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "direct" => Ok(Self::Direct),
+            "systemd-run" => Ok(Self::SystemdRun),
+            _ => Err(format!(
+                "Unknown spawn \"{}\", expected one of: direct, systemd-run",
+                text
+            )),
+        }
+    }
+}
+
+/// Starts the command of `RetroArch` as configured with `spawn` in the user config, and the
+/// resource limits of the systemd scope.
+///
+/// ```ini
+/// [options]
+/// spawn = systemd-run
+/// memory_max = 4G
+/// cpu_quota = 200%
+/// slice = games.slice
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Spawner {
+    pub kind: Kind,
+    /// Value of the systemd property `MemoryMax`, like `4G`.
+    pub memory_max: Option<String>,
+    /// Value of the systemd property `CPUQuota`, like `200%` for two full CPUs.
+    pub cpu_quota: Option<String>,
+    /// Slice the scope is placed in, to share limits with other programs.
+    pub slice: Option<String>,
+}
+
+impl Spawner {
+    /// Check if any limit of the systemd scope is set.
+    #[must_use]
+    pub fn has_limits(&self) -> bool {
+        self.memory_max.is_some()
+            || self.cpu_quota.is_some()
+            || self.slice.is_some()
+    }
+
+    /// The command to run instead of `command`, which starts it the configured way.  Arguments,
+    /// environment and working directory are kept.  The scope is started in the service manager
+    /// of the user, unless `enjoy` runs as root.
+    #[must_use]
+    pub fn wrap(&self, command: Command, game: &Path) -> Command {
+        if self.kind == Kind::Direct {
+            return command;
+        }
+
+        let mut spawned: Command = Command::new(SYSTEMD_RUN);
+        if file::current_uid().map_or(true, |uid| uid != 0) {
+            spawned.arg("--user");
+        }
+        spawned.args(["--scope", "--quiet", "--collect"]);
+        spawned.arg(format!(
+            "--description=enjoy: {}",
+            game.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        if let Some(slice) = &self.slice {
+            spawned.arg(format!("--slice={}", slice));
+        }
+        if let Some(memory_max) = &self.memory_max {
+            spawned.arg(format!("--property=MemoryMax={}", memory_max));
+        }
+        if let Some(cpu_quota) = &self.cpu_quota {
+            spawned.arg(format!("--property=CPUQuota={}", cpu_quota));
+        }
+        spawned
+            .arg("--")
+            .arg(command.get_program())
+            .args(command.get_args());
+
+        for (key, value) in command.get_envs() {
+            match value {
+                Some(value) => spawned.env(key, value),
+                None => spawned.env_remove(key),
+            };
+        }
+        if let Some(dir) = command.get_current_dir() {
+            spawned.current_dir(dir);
+        }
+
+        spawned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::ffi::OsStr;
+    use std::path::Path;
+    use std::process::Command;
+
+    use super::Kind;
+    use super::Spawner;
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Ok(Kind::SystemdRun), "systemd-run".parse());
+        assert_eq!(Ok(Kind::Direct), "Direct".parse());
+        assert!("systemd".parse::<Kind>().is_err());
+    }
+
+    #[test]
+    fn wrap() {
+        let mut command: Command = Command::new("retroarch");
+        command
+            .args(["-L", "snes9x", "/roms/Mario.smc"])
+            .env("LANG", "C")
+            .current_dir("/roms");
+        let spawner = Spawner {
+            kind: Kind::SystemdRun,
+            memory_max: Some("4G".to_string()),
+            cpu_quota: Some("200%".to_string()),
+            slice: None,
+        };
+
+        let wrapped = spawner.wrap(command, Path::new("/roms/Mario.smc"));
+        let arguments: Vec<&OsStr> = wrapped.get_args().collect();
+
+        assert_eq!("systemd-run", wrapped.get_program());
+        assert!(arguments.contains(&OsStr::new("--scope")));
+        assert!(arguments.ends_with(&[
+            OsStr::new("--description=enjoy: Mario.smc"),
+            OsStr::new("--property=MemoryMax=4G"),
+            OsStr::new("--property=CPUQuota=200%"),
+            OsStr::new("--"),
+            OsStr::new("retroarch"),
+            OsStr::new("-L"),
+            OsStr::new("snes9x"),
+            OsStr::new("/roms/Mario.smc"),
+        ]));
+        assert_eq!(
+            vec![(OsStr::new("LANG"), Some(OsStr::new("C")))],
+            wrapped.get_envs().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(Path::new("/roms")), wrapped.get_current_dir());
+    }
+
+    #[test]
+    fn wrap_direct() {
+        let wrapped =
+            Spawner::default().wrap(Command::new("retroarch"), Path::new(""));
+
+        assert_eq!("retroarch", wrapped.get_program());
+    }
+}
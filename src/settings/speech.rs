@@ -0,0 +1,56 @@
+use crate::settings::program::Program;
+
+use std::process::Command;
+use std::process::Stdio;
+use std::thread;
+
+/// Build the command of option `tts_command`, which speaks `title`.  The option is split like in
+/// a shell and the title is added as the last argument, like `spd-say "Super Mario World"`.
+pub fn command(tts_command: &str, title: &str) -> Result<Command, String> {
+    let mut command: Command =
+        Program::parse("tts_command", tts_command)?.command();
+    command
+        .arg(title)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    Ok(command)
+}
+
+/// Speak `title` with the `tts_command` in the background, so the launch is not delayed.  A
+/// failure is reported, but does not stop the program.
+pub fn announce(tts_command: &str, title: &str) {
+    let mut command: Command = match command(tts_command, title) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        if let Err(e) = command.status() {
+            eprintln!("Could not run tts_command: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    // Untested:
+    //  - announce()
+
+    #[test]
+    fn command() {
+        let command =
+            super::command("espeak-ng -s 140", "Super Mario World").unwrap();
+        let args: Vec<&std::ffi::OsStr> = command.get_args().collect();
+
+        assert_eq!("espeak-ng", command.get_program());
+        assert_eq!(vec!["-s", "140", "Super Mario World"], args);
+        assert!(super::command("", "Mario").is_err());
+        assert!(super::command("spd-say \"", "Mario").is_err());
+    }
+}
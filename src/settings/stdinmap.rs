@@ -0,0 +1,115 @@
+use crate::settings::file;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use indexmap::map::IndexMap;
+
+/// Section of the user config with the rules for the lines read from stdin.
+pub const SECTION: &str = "stdin";
+
+/// Prefix of the keys in `[stdin]`, which map a directory to another one, like
+/// `map /mnt/nas = /home/user/roms`.
+const MAP_PREFIX: &str = "map ";
+
+/// Rewrites the paths read from stdin before they are merged with the other games, so pipelines
+/// do not need an extra `sed` to fit the paths to the local machine.
+///
+/// ```ini
+/// [stdin]
+/// strip_prefix = ./
+/// map /mnt/nas = /home/user/roms
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StdinRules {
+    /// Text removed from the start of each line, if it starts with it.
+    pub strip_prefix: Option<String>,
+    /// Directories replaced by other ones, applied after `strip_prefix`.
+    pub mappings: Vec<(PathBuf, PathBuf)>,
+}
+
+impl StdinRules {
+    /// Create the rules from the keys of section `[stdin]`.  Any other key is an error.
+    pub fn from_section(
+        section: &IndexMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut rules: Self = Self::default();
+
+        for (key, value) in section {
+            if key == "strip_prefix" {
+                rules.strip_prefix = Some(value.to_string());
+            } else if let Some(from) = key.strip_prefix(MAP_PREFIX) {
+                rules
+                    .mappings
+                    .push((PathBuf::from(from.trim()), PathBuf::from(value)));
+            } else {
+                return Err(format!("[{}] Unknown key \"{}\".", SECTION, key));
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// Apply the rules to a single line read from stdin.
+    #[must_use]
+    pub fn apply(&self, line: &Path) -> PathBuf {
+        let line: &Path = match &self.strip_prefix {
+            Some(prefix) if !prefix.is_empty() => line
+                .to_str()
+                .and_then(|text| text.strip_prefix(prefix.as_str()))
+                .map_or(line, Path::new),
+            _ => line,
+        };
+
+        file::map_prefix(line, &self.mappings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::StdinRules;
+    use indexmap::map::IndexMap;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    fn rules() -> StdinRules {
+        StdinRules::from_section(&IndexMap::from([
+            ("strip_prefix".to_string(), "./".to_string()),
+            ("map /mnt/nas".to_string(), "/home/user/roms".to_string()),
+            ("map /mnt/nas/snes".to_string(), "/media/snes".to_string()),
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn apply() {
+        let rules = rules();
+
+        assert_eq!(
+            PathBuf::from("snes/mario.smc"),
+            rules.apply(Path::new("./snes/mario.smc"))
+        );
+        assert_eq!(
+            PathBuf::from("/home/user/roms/gb/tetris.gb"),
+            rules.apply(Path::new("/mnt/nas/gb/tetris.gb"))
+        );
+        assert_eq!(
+            PathBuf::from("/media/snes/mario.smc"),
+            rules.apply(Path::new("/mnt/nas/snes/mario.smc"))
+        );
+        assert_eq!(
+            PathBuf::from("/mnt/nasty/mario.smc"),
+            rules.apply(Path::new("/mnt/nasty/mario.smc"))
+        );
+    }
+
+    #[test]
+    fn from_section_invalid() {
+        assert!(StdinRules::from_section(&IndexMap::from([(
+            "mapping".to_string(),
+            "/x".to_string()
+        )]))
+        .is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Number of suggestions for a game which is not found, if option `suggestions` is not set.
+pub const DEFAULT_LIMIT: u64 = 3;
+
+/// Edit distance of two texts, the number of characters to insert, remove or replace to turn one
+/// into the other.
+#[must_use]
+pub fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current: Vec<usize> = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let replace: usize = previous[j] + usize::from(ca != *cb);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Files of `candidates` whose filename is closest to the filename of `game`, best first and at
+/// most `limit`.  Names are compared in lowercase and may differ in up to a third of their
+/// characters, but at least two, so unrelated files are not suggested.
+#[must_use]
+pub fn closest(
+    game: &Path,
+    candidates: &[PathBuf],
+    limit: usize,
+) -> Vec<PathBuf> {
+    let name = |path: &Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    };
+    let wanted: String = name(game);
+    let allowed: usize = (wanted.chars().count() / 3).max(2);
+
+    let mut scored: Vec<(usize, &PathBuf)> = candidates
+        .iter()
+        .map(|path| (distance(&wanted, &name(path)), path))
+        .filter(|(distance, _)| *distance <= allowed)
+        .collect();
+    scored.sort();
+    scored.dedup_by(|a, b| a.1 == b.1);
+
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, path)| path.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    #[test]
+    fn distance() {
+        assert_eq!(0, super::distance("mario.smc", "mario.smc"));
+        assert_eq!(1, super::distance("maro.smc", "mario.smc"));
+        assert_eq!(3, super::distance("kitten", "sitting"));
+        assert_eq!(3, super::distance("", "abc"));
+    }
+
+    #[test]
+    fn closest() {
+        let candidates: Vec<PathBuf> = [
+            "/roms/snes/Super Mario World.smc",
+            "/roms/snes/Super Mario Kart.smc",
+            "/roms/gb/Tetris.gb",
+            "/roms/snes/super mario world.sfc",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+
+        assert_eq!(
+            vec![
+                PathBuf::from("/roms/snes/Super Mario World.smc"),
+                PathBuf::from("/roms/snes/super mario world.sfc"),
+            ],
+            super::closest(
+                Path::new("/home/user/Super Mario Wrld.smc"),
+                &candidates,
+                2
+            )
+        );
+        assert!(
+            super::closest(Path::new("zelda.nes"), &candidates, 3).is_empty()
+        );
+    }
+}
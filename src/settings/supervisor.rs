@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+
+use tokio::runtime::Builder;
+use tokio::task::Id;
+use tokio::task::JoinSet;
+
+/// Run `future` to completion on a new multi-threaded runtime.  This is the entry point for the
+/// long-running modes, like `--serve`.
+pub fn block_on<F: Future>(future: F) -> io::Result<F::Output> {
+    Ok(Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(future))
+}
+
+/// Owner of all tasks started by a long-running mode.  Each task has a name, so a failed one can
+/// be reported.  No task is detached: on shutdown all of them are waited for.
+#[derive(Default)]
+pub struct Supervisor {
+    tasks: JoinSet<()>,
+    names: HashMap<Id, &'static str>,
+}
+
+impl Supervisor {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of tasks still running or not reaped yet.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Start blocking work, like waiting for a game or synchronous I/O, on the thread pool of the
+    /// runtime.
+    pub fn spawn_blocking<F>(&mut self, name: &'static str, task: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let id: Id = self.tasks.spawn_blocking(task).id();
+        self.names.insert(id, name);
+    }
+
+    /// Wait for the next task to finish.  A task which panicked is reported to stderr.  `None`
+    /// if there are no tasks.
+    pub async fn join_next(&mut self) -> Option<()> {
+        let (id, failed): (Id, bool) =
+            match self.tasks.join_next_with_id().await? {
+                Ok((id, ())) => (id, false),
+                Err(e) => (e.id(), e.is_panic()),
+            };
+
+        let name: &str = self.names.remove(&id).unwrap_or("unknown");
+        if failed {
+            eprintln!("Task {} ({}) failed.", name, id);
+        }
+
+        Some(())
+    }
+
+    /// Wait for all tasks to finish.
+    pub async fn shutdown(mut self) {
+        while self.join_next().await.is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use super::Supervisor;
+
+    #[test]
+    fn shutdown_waits_for_all_tasks() {
+        let done = Arc::new(AtomicUsize::new(0));
+
+        super::block_on(async {
+            let mut supervisor = Supervisor::new();
+            for _ in 0..3 {
+                let done = done.clone();
+                supervisor.spawn_blocking("count", move || {
+                    done.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+            supervisor.spawn_blocking("panic", || panic!("expected in test"));
+
+            assert_eq!(4, supervisor.len());
+            supervisor.shutdown().await;
+        })
+        .unwrap();
+
+        assert_eq!(3, done.load(Ordering::SeqCst));
+    }
+}
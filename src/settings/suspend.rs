@@ -0,0 +1,189 @@
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::net::Ipv4Addr;
+use std::net::UdpSocket;
+use std::process::Child;
+use std::process::Command;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+
+/// Default port of the network command interface of `RetroArch`.
+pub const DEFAULT_PORT: u16 = 55355;
+
+/// Time to wait for an answer of `RetroArch`.
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Check a line printed by `gdbus monitor` for the `PrepareForSleep` signal of logind.  `true`
+/// before the system suspends, `false` after it woke up, `None` for any other line.
+#[must_use]
+pub fn parse_signal(line: &str) -> Option<bool> {
+    let (_, args) = line.split_once(".PrepareForSleep ")?;
+
+    match args.trim() {
+        "(true,)" => Some(true),
+        "(false,)" => Some(false),
+        _ => None,
+    }
+}
+
+/// Send a single command like `PAUSE_TOGGLE` to the network command interface of `RetroArch` on
+/// localhost.  With `answer` the reply is waited for and returned.
+pub fn send(
+    port: u16,
+    command: &str,
+    answer: bool,
+) -> io::Result<Option<String>> {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    socket.send_to(command.as_bytes(), (Ipv4Addr::LOCALHOST, port))?;
+    if !answer {
+        return Ok(None);
+    }
+
+    let mut buffer: [u8; 4096] = [0; 4096];
+    let size: usize = socket.recv(&mut buffer)?;
+
+    Ok(Some(
+        String::from_utf8_lossy(&buffer[..size]).trim().to_string(),
+    ))
+}
+
+/// Check if the reply to `GET_STATUS` tells a game is running and not paused.
+#[must_use]
+pub fn is_playing(status: &str) -> bool {
+    status.split_whitespace().nth(1) == Some("PLAYING")
+}
+
+/// Listener for suspend and wake up of the system, which pauses `RetroArch` before suspend and
+/// resumes it after wake up.  A game paused by the user stays paused.  Listening ends when the
+/// watcher is dropped.
+pub struct Watcher {
+    monitor: Child,
+}
+
+impl Watcher {
+    /// Start listening to logind on the system bus with `gdbus monitor`.
+    pub fn start(port: u16) -> io::Result<Self> {
+        let mut monitor: Child = Command::new("gdbus")
+            .args([
+                "monitor",
+                "--system",
+                "--dest",
+                "org.freedesktop.login1",
+                "--object-path",
+                "/org/freedesktop/login1",
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = monitor
+            .stdout
+            .take()
+            .ok_or_else(|| io::Error::other("no output of gdbus"))?;
+
+        thread::spawn(move || {
+            let mut paused: bool = false;
+
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                match parse_signal(&line) {
+                    Some(true) => paused = pause(port),
+                    Some(false) if paused => {
+                        paused = false;
+                        if let Err(e) = send(port, "PAUSE_TOGGLE", false) {
+                            eprintln!("Could not resume RetroArch: {}", e);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        });
+
+        Ok(Self { monitor })
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = self.monitor.kill();
+        let _ = self.monitor.wait();
+    }
+}
+
+/// Pause the game, if it is currently playing.  Returns `true` if it was paused.
+fn pause(port: u16) -> bool {
+    match send(port, "GET_STATUS", true) {
+        Ok(Some(status)) if is_playing(&status) => {
+            match send(port, "PAUSE_TOGGLE", false) {
+                Ok(_) => true,
+                Err(e) => {
+                    eprintln!("Could not pause RetroArch: {}", e);
+                    false
+                }
+            }
+        }
+        Ok(_) => false,
+        Err(e) => {
+            eprintln!("Could not ask RetroArch for its status: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::Ipv4Addr;
+    use std::net::UdpSocket;
+
+    // Untested:
+    //  - Watcher::start()
+
+    #[test]
+    fn parse_signal() {
+        let prefix = "/org/freedesktop/login1: \
+                      org.freedesktop.login1.Manager.PrepareForSleep";
+        assert_eq!(
+            Some(true),
+            super::parse_signal(&format!("{} (true,)", prefix))
+        );
+        assert_eq!(
+            Some(false),
+            super::parse_signal(&format!("{} (false,)", prefix))
+        );
+        assert_eq!(
+            None,
+            super::parse_signal(
+                "/org/freedesktop/login1: \
+                 org.freedesktop.login1.Manager.SessionNew ('3', '/')"
+            )
+        );
+    }
+
+    #[test]
+    fn is_playing() {
+        assert!(super::is_playing("GET_STATUS PLAYING snes,Mario,crc32=1"));
+        assert!(!super::is_playing("GET_STATUS PAUSED snes,Mario,crc32=1"));
+        assert!(!super::is_playing("GET_STATUS CONTENTLESS"));
+    }
+
+    #[test]
+    fn send_with_answer() {
+        let retroarch = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port: u16 = retroarch.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let mut buffer: [u8; 64] = [0; 64];
+            let (size, from) = retroarch.recv_from(&mut buffer).unwrap();
+            assert_eq!(b"GET_STATUS", &buffer[..size]);
+            retroarch.send_to(b"GET_STATUS PAUSED\n", from).unwrap();
+        });
+
+        assert_eq!(
+            Some("GET_STATUS PAUSED".to_string()),
+            super::send(port, "GET_STATUS", true).unwrap()
+        );
+        server.join().unwrap();
+    }
+}
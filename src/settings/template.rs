@@ -0,0 +1,141 @@
+use std::str::FromStr;
+
+use indexmap::map::IndexMap;
+
+/// Names of all fields, which can be used in a template.  Not every field is available in every
+/// output, those without value are replaced by an empty text.
+pub const FIELDS: &[&str] = &[
+    "path", "name", "title", "ext", "dir", "core", "libretro", "label",
+    "system",
+];
+
+#[derive(Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field(String),
+}
+
+/// A format for a single line of output, like `{path}\t{core}`.  Fields are enclosed in curly
+/// braces and literal braces are written as `{{` and `}}`.  The escapes `\t`, `\n`, `\0` and `\\`
+/// are supported, as these are hard to type on the commandline.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Replace each field with its value from `values`.
+    #[must_use]
+    pub fn render(&self, values: &IndexMap<&str, String>) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.as_str(),
+                Part::Field(name) => {
+                    values.get(name.as_str()).map_or("", String::as_str)
+                }
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<Part> = vec![];
+        let mut literal = String::new();
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => literal.push(match chars.next() {
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    Some('0') => '\0',
+                    Some('\\') => '\\',
+                    Some(other) => {
+                        return Err(format!(
+                            "Unknown escape \\{} in format.",
+                            other
+                        ))
+                    }
+                    None => return Err("Format ends with a backslash.".into()),
+                }),
+                '{' if chars.clone().next() == Some('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.clone().next() == Some('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed: bool = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if !closed {
+                        return Err(
+                            "Unclosed { in format, use {{ instead.".into()
+                        );
+                    }
+                    if !FIELDS.contains(&name.as_str()) {
+                        return Err(format!(
+                            "Unknown field {{{}}} in format.  Available: {}",
+                            name,
+                            FIELDS.join(", ")
+                        ));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field(name));
+                }
+                '}' => {
+                    return Err("Unmatched } in format, use }} instead.".into())
+                }
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Text(literal));
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use indexmap::map::IndexMap;
+
+    use super::Template;
+
+    #[test]
+    fn render_fields_and_escapes() {
+        let template: Template =
+            "{path}\\t{core}\\0{{{title}}}".parse().unwrap();
+        let values: IndexMap<&str, String> = IndexMap::from([
+            ("path", "/roms/mario.smc".to_string()),
+            ("core", "snes".to_string()),
+        ]);
+
+        assert_eq!("/roms/mario.smc\tsnes\0{}", template.render(&values));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!("{unknown}".parse::<Template>().is_err());
+        assert!("{path".parse::<Template>().is_err());
+        assert!("path}".parse::<Template>().is_err());
+        assert!("\\x".parse::<Template>().is_err());
+        assert!("end\\".parse::<Template>().is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Size of the copier header in front of some SNES and NES dumps, which is not part of the
+/// checksum in the databases.
+const COPIER_HEADER: usize = 512;
+
+/// Lookup table for the CRC-32 checksum (IEEE polynomial), as used by DAT and RDB files.
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table: [u32; 256] = [0; 256];
+    let mut i: usize = 0;
+    while i < 256 {
+        let mut crc: u32 = i as u32;
+        let mut bit: usize = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32 checksum of the content of a file.
+#[must_use]
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc: u32, byte| {
+        CRC_TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Titles of games by the CRC-32 checksum of their ROM file, read from database files.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TitleDb {
+    titles: HashMap<u32, String>,
+}
+
+impl TitleDb {
+    /// Read all database files into a single lookup.  Files ending in `.rdb` are read as
+    /// `RetroArch` databases, any other as DAT files in clrmamepro or Logiqx XML format.  Entries
+    /// from earlier files win.
+    pub fn load(files: &[impl AsRef<Path>]) -> Result<Self, String> {
+        let mut db: Self = Self::default();
+
+        for file in files.iter().map(AsRef::as_ref) {
+            let content: Vec<u8> = fs::read(file).map_err(|error| {
+                format!(
+                    "Could not read database {}: {}",
+                    file.display(),
+                    error
+                )
+            })?;
+            let titles: HashMap<u32, String> = if file
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("rdb"))
+            {
+                parse_rdb(&content).ok_or_else(|| {
+                    format!(
+                        "Not a valid RetroArch database: {}",
+                        file.display()
+                    )
+                })?
+            } else {
+                parse_dat(&String::from_utf8_lossy(&content))
+            };
+            for (crc, title) in titles {
+                db.titles.entry(crc).or_insert(title);
+            }
+        }
+
+        Ok(db)
+    }
+
+    /// Check if no title is known.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.titles.is_empty()
+    }
+
+    /// Title of a checksum.
+    #[must_use]
+    pub fn title(&self, crc: u32) -> Option<&str> {
+        self.titles.get(&crc).map(String::as_str)
+    }
+
+    /// Title of a game file by its checksum.  A dump with a copier header is also looked up
+    /// without it.
+    pub fn lookup(&self, game: &Path) -> io::Result<Option<&str>> {
+        let data: Vec<u8> = fs::read(game)?;
+        let mut title: Option<&str> = self.title(crc32(&data));
+
+        if title.is_none() && data.len() % 1024 == COPIER_HEADER {
+            title = self.title(crc32(&data[COPIER_HEADER..]));
+        }
+
+        Ok(title)
+    }
+}
+
+/// Read the titles from a DAT file, either in clrmamepro format with `game ( name "..." rom (
+/// ... crc 12345678 ) )` or in Logiqx XML format with `<game name="..."><rom ... crc="12345678"/>`.
+/// Each ROM of a game maps to the name of the game.
+#[must_use]
+pub fn parse_dat(content: &str) -> HashMap<u32, String> {
+    let mut titles: HashMap<u32, String> = HashMap::new();
+    let mut game: Option<String> = None;
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with("<game ") || line.starts_with("<machine ") {
+            game = xml_attribute(line, "name").map(|name| unescape_xml(&name));
+        } else if line.starts_with("<rom ") {
+            let crc = xml_attribute(line, "crc")
+                .and_then(|crc| u32::from_str_radix(&crc, 16).ok());
+            if let (Some(crc), Some(game)) = (crc, &game) {
+                titles.entry(crc).or_insert_with(|| game.clone());
+            }
+        } else if line.starts_with("game (") || line == "game" {
+            game = None;
+        } else if let Some(name) = line.strip_prefix("name ") {
+            game = Some(name.trim().trim_matches('"').to_string());
+        } else if line.starts_with("rom (") {
+            let mut tokens = line.split_whitespace();
+            let crc = tokens
+                .by_ref()
+                .find(|token| *token == "crc")
+                .and(tokens.next())
+                .and_then(|crc| u32::from_str_radix(crc, 16).ok());
+            if let (Some(crc), Some(game)) = (crc, &game) {
+                titles.entry(crc).or_insert_with(|| game.clone());
+            }
+        }
+    }
+
+    titles
+}
+
+/// Value of an attribute in a single XML tag, like `name` in `<game name="Title">`.
+fn xml_attribute(tag: &str, name: &str) -> Option<String> {
+    let start: usize = tag.find(&format!(" {name}=\""))? + name.len() + 3;
+    let end: usize = tag[start..].find('"')? + start;
+
+    Some(tag[start..end].to_string())
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Read the titles from a `RetroArch` database.  It starts with the magic `RARCHDB\0` and the
+/// offset of the metadata, followed by one MessagePack map per entry until a nil.  The title is
+/// the `name` and the checksum the 4 byte binary `crc` of each entry.  Returns `None` if the
+/// content is not a valid database.
+#[must_use]
+pub fn parse_rdb(content: &[u8]) -> Option<HashMap<u32, String>> {
+    let mut titles: HashMap<u32, String> = HashMap::new();
+    let mut reader = MsgPack {
+        data: content.strip_prefix(b"RARCHDB\0")?.get(8..)?,
+    };
+
+    loop {
+        let fields: usize = match reader.value()? {
+            Value::Nil => break,
+            Value::Map(fields) => fields,
+            _ => return None,
+        };
+        let mut name: Option<String> = None;
+        let mut crc: Option<u32> = None;
+        for _ in 0..fields {
+            let key = reader.value()?;
+            let value = reader.value()?;
+            match (key, value) {
+                (Value::Str(key), Value::Str(value)) if key == b"name" => {
+                    name = Some(String::from_utf8_lossy(value).into_owned());
+                }
+                (Value::Str(key), Value::Bin(value)) if key == b"crc" => {
+                    crc = value.try_into().ok().map(u32::from_be_bytes);
+                }
+                _ => {}
+            }
+        }
+        if let (Some(crc), Some(name)) = (crc, name) {
+            titles.entry(crc).or_insert(name);
+        }
+    }
+
+    Some(titles)
+}
+
+/// A MessagePack value as far as needed for the databases.  Nested arrays and maps are skipped
+/// over as a whole.
+enum Value<'a> {
+    Nil,
+    Map(usize),
+    Str(&'a [u8]),
+    Bin(&'a [u8]),
+    Other,
+}
+
+struct MsgPack<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> MsgPack<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn uint(&mut self, size: usize) -> Option<usize> {
+        let bytes: &[u8] = self.take(size)?;
+        let value: u64 = bytes
+            .iter()
+            .fold(0, |value, byte| (value << 8) | u64::from(*byte));
+        usize::try_from(value).ok()
+    }
+
+    /// Read the next value.  For a map only its header is read, the caller reads the pairs.
+    fn value(&mut self) -> Option<Value<'a>> {
+        let marker: u8 = *self.take(1)?.first()?;
+        let value = match marker {
+            0x00..=0x7f | 0xe0..=0xff | 0xc2 | 0xc3 => Value::Other,
+            0xc0 => Value::Nil,
+            0x80..=0x8f => Value::Map(usize::from(marker & 0x0f)),
+            0xde => Value::Map(self.uint(2)?),
+            0xdf => Value::Map(self.uint(4)?),
+            0x90..=0x9f => self.skip(usize::from(marker & 0x0f))?,
+            0xdc => {
+                let len = self.uint(2)?;
+                self.skip(len)?
+            }
+            0xdd => {
+                let len = self.uint(4)?;
+                self.skip(len)?
+            }
+            0xa0..=0xbf => Value::Str(self.take(usize::from(marker & 0x1f))?),
+            0xd9..=0xdb => {
+                let len = self.uint(1 << (marker - 0xd9))?;
+                Value::Str(self.take(len)?)
+            }
+            0xc4..=0xc6 => {
+                let len = self.uint(1 << (marker - 0xc4))?;
+                Value::Bin(self.take(len)?)
+            }
+            0xcc..=0xcf => {
+                self.take(1 << (marker - 0xcc))?;
+                Value::Other
+            }
+            0xd0..=0xd3 => {
+                self.take(1 << (marker - 0xd0))?;
+                Value::Other
+            }
+            0xca => {
+                self.take(4)?;
+                Value::Other
+            }
+            0xcb => {
+                self.take(8)?;
+                Value::Other
+            }
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Skip over the elements of an array, including nested maps.
+    fn skip(&mut self, values: usize) -> Option<Value<'a>> {
+        let mut remaining: usize = values;
+        while remaining > 0 {
+            remaining -= 1;
+            if let Value::Map(fields) = self.value()? {
+                remaining += fields * 2;
+            }
+        }
+
+        Some(Value::Other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    // Untested:
+    //  - TitleDb::load()
+    //  - TitleDb::lookup()
+
+    #[test]
+    fn crc32() {
+        assert_eq!(0, super::crc32(b""));
+        assert_eq!(0xCBF4_3926, super::crc32(b"123456789"));
+    }
+
+    #[test]
+    fn parse_dat_clrmamepro() {
+        let content = r#"clrmamepro (
+	name "Nintendo - Super Nintendo Entertainment System"
+)
+
+game (
+	name "Super Mario World (USA)"
+	description "Super Mario World (USA)"
+	rom ( name "Super Mario World (USA).sfc" size 524288 crc B19ED489 md5 CDD3C8C37322978CA8669B34BC89C804 )
+)
+"#;
+        let titles = super::parse_dat(content);
+
+        assert_eq!(1, titles.len());
+        assert_eq!(
+            Some(&"Super Mario World (USA)".to_string()),
+            titles.get(&0xB19E_D489)
+        );
+    }
+
+    #[test]
+    fn parse_dat_xml() {
+        let content = r#"<?xml version="1.0"?>
+<datafile>
+	<header>
+		<name>Sega - Mega Drive - Genesis</name>
+	</header>
+	<game name="Sonic &amp; Knuckles (World)">
+		<description>Sonic &amp; Knuckles (World)</description>
+		<rom name="Sonic &amp; Knuckles (World).md" size="2097152" crc="0658f691"/>
+	</game>
+</datafile>
+"#;
+        let titles = super::parse_dat(content);
+
+        assert_eq!(1, titles.len());
+        assert_eq!(
+            Some(&"Sonic & Knuckles (World)".to_string()),
+            titles.get(&0x0658_F691)
+        );
+    }
+
+    #[test]
+    fn parse_rdb() {
+        let mut content: Vec<u8> = b"RARCHDB\0".to_vec();
+        content.extend([0; 8]);
+        // {"name": "Tetris (World)", "size": 32768, "crc": bin 46DF91AD}
+        content.push(0x83);
+        content.extend(b"\xa4name\xaeTetris (World)");
+        content.extend(b"\xa4size\xcd\x80\x00");
+        content.extend(b"\xa3crc\xc4\x04\x46\xdf\x91\xad");
+        // {"name": "No checksum", "tags": ["a"]}
+        content.push(0x82);
+        content.extend(b"\xa4name\xabNo checksum");
+        content.extend(b"\xa4tags\x91\xa1a");
+        content.push(0xc0);
+
+        let titles = super::parse_rdb(&content).unwrap();
+
+        assert_eq!(1, titles.len());
+        assert_eq!(
+            Some(&"Tetris (World)".to_string()),
+            titles.get(&0x46DF_91AD)
+        );
+        assert_eq!(None, super::parse_rdb(b"not a database"));
+    }
+}
@@ -0,0 +1,379 @@
+use crate::settings::picker;
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+
+/// A key pressed in the picker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Backspace,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Escape,
+    Other,
+}
+
+/// Decode the bytes read from the terminal in raw mode into keys.  `Ctrl+C` is handled like
+/// `Escape`, `Ctrl+P` and `Ctrl+N` like `Up` and `Down`.
+#[must_use]
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    let mut keys: Vec<Key> = vec![];
+
+    while let Some(c) = chars.next() {
+        let key: Key = match c {
+            '\x1b' => match chars.peek() {
+                Some('[' | 'O') => {
+                    chars.next();
+                    let mut number: String = String::new();
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit)
+                    {
+                        number.push(digit);
+                    }
+                    match (number.as_str(), chars.next()) {
+                        ("", Some('A')) => Key::Up,
+                        ("", Some('B')) => Key::Down,
+                        ("", Some('H')) | ("1" | "7", Some('~')) => Key::Home,
+                        ("", Some('F')) | ("4" | "8", Some('~')) => Key::End,
+                        ("5", Some('~')) => Key::PageUp,
+                        ("6", Some('~')) => Key::PageDown,
+                        _ => Key::Other,
+                    }
+                }
+                _ => Key::Escape,
+            },
+            '\x03' => Key::Escape,
+            '\r' | '\n' => Key::Enter,
+            '\x7f' | '\x08' => Key::Backspace,
+            '\x10' => Key::Up,
+            '\x0e' => Key::Down,
+            c if c.is_control() => Key::Other,
+            c => Key::Char(c),
+        };
+        keys.push(key);
+    }
+
+    keys
+}
+
+/// How the picker was left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Index of the chosen entry in the list.
+    Chosen(usize),
+    Cancelled,
+}
+
+/// State of the list shown in the terminal: the search text typed so far, the entries matching
+/// it and the highlighted one.
+#[derive(Debug)]
+pub struct Picker<'a> {
+    items: &'a [String],
+    query: String,
+    matches: Vec<usize>,
+    cursor: usize,
+    top: usize,
+}
+
+impl<'a> Picker<'a> {
+    #[must_use]
+    pub fn new(items: &'a [String]) -> Self {
+        Self {
+            items,
+            query: String::new(),
+            matches: (0..items.len()).collect(),
+            cursor: 0,
+            top: 0,
+        }
+    }
+
+    /// Keep the entries containing each word of the search text, in any case.
+    fn search(&mut self) {
+        let words: Vec<String> = self
+            .query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+        self.matches = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| {
+                let item: String = item.to_lowercase();
+                words.iter().all(|word| item.contains(word.as_str()))
+            })
+            .map(|(index, _)| index)
+            .collect();
+        self.cursor = 0;
+        self.top = 0;
+    }
+
+    /// Apply a key to the state, with `height` lines to show entries in.  Returns the outcome once
+    /// an entry is chosen or the picker is cancelled.
+    pub fn handle(&mut self, key: Key, height: usize) -> Option<Outcome> {
+        let height: usize = height.max(1);
+        let last: usize = self.matches.len().saturating_sub(1);
+
+        match key {
+            Key::Char(c) => {
+                self.query.push(c);
+                self.search();
+            }
+            Key::Backspace => {
+                if self.query.pop().is_some() {
+                    self.search();
+                }
+            }
+            Key::Up => self.cursor = self.cursor.saturating_sub(1),
+            Key::Down => self.cursor = (self.cursor + 1).min(last),
+            Key::PageUp => self.cursor = self.cursor.saturating_sub(height),
+            Key::PageDown => self.cursor = (self.cursor + height).min(last),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = last,
+            Key::Enter => {
+                return self
+                    .matches
+                    .get(self.cursor)
+                    .map(|i| Outcome::Chosen(*i));
+            }
+            Key::Escape => return Some(Outcome::Cancelled),
+            Key::Other => (),
+        }
+
+        // Scroll the highlighted entry into view.
+        if self.cursor < self.top {
+            self.top = self.cursor;
+        } else if self.cursor >= self.top + height {
+            self.top = self.cursor + 1 - height;
+        }
+
+        None
+    }
+
+    /// The whole screen of `rows` lines and `columns` characters as text with escape sequences:
+    /// the search line, the number of matches and the visible part of the list.
+    #[must_use]
+    pub fn render(&self, rows: usize, columns: usize) -> String {
+        let height: usize = rows.saturating_sub(2).max(1);
+        let width: usize = columns.saturating_sub(2);
+        let mut screen: String = format!(
+            "\x1b[H\x1b[2J> {}\r\n  {}/{}",
+            self.query,
+            self.matches.len(),
+            self.items.len()
+        );
+
+        for (line, index) in
+            self.matches.iter().enumerate().skip(self.top).take(height)
+        {
+            let item: String =
+                self.items[*index].chars().take(width).collect();
+            if line == self.cursor {
+                screen.push_str(&format!("\r\n\x1b[7m> {}\x1b[0m", item));
+            } else {
+                screen.push_str(&format!("\r\n  {}", item));
+            }
+        }
+        screen
+            .push_str(&format!("\x1b[1;{}H", self.query.chars().count() + 3));
+
+        screen
+    }
+}
+
+/// Puts the terminal into raw mode, so keys are read one by one without echo.  The previous mode
+/// is restored when dropped.
+struct RawMode {
+    fd: i32,
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable(fd: i32) -> io::Result<Self> {
+        // SAFETY: `termios` is plain data and only used after `tcgetattr()` filled it in.
+        let original: libc::termios = unsafe {
+            let mut termios: libc::termios = mem::zeroed();
+            if libc::tcgetattr(fd, &mut termios) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            termios
+        };
+        let mut raw: libc::termios = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        raw.c_iflag &= !(libc::ICRNL | libc::IXON);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+
+        // SAFETY: `raw` is a valid `termios` copied from the current mode.
+        if unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { fd, original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: `original` is the mode read from the same terminal before.
+        unsafe { libc::tcsetattr(self.fd, libc::TCSAFLUSH, &self.original) };
+    }
+}
+
+/// Number of rows and columns of the terminal, or 24 by 80 if unknown.
+fn size(fd: i32) -> (usize, usize) {
+    // SAFETY: `winsize` is plain data and only read after `ioctl()` returned success.
+    let size: Option<libc::winsize> = unsafe {
+        let mut size: libc::winsize = mem::zeroed();
+        (libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) == 0 && size.ws_row > 0)
+            .then_some(size)
+    };
+
+    size.map_or((24, 80), |size| {
+        (usize::from(size.ws_row), usize::from(size.ws_col))
+    })
+}
+
+/// Let the user choose one of `items` in a scrollable list on the terminal, narrowed down by
+/// typing.  `Enter` chooses the highlighted entry, `Escape` cancels with `None`.  The list is
+/// drawn on an alternate screen, which is left again afterwards.
+pub fn pick(items: &[String]) -> io::Result<Option<usize>> {
+    let mut tty: File = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(picker::TTY)?;
+    let fd: i32 = tty.as_raw_fd();
+    let raw = RawMode::enable(fd)?;
+    let mut picker = Picker::new(items);
+    let mut buffer: [u8; 64] = [0; 64];
+
+    write!(tty, "\x1b[?1049h")?;
+    let outcome: io::Result<Outcome> = (|| loop {
+        let (rows, columns) = size(fd);
+        tty.write_all(picker.render(rows, columns).as_bytes())?;
+        tty.flush()?;
+
+        let count: usize = tty.read(&mut buffer)?;
+        if count == 0 {
+            return Ok(Outcome::Cancelled);
+        }
+        for key in parse_keys(&buffer[..count]) {
+            if let Some(outcome) = picker.handle(key, rows.saturating_sub(2)) {
+                return Ok(outcome);
+            }
+        }
+    })();
+    write!(tty, "\x1b[?1049l")?;
+    drop(raw);
+
+    match outcome? {
+        Outcome::Chosen(index) => Ok(Some(index)),
+        Outcome::Cancelled => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Key;
+    use super::Outcome;
+    use super::Picker;
+
+    // Untested:
+    //  - pick()
+
+    fn test_items() -> Vec<String> {
+        [
+            "Super Mario World.smc",
+            "Mario Paint.smc",
+            "Zelda.smc",
+            "Super Metroid.smc",
+        ]
+        .iter()
+        .map(|item| item.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(
+            vec![
+                Key::Char('m'),
+                Key::Char('ä'),
+                Key::Up,
+                Key::PageDown,
+                Key::Backspace,
+                Key::Enter,
+                Key::Escape,
+            ],
+            super::parse_keys("mä\x1b[A\x1b[6~\x7f\r\x1b".as_bytes())
+        );
+        assert_eq!(
+            vec![Key::Home, Key::End],
+            super::parse_keys(b"\x1bOH\x1b[4~")
+        );
+        assert_eq!(vec![Key::Escape], super::parse_keys(b"\x03"));
+    }
+
+    #[test]
+    fn search() {
+        let items = test_items();
+        let mut picker = Picker::new(&items);
+
+        for c in "super m".chars() {
+            picker.handle(Key::Char(c), 10);
+        }
+        assert_eq!(vec![0, 3], picker.matches);
+
+        picker.handle(Key::Char('a'), 10);
+        assert_eq!(vec![0], picker.matches);
+
+        picker.handle(Key::Backspace, 10);
+        picker.handle(Key::Down, 10);
+        assert_eq!(Some(Outcome::Chosen(3)), picker.handle(Key::Enter, 10));
+    }
+
+    #[test]
+    fn navigate() {
+        let items = test_items();
+        let mut picker = Picker::new(&items);
+
+        picker.handle(Key::PageDown, 2);
+        picker.handle(Key::Down, 2);
+        picker.handle(Key::Down, 2);
+        assert_eq!(3, picker.cursor);
+        assert_eq!(2, picker.top);
+        picker.handle(Key::Home, 2);
+        assert_eq!(0, picker.top);
+        assert_eq!(Some(Outcome::Cancelled), picker.handle(Key::Escape, 2));
+
+        for c in "sonic".chars() {
+            picker.handle(Key::Char(c), 2);
+        }
+        assert_eq!(None, picker.handle(Key::Enter, 2));
+    }
+
+    #[test]
+    fn render() {
+        let items = test_items();
+        let mut picker = Picker::new(&items);
+        picker.handle(Key::Char('z'), 10);
+
+        assert_eq!(
+            "\x1b[H\x1b[2J> z\r\n  1/4\r\n\x1b[7m> Zelda.smc\x1b[0m\x1b[1;4H",
+            picker.render(12, 80)
+        );
+    }
+}
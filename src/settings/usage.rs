@@ -0,0 +1,166 @@
+use crate::settings::file;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use indexmap::map::IndexMap;
+
+/// File with the usage counters in the state directory of this program.
+#[must_use]
+pub fn usage_file() -> PathBuf {
+    file::state_dir().join("usage")
+}
+
+/// Local counters of how often each option, core and rule was used.  Nothing is ever sent
+/// anywhere, the counters only help to find rules which never apply.  The file has one
+/// `count<TAB>name` line for each name, like `option:--fullscreen` or `rule:[.smc]`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub counts: IndexMap<String, u64>,
+}
+
+impl Counters {
+    /// Read the counters from the content of a usage file.  Malformed lines are ignored.
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let mut counters: Self = Self::default();
+
+        for line in content.lines() {
+            if let Some((count, name)) = line.split_once('\t') {
+                if let Ok(count) = count.parse::<u64>() {
+                    *counters.counts.entry(name.to_string()).or_default() +=
+                        count;
+                }
+            }
+        }
+
+        counters
+    }
+
+    /// Read the counters from a usage file.  A missing file has no counters.
+    pub fn load(file: &Path) -> io::Result<Self> {
+        match fs::read_to_string(file) {
+            Ok(content) => Ok(Self::parse(&content)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Ok(Self::default())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Count each name once.
+    pub fn add(&mut self, names: &[String]) {
+        for name in names {
+            *self.counts.entry(name.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Content of the usage file.
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        self.counts
+            .iter()
+            .map(|(name, count)| format!("{}\t{}\n", count, name))
+            .collect()
+    }
+
+    /// All counters, most used first and by name for the same count.
+    #[must_use]
+    pub fn sorted(&self) -> Vec<(&str, u64)> {
+        let mut sorted: Vec<(&str, u64)> = self
+            .counts
+            .iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect();
+        sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        sorted
+    }
+}
+
+/// Add one use of each name to the counters in `file`.
+pub fn record(file: &Path, names: &[String]) -> io::Result<()> {
+    if names.is_empty() {
+        return Ok(());
+    }
+    let mut counters: Counters = Counters::load(file)?;
+    counters.add(names);
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(file, counters.to_text())
+}
+
+/// Names of the options in commandline `args` without their values, like `option:--filter` for
+/// `--filter=mario`.  Combined short options like `-xf` are counted each.  Arguments after `--`
+/// are for `RetroArch` and not included.
+#[must_use]
+pub fn options(args: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+
+    for arg in args.iter().take_while(|arg| *arg != "--") {
+        if arg.starts_with("--") {
+            let name: &str = arg.split_once('=').map_or(arg, |(name, _)| name);
+            names.push(format!("option:{}", name));
+        } else if let Some(short) = arg.strip_prefix('-') {
+            names.extend(
+                short
+                    .chars()
+                    .take_while(char::is_ascii_alphanumeric)
+                    .map(|c| format!("option:-{}", c)),
+            );
+        }
+    }
+    names.sort();
+    names.dedup();
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Counters;
+
+    #[test]
+    fn parse_and_add() {
+        let mut counters = Counters::parse(
+            "3\toption:--fullscreen\nbroken\n1\tcore:snes9x\n",
+        );
+        counters.add(&["core:snes9x".to_string(), "rule:[.gb]".to_string()]);
+
+        assert_eq!(
+            vec![
+                ("option:--fullscreen", 3),
+                ("core:snes9x", 2),
+                ("rule:[.gb]", 1)
+            ],
+            counters.sorted()
+        );
+        assert_eq!(counters, Counters::parse(&counters.to_text()));
+    }
+
+    #[test]
+    fn options() {
+        let args: Vec<String> = [
+            "--filter=mario",
+            "-xf",
+            "game.smc",
+            "--core",
+            "snes",
+            "--",
+            "--verbose",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+
+        assert_eq!(
+            vec!["option:--core", "option:--filter", "option:-f", "option:-x"],
+            super::options(&args)
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::settings::overrides;
+
+use std::process;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Extra time to sleep past the deadline, so the budget is surely exceeded on waking up.
+const MARGIN: Duration = Duration::from_millis(1);
+
+/// Exit code of this program, if the startup took too long.
+pub const EXIT_CODE: i32 = 3;
+
+#[derive(Debug)]
+struct State {
+    started: Instant,
+    budget: Option<Duration>,
+    /// The phase of the startup in progress, `None` while the watchdog is disarmed.
+    phase: Option<&'static str>,
+    /// Whether the thread checking the time is running.
+    watching: bool,
+}
+
+impl State {
+    /// Error message, if the phase in progress exceeds the budget at `now`.
+    fn overdue(&self, now: Instant) -> Option<String> {
+        let budget: Duration = self.budget?;
+        let phase: &str = self.phase?;
+
+        (now.duration_since(self.started) > budget).then(|| {
+            format!(
+                "Startup took longer than {} ms while {}, aborted.",
+                budget.as_millis(),
+                phase
+            )
+        })
+    }
+
+    /// Time to wait until the budget is used up at `now`, `None` if nothing is watched.
+    fn remaining(&self, now: Instant) -> Option<Duration> {
+        let budget: Duration = self.budget?;
+        self.phase?;
+
+        Some((self.started + budget).saturating_duration_since(now))
+    }
+}
+
+/// Aborts this program, if reading the configs and stdin or building the command take longer
+/// than the budget in total.  Huge or broken input so fails with a message naming the slow
+/// phase, instead of stalling silently.  A kiosk setup can so rely on a predictable latency.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    shared: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl Watchdog {
+    /// Start watching the time from now on.  Without a `budget` nothing is ever aborted.  The
+    /// thread checking the time only runs while there is a budget and a phase in progress.
+    #[must_use]
+    pub fn start(budget: Option<Duration>) -> Self {
+        Self {
+            shared: Arc::new((
+                Mutex::new(State {
+                    started: Instant::now(),
+                    budget,
+                    phase: None,
+                    watching: false,
+                }),
+                Condvar::new(),
+            )),
+        }
+    }
+
+    /// Change the budget, such as after it was read from the user config.
+    pub fn set_budget(&self, budget: Option<Duration>) {
+        self.update(|state| state.budget = budget);
+    }
+
+    /// Name the phase which starts now, as in "reading the user config".  This also arms the
+    /// watchdog again after `disarm()`.
+    pub fn phase(&self, phase: &'static str) {
+        self.update(|state| state.phase = Some(phase));
+    }
+
+    /// Stop aborting, such as for modes which are not a startup and may run for long.  The
+    /// thread checking the time ends.
+    pub fn disarm(&self) {
+        self.update(|state| state.phase = None);
+    }
+
+    /// Change the state and wake up the thread to check it again, or start the thread if there is
+    /// something to watch now.
+    fn update(&self, change: impl FnOnce(&mut State)) {
+        let (lock, changed) = &*self.shared;
+        let Ok(mut state) = lock.lock() else {
+            return;
+        };
+        change(&mut state);
+
+        if state.watching {
+            changed.notify_all();
+        } else if state.remaining(Instant::now()).is_some() {
+            state.watching = true;
+            let shared = Arc::clone(&self.shared);
+            thread::spawn(move || watch(&shared));
+        }
+    }
+}
+
+/// Sleep until the budget is used up or the state changes, and abort this program if the phase
+/// in progress is overdue.  Returns when there is nothing left to watch.
+fn watch(shared: &(Mutex<State>, Condvar)) {
+    let (lock, changed) = shared;
+    let Ok(mut state) = lock.lock() else {
+        return;
+    };
+
+    loop {
+        let now: Instant = Instant::now();
+        if let Some(message) = state.overdue(now) {
+            eprintln!("Error: {}", message);
+            let _ = overrides::cleanup();
+            process::exit(EXIT_CODE);
+        }
+        let Some(remaining) = state.remaining(now) else {
+            state.watching = false;
+            return;
+        };
+        state = match changed.wait_timeout(state, remaining + MARGIN) {
+            Ok((state, _)) => state,
+            Err(_) => return,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::State;
+    use super::Watchdog;
+    use std::thread;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    // Untested:
+    //  - Watchdog aborting the program
+
+    /// Wait a moment for the thread of `watchdog` to start or end.
+    fn is_watching(watchdog: &Watchdog) -> bool {
+        thread::sleep(Duration::from_millis(50));
+        watchdog.shared.0.lock().unwrap().watching
+    }
+
+    #[test]
+    fn overdue() {
+        let started = Instant::now();
+        let mut state = State {
+            started,
+            budget: Some(Duration::from_millis(500)),
+            phase: Some("reading stdin"),
+            watching: false,
+        };
+        let late = started + Duration::from_secs(1);
+
+        assert_eq!(None, state.overdue(started));
+        assert_eq!(
+            Some(
+                "Startup took longer than 500 ms while reading stdin, aborted."
+                    .to_string()
+            ),
+            state.overdue(late)
+        );
+
+        state.phase = None;
+        assert_eq!(None, state.overdue(late));
+        state.phase = Some("reading stdin");
+        state.budget = None;
+        assert_eq!(None, state.overdue(late));
+    }
+
+    #[test]
+    fn thread_only_while_armed() {
+        let watchdog = Watchdog::start(None);
+        watchdog.phase("reading the user config");
+        assert!(!is_watching(&watchdog));
+
+        watchdog.set_budget(Some(Duration::from_secs(3600)));
+        assert!(is_watching(&watchdog));
+
+        watchdog.disarm();
+        assert!(!is_watching(&watchdog));
+
+        watchdog.phase("building the command");
+        assert!(is_watching(&watchdog));
+        watchdog.set_budget(None);
+        assert!(!is_watching(&watchdog));
+    }
+}
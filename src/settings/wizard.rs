@@ -0,0 +1,454 @@
+use crate::settings::coreinfo;
+use crate::settings::discovery;
+use crate::settings::file;
+use crate::settings::migration;
+use crate::settings::retroarch;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use indexmap::map::IndexMap;
+
+/// Directory of the cores, if `retroarch.cfg` does not tell.
+pub const DEFAULT_LIBRETRO_DIRECTORY: &str = "~/.config/retroarch/cores";
+
+/// Short core aliases for the system names of the core info files, like `snes` for "Super
+/// Nintendo Entertainment System".  Cores of other systems are named after themselves.
+pub const SYSTEM_ALIASES: [(&str, &str); 22] = [
+    ("2600", "a26"),
+    ("Arcade (various)", "arcade"),
+    ("Dreamcast", "dc"),
+    ("Game Boy", "gb"),
+    ("Game Boy Advance", "gba"),
+    ("Game Boy Color", "gbc"),
+    ("Game Boy/Game Boy Color", "gb"),
+    ("Game Gear", "gg"),
+    ("Master System", "sms"),
+    ("Mega Drive - Genesis", "md"),
+    ("Neo Geo Pocket (Color)", "ngp"),
+    ("Nintendo 64", "n64"),
+    ("Nintendo DS", "nds"),
+    ("Nintendo Entertainment System", "nes"),
+    ("PC Engine/PCE-CD", "pce"),
+    ("PlayStation", "psx"),
+    ("PlayStation 2", "ps2"),
+    ("PlayStation Portable", "psp"),
+    ("Saturn", "saturn"),
+    ("Sega 32X", "32x"),
+    ("Super Nintendo Entertainment System", "snes"),
+    ("WonderSwan/Color", "ws"),
+];
+
+/// Archives many cores list as supported, which say nothing about the system of a game.
+const ARCHIVE_EXTENSIONS: [&str; 2] = ["zip", "7z"];
+
+/// An installed core with its info file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Core {
+    /// Name like `snes9x` for `snes9x_libretro.so`.
+    pub name: String,
+    pub info: Option<coreinfo::CoreInfo>,
+}
+
+impl Core {
+    /// Alias of the core by its system, or its own name if the system is not known.
+    #[must_use]
+    pub fn alias(&self) -> String {
+        let system: Option<&str> = self
+            .info
+            .as_ref()
+            .and_then(|info| info.systemname.as_deref());
+
+        SYSTEM_ALIASES
+            .iter()
+            .find(|(name, _)| Some(*name) == system)
+            .map_or_else(|| self.name.clone(), |(_, alias)| alias.to_string())
+    }
+
+    fn extensions(&self) -> &[String] {
+        self.info
+            .as_ref()
+            .map_or(&[], |info| info.supported_extensions.as_slice())
+    }
+}
+
+/// What was found on this system to start the first config with.
+#[derive(Debug, Clone, Default)]
+pub struct Detected {
+    pub retroarch: Option<PathBuf>,
+    pub libretro_directory: Option<PathBuf>,
+    pub libretro_info_directory: Option<PathBuf>,
+}
+
+/// Search the `RetroArch` executable and read the directories of the cores and their info files
+/// from its `retroarch.cfg`.
+#[must_use]
+pub fn detect() -> Detected {
+    let keys: HashSet<String> = ["libretro_directory", "libretro_info_path"]
+        .iter()
+        .map(|key| key.to_string())
+        .collect();
+    let raconfig: IndexMap<String, String> =
+        retroarch::search_default_config()
+            .and_then(|path| {
+                retroarch::parse_retroarch_config(&Some(path), &[], &keys).ok()
+            })
+            .unwrap_or_default();
+
+    Detected {
+        retroarch: discovery::search(&[]),
+        libretro_directory: raconfig
+            .get("libretro_directory")
+            .map(|dir| file::tilde(Path::new(dir))),
+        libretro_info_directory: raconfig
+            .get("libretro_info_path")
+            .map(|dir| file::tilde(Path::new(dir))),
+    }
+}
+
+/// All cores installed in `dir`, sorted by name, with their info files from `info_directory` or
+/// else `dir`.
+#[must_use]
+pub fn list_cores(dir: &Path, info_directory: Option<&Path>) -> Vec<Core> {
+    let mut cores: Vec<Core> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with("_libretro.so"))
+        .map(|name| {
+            let libretro: PathBuf = PathBuf::from(name);
+            Core {
+                name: coreinfo::core_name(&libretro),
+                info: coreinfo::load(info_directory.unwrap_or(dir), &libretro),
+            }
+        })
+        .collect();
+    cores.sort_by(|a, b| a.name.cmp(&b.name));
+
+    cores
+}
+
+/// Extensions for the extension rules of each chosen alias and its core.  Extensions supported by
+/// more than one of them and archives are left out, as they cannot tell the system.
+#[must_use]
+pub fn extension_rules(
+    chosen: &IndexMap<String, Core>,
+) -> IndexMap<String, Vec<String>> {
+    let mut claims: IndexMap<&str, usize> = IndexMap::new();
+    for core in chosen.values() {
+        for extension in core.extensions() {
+            *claims.entry(extension.as_str()).or_default() += 1;
+        }
+    }
+
+    chosen
+        .iter()
+        .map(|(alias, core)| {
+            let extensions: Vec<String> = core
+                .extensions()
+                .iter()
+                .filter(|e| claims.get(e.as_str()) == Some(&1))
+                .filter(|e| !ARCHIVE_EXTENSIONS.contains(&e.as_str()))
+                .cloned()
+                .collect();
+            (alias.to_string(), extensions)
+        })
+        .filter(|(_, extensions)| !extensions.is_empty())
+        .collect()
+}
+
+/// Text of the first user config.
+#[must_use]
+pub fn render(
+    retroarch: &str,
+    libretro_directory: &str,
+    chosen: &IndexMap<String, Core>,
+) -> String {
+    let mut config: String = format!(
+        "# Created by the setup of enjoy.  See the example config for all options.\n\
+         \n\
+         [options]\n\
+         version = {}\n\
+         retroarch = {}\n\
+         libretro_directory = {}\n\
+         \n\
+         [cores]\n",
+        migration::CURRENT_VERSION,
+        retroarch,
+        libretro_directory
+    );
+    for (alias, core) in chosen {
+        config.push_str(&format!("{} = {}\n", alias, core.name));
+    }
+    for (alias, extensions) in extension_rules(chosen) {
+        let section: Vec<String> =
+            extensions.iter().map(|e| format!(".{}", e)).collect();
+        config.push_str(&format!(
+            "\n[{}]\ncore = {}\n",
+            section.join(" "),
+            alias
+        ));
+    }
+
+    config
+}
+
+/// Print the `question` with its `default` answer and read the answer.  An empty answer is the
+/// default, the end of input an error of kind `UnexpectedEof`.
+fn ask(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: &str,
+) -> io::Result<String> {
+    write!(output, "{} [{}]: ", question, default)?;
+    output.flush()?;
+    let mut answer: String = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let answer: &str = answer.trim();
+
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer.to_string()
+    })
+}
+
+/// Guide through the first user config: confirm the `RetroArch` executable and the directory of
+/// the cores, then choose a core for each system found there.  Returns the text of the config,
+/// or `None` if it should not be written or the input ended early.
+pub fn run(
+    detected: &Detected,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<Option<String>> {
+    match dialog(detected, input, output) {
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+            writeln!(output)?;
+            Ok(None)
+        }
+        result => result,
+    }
+}
+
+fn dialog(
+    detected: &Detected,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<Option<String>> {
+    writeln!(
+        output,
+        "No user config found.  Answer a few questions to create one, press Enter to keep a \
+         suggestion."
+    )?;
+    let retroarch: String = ask(
+        input,
+        output,
+        "RetroArch executable",
+        &detected.retroarch.as_ref().map_or_else(
+            || "retroarch".to_string(),
+            |p| p.display().to_string(),
+        ),
+    )?;
+    let libretro_directory: String = ask(
+        input,
+        output,
+        "Directory of the cores",
+        &detected.libretro_directory.as_ref().map_or_else(
+            || DEFAULT_LIBRETRO_DIRECTORY.to_string(),
+            |p| p.display().to_string(),
+        ),
+    )?;
+
+    let mut systems: IndexMap<String, Vec<Core>> = IndexMap::new();
+    for core in list_cores(
+        &file::tilde(Path::new(&libretro_directory)),
+        detected.libretro_info_directory.as_deref(),
+    ) {
+        systems.entry(core.alias()).or_default().push(core);
+    }
+    systems.sort_keys();
+    if systems.is_empty() {
+        writeln!(output, "No cores found in {}.", libretro_directory)?;
+    }
+
+    let mut chosen: IndexMap<String, Core> = IndexMap::new();
+    for (alias, cores) in systems {
+        let system: String = cores[0]
+            .info
+            .as_ref()
+            .and_then(coreinfo::CoreInfo::system)
+            .unwrap_or_else(|| "Unknown system".to_string());
+        writeln!(output, "\n{} - {}", alias, system)?;
+        for (number, core) in cores.iter().enumerate() {
+            writeln!(output, "  {}) {}", number + 1, core.name)?;
+        }
+        let answer: String = ask(input, output, "Core, or 0 to skip", "1")?;
+        let core: Option<&Core> = answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| number.checked_sub(1))
+            .and_then(|index| cores.get(index));
+        if let Some(core) = core {
+            chosen.insert(alias, core.clone());
+        }
+    }
+
+    let config: String = render(&retroarch, &libretro_directory, &chosen);
+    writeln!(output, "\n{}", config)?;
+    let answer: String = ask(input, output, "Write this config?", "Y/n")?;
+
+    Ok(
+        matches!(answer.to_lowercase().as_str(), "y/n" | "y" | "yes")
+            .then_some(config),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    use indexmap::map::IndexMap;
+
+    use super::Core;
+    use crate::settings::coreinfo::CoreInfo;
+
+    // Untested:
+    //  - detect()
+
+    fn test_core(name: &str, system: &str, extensions: &[&str]) -> Core {
+        Core {
+            name: name.to_string(),
+            info: Some(CoreInfo {
+                systemname: Some(system.to_string()),
+                supported_extensions: extensions
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn alias() {
+        let snes =
+            test_core("snes9x", "Super Nintendo Entertainment System", &[]);
+        let other = test_core("vice_x64", "Commodore 64", &[]);
+
+        assert_eq!("snes", snes.alias());
+        assert_eq!("vice_x64", other.alias());
+        assert_eq!(
+            "mgba",
+            Core {
+                name: "mgba".into(),
+                info: None
+            }
+            .alias()
+        );
+    }
+
+    #[test]
+    fn render() {
+        let chosen: IndexMap<String, Core> = IndexMap::from([
+            (
+                "gba".to_string(),
+                test_core("mgba", "Game Boy Advance", &["gba", "gb", "zip"]),
+            ),
+            (
+                "gb".to_string(),
+                test_core("sameboy", "Game Boy", &["gb", "gbc"]),
+            ),
+        ]);
+
+        assert_eq!(
+            "# Created by the setup of enjoy.  See the example config for all options.\n\
+             \n\
+             [options]\n\
+             version = 2\n\
+             retroarch = /usr/bin/retroarch\n\
+             libretro_directory = ~/cores\n\
+             \n\
+             [cores]\n\
+             gba = mgba\n\
+             gb = sameboy\n\
+             \n\
+             [.gba]\n\
+             core = gba\n\
+             \n\
+             [.gbc]\n\
+             core = gb\n",
+            super::render("/usr/bin/retroarch", "~/cores", &chosen)
+        );
+    }
+
+    #[test]
+    fn run() {
+        let dir: PathBuf = std::env::temp_dir()
+            .join(format!("enjoy-test-wizard-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, system, extensions) in [
+            ("bsnes", "Super Nintendo Entertainment System", "sfc|smc"),
+            (
+                "snes9x",
+                "Super Nintendo Entertainment System",
+                "smc|sfc|fig",
+            ),
+            ("mgba", "Game Boy Advance", "gba"),
+        ] {
+            fs::write(dir.join(format!("{}_libretro.so", name)), "").unwrap();
+            fs::write(
+                dir.join(format!("{}_libretro.info", name)),
+                format!(
+                    "systemname = \"{}\"\nsupported_extensions = \"{}\"\n",
+                    system, extensions
+                ),
+            )
+            .unwrap();
+        }
+        let detected = super::Detected {
+            retroarch: Some(PathBuf::from("/usr/bin/retroarch")),
+            libretro_directory: Some(dir.clone()),
+            libretro_info_directory: None,
+        };
+        let mut output: Vec<u8> = vec![];
+
+        let config = super::run(
+            &detected,
+            &mut Cursor::new("\n\n0\n2\ny\n"),
+            &mut output,
+        )
+        .unwrap()
+        .unwrap();
+        let ended = super::run(
+            &detected,
+            &mut Cursor::new("\n\n1\n"),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        let declined = super::run(
+            &detected,
+            &mut Cursor::new("\n\n\n\nn\n"),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(config.contains("[cores]\nsnes = snes9x\n\n[.smc .sfc .fig]"));
+        assert!(!config.contains("gba"));
+        assert!(String::from_utf8_lossy(&output).contains("  2) snes9x"));
+        assert_eq!(None, declined);
+        assert_eq!(None, ended);
+    }
+}
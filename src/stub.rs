@@ -0,0 +1,49 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// Environment variable with the path of the record file.  If set, this program does not act as
+/// `enjoy`, but as a stand-in for `retroarch`.
+pub const RECORD_VAR: &str = "ENJOY_STUB_RECORD";
+
+/// Environment variable with the exit code the stub should return.  Defaults to `0`.
+pub const EXIT_VAR: &str = "ENJOY_STUB_EXIT";
+
+/// Act as a fake `retroarch`, if the process was started as a stub by option `--retroarch-stub`.
+/// The working directory, each commandline argument and the environment are appended to the
+/// record file, one `key=value` per line and each run terminated by a line `end`.  Returns the
+/// exit code to end the process with, or `None` to continue as `enjoy`.
+pub fn run_if_requested() -> Option<i32> {
+    let record = env::var_os(RECORD_VAR)?;
+
+    let mut content: String =
+        format!("cwd={}\n", env::current_dir().unwrap_or_default().display());
+    for arg in env::args_os().skip(1) {
+        content.push_str(&format!("arg={}\n", arg.to_string_lossy()));
+    }
+    for (key, value) in env::vars_os() {
+        content.push_str(&format!(
+            "env={}={}\n",
+            key.to_string_lossy(),
+            value.to_string_lossy()
+        ));
+    }
+    content.push_str("end\n");
+
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&record)
+        .and_then(|mut file| file.write_all(content.as_bytes()));
+    if let Err(e) = written {
+        eprintln!("Could not write stub record: {}", e);
+        return Some(1);
+    }
+
+    Some(
+        env::var(EXIT_VAR)
+            .ok()
+            .and_then(|code| code.parse().ok())
+            .unwrap_or(0),
+    )
+}
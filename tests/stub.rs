@@ -0,0 +1,297 @@
+// End-to-end tests, which run the `enjoy` binary with option `--retroarch-stub`.  Instead of
+// `retroarch` the stub records its working directory, arguments and environment to a file.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Temporary directory with a minimal setup: a core, a `retroarch.cfg`, a user config and a few
+/// game files.  It is removed when dropped.
+struct Sandbox {
+    dir: PathBuf,
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Self {
+        let dir: PathBuf = env::temp_dir().join(format!(
+            "enjoy-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("cores")).unwrap();
+        fs::create_dir_all(dir.join("roms")).unwrap();
+
+        fs::write(dir.join("cores/snes9x_libretro.so"), "").unwrap();
+        fs::write(dir.join("cores/sameboy_libretro.so"), "").unwrap();
+        fs::write(dir.join("roms/mario.smc"), "").unwrap();
+        fs::write(dir.join("roms/zelda.sfc"), "").unwrap();
+        fs::write(dir.join("roms/tetris.gb"), "").unwrap();
+        fs::write(
+            dir.join("retroarch.cfg"),
+            format!(
+                "libretro_directory = \"{}\"\n",
+                dir.join("cores").display()
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("enjoy.ini"),
+            "[cores]\n\
+             snes = snes9x\n\
+             gb = sameboy\n\
+             \n\
+             [.smc .sfc]\n\
+             core = snes\n\
+             env.ENJOY_TEST_RULE = snes\n\
+             \n\
+             [.gb]\n\
+             core = gb\n\
+             workdir = /\n",
+        )
+        .unwrap();
+
+        Self { dir }
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+
+    /// Command of `enjoy` with the sandbox setup and the stub, which still reads stdin.
+    fn command(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_enjoy"));
+        command
+            .current_dir(&self.dir)
+            .env("XDG_RUNTIME_DIR", &self.dir)
+            .env("XDG_CACHE_HOME", &self.dir)
+            .env("XDG_STATE_HOME", &self.dir)
+            .args(["--config", "enjoy.ini"])
+            .args(["--retroarch-config", "retroarch.cfg"])
+            .args(["--retroarch-stub", "record.txt"]);
+        command
+    }
+
+    /// Run `enjoy` with the sandbox setup and the stub, plus additional `args`.
+    fn enjoy(&self, args: &[&str], envs: &[(&str, &str)]) -> Output {
+        self.command()
+            .envs(envs.iter().copied())
+            .arg("--nostdin")
+            .args(args)
+            .output()
+            .unwrap()
+    }
+
+    /// Run `enjoy` with the sandbox setup and the stub, plus additional `args`, with `input` piped
+    /// into stdin and stdout captured by a pipe as well.
+    fn enjoy_piped(&self, args: &[&str], input: &str) -> Output {
+        let mut child = self
+            .command()
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+
+        child.wait_with_output().unwrap()
+    }
+
+    /// All runs of the stub, each as a list of its `key=value` lines.
+    fn records(&self) -> Vec<Vec<String>> {
+        let content: String =
+            fs::read_to_string(self.path("record.txt")).unwrap_or_default();
+
+        content
+            .split_terminator("end\n")
+            .map(|run| run.lines().map(str::to_string).collect())
+            .collect()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Commandline arguments of a single stub run.
+fn args(record: &[String]) -> Vec<String> {
+    record
+        .iter()
+        .filter_map(|line| line.strip_prefix("arg="))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn command_from_extension_rule() {
+    let sandbox = Sandbox::new("extension");
+    let output = sandbox.enjoy(&["roms/mario.smc"], &[]);
+
+    assert!(output.status.success());
+    let records = sandbox.records();
+    assert_eq!(1, records.len());
+    assert_eq!(
+        vec![
+            sandbox.path("roms/mario.smc").display().to_string(),
+            "--libretro".to_string(),
+            sandbox
+                .path("cores/snes9x_libretro.so")
+                .display()
+                .to_string(),
+            "--config".to_string(),
+            sandbox.path("retroarch.cfg").display().to_string(),
+        ],
+        args(&records[0])
+    );
+    assert!(records[0].contains(&"env=ENJOY_TEST_RULE=snes".to_string()));
+}
+
+#[test]
+fn workdir_from_rule() {
+    let sandbox = Sandbox::new("workdir");
+    sandbox.enjoy(&["roms/tetris.gb"], &[]);
+
+    let records = sandbox.records();
+    assert!(records[0].contains(&"cwd=/".to_string()));
+    assert!(args(&records[0]).contains(
+        &sandbox
+            .path("cores/sameboy_libretro.so")
+            .display()
+            .to_string()
+    ));
+}
+
+#[test]
+fn core_option_overrides_rule() {
+    let sandbox = Sandbox::new("core");
+    sandbox.enjoy(&["--core", "gb", "roms/mario.smc"], &[]);
+
+    assert!(args(&sandbox.records()[0]).contains(
+        &sandbox
+            .path("cores/sameboy_libretro.so")
+            .display()
+            .to_string()
+    ));
+}
+
+#[test]
+fn norun_does_not_start() {
+    let sandbox = Sandbox::new("norun");
+    let output = sandbox.enjoy(&["--norun", "roms/mario.smc"], &[]);
+
+    assert!(output.status.success());
+    assert!(sandbox.records().is_empty());
+}
+
+#[test]
+fn exit_code_reported() {
+    let sandbox = Sandbox::new("exitcode");
+    let output =
+        sandbox.enjoy(&["roms/mario.smc"], &[("ENJOY_STUB_EXIT", "3")]);
+
+    assert!(String::from_utf8_lossy(&output.stderr).contains("exit status: 3"));
+}
+
+#[test]
+fn queue_runs_each_game() {
+    let sandbox = Sandbox::new("queue");
+    let output = sandbox.enjoy(
+        &["--queue", "--json", "roms/mario.smc", "roms/zelda.sfc"],
+        &[("ENJOY_STUB_EXIT", "2")],
+    );
+
+    assert_eq!(2, sandbox.records().len());
+    let summary = String::from_utf8_lossy(&output.stdout);
+    assert!(summary.contains("\"exit_code\":2"));
+    assert!(summary.contains("\"failed\":2"));
+}
+
+#[test]
+fn fingerprint_is_stable() {
+    let sandbox = Sandbox::new("fingerprint");
+    let first = sandbox.enjoy(&["--fingerprint", "roms/mario.smc"], &[]);
+    let second = sandbox.enjoy(&["--fingerprint", "roms/mario.smc"], &[]);
+    let other = sandbox.enjoy(&["--fingerprint", "roms/tetris.gb"], &[]);
+
+    assert!(sandbox.records().is_empty());
+    assert_eq!(first.stdout, second.stdout);
+    assert_ne!(first.stdout, other.stdout);
+}
+
+#[test]
+fn stdin_left_open_times_out() {
+    let sandbox = Sandbox::new("stdin-timeout");
+    let mut child = sandbox
+        .command()
+        .args(["--fzf", "--stdin-timeout", "200"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Keep the pipe open like a GUI launcher would, while waiting for `enjoy`.
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"roms/mario.smc\n").unwrap();
+    let start = Instant::now();
+    let output = child.wait_with_output().unwrap();
+    drop(stdin);
+
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("mario.smc"));
+}
+
+#[test]
+fn list_games_from_piped_stdin() {
+    let sandbox = Sandbox::new("list-games-pipe");
+    let output = sandbox
+        .enjoy_piped(&["--list-games"], "roms/mario.smc\nroms/tetris.gb\n");
+
+    assert!(output.status.success());
+    assert_eq!(
+        format!(
+            "{}\n{}\n",
+            sandbox.path("roms/mario.smc").display(),
+            sandbox.path("roms/tetris.gb").display()
+        ),
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(sandbox.records().is_empty());
+}
+
+#[test]
+fn count_from_piped_stdin() {
+    let sandbox = Sandbox::new("count-pipe");
+    let output = sandbox.enjoy_piped(
+        &["--count"],
+        "roms/mario.smc\nroms/zelda.sfc\nroms/tetris.gb\n",
+    );
+
+    assert!(output.status.success());
+    assert_eq!("3\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn count_without_match_fails() {
+    let sandbox = Sandbox::new("count-none");
+    let output = sandbox.enjoy_piped(
+        &["--count", "--filter", "metroid"],
+        "roms/mario.smc\nroms/zelda.sfc\n",
+    );
+
+    assert!(!output.status.success());
+    assert_eq!("0\n", String::from_utf8_lossy(&output.stdout));
+}